@@ -0,0 +1,30 @@
+//! 应用版本和构建信息，供“关于”页面和问题反馈使用。
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Version and build details of the running app.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppInfo {
+    /// Version of the app itself, as given by the frontend crate.
+    pub version: String,
+    /// UTC date of the build, `YYYY-MM-DD`.
+    pub build_date: String,
+    /// Short hash of the commit built from; "unknown" outside a git checkout.
+    pub git_hash: String,
+    pub audio_core_version: String,
+    /// Settings file in use.
+    pub config_path: PathBuf,
+}
+
+impl AppInfo {
+    pub fn new(version: &str, config_path: PathBuf) -> Self {
+        Self {
+            version: version.to_string(),
+            build_date: env!("AUDIOROUTER_BUILD_DATE").to_string(),
+            git_hash: env!("AUDIOROUTER_GIT_HASH").to_string(),
+            audio_core_version: audio_core::VERSION.to_string(),
+            config_path,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! 命令行控制参数，供脚本和任务计划程序驱动应用。
+//!
+//! - `--profile <name>`：切换到配置档案
+//! - `--start-routing` / `--stop`：开始或停止路由
+//! - `--restart-routing`：按最新配置重启正在运行的路由（未运行时忽略）
+//! - `--list-devices`：列出输出设备
+//!
+//! 已有实例在运行时，GUI 把命令转发给它执行，见 [`CliCommand::to_args`]。
+
+use anyhow::{Context, Result};
+use audio_core::com_service::device::{DeviceInfo, DeviceState};
+use std::ffi::OsString;
+use std::fmt::Write;
+
+/// A control command given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    ActivateProfile(String),
+    StartRouting,
+    StopRouting,
+    RestartRouting,
+    ListDevices,
+}
+
+impl CliCommand {
+    /// The commands in `args`, in the order given. Other arguments, such
+    /// as `--minimized` or config overrides, are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if `--profile` has no value.
+    pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Vec<Self>> {
+        let mut commands = Vec::new();
+        let mut args = args
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned());
+        while let Some(arg) = args.next() {
+            let command = match arg.as_str() {
+                "--profile" => {
+                    Self::ActivateProfile(args.next().context("--profile needs a value")?)
+                }
+                "--start-routing" => Self::StartRouting,
+                "--stop" => Self::StopRouting,
+                "--restart-routing" => Self::RestartRouting,
+                "--list-devices" => Self::ListDevices,
+                _ => match arg.strip_prefix("--profile=") {
+                    Some(name) => Self::ActivateProfile(name.to_string()),
+                    None => continue,
+                },
+            };
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+
+    /// The arguments [`Self::parse`] reads back as this command, for
+    /// forwarding it to a running instance.
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            Self::ActivateProfile(name) => vec!["--profile".into(), name.clone()],
+            Self::StartRouting => vec!["--start-routing".into()],
+            Self::StopRouting => vec!["--stop".into()],
+            Self::RestartRouting => vec!["--restart-routing".into()],
+            Self::ListDevices => vec!["--list-devices".into()],
+        }
+    }
+}
+
+/// One line per device for `--list-devices`: ID, name and state, with
+/// `*` marking the default device.
+pub fn format_device_list(devices: &[DeviceInfo]) -> String {
+    let mut list = String::new();
+    for device in devices {
+        let state = match device.state {
+            DeviceState::Active => "active",
+            DeviceState::Disabled => "disabled",
+            DeviceState::Unplugged => "unplugged",
+            DeviceState::NotPresent => "not present",
+            DeviceState::Unknown => "unknown",
+        };
+        let default = if device.is_default { "*" } else { " " };
+        let _ = writeln!(
+            list,
+            "{default} {}\t{}\t{state}",
+            device.id, device.friendly_name
+        );
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_read_in_order_and_round_trip() {
+        let args = [
+            "--minimized",
+            "--set",
+            "general.language=en",
+            "--profile",
+            "Night",
+            "--start-routing",
+            "--profile=Day",
+            "--stop",
+            "--restart-routing",
+            "--list-devices",
+        ]
+        .map(OsString::from);
+        let commands = CliCommand::parse(args).unwrap();
+        assert_eq!(
+            commands,
+            [
+                CliCommand::ActivateProfile("Night".into()),
+                CliCommand::StartRouting,
+                CliCommand::ActivateProfile("Day".into()),
+                CliCommand::StopRouting,
+                CliCommand::RestartRouting,
+                CliCommand::ListDevices,
+            ]
+        );
+
+        let forwarded = commands
+            .iter()
+            .flat_map(CliCommand::to_args)
+            .map(OsString::from);
+        assert_eq!(CliCommand::parse(forwarded).unwrap(), commands);
+        assert!(CliCommand::parse([OsString::from("--profile")]).is_err());
+    }
+}
@@ -1,11 +1,54 @@
 //! 应用控制层，封装与具体 GUI 框架无关的状态和操作。
 
-use audio_core::com_service::device::{DeviceInfo, get_all_output_devices};
-use audio_core::router::{ChannelMode, Router, RouterConfig, RouterTarget};
-use config::ConfigManager;
-use config::config::{General, Output};
+use audio_core::com_service::apartment::{ApartmentCalibration, calibrate_apartments};
+use audio_core::com_service::device::{
+    DeviceFlow, DeviceInfo, DeviceRole, DeviceState, EndpointVolume, EndpointVolumeWatch,
+    get_default_output_device_for_role, get_endpoint_volume, set_default_output_device,
+    set_endpoint_mute, watch_endpoint_volume,
+};
+use audio_core::com_service::playback;
+use audio_core::device_registry::DeviceRegistry;
+use audio_core::device_watcher::DeviceEvent;
+use audio_core::dsp::{
+    BassManagement, BassRole, CrossfeedPreset, DitherMode, DspChain, EqPreset, LimiterSettings,
+    LoudnessSettings, MAX_GAIN_DB, MIN_GAIN_DB, MidSideSettings, NoiseGateSettings,
+    ResamplerQuality, db_to_linear, volume_scalar_to_db,
+};
+use audio_core::plugin::PluginSlot;
+use audio_core::router::{
+    ChannelMatrix, ChannelMode, ChannelTrim, EngineSettings, MixLevels, OutputLatency,
+    OverflowPolicy, Router, RouterConfig, RouterLevels, RouterTarget, SourceRole, SpectrumFrame,
+};
+use config::config::{
+    Config, General, HotplugSettings, Output, SourceSelection, StartupSettings, UnplugAction,
+    UpdateChannel,
+};
+use config::hotkeys::{HotkeyAction, HotkeyBinding};
+use config::state::{ReplacedDefault, RuntimeState};
+use config::{ConfigFormat, ConfigManager, ResetScope, ValidationIssue};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::about::AppInfo;
+use crate::cli::{self, CliCommand};
+use crate::events::{self, AppEvent, EventType};
 use crate::i18n::I18n;
+use crate::levels::LevelStream;
+use crate::notifications::Notification;
+
+/// COM apartment 校准时每种负载的测量轮数。
+const COM_CALIBRATION_ROUNDS: u32 = 5;
+
+/// 待显示的通知上限，超出后丢弃最旧的通知。
+const MAX_PENDING_NOTIFICATIONS: usize = 256;
+
+/// 音量热键每次调整的增益（dB）。
+const HOTKEY_VOLUME_STEP_DB: f32 = 2.0;
+
+/// [`AppController::restart_routing`] 停止前的淡出时长（ms）。
+const RESTART_FADE_OUT_MS: f32 = 150.0;
 
 /// 应用业务状态和操作入口。
 pub struct AppController {
@@ -17,6 +60,30 @@ pub struct AppController {
     pub is_running: bool,
     pub status_text: String,
     pub draft_general: General,
+    /// 设置页中编辑的启动行为，保存到当前配置（随配置档切换）。
+    pub draft_startup: StartupSettings,
+    /// 设备列表缓存，设备变化时才重新枚举。
+    device_registry: DeviceRegistry,
+    /// 设备监听事件，用于自动路由规则。
+    device_events: Option<Receiver<DeviceEvent>>,
+    /// 后台进行中的 COM apartment 校准，完成后在刷新设备时保存结果。
+    com_calibration: Option<Receiver<anyhow::Result<ApartmentCalibration>>>,
+    /// 最近一帧频谱（仅在开启频谱时更新）。
+    spectrum: Option<SpectrumFrame>,
+    /// 前端开启电平表时的推送线程。
+    level_stream: Option<LevelStream>,
+    /// 路由期间被替换的系统默认设备，停止时恢复。
+    replaced_defaults: Vec<(DeviceRole, String)>,
+    /// 跟随源设备音量时对其音量变化的监听，停止路由时释放。
+    source_volume: Option<(EndpointVolumeWatch, Receiver<EndpointVolume>)>,
+    /// 路由期间被静音的源设备，停止时取消静音。
+    muted_source: Option<String>,
+    /// 当前会话实际捕获的源设备（跟随默认设备时为启动时解析出的设备）。
+    routed_source: Option<String>,
+    /// 待前端显示的桌面通知。
+    pending_notifications: VecDeque<Notification>,
+    /// [`Self::restart_routing`] 已请求淡出，等待 worker 报告淡出结束。
+    restart_pending: bool,
     initialized: bool,
 }
 
@@ -30,14 +97,22 @@ impl AppController {
             router,
             i18n: I18n::new(&locale),
             devices: Vec::new(),
-            selected_source: if cfg.source_device_id.is_empty() {
-                None
-            } else {
-                Some(cfg.source_device_id.clone())
-            },
+            selected_source: Self::source_for(&cfg.source),
             is_running: false,
             status_text: String::new(),
             draft_general: cfg.general.clone(),
+            draft_startup: cfg.startup,
+            device_registry: DeviceRegistry::new(),
+            device_events: None,
+            com_calibration: None,
+            spectrum: None,
+            level_stream: None,
+            replaced_defaults: Vec::new(),
+            source_volume: None,
+            muted_source: None,
+            routed_source: None,
+            pending_notifications: VecDeque::new(),
+            restart_pending: false,
             initialized: false,
         }
     }
@@ -47,6 +122,26 @@ impl AppController {
             return;
         }
         self.initialized = true;
+        if let Some(recovery) = self.config_manager.recovery().cloned() {
+            log::warn!(
+                "Settings file was broken ({}), moved to {}",
+                recovery.reason,
+                recovery.quarantined.display()
+            );
+            self.emit(AppEvent::ConfigRecovered {
+                quarantined: recovery.quarantined.display().to_string(),
+                backup: recovery.restored_backup,
+                reason: recovery.reason,
+            });
+        }
+        self.undo_leftover_system_changes();
+        self.calibrate_com_if_needed();
+        match self.device_registry.start_watching() {
+            Ok(()) => self.device_events = Some(self.device_registry.subscribe()),
+            Err(e) => {
+                log::warn!("Device watcher failed to start, device list will not be cached: {e}")
+            }
+        }
         self.refresh_devices();
         self.is_running = self.router.is_running();
 
@@ -54,17 +149,51 @@ impl AppController {
             self.status_text = self.i18n.t("NoDevices").to_string();
         }
 
-        self.start_auto_route_if_enabled();
+        if !self.resume_last_state() {
+            self.start_auto_route_if_enabled();
+        }
     }
 
     pub fn refresh_devices(&mut self) {
-        match get_all_output_devices() {
+        if let Err(e) = self.config_manager.flush_if_due() {
+            log::error!("Save delayed config changes failed: {e:#}");
+        }
+        self.reload_config_if_changed();
+        self.apply_com_calibration();
+        // 先取事件再刷新列表：事件到达前缓存已失效，刷新后的列表包含新设备
+        let (activated, default_source) = self.take_device_events();
+        self.reload_devices();
+        self.apply_hotplug(&activated);
+        self.apply_auto_route_rules(&activated);
+        let Some(source_id) = default_source else {
+            return;
+        };
+        self.selected_source = Some(source_id.clone());
+        // 列表变化时上面已经重启过，此时源已是新的默认设备
+        if self.is_running && self.routed_source.as_ref() != Some(&source_id) {
+            log::info!("Default source device changed to {source_id}, re-routing");
+            self.apply_running_config();
+        }
+    }
+
+    fn reload_devices(&mut self) {
+        let enumeration_apartment = self
+            .config_manager
+            .handle()
+            .read()
+            .com
+            .enumeration_apartment;
+        match self.device_registry.devices(enumeration_apartment) {
             Ok(devices) => {
                 if devices == self.devices {
                     return;
                 }
 
-                self.devices = devices;
+                let previous = std::mem::replace(&mut self.devices, devices);
+                self.track_missing_outputs(&previous);
+                self.emit(AppEvent::DevicesChanged {
+                    device_count: self.devices.len() as u32,
+                });
                 if self.devices.is_empty() {
                     self.status_text = self.i18n.t("NoDevices").to_string();
                 } else if !self.is_running {
@@ -80,11 +209,243 @@ impl AppController {
         }
     }
 
+    /// 拔出的输出设备保留配置并记录消失时间，按 `hotplug.on_unplug` 决定是否
+    /// 停用；重新接入时清除标记，原有设置随即生效。`previous` 是变化前的
+    /// 设备列表，用于取得已拔出设备的名称。
+    fn track_missing_outputs(&mut self, previous: &[DeviceInfo]) {
+        let present: Vec<&str> = self.devices.iter().map(|d| d.id.as_str()).collect();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut cfg = self.config_manager.handle().read().clone();
+        let was_missing: Vec<bool> = cfg
+            .outputs
+            .iter()
+            .map(|o| o.missing_since.is_some())
+            .collect();
+        if !cfg.track_missing_outputs(&present, now) {
+            return;
+        }
+        let mut unplugged = Vec::new();
+        for (output, was_missing) in cfg.outputs.iter_mut().zip(was_missing) {
+            if !was_missing && output.missing_since.is_some() {
+                if cfg.hotplug.on_unplug == UnplugAction::Disable {
+                    output.enabled = false;
+                }
+                unplugged.push(output.device_id.clone());
+            }
+        }
+        if let Err(e) = self.config_manager.update(|c| c.outputs = cfg.outputs) {
+            log::error!("Save missing outputs failed: {e}");
+        }
+        // 首次枚举时的缺失设备不是刚拔出的，不发通知
+        if !previous.is_empty() && cfg.hotplug.notify {
+            for device_id in unplugged {
+                let name = previous
+                    .iter()
+                    .find(|d| d.id == device_id)
+                    .map_or_else(|| device_id.clone(), |d| d.friendly_name.clone());
+                self.emit(AppEvent::OutputDisconnected { device_id, name });
+            }
+        }
+    }
+
+    /// 新接入的设备：按设置通知，并把从未配置过的设备启用为输出。
+    fn apply_hotplug(&mut self, activated: &[String]) {
+        let hotplug = self.config_manager.handle().read().hotplug.clone();
+        let mut enabled = false;
+        for id in activated {
+            if self.selected_source.as_ref() == Some(id) {
+                continue;
+            }
+            let Some(device) = self.devices.iter().find(|d| &d.id == id) else {
+                continue;
+            };
+            if hotplug.notify {
+                let event = AppEvent::OutputConnected {
+                    device_id: id.clone(),
+                    name: device.friendly_name.clone(),
+                };
+                self.emit(event);
+            }
+            let known = self
+                .config_manager
+                .handle()
+                .read()
+                .outputs
+                .iter()
+                .any(|o| &o.device_id == id);
+            if known || !hotplug.auto_enable_new_devices {
+                continue;
+            }
+            match self.update_output(id, |output| output.enabled = true) {
+                Ok(()) => {
+                    log::info!("Enabled new output device {id}");
+                    enabled = true;
+                }
+                Err(e) => log::error!("Save new output failed: {e}"),
+            }
+        }
+        if enabled {
+            self.apply_running_config();
+        }
+    }
+
+    pub fn set_hotplug_settings(&mut self, hotplug: HotplugSettings) -> anyhow::Result<()> {
+        self.config_manager.update(|cfg| cfg.hotplug = hotplug)
+    }
+
+    /// 设备当前不在的输出；设备重新插入后其设置再次生效。
+    pub fn missing_outputs(&self) -> Vec<Output> {
+        let cfg = self.config_manager.handle();
+        let cfg = cfg.read();
+        cfg.outputs
+            .iter()
+            .filter(|o| o.missing_since.is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// 删除不在的设备已保存的设置。
+    pub fn forget_missing_output(&mut self, device_id: &str) -> anyhow::Result<()> {
+        self.config_manager.update(|cfg| {
+            cfg.outputs
+                .retain(|o| o.device_id != device_id || o.missing_since.is_none())
+        })
+    }
+
+    /// 自上次调用以来新接入或变为可用的设备，以及源所跟随角色的最新默认输出设备。
+    fn take_device_events(&mut self) -> (Vec<String>, Option<String>) {
+        let Some(rx) = &self.device_events else {
+            return (Vec::new(), None);
+        };
+        let source_role = self
+            .config_manager
+            .handle()
+            .read()
+            .source
+            .role()
+            .map(DeviceRole::from);
+        let mut activated = Vec::new();
+        let mut default_source = None;
+        for event in rx.try_iter() {
+            match event {
+                DeviceEvent::Added(id) | DeviceEvent::StateChanged(id, DeviceState::Active) => {
+                    activated.push(id)
+                }
+                DeviceEvent::DefaultChanged {
+                    flow: DeviceFlow::Render,
+                    role,
+                    device: Some(device),
+                } if Some(role) == source_role => default_source = Some(device.id),
+                _ => {}
+            }
+        }
+        (activated, default_source)
+    }
+
+    /// 对变为可用、名称匹配自动路由规则的输出设备执行规则：启用输出、
+    /// 设置声道模式，必要时开始路由。
+    fn apply_auto_route_rules(&mut self, activated: &[String]) {
+        let rules = self.config_manager.handle().read().auto_route_rules.clone();
+        if activated.is_empty() || rules.is_empty() {
+            return;
+        }
+
+        let mut outputs_changed = false;
+        let mut start = false;
+        for id in activated {
+            if self.selected_source.as_ref() == Some(id) {
+                continue;
+            }
+            // 不在列表中说明不是可用的输出设备
+            let Some(device) = self.devices.iter().find(|d| &d.id == id) else {
+                continue;
+            };
+            let name = device.friendly_name.clone();
+            for rule in rules.iter().filter(|rule| rule.matches(&name)) {
+                log::info!("Auto-route rule {:?} matched {name}", rule.device_name);
+                if rule.enable_output {
+                    let channel_mode = rule.channel_mode.clone();
+                    match self.update_output(id, |output| {
+                        output.enabled = true;
+                        if channel_mode.is_some() {
+                            output.channel_mode = channel_mode;
+                        }
+                    }) {
+                        Ok(()) => outputs_changed = true,
+                        Err(e) => log::error!("Save auto-route output failed: {e}"),
+                    }
+                }
+                start |= rule.start_routing;
+            }
+        }
+
+        if self.is_running {
+            if outputs_changed {
+                self.apply_running_config();
+            }
+        } else if start {
+            self.start_routing();
+        }
+    }
+
+    /// 在后台线程重新测量 STA/MTA 的设备枚举调度延迟；结果在之后的
+    /// [`Self::refresh_devices`] 中保存，此后的枚举生效。已在测量时忽略。
+    pub fn recalibrate_com_apartments(&mut self) {
+        if self.com_calibration.is_some() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("com-calibration".into())
+            .spawn(move || {
+                let _ = tx.send(calibrate_apartments(COM_CALIBRATION_ROUNDS));
+            });
+        match spawned {
+            Ok(_) => self.com_calibration = Some(rx),
+            Err(e) => log::warn!("Start COM apartment calibration failed: {e}"),
+        }
+    }
+
+    /// 首次运行时（配置中尚无测量结果）在后台执行一次 COM apartment 校准，
+    /// 不阻塞窗口创建。
+    fn calibrate_com_if_needed(&mut self) {
+        if self.config_manager.handle().read().com.is_calibrated() {
+            return;
+        }
+        self.recalibrate_com_apartments();
+    }
+
+    /// 后台校准完成时保存结果。
+    fn apply_com_calibration(&mut self) {
+        let Some(rx) = &self.com_calibration else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow::anyhow!("calibration thread exited")),
+        };
+        self.com_calibration = None;
+        match result {
+            Ok(calibration) => {
+                if let Err(e) = self.config_manager.update(|cfg| {
+                    cfg.com.enumeration_apartment = Some(calibration.enumeration);
+                }) {
+                    log::error!("Save COM apartment calibration failed: {e}");
+                }
+            }
+            Err(e) => log::warn!("COM apartment calibration failed: {e}"),
+        }
+    }
+
     /// 轮询路由 worker 事件，同步运行状态到 GUI。
     /// 应由 GUI 定时器定期调用（与 refresh_devices 同频率）。
     pub fn poll_router_events(&mut self) {
         use audio_core::router::WorkerEvent;
 
+        self.apply_source_volume();
         let events = self.router.poll_events();
         if events.is_empty() {
             return;
@@ -96,69 +457,516 @@ impl AppController {
                     self.is_running = true;
                 }
                 WorkerEvent::Restarting => {
+                    self.emit(AppEvent::RoutingRestarting);
                     self.status_text = self.i18n.t("Restarting").to_string();
                     log::info!("Router: {}", self.status_text);
                 }
                 WorkerEvent::Restarted => {
                     self.is_running = true;
+                    self.emit(AppEvent::RoutingRestarted);
                     self.status_text = self.i18n.t("Restarted").to_string();
                     log::info!("Router: {}", self.status_text);
                     // 短暂延迟后恢复正常的 "Running" 状态文本
                     // 下次 refresh_devices 或状态变化时会自然更新
                 }
                 WorkerEvent::Failed(msg) => {
-                    self.is_running = false;
+                    self.end_failed_session(&msg);
+                    log::error!("Router failed: {msg}");
+                    self.emit(AppEvent::RoutingFailed { error: msg });
+                }
+                WorkerEvent::Exited(reason) => {
+                    self.end_failed_session(&reason);
+                    log::error!("Router stopped unexpectedly: {reason}");
+                    self.emit(AppEvent::RoutingStoppedUnexpectedly { reason });
+                }
+                WorkerEvent::GlitchDetected { count, window_secs } => {
+                    self.emit(AppEvent::GlitchDetected { count, window_secs });
                     self.status_text = self
                         .i18n
-                        .t("RoutingFailed")
-                        .replace("{error}", &msg);
-                    log::error!("Router failed: {msg}");
+                        .t("GlitchDetected")
+                        .replace("{count}", &count.to_string())
+                        .replace("{secs}", &window_secs.to_string());
+                    log::warn!("Router: {}", self.status_text);
                 }
+                WorkerEvent::Clipping {
+                    device_id,
+                    count,
+                    window_secs,
+                } => {
+                    let device = self
+                        .devices
+                        .iter()
+                        .find(|d| d.id == device_id)
+                        .map_or(device_id.as_str(), |d| d.friendly_name.as_str());
+                    self.status_text = self
+                        .i18n
+                        .t("ClippingDetected")
+                        .replace("{device}", device)
+                        .replace("{count}", &count.to_string())
+                        .replace("{secs}", &window_secs.to_string());
+                    log::warn!("Router: {}", self.status_text);
+                    self.emit(AppEvent::ClippingDetected {
+                        device_id,
+                        count,
+                        window_secs,
+                    });
+                }
+                // 高频数据，不转成 AppEvent，由 GUI 按需读取
+                WorkerEvent::Spectrum(frame) => {
+                    self.spectrum = Some(frame);
+                }
+                WorkerEvent::SourceSwitched(device_id) => {
+                    self.watch_source_volume(Some(&device_id));
+                    // 同时取消对原来源设备的静音
+                    self.mute_source(Some(&device_id));
+                    self.routed_source = Some(device_id.clone());
+                    self.record_runtime_state();
+                    self.emit(AppEvent::SourceChanged { device_id });
+                }
+                WorkerEvent::SourceSwitchFailed(error) => {
+                    // worker 已回到原来的源；按配置重启，失败时照常报告
+                    log::warn!("Live source switch failed, restarting routing: {error}");
+                    self.apply_running_config();
+                    if let Some(device_id) = self.routed_source.clone() {
+                        self.emit(AppEvent::SourceChanged { device_id });
+                    }
+                }
+                WorkerEvent::FadedOut => {
+                    if self.restart_pending
+                        && let Err(e) = self.finish_restart()
+                    {
+                        log::error!("Restart routing failed: {e:#}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// 路由异常结束后恢复路由期间改动的系统状态，并在状态栏显示原因。
+    fn end_failed_session(&mut self, reason: &str) {
+        self.is_running = false;
+        self.restart_pending = false;
+        self.restore_default_devices();
+        self.unmute_source();
+        self.source_volume = None;
+        self.routed_source = None;
+        self.status_text = self
+            .i18n
+            .t("RoutingFailed")
+            .replace("{error}", reason);
+    }
+
+    /// 源的最新频谱；需开启频谱分析且路由已产生过频谱。
+    pub fn latest_spectrum(&self) -> Option<&SpectrumFrame> {
+        self.spectrum.as_ref()
+    }
+
+    /// 取走待显示的桌面通知；关闭 `general.notifications` 时始终为空。
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        self.pending_notifications.drain(..).collect()
+    }
+
+    /// 列出所有公开事件类型及其负载字段。
+    pub fn list_event_types(&self) -> Vec<EventType> {
+        events::list_event_types()
+    }
+
+    fn emit(&mut self, event: AppEvent) {
+        log::debug!("Event: {}", event.name());
+        if self.config_manager.handle().read().general.notifications
+            && let Some(notification) = Notification::for_event(&event, &self.i18n)
+        {
+            if self.pending_notifications.len() >= MAX_PENDING_NOTIFICATIONS {
+                self.pending_notifications.pop_front();
             }
+            self.pending_notifications.push_back(notification);
         }
     }
 
+    /// 此后从 `device_id` 路由，即使它不再是默认设备。
+    ///
+    /// 新旧源都不是已启用的输出时，运行中的会话实时切换，否则重启路由。
+    /// 路由用上新源后发出 [`AppEvent::SourceChanged`]。
     pub fn select_source_device(&mut self, device_id: String) {
-        self.selected_source = Some(device_id);
-        self.save_routing_config();
+        self.selected_source = Some(device_id.clone());
+        self.save_routing(SourceSelection::Device(device_id.clone()));
+        // 实时切换的结果由 poll_router_events 处理
+        if self.can_switch_source_live(&device_id) && self.router.switch_source(&device_id) {
+            return;
+        }
         self.apply_running_config();
+        self.emit(AppEvent::SourceChanged { device_id });
+    }
+
+    /// 源设备与输出集合互斥：新旧源都不是启用的输出时，换源不改变输出集合，
+    /// 可以不重启直接切换。
+    fn can_switch_source_live(&self, device_id: &str) -> bool {
+        let Some(routed) = self.routed_source.as_deref() else {
+            return false;
+        };
+        if !self.is_running || routed == device_id {
+            return false;
+        }
+        let cfg = self.config_manager.handle();
+        let cfg = cfg.read();
+        !cfg.outputs
+            .iter()
+            .any(|o| o.enabled && (o.device_id == device_id || o.device_id == routed))
+    }
+
+    /// 修改并保存 `device_id` 的输出设置；配置中还没有该输出时先以立体声
+    /// 默认设置添加。
+    fn update_output(
+        &self,
+        device_id: &str,
+        update: impl FnOnce(&mut Output),
+    ) -> anyhow::Result<()> {
+        self.config_manager.update(|cfg| {
+            let index = match cfg.outputs.iter().position(|o| o.device_id == device_id) {
+                Some(index) => index,
+                None => {
+                    cfg.outputs.push(Output {
+                        channel_mode: Some(ChannelMode::Stereo.as_config_str().to_string()),
+                        ..Output::new(device_id.to_string())
+                    });
+                    cfg.outputs.len() - 1
+                }
+            };
+            update(&mut cfg.outputs[index]);
+        })
     }
 
     pub fn set_output_enabled(&mut self, device_id: &str, enabled: bool) {
-        let device_id = device_id.to_string();
-        if let Err(e) = self.config_manager.update(|cfg| {
-            if let Some(output) = cfg.outputs.iter_mut().find(|o| o.device_id == device_id) {
-                output.enabled = enabled;
-            } else {
-                cfg.outputs.push(Output {
-                    device_id,
-                    enabled,
-                    channel_mode: Some(ChannelMode::Stereo.as_config_str().to_string()),
-                });
-            }
-        }) {
+        if let Err(e) = self.update_output(device_id, |output| output.enabled = enabled) {
             log::error!("Save output enabled state failed: {e}");
             return;
         }
         self.apply_running_config();
     }
 
+    /// 保存声道模式并应用到运行中的会话：混音路径的输出实时交叉淡化到新模式，
+    /// 其余输出重启路由。
     pub fn set_output_channel_mode(&mut self, device_id: &str, channel_mode: ChannelMode) {
-        let device_id = device_id.to_string();
-        if let Err(e) = self.config_manager.update(|cfg| {
-            if let Some(output) = cfg.outputs.iter_mut().find(|o| o.device_id == device_id) {
-                output.channel_mode = Some(channel_mode.as_config_str().to_string());
-            } else {
-                cfg.outputs.push(Output {
-                    device_id,
-                    enabled: false,
-                    channel_mode: Some(channel_mode.as_config_str().to_string()),
-                });
-            }
+        if let Err(e) = self.update_output(device_id, |output| {
+            output.channel_mode = Some(channel_mode.as_config_str().to_string());
         }) {
             log::error!("Save output channel mode failed: {e}");
             return;
         }
+        if !self.router.set_output_mode(device_id, channel_mode) {
+            self.apply_running_config();
+        }
+    }
+
+    /// 设置输出的自定义增益矩阵并切换到 `ChannelMode::Matrix`；`None` 清除矩阵
+    /// 并回到立体声。无效的矩阵会被拒绝，已保存的配置保持不变。
+    pub fn set_output_channel_matrix(
+        &mut self,
+        device_id: &str,
+        matrix: Option<ChannelMatrix>,
+    ) -> anyhow::Result<()> {
+        if let Some(matrix) = &matrix {
+            matrix.validate()?;
+        }
+        let mode = if matrix.is_some() {
+            ChannelMode::Matrix
+        } else {
+            ChannelMode::Stereo
+        };
+        self.update_output(device_id, |output| {
+            let was_matrix =
+                ChannelMode::from_config(output.channel_mode.as_deref()) == ChannelMode::Matrix;
+            if matrix.is_some() || was_matrix {
+                output.channel_mode = Some(mode.as_config_str().to_string());
+            }
+            output.channel_matrix = matrix;
+        })?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    pub fn set_output_downmix_lfe(&mut self, device_id: &str, include_lfe: bool) {
+        if let Err(e) = self.update_output(device_id, |output| output.downmix_lfe = include_lfe) {
+            log::error!("Save output downmix LFE option failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    pub fn set_output_eq_preset(&mut self, device_id: &str, preset: EqPreset) {
+        if let Err(e) = self.update_output(device_id, |output| output.eq_preset = preset) {
+            log::error!("Save output EQ preset failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 保存输出增益并应用到运行中的会话。
+    ///
+    /// 已有增益级的输出以短暂的渐变实时生效，否则用新配置重启路由。
+    pub fn set_output_gain_db(&mut self, device_id: &str, gain_db: f32) {
+        let gain_db = gain_db.clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+        if let Err(e) = self.update_output(device_id, |output| output.gain_db = gain_db) {
+            log::error!("Save output gain failed: {e}");
+            return;
+        }
+        if !self.router.set_output_gain(device_id, gain_db) {
+            self.apply_running_config();
+        }
+    }
+
+    /// 按音量滑块设置输出增益，`volume` 为线性倍数（1.0 保持原电平）。
+    /// 保存为 `gain_db`，生效方式同 [`Self::set_output_gain_db`]，
+    /// 播放中拖动滑块时电平实时变化。
+    pub fn set_output_volume(&mut self, device_id: &str, volume: f32) {
+        // 0 对应 -inf dB，由 set_output_gain_db 截到最小增益
+        self.set_output_gain_db(device_id, 20.0 * volume.max(0.0).log10());
+    }
+
+    /// 输出的音量滑块位置，见 [`Self::set_output_volume`]。
+    pub fn output_volume(&self, device_id: &str) -> f32 {
+        let gain_db = self
+            .config_manager
+            .handle()
+            .read()
+            .outputs
+            .iter()
+            .find(|o| o.device_id == device_id)
+            .map_or(0.0, |o| o.gain_db);
+        db_to_linear(gain_db)
+    }
+
+    /// 静音或取消静音一个输出，其他输出继续播放。
+    /// 有增益级的输出实时切换，否则重启路由。
+    pub fn set_output_muted(&mut self, device_id: &str, muted: bool) {
+        if let Err(e) = self.update_output(device_id, |output| output.muted = muted) {
+            log::error!("Save output mute failed: {e}");
+            return;
+        }
+        if !self.router.set_output_muted(device_id, muted) {
+            self.apply_running_config();
+        }
+    }
+
+    /// 将输出延迟 `delay_ms`，与其他输出对齐。
+    pub fn set_output_delay_ms(&mut self, device_id: &str, delay_ms: f32) {
+        let delay_ms = delay_ms.clamp(
+            *Output::DELAY_RANGE_MS.start(),
+            *Output::DELAY_RANGE_MS.end(),
+        );
+        if let Err(e) = self.update_output(device_id, |output| output.delay_ms = delay_ms) {
+            log::error!("Save output delay failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 设置输出中反相的声道（bit 0 为第一个声道）。
+    pub fn set_output_phase_invert(&mut self, device_id: &str, mask: u32) {
+        if let Err(e) = self.update_output(device_id, |output| output.phase_invert = mask) {
+            log::error!("Save output phase invert failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 设置输出各声道的电平微调，例如让环绕声输出的各音箱电平一致。
+    /// 超出范围的微调会被拒绝，已保存的配置保持不变。
+    pub fn set_output_channel_trim(
+        &mut self,
+        device_id: &str,
+        trim: ChannelTrim,
+    ) -> anyhow::Result<()> {
+        trim.validate()?;
+        self.update_output(device_id, |output| output.channel_trim = trim)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    pub fn set_output_crossfeed(&mut self, device_id: &str, preset: CrossfeedPreset) {
+        if let Err(e) = self.update_output(device_id, |output| output.crossfeed = preset) {
+            log::error!("Save output crossfeed failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 设置输出前置左右声道的中置/侧向增益。超出范围的增益会被拒绝，
+    /// 已保存的配置保持不变。
+    pub fn set_output_mid_side(
+        &mut self,
+        device_id: &str,
+        mid_side: MidSideSettings,
+    ) -> anyhow::Result<()> {
+        mid_side.validate()?;
+        self.update_output(device_id, |output| output.mid_side = mid_side)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    pub fn set_output_dither(&mut self, device_id: &str, mode: DitherMode) {
+        if let Err(e) = self.update_output(device_id, |output| output.dither = mode) {
+            log::error!("Save output dither failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// `None` 表示输出使用引擎的重采样器。
+    pub fn set_output_resampler(&mut self, device_id: &str, quality: Option<ResamplerQuality>) {
+        if let Err(e) = self.update_output(device_id, |output| output.resampler = quality) {
+            log::error!("Save output resampler failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    pub fn set_output_bass_role(&mut self, device_id: &str, role: BassRole) {
+        if let Err(e) = self.update_output(device_id, |output| output.bass_role = role) {
+            log::error!("Save output bass role failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 设置低音管理的分频点，截到支持的范围内。
+    pub fn set_bass_crossover_hz(&mut self, crossover_hz: f32) {
+        let range = BassManagement::CROSSOVER_RANGE_HZ;
+        let crossover_hz = crossover_hz.clamp(*range.start(), *range.end());
+        if let Err(e) = self
+            .config_manager
+            .update(|cfg| cfg.bass_management.crossover_hz = crossover_hz)
+        {
+            log::error!("Save bass crossover failed: {e}");
+            return;
+        }
+        self.apply_running_config();
+    }
+
+    /// 设置输出的砖墙限幅器。超出范围的设置会被拒绝，已保存的配置保持不变。
+    pub fn set_output_limiter(
+        &mut self,
+        device_id: &str,
+        limiter: LimiterSettings,
+    ) -> anyhow::Result<()> {
+        limiter.validate()?;
+        self.update_output(device_id, |output| output.limiter = limiter)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 替换输出的插件链。不是有效 CLAP 插件的槽位会被拒绝，已保存的配置保持不变。
+    pub fn set_output_plugins(
+        &mut self,
+        device_id: &str,
+        plugins: Vec<PluginSlot>,
+    ) -> anyhow::Result<()> {
+        for plugin in &plugins {
+            plugin.validate()?;
+        }
+        self.update_output(device_id, |output| output.plugins = plugins)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置输出 DSP 各级的顺序。重复列出某一级或在限幅器之后还有处理级的顺序
+    /// 会被拒绝。
+    pub fn set_output_dsp_chain(&mut self, device_id: &str, chain: DspChain) -> anyhow::Result<()> {
+        chain.validate()?;
+        self.update_output(device_id, |output| output.dsp_chain = chain)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置输出的响度归一化。超出范围的设置会被拒绝，已保存的配置保持不变。
+    pub fn set_output_loudness(
+        &mut self,
+        device_id: &str,
+        loudness: LoudnessSettings,
+    ) -> anyhow::Result<()> {
+        loudness.validate()?;
+        self.update_output(device_id, |output| output.loudness = loudness)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置作用于捕获信号的噪声门。超出范围的设置会被拒绝，已保存的配置保持不变。
+    pub fn set_noise_gate(&mut self, gate: NoiseGateSettings) -> anyhow::Result<()> {
+        gate.validate()?;
+        self.config_manager.update(|cfg| cfg.noise_gate = gate)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置路由启动时的淡入时长（0 为关闭）。
+    pub fn set_fade_in_ms(&mut self, fade_in_ms: f32) -> anyhow::Result<()> {
+        if !Config::FADE_IN_RANGE_MS.contains(&fade_in_ms) {
+            anyhow::bail!("fade-in {fade_in_ms} ms is out of range");
+        }
+        self.config_manager
+            .update(|cfg| cfg.fade_in_ms = fade_in_ms)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置 [`Router::levels`] 报告的峰值/RMS 表的窗口。
+    pub fn set_meter_window_ms(&mut self, window_ms: f32) -> anyhow::Result<()> {
+        if !Config::METER_WINDOW_RANGE_MS.contains(&window_ms) {
+            anyhow::bail!("meter window {window_ms} ms is out of range");
+        }
+        self.config_manager
+            .update(|cfg| cfg.meter_window_ms = window_ms)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 开始向返回的 receiver 发送 [`Router::levels`] 的电平，路由运行期间每秒
+    /// [`LEVEL_FRAMES_PER_SEC`](crate::levels::LEVEL_FRAMES_PER_SEC) 次。
+    /// 替换之前的电平流。worker 从此刻起计量，直到 [`Self::disable_level_stream`]。
+    pub fn enable_level_stream(&mut self) -> Receiver<RouterLevels> {
+        self.level_stream = None;
+        self.router.start_metering();
+        let (stream, levels) = LevelStream::spawn(self.router.clone());
+        self.level_stream = Some(stream);
+        levels
+    }
+
+    pub fn disable_level_stream(&mut self) {
+        self.level_stream = None;
+        self.router.stop_metering();
+    }
+
+    /// 开启或关闭 [`Self::latest_spectrum`] 返回的频谱；重启路由后生效。
+    pub fn set_spectrum_enabled(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.config_manager.update(|cfg| cfg.spectrum = enabled)?;
+        if !enabled {
+            self.spectrum = None;
+        }
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 替换 Downmix/Upmix 的声道电平。超出范围的电平会被拒绝，已保存的配置保持不变。
+    pub fn set_mix_levels(&mut self, levels: MixLevels) -> anyhow::Result<()> {
+        levels.validate()?;
+        self.config_manager.update(|cfg| cfg.mix_levels = levels)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 设置音频引擎参数；运行中的会话用新参数重启。
+    pub fn set_engine_settings(&mut self, engine: EngineSettings) -> anyhow::Result<()> {
+        self.config_manager.update(|cfg| cfg.engine = engine)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// `None` 表示输出使用引擎的溢出策略。
+    pub fn set_output_overflow_policy(&mut self, device_id: &str, policy: Option<OverflowPolicy>) {
+        if let Err(e) = self.update_output(device_id, |output| output.overflow_policy = policy) {
+            log::error!("Save output overflow policy failed: {e}");
+            return;
+        }
         self.apply_running_config();
     }
 
@@ -168,15 +976,24 @@ impl AppController {
             None => return,
         };
         let running_count = router_cfg.targets.len();
+        let source_id = router_cfg.source_device_id.clone();
 
         self.status_text = self.i18n.t("Starting").to_string();
         match self.router.start(router_cfg) {
             Ok(()) => {
                 self.is_running = true;
+                self.watch_source_volume(source_id.as_deref());
+                self.mute_source(source_id.as_deref());
+                self.routed_source = source_id;
                 self.status_text = self
                     .i18n
                     .t("RunningOn")
                     .replace("{count}", &running_count.to_string());
+                self.emit(AppEvent::RoutingStarted {
+                    output_count: running_count as u32,
+                });
+                self.replace_default_device();
+                self.record_runtime_state();
             }
             Err(e) => {
                 self.is_running = false;
@@ -186,12 +1003,31 @@ impl AppController {
         }
     }
 
+    /// [`Self::start_routing`] 是否有源设备和至少一个已连接的输出可路由。
+    pub fn can_start_routing(&self) -> bool {
+        let Some(source_id) = &self.selected_source else {
+            return false;
+        };
+        let cfg = self.config_manager.handle();
+        let cfg = cfg.read();
+        self.devices.iter().any(|d| {
+            &d.id != source_id && cfg.outputs.iter().any(|o| o.device_id == d.id && o.enabled)
+        })
+    }
+
     pub fn stop_routing(&mut self) {
         self.status_text = self.i18n.t("Stopping").to_string();
         match self.router.stop() {
             Ok(()) => {
                 self.is_running = false;
+                self.restart_pending = false;
                 self.status_text = self.i18n.t("StatusReady").to_string();
+                self.restore_default_devices();
+                self.unmute_source();
+                self.source_volume = None;
+                self.routed_source = None;
+                self.emit(AppEvent::RoutingStopped);
+                self.record_runtime_state();
             }
             Err(e) => {
                 self.is_running = self.router.is_running();
@@ -201,9 +1037,557 @@ impl AppController {
         }
     }
 
+    /// 用最新配置重启运行中的会话：淡出、停止，再按配置的淡入重新启动。
+    /// 一次修改多项设置后用它代替停止 + 启动。
+    ///
+    /// 不等待淡出：worker 报告淡出结束后由 [`Self::poll_router_events`] 完成重启。
+    pub fn restart_routing(&mut self) -> anyhow::Result<()> {
+        if !self.is_running {
+            anyhow::bail!("routing is not running");
+        }
+        self.status_text = self.i18n.t("Restarting").to_string();
+        if self.router.fade_out(RESTART_FADE_OUT_MS) {
+            self.restart_pending = true;
+            return Ok(());
+        }
+        // worker 已不在运行，没有可淡出的会话
+        self.finish_restart()
+    }
+
+    /// 淡出结束后停止会话，并按最新配置重新启动。
+    fn finish_restart(&mut self) -> anyhow::Result<()> {
+        self.restart_pending = false;
+        if let Err(e) = self.router.stop() {
+            self.is_running = self.router.is_running();
+            self.status_text = format!("Error: {e}");
+            return Err(e.context("stopping routing for the restart failed"));
+        }
+        self.is_running = false;
+
+        self.start_routing();
+        if !self.is_running {
+            // 没能重新开始时按停止处理，恢复默认设备和源的静音
+            let reason = self.status_text.clone();
+            self.restore_default_devices();
+            self.unmute_source();
+            self.source_volume = None;
+            self.routed_source = None;
+            self.emit(AppEvent::RoutingStopped);
+            self.record_runtime_state();
+            anyhow::bail!("routing did not restart: {reason}");
+        }
+        Ok(())
+    }
+
+    /// 设置路由期间设为 Windows 默认设备的输出设备；`None` 不改动系统默认设备。
+    pub fn set_default_device_while_routing(
+        &mut self,
+        device_id: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.config_manager
+            .update(|cfg| cfg.default_device_while_routing = device_id)?;
+        if self.is_running {
+            self.restore_default_devices();
+            self.replace_default_device();
+        }
+        Ok(())
+    }
+
+    pub fn source_selection(&self) -> SourceSelection {
+        self.config_manager.handle().read().source.clone()
+    }
+
+    /// 从 `role` 当前的默认设备路由而不是选中的源设备，默认设备变化时重新路由；
+    /// `None` 保留当前作为源的设备。
+    pub fn set_source_role(&mut self, role: Option<SourceRole>) -> anyhow::Result<()> {
+        let source = match role {
+            Some(role) => SourceSelection::Default(role),
+            None => SourceSelection::Device(self.selected_source.clone().unwrap_or_default()),
+        };
+        self.config_manager.update(|cfg| cfg.source = source)?;
+        self.selected_source = Self::source_for(&self.config_manager.handle().read().source);
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// `source` 当前对应的设备。
+    fn source_for(source: &SourceSelection) -> Option<String> {
+        match source {
+            SourceSelection::Default(role) => Self::default_source_id(*role),
+            SourceSelection::Device(id) => (!id.is_empty()).then(|| id.clone()),
+        }
+    }
+
+    /// `role` 当前的默认输出设备，作为源使用。
+    fn default_source_id(role: SourceRole) -> Option<String> {
+        match get_default_output_device_for_role(role.into()) {
+            Ok(device) => Some(device.id),
+            Err(e) => {
+                log::warn!("No default {role:?} device to route from: {e}");
+                None
+            }
+        }
+    }
+
+    /// 执行本实例或之后启动的实例命令行中给出的命令。设备列表写入日志。
+    pub fn run_cli_command(&mut self, command: &CliCommand) -> anyhow::Result<()> {
+        match command {
+            CliCommand::ActivateProfile(name) => self.activate_profile(name)?,
+            CliCommand::StartRouting if self.is_running => {}
+            CliCommand::StartRouting => {
+                self.start_routing();
+                if !self.is_running {
+                    anyhow::bail!("routing did not start: {}", self.status_text);
+                }
+            }
+            CliCommand::StopRouting if self.is_running => self.stop_routing(),
+            CliCommand::StopRouting => {}
+            CliCommand::RestartRouting if self.is_running => self.restart_routing()?,
+            CliCommand::RestartRouting => {}
+            CliCommand::ListDevices => {
+                let list = cli::format_device_list(&self.devices);
+                log::info!("Output devices:\n{list}")
+            }
+        }
+        Ok(())
+    }
+
+    /// 配置中绑定的全局热键，供 GUI 注册。
+    pub fn hotkey_bindings(&self) -> Vec<HotkeyBinding> {
+        self.config_manager.handle().read().hotkeys.bindings()
+    }
+
+    /// 执行按下的全局热键对应的操作。
+    pub fn run_hotkey(&mut self, action: &HotkeyAction) -> anyhow::Result<()> {
+        match action {
+            HotkeyAction::ToggleRouting if self.is_running => self.stop_routing(),
+            HotkeyAction::ToggleRouting => self.start_routing(),
+            HotkeyAction::MuteOutputs => {
+                let all_muted = {
+                    let cfg = self.config_manager.handle();
+                    let cfg = cfg.read();
+                    cfg.outputs.iter().filter(|o| o.enabled).all(|o| o.muted)
+                };
+                self.update_enabled_outputs(|router, output| {
+                    output.muted = !all_muted;
+                    router.set_output_muted(&output.device_id, output.muted)
+                })?;
+            }
+            HotkeyAction::NextProfile => {
+                let names = self.profile_names();
+                let active = self.active_profile();
+                let next = match names.iter().position(|n| Some(n) == active.as_ref()) {
+                    Some(i) => names.get((i + 1) % names.len()),
+                    None => names.first(),
+                };
+                if let Some(next) = next {
+                    self.activate_profile(next)?;
+                }
+            }
+            HotkeyAction::VolumeUp | HotkeyAction::VolumeDown => {
+                let step = match action {
+                    HotkeyAction::VolumeUp => HOTKEY_VOLUME_STEP_DB,
+                    _ => -HOTKEY_VOLUME_STEP_DB,
+                };
+                self.update_enabled_outputs(|router, output| {
+                    output.gain_db = (output.gain_db + step).clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+                    router.set_output_gain(&output.device_id, output.gain_db)
+                })?;
+            }
+            HotkeyAction::ActivateProfile(name) => self.activate_profile(name)?,
+        }
+        Ok(())
+    }
+
+    /// 对所有已启用的输出执行 `f` 并保存；`f` 返回能否实时生效，
+    /// 有任一输出不能时重启路由一次。
+    fn update_enabled_outputs(
+        &mut self,
+        mut f: impl FnMut(&Router, &mut Output) -> bool,
+    ) -> anyhow::Result<()> {
+        let router = self.router.clone();
+        let mut live = true;
+        self.config_manager.update(|cfg| {
+            for output in cfg.outputs.iter_mut().filter(|o| o.enabled) {
+                live &= f(&router, output);
+            }
+        })?;
+        if !live {
+            self.apply_running_config();
+        }
+        Ok(())
+    }
+
+    /// 已保存的配置档案名称。
+    pub fn profile_names(&self) -> Vec<String> {
+        let cfg = self.config_manager.handle();
+        let cfg = cfg.read();
+        cfg.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn active_profile(&self) -> Option<String> {
+        self.config_manager.handle().read().active_profile.clone()
+    }
+
+    /// 将当前的源、输出和 DSP 设置保存为档案 `name`。
+    pub fn create_profile(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.create_profile(name))
+    }
+
+    pub fn clone_profile(&mut self, from: &str, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.clone_profile(from, name))
+    }
+
+    pub fn delete_profile(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.delete_profile(name))
+    }
+
+    /// 切换到档案 `name`，当前设置保留在之前的档案中；路由运行中时重启路由。
+    pub fn activate_profile(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.activate_profile(name))?;
+        self.selected_source = Self::source_for(&self.config_manager.handle().read().source);
+        self.emit(AppEvent::ProfileActivated {
+            name: name.to_string(),
+        });
+        self.apply_running_config();
+        if self.is_running {
+            self.record_runtime_state();
+        }
+        Ok(())
+    }
+
+    /// 将当前的源和已启用的输出保存为预设 `name`。
+    pub fn save_preset(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.save_preset(name))
+    }
+
+    pub fn delete_preset(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.delete_preset(name))
+    }
+
+    /// 切换到预设 `name` 的源和输出；路由运行中时重启路由。
+    pub fn apply_preset(&mut self, name: &str) -> anyhow::Result<()> {
+        self.update_config_checked(|cfg| cfg.apply_preset(name))?;
+        self.selected_source = Self::source_for(&self.config_manager.handle().read().source);
+        self.apply_running_config();
+        if self.is_running {
+            self.record_runtime_state();
+        }
+        Ok(())
+    }
+
+    pub fn export_config(&self, path: &Path, format: ConfigFormat) -> anyhow::Result<()> {
+        self.config_manager.export(path, format)
+    }
+
+    /// 写出设置格式的 JSON Schema，见 [`config::schema`]。
+    pub fn export_config_schema(&self, path: &Path) -> anyhow::Result<()> {
+        config::schema::write_json_schema(path)
+    }
+
+    /// 会让 [`Self::import_config`] 导入 `path` 失败的问题，供导入对话框在替换
+    /// 任何设置之前列出。
+    pub fn check_config_file(&self, path: &Path) -> Vec<ValidationIssue> {
+        ConfigManager::validate_file(path)
+    }
+
+    /// 用导出的配置文件替换全部设置并应用；路由运行中时重启路由。
+    pub fn import_config(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.config_manager.import(path)?;
+        self.sync_from_config();
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 已保存的设置备份编号，最新的在前。
+    pub fn config_backups(&self) -> Vec<u32> {
+        self.config_manager.backups()
+    }
+
+    /// 恢复设置备份 `n`，应用方式同导入。
+    pub fn restore_config_backup(&mut self, n: u32) -> anyhow::Result<()> {
+        self.config_manager.restore_backup(n)?;
+        self.sync_from_config();
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 将 `scope` 中的设置恢复为默认值，应用方式同导入；开机自启项跟随
+    /// 重置后的设置。
+    pub fn reset_config(&mut self, scope: ResetScope) -> anyhow::Result<()> {
+        self.config_manager.reset(scope)?;
+        self.sync_from_config();
+        if scope != ResetScope::Routing {
+            crate::autostart::set_autostart(self.draft_general.start_with_windows)?;
+        }
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 写出因写入延迟尚未保存的配置修改，例如在进程退出前。
+    pub fn flush_config(&self) {
+        if let Err(e) = self.config_manager.flush() {
+            log::error!("Save config failed: {e:#}");
+        }
+    }
+
+    /// 退出应用前调用：停止路由，撤销路由期间对系统的改动（源设备静音、
+    /// 替换的默认设备），并保存尚未写入的配置。不更新运行状态记录，下次启动仍按退出前的
+    /// 路由状态恢复。
+    pub fn shutdown(&mut self) {
+        if self.router.is_running()
+            && let Err(e) = self.router.stop()
+        {
+            log::error!("Stop routing on exit failed: {e}");
+        }
+        self.is_running = false;
+        self.restore_default_devices();
+        self.unmute_source();
+        self.flush_config();
+    }
+
+    /// 配置文件在应用外被修改时重新加载，并按新配置重启路由。
+    fn reload_config_if_changed(&mut self) {
+        match self.config_manager.reload_if_changed() {
+            Ok(true) => {
+                log::info!("Settings file changed on disk, reloaded");
+                self.sync_from_config();
+                self.emit(AppEvent::ConfigReloaded);
+                self.apply_running_config();
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("Ignoring invalid settings file change: {e:#}"),
+        }
+    }
+
+    /// 整个配置被替换后，同步控制器中缓存的选择和设置草稿。
+    fn sync_from_config(&mut self) {
+        let cfg = self.config_manager.handle().read().clone();
+        self.selected_source = Self::source_for(&cfg.source);
+        if cfg.general.language != self.i18n.locale() {
+            self.i18n.set_locale(&cfg.general.language);
+        }
+        self.draft_general = cfg.general;
+        self.draft_startup = cfg.startup;
+    }
+
+    /// 在配置副本上执行 `f`，成功后才写回并保存。
+    fn update_config_checked(
+        &mut self,
+        f: impl FnOnce(&mut Config) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let mut updated = self.config_manager.handle().read().clone();
+        f(&mut updated)?;
+        self.config_manager.update(|cfg| *cfg = updated)
+    }
+
+    /// 让所有路由输出跟随源设备的 Windows 音量和静音；重启路由后生效。
+    pub fn set_mirror_source_volume(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.config_manager
+            .update(|cfg| cfg.mirror_source_volume = enabled)?;
+        self.apply_running_config();
+        Ok(())
+    }
+
+    /// 开启跟随源音量时，按源设备当前音量设置主增益并监听后续变化。
+    fn watch_source_volume(&mut self, source_id: Option<&str>) {
+        self.source_volume = None;
+        let Some(source_id) = source_id else {
+            return;
+        };
+        if !self.config_manager.handle().read().mirror_source_volume {
+            return;
+        }
+        match get_endpoint_volume(source_id) {
+            Ok(volume) => {
+                self.router.set_master_gain(self.source_gain_db(volume));
+            }
+            Err(e) => log::warn!("Read source volume failed: {e}"),
+        }
+        match watch_endpoint_volume(source_id) {
+            Ok(watch) => self.source_volume = Some(watch),
+            Err(e) => log::warn!("Watch source volume failed: {e}"),
+        }
+    }
+
+    /// 把源设备最近一次音量变化应用到主增益。
+    fn apply_source_volume(&mut self) {
+        let Some((_, rx)) = &self.source_volume else {
+            return;
+        };
+        if let Some(volume) = rx.try_iter().last() {
+            self.router.set_master_gain(self.source_gain_db(volume));
+        }
+    }
+
+    /// 源设备音量对应的主增益。源设备由本程序静音时忽略静音状态，
+    /// 否则输出会跟着一起静音。
+    fn source_gain_db(&self, volume: EndpointVolume) -> f32 {
+        volume_scalar_to_db(volume.scalar, volume.muted && self.muted_source.is_none())
+    }
+
+    /// 路由期间静音源设备，避免它与输出一起发声，停止时取消静音；
+    /// 对运行中的会话立即生效。
+    pub fn set_mute_source_while_routing(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.config_manager
+            .update(|cfg| cfg.mute_source_while_routing = enabled)?;
+        if self.is_running {
+            if enabled {
+                let source_id = self.routed_source.clone();
+                self.mute_source(source_id.as_deref());
+            } else {
+                self.unmute_source();
+            }
+        }
+        Ok(())
+    }
+
+    /// 开启路由时静音源设备。已经静音的设备不记录，停止时也不会取消静音；
+    /// 配置变更导致的重启保留已记下的设备。
+    fn mute_source(&mut self, source_id: Option<&str>) {
+        if self.muted_source.as_deref() == source_id {
+            return;
+        }
+        self.unmute_source();
+        let Some(source_id) = source_id.filter(|id| !id.is_empty()) else {
+            return;
+        };
+        if !self
+            .config_manager
+            .handle()
+            .read()
+            .mute_source_while_routing
+        {
+            return;
+        }
+        match get_endpoint_volume(source_id) {
+            Ok(volume) if volume.muted => return,
+            Ok(_) => {}
+            Err(e) => log::warn!("Read source mute state failed: {e}"),
+        }
+        // 先记下再静音：音量回调看到静音时已知道是本程序所为；
+        // 同时写入磁盘，崩溃后下次启动仍能取消静音
+        self.muted_source = Some(source_id.to_string());
+        self.record_system_changes();
+        if let Err(e) = set_endpoint_mute(source_id, true) {
+            log::error!("Mute source device failed: {e}");
+            self.muted_source = None;
+            self.record_system_changes();
+        }
+    }
+
+    fn unmute_source(&mut self) {
+        let Some(source_id) = self.muted_source.take() else {
+            return;
+        };
+        if let Err(e) = set_endpoint_mute(&source_id, false) {
+            log::error!("Unmute source device failed: {e}");
+        }
+        self.record_system_changes();
+    }
+
+    /// 把配置的设备设为所有角色的系统默认设备，并记下原来的默认设备。
+    /// 配置变更导致的重启不会覆盖已记下的设备。
+    fn replace_default_device(&mut self) {
+        if !self.replaced_defaults.is_empty() {
+            return;
+        }
+        let Some(target) = self
+            .config_manager
+            .handle()
+            .read()
+            .default_device_while_routing
+            .clone()
+        else {
+            return;
+        };
+        for role in DeviceRole::ALL {
+            let previous = match get_default_output_device_for_role(role) {
+                Ok(device) if device.id == target => continue,
+                Ok(device) => device.id,
+                Err(e) => {
+                    log::warn!("Query default {role:?} device failed: {e}");
+                    continue;
+                }
+            };
+            match set_default_output_device(&target, role) {
+                Ok(()) => self.replaced_defaults.push((role, previous)),
+                Err(e) => log::error!("Set default {role:?} device failed: {e}"),
+            }
+        }
+        if !self.replaced_defaults.is_empty() {
+            self.record_system_changes();
+        }
+    }
+
+    fn restore_default_devices(&mut self) {
+        if self.replaced_defaults.is_empty() {
+            return;
+        }
+        for (role, device_id) in std::mem::take(&mut self.replaced_defaults) {
+            if let Err(e) = set_default_output_device(&device_id, role) {
+                log::error!("Restore default {role:?} device failed: {e}");
+            }
+        }
+        self.record_system_changes();
+    }
+
+    /// 在一个运行中的输出上播放校准用粉红噪声，替代路由的音频；
+    /// 见 [`Router::play_pink_noise`]。
+    pub fn play_pink_noise(&mut self, device_id: &str, level_db: f32) -> anyhow::Result<()> {
+        if !self.router.play_pink_noise(device_id, level_db) {
+            return Err(anyhow::anyhow!("output {device_id} is not running"));
+        }
+        Ok(())
+    }
+
+    pub fn stop_pink_noise(&mut self) {
+        self.router.stop_pink_noise();
+    }
+
+    /// 在输出 `device_id` 上播放一段短测试音（无论路由是否运行），方便用户确认
+    /// 设备。播放开始后返回；见 [`playback::play_test_tone`]。
+    pub fn play_test_tone(&self, device_id: &str) -> anyhow::Result<()> {
+        if !self.devices.iter().any(|d| d.id == device_id) {
+            anyhow::bail!("output device {device_id} is not available");
+        }
+        let device_id = device_id.to_string();
+        // 播放会阻塞约一秒，不占用调用方（通常是 UI 线程）
+        std::thread::Builder::new()
+            .name("test-tone".into())
+            .spawn(move || {
+                if let Err(e) = playback::play_test_tone(&device_id) {
+                    log::warn!("Test tone on {device_id} failed: {e:#}");
+                }
+            })?;
+        Ok(())
+    }
+
+    /// 测量路由给每个运行中的输出增加的大致延迟，按设备分项；
+    /// 见 [`Router::latency`]。
+    pub fn run_latency_test(&self) -> anyhow::Result<Vec<OutputLatency>> {
+        if !self.is_running {
+            anyhow::bail!("routing is not running");
+        }
+        let latency = self.router.latency();
+        for output in &latency {
+            log::info!(
+                "Latency to {}: {:.1} ms (capture {:.1}, processing {:.1}, buffer {:.1}, engine {:.1})",
+                output.device_id,
+                output.total_ms,
+                output.capture_ms,
+                output.processing_ms,
+                output.buffer_ms,
+                output.engine_ms
+            );
+        }
+        Ok(latency)
+    }
+
     pub fn begin_settings_edit(&mut self) {
         let cfg = self.config_manager.handle().read().clone();
         self.draft_general = cfg.general;
+        self.draft_startup = cfg.startup;
     }
 
     pub fn nav_pane_expanded(&self) -> bool {
@@ -226,11 +1610,53 @@ impl AppController {
         self.config_manager.handle().read().general.close_to_tray
     }
 
+    /// 检查更新时提供哪个渠道的版本；传给 [`crate::update::check_for_update`]。
+    pub fn update_channel(&self) -> UpdateChannel {
+        self.config_manager.handle().read().general.update_channel
+    }
+
+    /// 版本和构建信息，例如用于关于页面。`version` 为应用自身的版本，
+    /// 只有前端 crate 知道。
+    pub fn app_info(&self, version: &str) -> AppInfo {
+        AppInfo::new(version, self.config_manager.path().to_path_buf())
+    }
+
+    /// 在文件管理器中打开 `settings.toml` 和 `logs` 文件夹所在的目录，
+    /// 设置文件存在时将其选中。
+    pub fn open_config_folder(&self) -> anyhow::Result<()> {
+        use anyhow::Context as _;
+
+        let path = self.config_manager.path();
+        let dir = path
+            .parent()
+            .context("settings path has no parent folder")?;
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+
+            let mut command = std::process::Command::new("explorer");
+            if path.exists() {
+                // explorer 自行解析命令行：/select, 后的路径要整体加引号
+                command.raw_arg(format!("/select,\"{}\"", path.display()));
+            } else {
+                command.arg(dir);
+            }
+            command.spawn().context("failed to start Explorer")?;
+        }
+        #[cfg(not(windows))]
+        std::process::Command::new("xdg-open")
+            .arg(dir)
+            .spawn()
+            .context("failed to start the file manager")?;
+        Ok(())
+    }
+
     pub fn save_general_config(&mut self) -> Option<String> {
         let new_language = self.draft_general.language.clone();
 
         if let Err(e) = self.config_manager.update(|cfg| {
             cfg.general = self.draft_general.clone();
+            cfg.startup = self.draft_startup;
         }) {
             log::error!("Save general config failed: {e}");
             return None;
@@ -250,25 +1676,42 @@ impl AppController {
         None
     }
 
+    /// 保存输出列表；源不跟随默认设备时同时保存选中的源设备。
     pub fn save_routing_config(&mut self) {
-        let source_id = self.selected_source.clone().unwrap_or_default();
-        let outputs: Vec<Output> = self
+        let source = match self.config_manager.handle().read().source.clone() {
+            SourceSelection::Device(_) => {
+                SourceSelection::Device(self.selected_source.clone().unwrap_or_default())
+            }
+            following => following,
+        };
+        self.save_routing(source);
+    }
+
+    fn save_routing(&mut self, source: SourceSelection) {
+        // 跟随默认设备时源随时可能变化，所有设备都保留输出配置
+        let source_id = source.device_id().unwrap_or_default().to_string();
+        let cfg = self.config_manager.handle().read().clone();
+        let mut outputs: Vec<Output> = self
             .devices
             .iter()
             .filter(|d| d.id != source_id)
             .map(|d| {
-                let cfg = self.config_manager.handle().read().clone();
-                let existing = cfg.outputs.iter().find(|o| o.device_id == d.id);
-                Output {
-                    device_id: d.id.clone(),
-                    enabled: existing.map(|o| o.enabled).unwrap_or(false),
-                    channel_mode: existing.and_then(|o| o.channel_mode.clone()),
-                }
+                cfg.outputs
+                    .iter()
+                    .find(|o| o.device_id == d.id)
+                    .cloned()
+                    .unwrap_or_else(|| Output::new(d.id.clone()))
             })
             .collect();
+        // 当前不在的设备保留原配置，接回后继续使用
+        outputs.extend(
+            cfg.outputs
+                .into_iter()
+                .filter(|o| o.missing_since.is_some() && o.device_id != source_id),
+        );
 
         if let Err(e) = self.config_manager.update(|cfg| {
-            cfg.source_device_id = source_id;
+            cfg.source = source;
             cfg.outputs = outputs;
         }) {
             log::error!("Save routing config failed: {e}");
@@ -284,15 +1727,12 @@ impl AppController {
     }
 
     fn build_router_config(&mut self) -> Option<RouterConfig> {
-        let source_id = match &self.selected_source {
-            Some(id) if !id.is_empty() => id.clone(),
-            _ => {
-                self.status_text = self.i18n.t("SelectDevice").to_string();
-                return None;
-            }
+        let cfg = self.config_manager.handle().read().clone();
+        let Some(source_id) = Self::source_for(&cfg.source) else {
+            self.status_text = self.i18n.t("SelectDevice").to_string();
+            return None;
         };
 
-        let cfg = self.config_manager.handle().read().clone();
         let targets: Vec<RouterTarget> = self
             .devices
             .iter()
@@ -303,10 +1743,7 @@ impl AppController {
                 cfg.outputs
                     .iter()
                     .find(|o| o.device_id == d.id && o.enabled)
-                    .map(|o| RouterTarget {
-                        device_id: d.id.clone(),
-                        channel_mode: ChannelMode::from_config(o.channel_mode.as_deref()),
-                    })
+                    .map(|o| RouterTarget::from_output(o, &cfg.engine))
             })
             .collect();
 
@@ -317,7 +1754,17 @@ impl AppController {
 
         Some(RouterConfig {
             source_device_id: Some(source_id),
+            source_role: cfg.source.role(),
             targets,
+            affinity: cfg.affinity,
+            engine: cfg.engine,
+            bass_management: cfg.bass_management,
+            mix_levels: cfg.mix_levels,
+            noise_gate: cfg.noise_gate,
+            fade_in_ms: cfg.fade_in_ms,
+            meter_window_ms: cfg.meter_window_ms,
+            spectrum: cfg.spectrum,
+            mirror_source_volume: cfg.mirror_source_volume,
         })
     }
 
@@ -333,6 +1780,7 @@ impl AppController {
             return;
         }
         self.is_running = false;
+        self.restart_pending = false;
 
         if self.build_router_config().is_some() {
             self.start_routing();
@@ -350,36 +1798,95 @@ impl AppController {
     }
 
     fn start_auto_route_if_enabled(&mut self) {
-        let cfg = self.config_manager.handle().read().clone();
-        if !cfg.general.auto_route || cfg.source_device_id.is_empty() {
-            return;
+        if self.config_manager.handle().read().startup.auto_route {
+            self.start_routing();
         }
+    }
 
-        let enabled_targets: Vec<RouterTarget> = cfg
-            .outputs
+    /// 记录路由是否在运行及当前配置方案；只在用户启停路由时调用，
+    /// 退出应用不改变记录。
+    fn record_runtime_state(&self) {
+        let state = RuntimeState {
+            routing_active: self.is_running,
+            profile: self.active_profile(),
+            muted_source: self.muted_source.clone(),
+            replaced_defaults: self.replaced_defaults_record(),
+        };
+        if let Err(e) = self.config_manager.save_runtime_state(&state) {
+            log::warn!("Save runtime state failed: {e}");
+        }
+    }
+
+    /// 记下尚未撤销的系统改动（源设备静音、替换的默认设备），不改变记录中的
+    /// 路由状态。应用崩溃后，下次启动据此撤销。
+    fn record_system_changes(&self) {
+        let mut state = self.config_manager.runtime_state().unwrap_or_default();
+        state.muted_source = self.muted_source.clone();
+        state.replaced_defaults = self.replaced_defaults_record();
+        if let Err(e) = self.config_manager.save_runtime_state(&state) {
+            log::warn!("Save runtime state failed: {e}");
+        }
+    }
+
+    fn replaced_defaults_record(&self) -> Vec<ReplacedDefault> {
+        self.replaced_defaults
             .iter()
-            .filter(|o| o.enabled)
-            .map(|o| RouterTarget {
-                device_id: o.device_id.clone(),
-                channel_mode: ChannelMode::from_config(o.channel_mode.as_deref()),
+            .map(|(role, device_id)| ReplacedDefault {
+                role: (*role).into(),
+                device_id: device_id.clone(),
             })
-            .collect();
+            .collect()
+    }
 
-        if enabled_targets.is_empty() {
+    /// 上次运行没能正常退出时，撤销它留下的系统改动。
+    fn undo_leftover_system_changes(&mut self) {
+        let Some(mut state) = self.config_manager.runtime_state() else {
             return;
+        };
+        if state.muted_source.is_none() && state.replaced_defaults.is_empty() {
+            return;
+        }
+        if let Some(source_id) = state.muted_source.take() {
+            log::info!("Unmuting source {source_id} left muted by the last session");
+            if let Err(e) = set_endpoint_mute(&source_id, false) {
+                log::warn!("Unmute source device failed: {e}");
+            }
         }
+        for replaced in std::mem::take(&mut state.replaced_defaults) {
+            let role = DeviceRole::from(replaced.role);
+            log::info!(
+                "Restoring default {role:?} device {} replaced by the last session",
+                replaced.device_id
+            );
+            if let Err(e) = set_default_output_device(&replaced.device_id, role) {
+                log::warn!("Restore default {role:?} device failed: {e}");
+            }
+        }
+        if let Err(e) = self.config_manager.save_runtime_state(&state) {
+            log::warn!("Save runtime state failed: {e}");
+        }
+    }
 
-        let running_count = enabled_targets.len();
-        let router_cfg = RouterConfig {
-            source_device_id: Some(cfg.source_device_id.clone()),
-            targets: enabled_targets,
+    /// 开启“恢复上次状态”且有记录时，切回记录中的配置方案并按记录决定是否开始路由。
+    /// 返回 `true` 表示已按记录处理，不再执行启动时自动路由。
+    fn resume_last_state(&mut self) -> bool {
+        let general = self.config_manager.handle().read().general.clone();
+        if !general.resume_last_state {
+            return false;
+        }
+        let Some(state) = self.config_manager.runtime_state() else {
+            return false;
         };
-        if self.router.start(router_cfg).is_ok() {
-            self.is_running = true;
-            self.status_text = self
-                .i18n
-                .t("RunningOn")
-                .replace("{count}", &running_count.to_string());
+        if let Some(profile) = &state.profile
+            && self.active_profile().as_ref() != Some(profile)
+            && let Err(e) = self.activate_profile(profile)
+        {
+            log::warn!("Cannot resume profile {profile:?}: {e}");
+        }
+        if state.routing_active {
+            log::info!("Resuming routing from the last session");
+            self.start_routing();
         }
+        true
     }
 }
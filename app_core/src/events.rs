@@ -0,0 +1,293 @@
+//! 对外公开的事件契约。
+//!
+//! 所有发往 GUI 以外消费者（脚本、Stream Deck、webhook 等）的事件名称和负载
+//! 都在这里定义。负载派生 `specta::Type`，可导出为 TypeScript 定义。
+//!
+//! 兼容性约定：
+//! - 事件名称一经发布不再修改；
+//! - 负载只允许新增字段，删除/重命名字段或改变类型时必须提升 [`EVENT_SCHEMA_VERSION`]。
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 事件负载的 schema 版本，随 [`EventEnvelope`] 一起发送。
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+pub const ROUTING_STARTED: &str = "routing_started";
+pub const ROUTING_STOPPED: &str = "routing_stopped";
+pub const ROUTING_RESTARTING: &str = "routing_restarting";
+pub const ROUTING_RESTARTED: &str = "routing_restarted";
+pub const ROUTING_FAILED: &str = "routing_failed";
+pub const ROUTING_STOPPED_UNEXPECTEDLY: &str = "routing_stopped_unexpectedly";
+pub const GLITCH_DETECTED: &str = "glitch_detected";
+pub const CLIPPING_DETECTED: &str = "clipping_detected";
+pub const DEVICES_CHANGED: &str = "devices_changed";
+pub const PROFILE_ACTIVATED: &str = "profile_activated";
+pub const SOURCE_CHANGED: &str = "source_changed";
+pub const CONFIG_RELOADED: &str = "config_reloaded";
+pub const OUTPUT_CONNECTED: &str = "output_connected";
+pub const OUTPUT_DISCONNECTED: &str = "output_disconnected";
+pub const CONFIG_RECOVERED: &str = "config_recovered";
+
+/// 应用事件及其负载。序列化为 `{"type": "<name>", "payload": {...}}`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum AppEvent {
+    /// 路由已启动
+    RoutingStarted { output_count: u32 },
+    /// 路由已停止（用户操作）
+    RoutingStopped,
+    /// 设备变更，路由正在重启
+    RoutingRestarting,
+    /// 设备变更后路由已恢复
+    RoutingRestarted,
+    /// 路由失败且无法自动恢复
+    RoutingFailed { error: String },
+    /// 路由线程意外退出（如 panic），路由已停止
+    RoutingStoppedUnexpectedly { reason: String },
+    /// 检测窗口内的捕获断续次数超过阈值
+    GlitchDetected { count: u64, window_secs: u64 },
+    /// 某个输出在检测窗口内的削波采样数超过阈值
+    ClippingDetected {
+        device_id: String,
+        count: u64,
+        window_secs: u64,
+    },
+    /// 可用输出设备列表发生变化
+    DevicesChanged { device_count: u32 },
+    /// 切换到了另一个配置方案
+    ProfileActivated { name: String },
+    /// 选择了另一个源设备；路由运行时在切换完成后发送
+    SourceChanged { device_id: String },
+    /// 配置文件在应用外被修改，已重新加载
+    ConfigReloaded,
+    /// 输出设备接入（需开启 `hotplug.notify`）
+    OutputConnected { device_id: String, name: String },
+    /// 已配置的输出设备被拔出（需开启 `hotplug.notify`）
+    OutputDisconnected { device_id: String, name: String },
+    /// 设置文件损坏，已隔离并改用备份（`backup` 为备份编号）或默认配置
+    ConfigRecovered {
+        quarantined: String,
+        backup: Option<u32>,
+        reason: String,
+    },
+}
+
+impl AppEvent {
+    /// 事件名称，与序列化后的 `type` 字段一致。
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::RoutingStarted { .. } => ROUTING_STARTED,
+            AppEvent::RoutingStopped => ROUTING_STOPPED,
+            AppEvent::RoutingRestarting => ROUTING_RESTARTING,
+            AppEvent::RoutingRestarted => ROUTING_RESTARTED,
+            AppEvent::RoutingFailed { .. } => ROUTING_FAILED,
+            AppEvent::RoutingStoppedUnexpectedly { .. } => ROUTING_STOPPED_UNEXPECTEDLY,
+            AppEvent::GlitchDetected { .. } => GLITCH_DETECTED,
+            AppEvent::ClippingDetected { .. } => CLIPPING_DETECTED,
+            AppEvent::DevicesChanged { .. } => DEVICES_CHANGED,
+            AppEvent::ProfileActivated { .. } => PROFILE_ACTIVATED,
+            AppEvent::SourceChanged { .. } => SOURCE_CHANGED,
+            AppEvent::ConfigReloaded => CONFIG_RELOADED,
+            AppEvent::OutputConnected { .. } => OUTPUT_CONNECTED,
+            AppEvent::OutputDisconnected { .. } => OUTPUT_DISCONNECTED,
+            AppEvent::ConfigRecovered { .. } => CONFIG_RECOVERED,
+        }
+    }
+}
+
+/// 事件发往外部集成时的外层结构，附带 schema 版本和时间戳。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct EventEnvelope {
+    pub schema_version: u32,
+    /// Unix 时间戳（毫秒）
+    pub timestamp_ms: u64,
+    pub event: AppEvent,
+}
+
+impl EventEnvelope {
+    pub fn new(event: AppEvent) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            timestamp_ms,
+            event,
+        }
+    }
+}
+
+/// `list_event_types` 返回的事件描述。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct EventType {
+    pub name: String,
+    pub description: String,
+    /// 负载字段名，无负载时为空
+    pub payload_fields: Vec<String>,
+}
+
+const EVENT_TYPES: &[(&str, &str, &[&str])] = &[
+    (ROUTING_STARTED, "Routing started", &["output_count"]),
+    (ROUTING_STOPPED, "Routing stopped by the user", &[]),
+    (
+        ROUTING_RESTARTING,
+        "Device changed, routing is restarting",
+        &[],
+    ),
+    (
+        ROUTING_RESTARTED,
+        "Routing restored after a device change",
+        &[],
+    ),
+    (
+        ROUTING_FAILED,
+        "Routing failed and could not recover",
+        &["error"],
+    ),
+    (
+        ROUTING_STOPPED_UNEXPECTEDLY,
+        "The routing thread exited unexpectedly, e.g. after a crash",
+        &["reason"],
+    ),
+    (
+        GLITCH_DETECTED,
+        "Capture glitches exceeded the threshold",
+        &["count", "window_secs"],
+    ),
+    (
+        CLIPPING_DETECTED,
+        "An output clipped more samples than the threshold",
+        &["device_id", "count", "window_secs"],
+    ),
+    (
+        DEVICES_CHANGED,
+        "The set of output devices changed",
+        &["device_count"],
+    ),
+    (
+        PROFILE_ACTIVATED,
+        "Another configuration profile was activated",
+        &["name"],
+    ),
+    (
+        SOURCE_CHANGED,
+        "Another source device was selected; while routing, once capture has moved to it",
+        &["device_id"],
+    ),
+    (
+        CONFIG_RELOADED,
+        "The settings file was edited outside the app and reloaded",
+        &[],
+    ),
+    (
+        OUTPUT_CONNECTED,
+        "An output device was plugged in",
+        &["device_id", "name"],
+    ),
+    (
+        OUTPUT_DISCONNECTED,
+        "A configured output device was unplugged",
+        &["device_id", "name"],
+    ),
+    (
+        CONFIG_RECOVERED,
+        "The settings file was broken and replaced with a backup or the defaults",
+        &["quarantined", "backup", "reason"],
+    ),
+];
+
+/// 列出所有公开事件类型。
+pub fn list_event_types() -> Vec<EventType> {
+    EVENT_TYPES
+        .iter()
+        .map(|(name, description, fields)| EventType {
+            name: name.to_string(),
+            description: description.to_string(),
+            payload_fields: fields.iter().map(|f| f.to_string()).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_events() -> Vec<AppEvent> {
+        vec![
+            AppEvent::RoutingStarted { output_count: 2 },
+            AppEvent::RoutingStopped,
+            AppEvent::RoutingRestarting,
+            AppEvent::RoutingRestarted,
+            AppEvent::RoutingFailed {
+                error: "boom".into(),
+            },
+            AppEvent::RoutingStoppedUnexpectedly {
+                reason: "router worker panicked: boom".into(),
+            },
+            AppEvent::GlitchDetected {
+                count: 3,
+                window_secs: 5,
+            },
+            AppEvent::ClippingDetected {
+                device_id: "out1".into(),
+                count: 120,
+                window_secs: 5,
+            },
+            AppEvent::DevicesChanged { device_count: 4 },
+            AppEvent::ProfileActivated {
+                name: "Desk".into(),
+            },
+            AppEvent::SourceChanged {
+                device_id: "in1".into(),
+            },
+            AppEvent::ConfigReloaded,
+            AppEvent::OutputConnected {
+                device_id: "out2".into(),
+                name: "Headphones".into(),
+            },
+            AppEvent::OutputDisconnected {
+                device_id: "out2".into(),
+                name: "Headphones".into(),
+            },
+            AppEvent::ConfigRecovered {
+                quarantined: "settings.toml.corrupt.1700000000".into(),
+                backup: Some(1),
+                reason: "parsing TOML config".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn event_types_match_serialized_payloads() {
+        let types = list_event_types();
+        let events = all_events();
+        assert_eq!(types.len(), events.len());
+
+        for (ty, event) in types.iter().zip(&events) {
+            let value = serde_json::to_value(event).expect("serialize");
+            assert_eq!(value["type"], ty.name.as_str());
+            assert_eq!(event.name(), ty.name);
+
+            let mut fields: Vec<String> = value
+                .get("payload")
+                .and_then(|p| p.as_object())
+                .map(|p| p.keys().cloned().collect())
+                .unwrap_or_default();
+            fields.sort();
+            let mut expected = ty.payload_fields.clone();
+            expected.sort();
+            assert_eq!(fields, expected, "payload fields of {}", ty.name);
+        }
+    }
+
+    #[test]
+    fn envelope_carries_schema_version() {
+        let envelope = EventEnvelope::new(AppEvent::RoutingStopped);
+        let value = serde_json::to_value(&envelope).expect("serialize");
+        assert_eq!(value["schema_version"], EVENT_SCHEMA_VERSION);
+        assert_eq!(value["event"]["type"], ROUTING_STOPPED);
+    }
+}
@@ -29,6 +29,9 @@ pub const EN_MAP: &[(&str, &str)] = &[
     ("channelModes.Swap", "Swap L/R"),
     ("channelModes.LeftOnly", "Left Only"),
     ("channelModes.RightOnly", "Right Only"),
+    ("channelModes.Downmix", "Surround Downmix"),
+    ("channelModes.Upmix", "Surround Upmix"),
+    ("channelModes.Matrix", "Custom Matrix"),
     ("channelModeDesc.Stereo", "Keep original left and right channels unchanged"),
     ("channelModeDesc.LeftMono", "Copy left channel to both left and right outputs"),
     ("channelModeDesc.RightMono", "Copy right channel to both left and right outputs"),
@@ -36,6 +39,13 @@ pub const EN_MAP: &[(&str, &str)] = &[
     ("channelModeDesc.Swap", "Swap left and right channels"),
     ("channelModeDesc.LeftOnly", "Output only the left channel, mute the right"),
     ("channelModeDesc.RightOnly", "Output only the right channel, mute the left"),
+    ("channelModeDesc.Downmix", "Fold surround channels into stereo (center and surrounds at -3 dB)"),
+    ("channelModeDesc.Upmix", "Spread stereo over all speakers: fronts, ambience to rears, bass to LFE"),
+    ("channelModeDesc.Matrix", "Mix channels with the custom gain matrix from the config file"),
+    ("eqPresets.Flat", "Flat"),
+    ("eqPresets.BassBoost", "Bass Boost"),
+    ("eqPresets.Speech", "Speech"),
+    ("EqPreset", "Equalizer preset"),
     ("UpdateAvailable", "New version available!"),
     ("UpdateAvailableVersion", "New version {v} available!"),
     ("UpdateCheckFailed", "Update check failed: {e}"),
@@ -54,9 +64,20 @@ pub const EN_MAP: &[(&str, &str)] = &[
     ("BackdropAcrylic", "Acrylic"),
     ("TrayShowHide", "Show / Hide"),
     ("TrayQuit", "Quit"),
+    ("TrayStartRouting", "Start Routing"),
+    ("TrayStopRouting", "Stop Routing"),
+    ("TraySource", "Source: {name}"),
+    ("TrayRouteSummary", "{source} → {outputs} ({state})"),
+    ("Stopped", "Stopped"),
     ("Restarting", "Device changed, restarting..."),
     ("Restarted", "Routing restored"),
+    ("GlitchDetected", "Audio glitches detected: {count} in {secs}s"),
+    ("ClippingDetected", "Clipping on {device}: {count} samples in {secs}s"),
     ("RoutingFailed", "Routing failed: {error}"),
+    ("NotifyOutputDisconnected", "Output device disconnected"),
+    ("NotifyOutputDisconnectedBody", "{name} is no longer available."),
+    ("NotifyRoutingStopped", "Routing stopped"),
+    ("NotifyRecovering", "Recovering routing"),
     ("CloseToTray", "Minimize to tray on close"),
     ("CheckForUpdates", "Check for Updates"),
     ("CheckingForUpdates", "Checking for updates..."),
@@ -71,6 +92,12 @@ pub const EN_MAP: &[(&str, &str)] = &[
     ("UpdateFailed", "Update failed: {error}"),
     ("UpToDate", "You're up to date"),
     ("AutoUpdateCheck", "Automatically check for updates on startup"),
+    ("UpdateChannel", "Update channel"),
+    ("UpdateChannelStable", "Stable"),
+    ("UpdateChannelBeta", "Beta (pre-releases)"),
+    ("OpenConfigFolder", "Open settings folder"),
+    ("ResumeLastState", "Resume the routing state of the last session on startup"),
+    ("ShowNotifications", "Show a notification when routing is interrupted"),
     ("ReleaseNotes", "Release Notes"),
     ("GitHub", "GitHub Repository"),
 ];
@@ -29,6 +29,9 @@ pub const ZH_MAP: &[(&str, &str)] = &[
     ("channelModes.Swap", "左右互换"),
     ("channelModes.LeftOnly", "仅左侧"),
     ("channelModes.RightOnly", "仅右侧"),
+    ("channelModes.Downmix", "环绕声缩混"),
+    ("channelModes.Upmix", "环绕声扩展"),
+    ("channelModes.Matrix", "自定义矩阵"),
     ("channelModeDesc.Stereo", "保持原始左右声道不变"),
     ("channelModeDesc.LeftMono", "将左声道复制到左右两侧输出"),
     ("channelModeDesc.RightMono", "将右声道复制到左右两侧输出"),
@@ -36,6 +39,13 @@ pub const ZH_MAP: &[(&str, &str)] = &[
     ("channelModeDesc.Swap", "交换左右声道后输出"),
     ("channelModeDesc.LeftOnly", "仅输出左声道,静音右声道"),
     ("channelModeDesc.RightOnly", "仅输出右声道,静音左声道"),
+    ("channelModeDesc.Downmix", "将环绕声道缩混为立体声(中置和环绕声道 -3 dB)"),
+    ("channelModeDesc.Upmix", "将立体声扩展到所有扬声器:前置保持不变,环境声送往后置,低频送往 LFE"),
+    ("channelModeDesc.Matrix", "按配置文件中的自定义增益矩阵混合声道"),
+    ("eqPresets.Flat", "平直"),
+    ("eqPresets.BassBoost", "低音增强"),
+    ("eqPresets.Speech", "人声"),
+    ("EqPreset", "均衡器预设"),
     ("UpdateAvailable", "发现新版本！"),
     ("UpdateAvailableVersion", "发现新版本 {v}！"),
     ("UpdateCheckFailed", "更新检查失败：{e}"),
@@ -54,9 +64,20 @@ pub const ZH_MAP: &[(&str, &str)] = &[
     ("BackdropAcrylic", "亚克力"),
     ("TrayShowHide", "显示/隐藏"),
     ("TrayQuit", "退出"),
+    ("TrayStartRouting", "开始路由"),
+    ("TrayStopRouting", "停止路由"),
+    ("TraySource", "源：{name}"),
+    ("TrayRouteSummary", "{source} → {outputs}（{state}）"),
+    ("Stopped", "已停止"),
     ("Restarting", "设备已变更，正在重启..."),
     ("Restarted", "路由已恢复"),
+    ("GlitchDetected", "检测到音频断续：{secs} 秒内 {count} 次"),
+    ("ClippingDetected", "{device} 出现削波：{secs} 秒内 {count} 个采样"),
     ("RoutingFailed", "路由失败：{error}"),
+    ("NotifyOutputDisconnected", "输出设备已断开"),
+    ("NotifyOutputDisconnectedBody", "{name} 已不可用。"),
+    ("NotifyRoutingStopped", "路由已停止"),
+    ("NotifyRecovering", "正在恢复路由"),
     ("CloseToTray", "关闭时缩小到托盘"),
     ("CheckForUpdates", "检查更新"),
     ("CheckingForUpdates", "正在检查更新..."),
@@ -71,6 +92,12 @@ pub const ZH_MAP: &[(&str, &str)] = &[
     ("UpdateFailed", "更新失败：{error}"),
     ("UpToDate", "当前已是最新版本"),
     ("AutoUpdateCheck", "启动时自动检查更新"),
+    ("UpdateChannel", "更新通道"),
+    ("UpdateChannelStable", "稳定版"),
+    ("UpdateChannelBeta", "测试版（含预发布版本）"),
+    ("OpenConfigFolder", "打开配置文件夹"),
+    ("ResumeLastState", "启动时恢复上次的路由状态"),
+    ("ShowNotifications", "路由中断时显示通知"),
     ("ReleaseNotes", "更新说明"),
     ("GitHub", "GitHub 仓库"),
 ];
@@ -0,0 +1,70 @@
+//! 电平表数据流。
+//!
+//! 路由运行期间按固定频率读取 [`Router::levels`]，通过通道推送给前端绘制
+//! 电平表。数据量较大、频率较高，因此不走 [`crate::events`] 的事件队列。
+
+use audio_core::router::{Router, RouterLevels};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// 每秒推送的电平帧数。
+pub const LEVEL_FRAMES_PER_SEC: u32 = 20;
+
+/// 后台推送线程；drop 时停止。
+pub struct LevelStream {
+    stop: Arc<AtomicBool>,
+    join: Option<JoinHandle<()>>,
+}
+
+impl LevelStream {
+    /// 开始推送 `router` 的电平，返回流和接收端。路由停止时不推送；
+    /// 接收端被丢弃后线程自行退出。
+    pub fn spawn(router: Router) -> (Self, Receiver<RouterLevels>) {
+        let (tx, rx) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let join = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("level-stream".into())
+                .spawn(move || run(router, tx, stop))
+                .map_err(|e| log::error!("Level stream thread failed to start: {e}"))
+                .ok()
+        };
+        (Self { stop, join }, rx)
+    }
+}
+
+impl Drop for LevelStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(join) = self.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+fn run(router: Router, tx: Sender<RouterLevels>, stop: Arc<AtomicBool>) {
+    let interval = Duration::from_secs(1) / LEVEL_FRAMES_PER_SEC;
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(interval);
+        if router.is_running() && tx.send(router.levels()).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_sent_while_the_router_is_stopped() {
+        let (stream, rx) = LevelStream::spawn(Router::new());
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(stream);
+        assert!(rx.recv().is_err());
+    }
+}
@@ -1,7 +1,12 @@
 //! AudioRouter 公共业务逻辑层，与具体 GUI 框架无关。
 
+pub mod about;
+pub mod cli;
 pub mod controller;
+pub mod events;
 pub mod i18n;
+pub mod levels;
+pub mod notifications;
 pub mod update;
 
 #[cfg(target_os = "windows")]
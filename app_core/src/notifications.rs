@@ -0,0 +1,76 @@
+//! 桌面通知：路由被打断时提醒用户，窗口隐藏在托盘中时也能看到。
+//!
+//! 这里只根据 [`AppEvent`] 生成标题和正文，由前端用系统通知显示。是否生成
+//! 由 `general.notifications` 控制；输出断开的事件还需要开启 `hotplug.notify`。
+
+use crate::events::AppEvent;
+use crate::i18n::I18n;
+
+/// A desktop notification for the frontend to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+impl Notification {
+    /// The notification for `event`, or `None` if the event does not
+    /// interrupt routing.
+    pub fn for_event(event: &AppEvent, i18n: &I18n) -> Option<Self> {
+        let (title, body) = match event {
+            AppEvent::OutputDisconnected { name, .. } => (
+                i18n.t("NotifyOutputDisconnected"),
+                i18n.t("NotifyOutputDisconnectedBody")
+                    .replace("{name}", name),
+            ),
+            AppEvent::RoutingFailed { error: reason }
+            | AppEvent::RoutingStoppedUnexpectedly { reason } => {
+                (i18n.t("NotifyRoutingStopped"), reason.clone())
+            }
+            AppEvent::RoutingRestarting => {
+                (i18n.t("NotifyRecovering"), i18n.t("Restarting").to_string())
+            }
+            _ => return None,
+        };
+        Some(Self {
+            title: title.to_string(),
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_interruptions_are_notified() {
+        let i18n = I18n::new("en");
+        let unplugged = AppEvent::OutputDisconnected {
+            device_id: "out1".into(),
+            name: "Speakers".into(),
+        };
+        assert_eq!(
+            Notification::for_event(&unplugged, &i18n),
+            Some(Notification {
+                title: "Output device disconnected".into(),
+                body: "Speakers is no longer available.".into(),
+            })
+        );
+        let crashed = AppEvent::RoutingStoppedUnexpectedly {
+            reason: "router worker panicked".into(),
+        };
+        let body = Notification::for_event(&crashed, &i18n).unwrap().body;
+        assert_eq!(body, "router worker panicked");
+        assert!(Notification::for_event(&AppEvent::RoutingRestarting, &i18n).is_some());
+
+        assert_eq!(
+            Notification::for_event(&AppEvent::RoutingStopped, &i18n),
+            None
+        );
+        assert_eq!(
+            Notification::for_event(&AppEvent::RoutingRestarted, &i18n),
+            None
+        );
+    }
+}
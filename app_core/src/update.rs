@@ -1,5 +1,6 @@
 //! GitHub Release 版本更新检查
 
+use config::config::UpdateChannel;
 use serde::Deserialize;
 
 /// 更新检查状态
@@ -19,25 +20,45 @@ pub enum UpdateStatus {
 struct GitHubRelease {
     tag_name: String,
     html_url: String,
+    #[serde(default)]
+    draft: bool,
 }
 
-/// 检查 GitHub 上是否有新版本
-pub fn check_for_update() -> UpdateStatus {
-    let url = "https://api.github.com/repos/fangfuzha/AudioRouter/releases/latest";
-    let version = env!("CARGO_PKG_VERSION");
+/// 检查 GitHub 上是否有比 `current_version` 新的版本；`channel` 为 Beta 时
+/// 预发布版本也算。阻塞调用，应在后台线程中执行。
+pub fn check_for_update(current_version: &str, channel: UpdateChannel) -> UpdateStatus {
+    // releases/latest 不含预发布版本；列表按发布时间倒序
+    let url = match channel {
+        UpdateChannel::Stable => {
+            "https://api.github.com/repos/fangfuzha/AudioRouter/releases/latest"
+        }
+        UpdateChannel::Beta => {
+            "https://api.github.com/repos/fangfuzha/AudioRouter/releases?per_page=20"
+        }
+    };
     let resp = match ureq::get(url)
-        .header("User-Agent", format!("AudioRouter/{version}"))
+        .header("User-Agent", format!("AudioRouter/{current_version}"))
         .call()
     {
         Ok(r) => r,
         Err(e) => return UpdateStatus::Error(format!("HTTP request failed: {e}")),
     };
 
-    let release: GitHubRelease = match resp.into_body().read_json() {
-        Ok(r) => r,
-        Err(e) => return UpdateStatus::Error(format!("JSON parse failed: {e}")),
+    let mut body = resp.into_body();
+    let release = match channel {
+        UpdateChannel::Stable => body.read_json::<GitHubRelease>().map(Some),
+        UpdateChannel::Beta => body
+            .read_json::<Vec<GitHubRelease>>()
+            .map(|releases| releases.into_iter().find(|r| !r.draft)),
     };
+    match release {
+        Ok(Some(release)) => compare_release(current_version, release),
+        Ok(None) => UpdateStatus::UpToDate,
+        Err(e) => UpdateStatus::Error(format!("JSON parse failed: {e}")),
+    }
+}
 
+fn compare_release(version: &str, release: GitHubRelease) -> UpdateStatus {
     // 语义化版本比较
     fn normalize(v: &str) -> &str {
         v.trim_start_matches(['v', 'V'])
@@ -69,3 +90,30 @@ pub fn check_for_update() -> UpdateStatus {
         UpdateStatus::UpToDate
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str) -> GitHubRelease {
+        GitHubRelease {
+            tag_name: tag.into(),
+            html_url: format!("https://example.com/{tag}"),
+            draft: false,
+        }
+    }
+
+    #[test]
+    fn pre_releases_compare_below_their_release() {
+        let available = |current: &str, tag: &str| {
+            matches!(
+                compare_release(current, release(tag)),
+                UpdateStatus::Available { .. }
+            )
+        };
+        assert!(available("0.3.8", "v0.4.0-beta.1"));
+        assert!(available("0.4.0-beta.1", "v0.4.0-beta.2"));
+        assert!(!available("0.4.0", "v0.4.0-beta.2"));
+        assert!(!available("0.4.0", "V0.4.0"));
+    }
+}
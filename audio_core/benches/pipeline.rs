@@ -0,0 +1,186 @@
+//! Packet pipeline benchmarks: `cargo bench -p audio_core --features bench`.
+//!
+//! `pipeline` drives the router worker's per-packet code over in-memory
+//! endpoints for a matrix of formats, channel modes and output counts;
+//! `mixer` times `ChannelMixer::process` with each DSP stage on its own.
+//! Allocations in the steady-state packet loop are printed per case.
+
+use audio_core::bench::{
+    BenchFormat, CountingAllocator, MixerBench, Pipeline, PipelineConfig, outputs, run_pipeline,
+};
+use audio_core::dsp::{
+    BassRole, CrossfeedPreset, EqPreset, LimiterSettings, LoudnessSettings, MidSideSettings,
+    NoiseGateSettings, ResamplerQuality,
+};
+use audio_core::router::{ChannelMode, RouterConfig, RouterTarget};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+/// Packets run once per case to count allocations.
+const ALLOCATION_PACKETS: u64 = 1_000;
+
+fn session(format: BenchFormat, targets: Vec<RouterTarget>) -> PipelineConfig {
+    PipelineConfig {
+        format,
+        router: RouterConfig {
+            targets,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn processed(mode: ChannelMode) -> Vec<RouterTarget> {
+    let mut targets = outputs(mode, 1);
+    targets[0].eq_preset = EqPreset::BassBoost;
+    targets[0].limiter = LimiterSettings {
+        enabled: true,
+        ..Default::default()
+    };
+    targets
+}
+
+fn pipeline_cases() -> Vec<(&'static str, PipelineConfig)> {
+    let resampled = PipelineConfig {
+        device_sample_rate: 44_100,
+        ..session(BenchFormat::F32, {
+            let mut targets = processed(ChannelMode::Stereo);
+            targets[0].resampler = ResamplerQuality::Balanced;
+            targets
+        })
+    };
+    let surround = PipelineConfig {
+        channels: 6,
+        ..session(BenchFormat::F32, outputs(ChannelMode::Downmix, 1))
+    };
+    vec![
+        (
+            "f32 stereo passthrough x1",
+            session(BenchFormat::F32, outputs(ChannelMode::Stereo, 1)),
+        ),
+        (
+            "f32 stereo passthrough x4",
+            session(BenchFormat::F32, outputs(ChannelMode::Stereo, 4)),
+        ),
+        (
+            "i16 swap x2",
+            session(BenchFormat::I16, outputs(ChannelMode::Swap, 2)),
+        ),
+        (
+            "i32 left-mono x2",
+            session(BenchFormat::I32, outputs(ChannelMode::LeftMono, 2)),
+        ),
+        ("f32 5.1 downmix x1", surround),
+        (
+            "f32 eq+limiter x1",
+            session(BenchFormat::F32, processed(ChannelMode::Stereo)),
+        ),
+        (
+            "i16 eq+limiter+dither x1",
+            session(BenchFormat::I16, processed(ChannelMode::Stereo)),
+        ),
+        ("f32 eq+limiter 48k->44.1k x1", resampled),
+        (
+            "f32 stereo x1 + tap",
+            PipelineConfig {
+                tap: true,
+                ..session(BenchFormat::F32, outputs(ChannelMode::Stereo, 1))
+            },
+        ),
+        (
+            "f32 stereo x2 + meters",
+            PipelineConfig {
+                metering: true,
+                ..session(BenchFormat::F32, outputs(ChannelMode::Stereo, 2))
+            },
+        ),
+        (
+            "f32 stereo x1 + noise gate",
+            PipelineConfig {
+                router: RouterConfig {
+                    targets: outputs(ChannelMode::Stereo, 1),
+                    noise_gate: NoiseGateSettings {
+                        enabled: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+fn pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pipeline");
+    for (name, cfg) in pipeline_cases() {
+        let report = run_pipeline(&cfg, ALLOCATION_PACKETS);
+        println!(
+            "{name}: {} allocations in {ALLOCATION_PACKETS} packets",
+            report.allocations
+        );
+
+        let mut pipeline = Pipeline::new(&cfg);
+        group.throughput(Throughput::Elements(cfg.frames_per_packet as u64));
+        group.bench_function(name, |b| b.iter(|| pipeline.process_packet()));
+    }
+    group.finish();
+}
+
+/// One stereo output with a single DSP stage enabled.
+fn stage(
+    name: &'static str,
+    configure: impl FnOnce(&mut RouterTarget),
+) -> (&'static str, RouterTarget) {
+    let mut target = outputs(ChannelMode::Stereo, 1).remove(0);
+    configure(&mut target);
+    (name, target)
+}
+
+fn mixer(c: &mut Criterion) {
+    // Upmix 输出到 5.1 设备
+    let cfg = PipelineConfig {
+        device_channels: 6,
+        ..Default::default()
+    };
+    let stages = [
+        stage("matrix only", |_| {}),
+        stage("gain", |t| t.gain_db = -6.0),
+        stage("eq", |t| t.eq_preset = EqPreset::BassBoost),
+        stage("delay", |t| t.delay_ms = 20.0),
+        stage("crossover", |t| t.bass_role = BassRole::Satellite),
+        stage("mid/side", |t| {
+            t.mid_side = MidSideSettings {
+                enabled: true,
+                ..Default::default()
+            }
+        }),
+        stage("crossfeed", |t| t.crossfeed = CrossfeedPreset::Default),
+        stage("loudness", |t| {
+            t.loudness = LoudnessSettings {
+                enabled: true,
+                ..Default::default()
+            }
+        }),
+        stage("limiter", |t| {
+            t.limiter = LimiterSettings {
+                enabled: true,
+                ..Default::default()
+            }
+        }),
+        stage("upmix", |t| t.channel_mode = ChannelMode::Upmix),
+    ];
+
+    let mut group = c.benchmark_group("mixer");
+    group.throughput(Throughput::Elements(cfg.frames_per_packet as u64));
+    for (name, target) in stages {
+        let mut mixer = MixerBench::new(&cfg, &target);
+        group.bench_function(name, |b| b.iter(|| mixer.process()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pipeline, mixer);
+criterion_main!(benches);
@@ -0,0 +1,492 @@
+//! Device-free benchmark helpers for the packet pipeline (feature `bench`).
+//!
+//! [`Pipeline`] runs the router worker's own per-packet code (fade, format
+//! conversion, noise gate, meters, taps, overflow handling, mixer, DSP chain,
+//! resampler and dither) against in-memory capture and render endpoints, so
+//! regressions can be measured without audio hardware. [`MixerBench`] times
+//! one output's mixer and DSP chain on its own. Used by `benches/pipeline.rs`
+//! and the `audio_bench` synthetic-load binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use ::config::config::Output;
+use anyhow::Result;
+
+use crate::com_service::router::{
+    CaptureEndpoint, RenderEndpoint, RouterInitialized, RouterOutputClient, RouterRenderClient,
+    SampleFormat, SessionSetup, StreamFormat, build_mixer, capture_to_f32,
+    process_available_packets,
+};
+use crate::router::mixer::{ChannelMixer, default_channel_mask};
+use crate::router::tap::{TAP_CAPACITY_SAMPLES, TapConsumer, Taps, tap_channel};
+use crate::router::{
+    ChannelMode, EngineSettings, RouterConfig, RouterControls, RouterStats, RouterTarget,
+};
+
+/// Sample encoding of the generated capture data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchFormat {
+    F32,
+    I16,
+    I32,
+}
+
+impl BenchFormat {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            BenchFormat::I16 => 2,
+            BenchFormat::F32 | BenchFormat::I32 => 4,
+        }
+    }
+
+    fn sample_format(self) -> SampleFormat {
+        match self {
+            BenchFormat::F32 => SampleFormat::F32,
+            BenchFormat::I16 => SampleFormat::I16,
+            BenchFormat::I32 => SampleFormat::I32,
+        }
+    }
+
+    fn stream_format(self, channels: u16, sample_rate: u32) -> StreamFormat {
+        StreamFormat {
+            sample_format: self.sample_format(),
+            channels,
+            sample_rate,
+            block_align: self.bytes_per_sample() as u16 * channels,
+            channel_mask: default_channel_mask(channels),
+        }
+    }
+}
+
+/// Shape of the simulated routing session.
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    pub format: BenchFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Frames per capture packet (480 = 10ms at 48 kHz, the usual engine period).
+    pub frames_per_packet: u32,
+    /// Mix format of the simulated output devices. As in a real session only
+    /// Upmix outputs and outputs with their own resampler use it.
+    pub device_channels: u16,
+    pub device_sample_rate: u32,
+    /// Session settings; `targets` are the simulated outputs.
+    pub router: RouterConfig,
+    /// Whether a PCM tap (callback) is attached.
+    pub tap: bool,
+    /// Whether something reads the level meters.
+    pub metering: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            format: BenchFormat::F32,
+            channels: 2,
+            sample_rate: 48_000,
+            frames_per_packet: 480,
+            device_channels: 2,
+            device_sample_rate: 48_000,
+            router: RouterConfig {
+                targets: outputs(ChannelMode::Stereo, 1),
+                ..Default::default()
+            },
+            tap: false,
+            metering: false,
+        }
+    }
+}
+
+impl PipelineConfig {
+    fn capture_format(&self) -> StreamFormat {
+        self.format.stream_format(self.channels, self.sample_rate)
+    }
+
+    /// Format a session would open `output` with.
+    fn render_format<C>(&self, output: &RouterOutputClient<C>) -> StreamFormat {
+        let capture = self.capture_format();
+        let device = self
+            .format
+            .stream_format(self.device_channels, self.device_sample_rate);
+        output.render_format(
+            capture,
+            output.needs_device_format(capture).then_some(device),
+        )
+    }
+}
+
+/// `count` outputs in channel mode `mode` with default settings.
+pub fn outputs(mode: ChannelMode, count: usize) -> Vec<RouterTarget> {
+    (0..count)
+        .map(|i| RouterTarget {
+            channel_mode: mode,
+            ..RouterTarget::from_output(
+                &Output::new(format!("bench-output-{i}")),
+                &EngineSettings::default(),
+            )
+        })
+        .collect()
+}
+
+/// Result of [`run_pipeline`].
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineReport {
+    pub packets: u64,
+    pub frames: u64,
+    pub elapsed: Duration,
+    /// Heap allocations during the run, if a [`CountingAllocator`] is installed.
+    pub allocations: u64,
+}
+
+impl PipelineReport {
+    pub fn frames_per_sec(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// How many times faster than real time the pipeline ran.
+    pub fn realtime_factor(&self, sample_rate: u32) -> f64 {
+        self.frames_per_sec() / sample_rate as f64
+    }
+}
+
+/// Generates one packet of a 440 Hz sine in the requested encoding.
+fn generate_packet(cfg: &PipelineConfig) -> Vec<u8> {
+    let channels = cfg.channels as usize;
+    let mut bytes = Vec::with_capacity(
+        cfg.frames_per_packet as usize * channels * cfg.format.bytes_per_sample(),
+    );
+    for frame in 0..cfg.frames_per_packet {
+        let phase = frame as f32 * 440.0 * std::f32::consts::TAU / cfg.sample_rate as f32;
+        for ch in 0..channels {
+            // 各声道幅度不同，保证声道映射确实在搬运不同数据
+            let value = phase.sin() * (0.5 + 0.1 * ch as f32);
+            match cfg.format {
+                BenchFormat::F32 => bytes.extend_from_slice(&value.to_le_bytes()),
+                BenchFormat::I16 => {
+                    bytes.extend_from_slice(&((value * 32767.0) as i16).to_le_bytes())
+                }
+                BenchFormat::I32 => {
+                    bytes.extend_from_slice(&((value * 2147483647.0) as i32).to_le_bytes())
+                }
+            }
+        }
+    }
+    bytes
+}
+
+/// Capture endpoint that hands out the same generated packet each time one
+/// is queued.
+struct MemoryCapture {
+    packet: Vec<u8>,
+    frames: u32,
+    queued: Cell<bool>,
+}
+
+impl CaptureEndpoint for MemoryCapture {
+    fn next_packet_size(&self) -> Result<u32> {
+        Ok(if self.queued.get() { self.frames } else { 0 })
+    }
+
+    fn get_buffer(&self) -> Result<(*mut u8, u32, u32)> {
+        self.queued.set(false);
+        // 只读：处理代码不会写入捕获缓冲区
+        Ok((self.packet.as_ptr().cast_mut(), self.frames, 0))
+    }
+
+    fn release_buffer(&self, _frames: u32) {}
+}
+
+/// Packets the simulated render buffer holds.
+const RENDER_BUFFER_PACKETS: u32 = 10;
+
+/// Render endpoint whose device plays at the capture rate: padding stays at
+/// one packet, below the overflow target, so every packet is written.
+struct MemoryRender {
+    buffer: RefCell<Vec<u8>>,
+    padding: u32,
+    buffer_frames: u32,
+}
+
+impl MemoryRender {
+    fn new(format: StreamFormat, cfg: &PipelineConfig) -> Self {
+        // 重采样后每个 packet 的帧数可能多出一帧
+        let packet_frames = (cfg.frames_per_packet as u64 * format.sample_rate as u64)
+            .div_ceil(cfg.sample_rate as u64) as u32
+            + 1;
+        let buffer_frames = packet_frames * RENDER_BUFFER_PACKETS;
+        Self {
+            buffer: RefCell::new(vec![
+                0;
+                buffer_frames as usize * format.block_align as usize
+            ]),
+            padding: packet_frames,
+            buffer_frames,
+        }
+    }
+}
+
+impl RenderEndpoint for MemoryRender {
+    fn buffer_state(&self) -> Result<Option<(u32, u32)>> {
+        Ok(Some((self.padding, self.buffer_frames)))
+    }
+
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_buffer(&self, frames: u32) -> Result<Option<*mut u8>> {
+        debug_assert!(frames <= self.buffer_frames - self.padding);
+        Ok(Some(self.buffer.borrow_mut().as_mut_ptr()))
+    }
+
+    fn release_buffer(&self, _frames: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A routing session over in-memory endpoints, built and driven by the same
+/// code as a real session.
+pub struct Pipeline {
+    state: RouterInitialized<MemoryCapture, MemoryRender>,
+    taps: Taps,
+    tap: Option<TapConsumer>,
+    drained: Vec<f32>,
+    stats: RouterStats,
+    controls: RouterControls,
+}
+
+impl Pipeline {
+    pub fn new(cfg: &PipelineConfig) -> Self {
+        let stats = RouterStats::default();
+        let controls = RouterControls::new(Arc::new(AtomicBool::new(cfg.metering)));
+        let session = SessionSetup {
+            capture_format: cfg.capture_format(),
+            capture_latency: Duration::ZERO,
+            stats: &stats,
+            cfg: &cfg.router,
+        };
+        let renders = cfg
+            .router
+            .targets
+            .iter()
+            .map(|target| {
+                let control = controls.register_output(
+                    &target.device_id,
+                    target.gain_db,
+                    target.channel_mode,
+                );
+                control.set_muted(target.muted);
+                let output = RouterOutputClient::new(target, &cfg.router, ());
+                let render_format = cfg.render_format(&output);
+                RouterRenderClient::new(
+                    &session,
+                    &output,
+                    control,
+                    MemoryRender::new(render_format, cfg),
+                    render_format,
+                    Duration::ZERO,
+                )
+            })
+            .collect();
+        let capture = MemoryCapture {
+            packet: generate_packet(cfg),
+            frames: cfg.frames_per_packet,
+            queued: Cell::new(false),
+        };
+        let state = RouterInitialized::new(&session, capture, None, renders);
+
+        let (producer, consumer) = tap_channel(TAP_CAPACITY_SAMPLES);
+        let (pcm, tap) = if cfg.tap {
+            (Some(producer), Some(consumer))
+        } else {
+            (None, None)
+        };
+        Self {
+            state,
+            taps: Taps {
+                pcm,
+                spectrum: None,
+            },
+            tap,
+            drained: Vec::with_capacity(TAP_CAPACITY_SAMPLES),
+            stats,
+            controls,
+        }
+    }
+
+    /// Captures one packet and routes it to every output.
+    pub fn process_packet(&mut self) {
+        self.state.capture_service.queued.set(true);
+        process_available_packets(&self.state, &self.taps, &self.stats, &self.controls)
+            .expect("in-memory endpoints do not fail");
+        // tap 线程的角色：及时取走样本，队列不会满
+        if let Some(tap) = &self.tap {
+            tap.pop_into(&mut self.drained, TAP_CAPACITY_SAMPLES);
+        }
+    }
+}
+
+/// Runs `packets` packets through the pipeline and reports throughput.
+///
+/// The first packet sizes the scratch buffers like in a real session and is
+/// not measured; the timed loop only covers steady-state per-packet work.
+pub fn run_pipeline(cfg: &PipelineConfig, packets: u64) -> PipelineReport {
+    let mut pipeline = Pipeline::new(cfg);
+    pipeline.process_packet();
+
+    let allocations_before = allocations();
+    let started = Instant::now();
+    for _ in 0..packets {
+        pipeline.process_packet();
+    }
+    let elapsed = started.elapsed();
+    let allocations = allocations() - allocations_before;
+
+    PipelineReport {
+        packets,
+        frames: packets * cfg.frames_per_packet as u64,
+        elapsed,
+        allocations,
+    }
+}
+
+/// The mixer and DSP chain of one `RenderPath::Mixed` output, built the way
+/// a session builds it, fed with one generated packet per call.
+pub struct MixerBench {
+    mixer: ChannelMixer,
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl MixerBench {
+    pub fn new(cfg: &PipelineConfig, target: &RouterTarget) -> Self {
+        let capture = cfg.capture_format();
+        let output = RouterOutputClient::new(target, &cfg.router, ());
+        // 与会话一致：DSP 在捕获端采样率上进行
+        let render = StreamFormat {
+            sample_rate: capture.sample_rate,
+            ..cfg.render_format(&output)
+        };
+        let mut input = Vec::new();
+        capture_to_f32(
+            &generate_packet(cfg),
+            cfg.frames_per_packet,
+            cfg.channels as usize,
+            cfg.format.sample_format(),
+            false,
+            &mut input,
+        );
+        Self {
+            mixer: build_mixer(&output, capture, render, target.gain_db),
+            input,
+            output: Vec::new(),
+        }
+    }
+
+    /// Runs `ChannelMixer::process` over one packet.
+    pub fn process(&mut self) {
+        self.mixer.process(&self.input, &mut self.output);
+    }
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Global allocator wrapper that counts allocations.
+///
+/// Install it in a bench/binary crate with
+/// `#[global_allocator] static ALLOC: CountingAllocator = CountingAllocator;`
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocations counted so far (always 0 without [`CountingAllocator`]).
+pub fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::com_service::router::RenderPath;
+    use crate::dsp::{EqPreset, LimiterSettings, ResamplerQuality};
+
+    #[test]
+    fn pipeline_processes_every_frame() {
+        let mut targets = outputs(ChannelMode::Stereo, 2);
+        targets[1].channel_mode = ChannelMode::Mono;
+        let cfg = PipelineConfig {
+            format: BenchFormat::I16,
+            router: RouterConfig {
+                targets,
+                ..Default::default()
+            },
+            tap: true,
+            ..Default::default()
+        };
+        let report = run_pipeline(&cfg, 200);
+        assert_eq!(report.packets, 200);
+        assert_eq!(report.frames, 200 * 480);
+        assert!(report.frames_per_sec() > 0.0);
+    }
+
+    #[test]
+    fn pipeline_runs_mixed_outputs_and_meters_them() {
+        let mut target = outputs(ChannelMode::Stereo, 1).remove(0);
+        target.eq_preset = EqPreset::BassBoost;
+        target.limiter = LimiterSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        target.resampler = ResamplerQuality::Balanced;
+        let cfg = PipelineConfig {
+            device_sample_rate: 44_100,
+            router: RouterConfig {
+                targets: vec![target],
+                ..Default::default()
+            },
+            metering: true,
+            ..Default::default()
+        };
+        let mut pipeline = Pipeline::new(&cfg);
+        let render = &pipeline.state.render_services[0];
+        assert_eq!(render.path, RenderPath::Mixed);
+        assert_eq!(render.format.sample_rate, 44_100);
+
+        for _ in 0..50 {
+            pipeline.process_packet();
+        }
+        let snapshot = pipeline.stats.snapshot();
+        assert_eq!(snapshot.packets, 50);
+        assert_eq!(snapshot.overflows, 0);
+        let levels = pipeline.stats.levels();
+        assert!(levels.outputs[0].levels.peak_db[0] > -20.0);
+    }
+
+    #[test]
+    fn mixer_bench_outputs_one_packet() {
+        let cfg = PipelineConfig::default();
+        let mut target = outputs(ChannelMode::Mono, 1).remove(0);
+        target.eq_preset = EqPreset::Speech;
+        let mut bench = MixerBench::new(&cfg, &target);
+        bench.process();
+        assert_eq!(bench.output.len(), 480 * 2);
+    }
+}
@@ -0,0 +1,91 @@
+//! Synthetic-load driver for the audio pipeline.
+//!
+//! ```text
+//! cargo run -p audio_core --release --features bench --bin audio_bench -- \
+//!     --seconds 10 --format i16 --channels 2 --outputs 3 --mode Mono --tap
+//! ```
+
+use std::time::{Duration, Instant};
+
+use audio_core::bench::{BenchFormat, CountingAllocator, PipelineConfig, outputs, run_pipeline};
+use audio_core::router::ChannelMode;
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+/// Packets per measurement round (~10s of audio at 480 frames/packet).
+const PACKETS_PER_ROUND: u64 = 1_000;
+
+fn main() {
+    let (cfg, seconds) = match parse_args(std::env::args().skip(1)) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "usage: audio_bench [--seconds N] [--format f32|i16|i32] [--channels N] \
+                 [--outputs N] [--mode Stereo|LeftMono|RightMono|Mono|Swap|LeftOnly|RightOnly] [--tap]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    println!("{cfg:?}, running for {seconds}s");
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let mut frames = 0u64;
+    let mut allocations = 0u64;
+    let mut busy = Duration::ZERO;
+    let mut slowest_round = Duration::ZERO;
+    while Instant::now() < deadline {
+        let report = run_pipeline(&cfg, PACKETS_PER_ROUND);
+        frames += report.frames;
+        allocations += report.allocations;
+        busy += report.elapsed;
+        slowest_round = slowest_round.max(report.elapsed);
+    }
+
+    let frames_per_sec = frames as f64 / busy.as_secs_f64().max(f64::EPSILON);
+    println!("frames processed : {frames}");
+    println!("frames/sec       : {frames_per_sec:.0}");
+    println!(
+        "realtime factor  : {:.1}x",
+        frames_per_sec / cfg.sample_rate as f64
+    );
+    println!(
+        "per packet       : {:.2} us (slowest round {:.2} us/packet)",
+        busy.as_secs_f64() * 1e6 / (frames / cfg.frames_per_packet as u64).max(1) as f64,
+        slowest_round.as_secs_f64() * 1e6 / PACKETS_PER_ROUND as f64
+    );
+    println!("allocations      : {allocations} (in timed loops)");
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(PipelineConfig, u64), String> {
+    let mut cfg = PipelineConfig::default();
+    let mut seconds = 5;
+    let mut count = 1;
+    let mut mode = ChannelMode::Stereo;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--seconds" => seconds = value()?.parse().map_err(|e| format!("--seconds: {e}"))?,
+            "--channels" => {
+                cfg.channels = value()?.parse().map_err(|e| format!("--channels: {e}"))?
+            }
+            "--outputs" => count = value()?.parse().map_err(|e| format!("--outputs: {e}"))?,
+            "--format" => {
+                cfg.format = match value()?.as_str() {
+                    "f32" => BenchFormat::F32,
+                    "i16" => BenchFormat::I16,
+                    "i32" => BenchFormat::I32,
+                    other => return Err(format!("unknown format {other}")),
+                }
+            }
+            "--mode" => mode = ChannelMode::from_config(Some(&value()?)),
+            "--tap" => cfg.tap = true,
+            other => return Err(format!("unknown argument {other}")),
+        }
+    }
+
+    cfg.router.targets = outputs(mode, count);
+    Ok((cfg, seconds))
+}
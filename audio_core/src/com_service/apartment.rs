@@ -0,0 +1,262 @@
+//! COM apartment selection and calibration.
+//!
+//! Device enumeration normally goes through `callcomapi`'s `#[with_com]` worker.
+//! On some machines (driver stacks, Bluetooth endpoints) one apartment model
+//! dispatches noticeably faster than the other, so this module can measure both
+//! and run enumeration on a long-lived thread in the chosen one.
+//!
+//! The router worker always runs in the MTA: it never pumps messages, which an
+//! STA thread would have to do.
+
+use anyhow::{Result, anyhow};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Media::Audio::{
+    DEVICE_STATE_ACTIVE, IMMDeviceEnumerator, MMDeviceEnumerator, eRender,
+};
+use windows::Win32::System::Com::{
+    CLSCTX_ALL, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx,
+    CoUninitialize,
+};
+
+pub use ::config::config::Apartment;
+
+/// RAII guard that initializes COM on the current thread and uninitializes on drop.
+pub struct ComApartment;
+
+impl ComApartment {
+    /// Enters the given apartment on the current thread.
+    pub fn enter(apartment: Apartment) -> Result<Self> {
+        let coinit = match apartment {
+            Apartment::Sta => COINIT_APARTMENTTHREADED,
+            Apartment::Mta => COINIT_MULTITHREADED,
+        };
+        unsafe { CoInitializeEx(None, coinit) }
+            .map_err(|e| anyhow!("CoInitializeEx({apartment:?}) failed: {e:?}"))?;
+        Ok(Self)
+    }
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        unsafe {
+            CoUninitialize();
+        }
+    }
+}
+
+/// Runs `f` on a fresh thread initialized in `apartment` and returns its result.
+///
+/// For long-running work such as playback; short COM calls should use
+/// [`dispatch_in_apartment`] instead of paying for a new thread each time.
+pub fn run_in_apartment<T, F>(apartment: Apartment, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    thread::spawn(move || {
+        let _com = ComApartment::enter(apartment)?;
+        f()
+    })
+    .join()
+    .map_err(|panic| anyhow!("COM apartment thread panicked: {panic:?}"))?
+}
+
+/// Runs `f` on the long-lived thread for `apartment` and returns its result.
+///
+/// The thread is created on first use and shared by all callers, so jobs run
+/// one at a time. This is the path [`calibrate_apartments`] measures.
+pub fn dispatch_in_apartment<T, F>(apartment: Apartment, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    dispatch_timed(apartment, f)?.0
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// 每种 apartment 一个常驻线程，首次使用时创建；线程退出（任务 panic）后下次重建。
+static STA_DISPATCHER: Mutex<Option<ApartmentDispatcher>> = Mutex::new(None);
+static MTA_DISPATCHER: Mutex<Option<ApartmentDispatcher>> = Mutex::new(None);
+
+/// Dispatches `f` to the shared thread for `apartment`, returning its result
+/// with the round-trip time (send job → COM work → result back).
+fn dispatch_timed<T, F>(apartment: Apartment, f: F) -> Result<(T, Duration)>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let slot = match apartment {
+        Apartment::Sta => &STA_DISPATCHER,
+        Apartment::Mta => &MTA_DISPATCHER,
+    };
+    let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+    let dispatcher = match slot.take() {
+        Some(dispatcher) => dispatcher,
+        None => ApartmentDispatcher::spawn(apartment)?,
+    };
+    let result = dispatcher.dispatch(f);
+    if result.is_ok() {
+        *slot = Some(dispatcher);
+    }
+    result
+}
+
+/// A long-lived thread in a given apartment that executes dispatched jobs.
+struct ApartmentDispatcher {
+    tx: Option<mpsc::Sender<Job>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ApartmentDispatcher {
+    fn spawn(apartment: Apartment) -> Result<Self> {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+        let handle = thread::spawn(move || {
+            let _com = match ComApartment::enter(apartment) {
+                Ok(com) => {
+                    let _ = ready_tx.send(Ok(()));
+                    com
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            while let Ok(job) = rx.recv() {
+                job();
+            }
+        });
+        ready_rx
+            .recv()
+            .map_err(|_| anyhow!("apartment dispatcher exited during startup"))??;
+        Ok(Self {
+            tx: Some(tx),
+            handle: Some(handle),
+        })
+    }
+
+    /// Dispatches `f`, waits for it, and returns the result with the round-trip time.
+    fn dispatch<T, F>(&self, f: F) -> Result<(T, Duration)>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::channel();
+        let started = Instant::now();
+        self.tx
+            .as_ref()
+            .ok_or_else(|| anyhow!("apartment dispatcher stopped"))?
+            .send(Box::new(move || {
+                let _ = result_tx.send(f());
+            }))
+            .map_err(|_| anyhow!("apartment dispatcher stopped"))?;
+        let value = result_rx
+            .recv()
+            .map_err(|_| anyhow!("apartment dispatcher dropped the job"))?;
+        Ok((value, started.elapsed()))
+    }
+}
+
+impl Drop for ApartmentDispatcher {
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Average enumeration dispatch latency in one apartment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApartmentTiming {
+    pub apartment: Apartment,
+    pub enumeration: Duration,
+}
+
+/// Result of [`calibrate_apartments`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApartmentCalibration {
+    /// Faster apartment for device enumeration.
+    pub enumeration: Apartment,
+    /// Raw measurements, STA first.
+    pub timings: Vec<ApartmentTiming>,
+}
+
+/// Measures device-enumeration dispatch latency through
+/// [`dispatch_in_apartment`] in both apartments and picks the faster one.
+///
+/// `rounds` jobs are dispatched per apartment (after one warm-up round).
+/// Takes a few hundred milliseconds on typical systems, so it is meant to run
+/// once, off the UI thread, and have its result persisted.
+pub fn calibrate_apartments(rounds: u32) -> Result<ApartmentCalibration> {
+    let rounds = rounds.max(1);
+    let mut timings = Vec::with_capacity(2);
+    for apartment in [Apartment::Sta, Apartment::Mta] {
+        timings.push(measure_apartment(apartment, rounds)?);
+    }
+
+    let (sta, mta) = (timings[0], timings[1]);
+    let calibration = ApartmentCalibration {
+        enumeration: faster(sta.enumeration, mta.enumeration),
+        timings,
+    };
+    log::info!("COM apartment calibration: {calibration:?}");
+    Ok(calibration)
+}
+
+/// Ties go to MTA, the apartment the rest of the app uses.
+fn faster(sta: Duration, mta: Duration) -> Apartment {
+    if sta < mta { Apartment::Sta } else { Apartment::Mta }
+}
+
+fn measure_apartment(apartment: Apartment, rounds: u32) -> Result<ApartmentTiming> {
+    // 预热一次，排除创建线程和首次加载驱动/COM 类工厂的开销
+    dispatch_timed(apartment, enumeration_workload)?.0?;
+
+    let mut enumeration = Duration::ZERO;
+    for _ in 0..rounds {
+        let (result, elapsed) = dispatch_timed(apartment, enumeration_workload)?;
+        result?;
+        enumeration += elapsed;
+    }
+
+    Ok(ApartmentTiming {
+        apartment,
+        enumeration: enumeration / rounds,
+    })
+}
+
+/// Representative enumeration work: create an enumerator and list active render endpoints.
+fn enumeration_workload() -> Result<()> {
+    let enumerator: IMMDeviceEnumerator =
+        unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
+            .map_err(|e| anyhow!("CoCreateInstance MMDeviceEnumerator failed: {:?}", e))?;
+    let collection = unsafe { enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE) }
+        .map_err(|e| anyhow!("EnumAudioEndpoints failed: {:?}", e))?;
+    unsafe { collection.GetCount() }.map_err(|e| anyhow!("GetCount failed: {:?}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ties_prefer_mta() {
+        let d = Duration::from_micros(250);
+        assert_eq!(faster(d, d), Apartment::Mta);
+        assert_eq!(faster(d / 2, d), Apartment::Sta);
+        assert_eq!(faster(d, d / 2), Apartment::Mta);
+    }
+
+    #[test]
+    #[ignore = "requires real Windows audio devices"]
+    fn calibration_measures_both_apartments() {
+        let calibration = calibrate_apartments(3).expect("calibrate");
+        assert_eq!(calibration.timings.len(), 2);
+        println!("{calibration:#?}");
+    }
+}
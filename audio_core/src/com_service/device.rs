@@ -1,20 +1,31 @@
 //! Audio device enumeration and management.
 //!
-//! This module provides functionality to enumerate, query, and manage audio output devices
-//! using Windows Core Audio APIs. It handles device discovery, state checking, and format
+//! This module provides functionality to enumerate, query, and manage audio output and
+//! input (capture) devices using Windows Core Audio APIs. It handles device discovery, state checking, and format
 //! information retrieval in a thread-safe manner via the COM worker.
 
-use crate::utils::{map_state, win_helpers};
+use super::policy_config::{CLSID_POLICY_CONFIG_CLIENT, IPolicyConfig};
+use crate::router::SourceRole;
+use crate::utils::{ComSend, MixFormatInfo, map_state, win_helpers};
 use anyhow::{Result, anyhow};
 use callcomapi::with_com;
+use config::state::DefaultRole;
 use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::Media::Audio::Endpoints::{
+    IAudioEndpointVolume, IAudioEndpointVolumeCallback, IAudioEndpointVolumeCallback_Impl,
+};
 use windows::Win32::Media::Audio::{
-    DEVICE_STATE_ACTIVE, IAudioClient, IMMDevice, IMMDeviceCollection, IMMDeviceEnumerator,
-    MMDeviceEnumerator, eConsole, eRender,
+    AUDIO_VOLUME_NOTIFICATION_DATA, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED,
+    DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED, DEVICE_STATEMASK_ALL, EDataFlow, ERole,
+    IAudioClient, IMMDevice, IMMDeviceCollection, IMMDeviceEnumerator, MMDeviceEnumerator,
+    PKEY_AudioEndpoint_FormFactor, eCapture, eCommunications, eConsole, eMultimedia, eRender,
 };
 use windows::Win32::System::Com::{CLSCTX_ALL, CoCreateInstance, STGM_READ};
+use windows::core::implement;
 
 /// Device connection/state
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -26,6 +37,189 @@ pub enum DeviceState {
     Unknown,
 }
 
+/// Role a default device is chosen for (`ERole`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceRole {
+    /// Games, system sounds and most applications
+    Console,
+    /// Music and video playback
+    Multimedia,
+    /// Voice calls and chat
+    Communications,
+}
+
+impl DeviceRole {
+    pub const ALL: [DeviceRole; 3] = [
+        DeviceRole::Console,
+        DeviceRole::Multimedia,
+        DeviceRole::Communications,
+    ];
+
+    fn as_erole(self) -> ERole {
+        match self {
+            DeviceRole::Console => eConsole,
+            DeviceRole::Multimedia => eMultimedia,
+            DeviceRole::Communications => eCommunications,
+        }
+    }
+
+    pub(crate) fn from_erole(role: ERole) -> Option<Self> {
+        DeviceRole::ALL.into_iter().find(|r| r.as_erole() == role)
+    }
+}
+
+impl From<DefaultRole> for DeviceRole {
+    fn from(role: DefaultRole) -> Self {
+        match role {
+            DefaultRole::Console => DeviceRole::Console,
+            DefaultRole::Multimedia => DeviceRole::Multimedia,
+            DefaultRole::Communications => DeviceRole::Communications,
+        }
+    }
+}
+
+impl From<DeviceRole> for DefaultRole {
+    fn from(role: DeviceRole) -> Self {
+        match role {
+            DeviceRole::Console => DefaultRole::Console,
+            DeviceRole::Multimedia => DefaultRole::Multimedia,
+            DeviceRole::Communications => DefaultRole::Communications,
+        }
+    }
+}
+
+impl From<SourceRole> for DeviceRole {
+    fn from(role: SourceRole) -> Self {
+        match role {
+            SourceRole::Console => DeviceRole::Console,
+            SourceRole::Communications => DeviceRole::Communications,
+        }
+    }
+}
+
+/// Direction of an endpoint (`EDataFlow`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceFlow {
+    /// Output (playback) device
+    Render,
+    /// Input (recording) device
+    Capture,
+}
+
+impl DeviceFlow {
+    pub const ALL: [DeviceFlow; 2] = [DeviceFlow::Render, DeviceFlow::Capture];
+
+    fn as_edataflow(self) -> EDataFlow {
+        match self {
+            DeviceFlow::Render => eRender,
+            DeviceFlow::Capture => eCapture,
+        }
+    }
+
+    /// `None` for `eAll`.
+    pub(crate) fn from_edataflow(flow: EDataFlow) -> Option<Self> {
+        DeviceFlow::ALL
+            .into_iter()
+            .find(|f| f.as_edataflow() == flow)
+    }
+}
+
+/// Set of [`DeviceState`]s to enumerate, combined with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceStateMask(u32);
+
+impl DeviceStateMask {
+    pub const ACTIVE: Self = Self(DEVICE_STATE_ACTIVE);
+    pub const DISABLED: Self = Self(DEVICE_STATE_DISABLED);
+    pub const NOT_PRESENT: Self = Self(DEVICE_STATE_NOTPRESENT);
+    pub const UNPLUGGED: Self = Self(DEVICE_STATE_UNPLUGGED);
+    pub const ALL: Self = Self(DEVICE_STATEMASK_ALL);
+
+    /// Raw `DEVICE_STATE_*` bits.
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for DeviceStateMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Physical kind of endpoint (`PKEY_AudioEndpoint_FormFactor`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FormFactor {
+    RemoteNetworkDevice,
+    Speakers,
+    LineLevel,
+    Headphones,
+    Microphone,
+    Headset,
+    Handset,
+    /// Digital passthrough of unknown kind
+    DigitalPassthrough,
+    Spdif,
+    /// HDMI or DisplayPort display
+    Hdmi,
+    Unknown,
+}
+
+impl FormFactor {
+    /// Maps an `EndpointFormFactor` value.
+    pub fn from_raw(value: u32) -> Self {
+        match value {
+            0 => FormFactor::RemoteNetworkDevice,
+            1 => FormFactor::Speakers,
+            2 => FormFactor::LineLevel,
+            3 => FormFactor::Headphones,
+            4 => FormFactor::Microphone,
+            5 => FormFactor::Headset,
+            6 => FormFactor::Handset,
+            7 => FormFactor::DigitalPassthrough,
+            8 => FormFactor::Spdif,
+            9 => FormFactor::Hdmi,
+            _ => FormFactor::Unknown,
+        }
+    }
+}
+
+/// How the device is attached, derived from its bus enumerator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceBus {
+    Usb,
+    Bluetooth,
+    /// Onboard HD Audio codec, including HDMI/DisplayPort audio of the GPU
+    HdAudio,
+    /// Software or virtual device (e.g. a virtual cable)
+    Software,
+    Other,
+}
+
+impl DeviceBus {
+    /// Maps a `PKEY_Device_EnumeratorName` value such as `USB` or `BTHENUM`.
+    pub fn from_enumerator(name: &str) -> Self {
+        let name = name.to_ascii_uppercase();
+        if name == "USB" {
+            DeviceBus::Usb
+        } else if name.starts_with("BTH") {
+            DeviceBus::Bluetooth
+        } else if name == "HDAUDIO" {
+            DeviceBus::HdAudio
+        } else if name == "SWD" || name == "ROOT" {
+            DeviceBus::Software
+        } else {
+            DeviceBus::Other
+        }
+    }
+}
+
 /// Basic device info used by the rest of the system
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DeviceInfo {
@@ -35,66 +229,133 @@ pub struct DeviceInfo {
     pub channels: Option<u16>, // Number of channels
     /// Optional channel mask (WAVEFORMATEXTENSIBLE.dwChannelMask)
     pub channel_mask: Option<u32>, // Bitmask of speaker positions
-    pub is_default: bool,      // Is this the default output device?
+    pub is_default: bool,      // Is this the default (console) device of its direction?
+    /// Roles this device is the default device for
+    pub default_roles: Vec<DeviceRole>,
+    /// Mix format sample rate in Hz
+    pub sample_rate: Option<u32>,
+    /// Mix format container size in bits (32 for the usual float mix format)
+    pub bits_per_sample: Option<u16>,
+    /// Speakers, headphones, HDMI, ...
+    pub form_factor: FormFactor,
+    /// Icon resource of the endpoint, e.g. `%windir%\system32\mmres.dll,-3010`
+    pub icon_path: Option<String>,
+    /// Bus enumerator name, e.g. `USB`, `BTHENUM`, `HDAUDIO`
+    pub enumerator_name: Option<String>,
+}
+
+impl DeviceInfo {
+    /// How the device is attached; [`DeviceBus::Other`] if unknown.
+    pub fn bus(&self) -> DeviceBus {
+        self.enumerator_name
+            .as_deref()
+            .map_or(DeviceBus::Other, DeviceBus::from_enumerator)
+    }
+}
+
+/// Hardware (endpoint) volume of a device, as set by the Windows volume slider
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EndpointVolume {
+    /// Master volume scalar, 0.0–1.0 (the slider position, not linear gain)
+    pub scalar: f32,
+    pub muted: bool,
+}
+
+/// How much [`enumerate_output_devices`] reads for each device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceDetail {
+    /// Property store only. `channels`, `channel_mask`, `sample_rate` and `bits_per_sample` are
+    /// `None`; no audio client is activated, so sleeping Bluetooth devices are not woken up.
+    Properties,
+    /// Also activates an `IAudioClient` per device to read its mix format.
+    Format,
 }
 
 /// Internal function to get all output devices. Must be called in a COM-initialized environment.
+fn get_all_output_devices_internal() -> Result<Vec<DeviceInfo>> {
+    get_all_devices_internal(eRender, DeviceStateMask::ACTIVE, DeviceDetail::Format)
+}
+
+/// Internal function to get all devices of one direction. Must be called in a COM-initialized
+/// environment.
 ///
-/// This function enumerates all active audio rendering endpoints and collects
-/// their information, including whether each is the default device.
+/// This function enumerates the endpoints of `flow` (`eRender` or `eCapture`) whose state is in
+/// `states` and collects their information, including whether each is the default device of that
+/// direction. Devices that are not active have no mix format, and no device has one with
+/// [`DeviceDetail::Properties`].
 ///
 /// # Returns
-/// A vector of `DeviceInfo` for all active output devices.
+/// A vector of `DeviceInfo` for the matching devices of `flow`.
 ///
 /// # Errors
 /// Returns an error if COM operations fail.
-fn get_all_output_devices_internal() -> Result<Vec<DeviceInfo>> {
+fn get_all_devices_internal(
+    flow: EDataFlow,
+    states: DeviceStateMask,
+    detail: DeviceDetail,
+) -> Result<Vec<DeviceInfo>> {
     let enumerator: IMMDeviceEnumerator =
         unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
             .map_err(|e| anyhow!("CoCreateInstance MMDeviceEnumerator failed: {:?}", e))?;
 
     let collection: IMMDeviceCollection =
-        unsafe { enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE) }
+        unsafe { enumerator.EnumAudioEndpoints(flow, states.bits()) }
             .map_err(|e| anyhow!("EnumAudioEndpoints failed: {:?}", e))?;
 
     let count =
         unsafe { collection.GetCount() }.map_err(|e| anyhow!("GetCount failed: {:?}", e))? as u32;
 
-    // Determine default device id so we can mark `is_default` correctly
-    let default_device_id = unsafe { enumerator.GetDefaultAudioEndpoint(eRender, eConsole) }
-        .ok()
-        .and_then(|dev| unsafe { dev.GetId() }.ok())
-        .and_then(|id_pwstr| unsafe { id_pwstr.to_string() }.ok());
+    // Determine default device ids so we can mark `is_default` / `default_roles` correctly
+    let defaults = default_device_ids(&enumerator, flow);
 
     let mut out = Vec::new();
     for i in 0..count {
         let device =
             unsafe { collection.Item(i) }.map_err(|e| anyhow!("Item({}) failed: {:?}", i, e))?;
-        let info = get_device_info_internal(&device, default_device_id.as_deref())?;
+        let info = get_device_info_internal(&device, &defaults, detail)?;
         out.push(info);
     }
 
     Ok(out)
 }
 
-/// Internal function to get the default output device. Must be called in a COM-initialized environment.
+/// Ids of the default devices of `flow`, for every role that has one.
+pub(super) fn default_device_ids(
+    enumerator: &IMMDeviceEnumerator,
+    flow: EDataFlow,
+) -> Vec<(DeviceRole, String)> {
+    DeviceRole::ALL
+        .into_iter()
+        .filter_map(|role| {
+            let dev = unsafe { enumerator.GetDefaultAudioEndpoint(flow, role.as_erole()) }.ok()?;
+            let id_pwstr = unsafe { dev.GetId() }.ok()?;
+            let id = unsafe { id_pwstr.to_string() }.ok()?;
+            Some((role, id))
+        })
+        .collect()
+}
+
+/// Internal function to get the default device of `flow` for `role`. Must be called in a
+/// COM-initialized environment.
 ///
 /// # Returns
-/// A `DeviceInfo` for the default audio output device.
+/// A `DeviceInfo` for the default audio device of `flow` and `role`.
 ///
 /// # Errors
 /// Returns an error if the default device cannot be retrieved or queried.
-fn get_default_output_device_internal() -> Result<DeviceInfo> {
+pub(crate) fn get_default_device_internal(flow: EDataFlow, role: DeviceRole) -> Result<DeviceInfo> {
     let enumerator: IMMDeviceEnumerator =
         unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
             .map_err(|e| anyhow!("CoCreateInstance MMDeviceEnumerator failed: {:?}", e))?;
 
-    let dev = unsafe { enumerator.GetDefaultAudioEndpoint(eRender, eConsole) }
+    let dev = unsafe { enumerator.GetDefaultAudioEndpoint(flow, role.as_erole()) }
         .map_err(|e| anyhow!("GetDefaultAudioEndpoint failed: {:?}", e))?;
-    let id_pwstr = unsafe { dev.GetId() }.map_err(|e| anyhow!("GetId failed: {:?}", e))?;
-    let default_id = unsafe { id_pwstr.to_string() }.unwrap_or_default();
 
-    get_device_info_internal(&dev, Some(&default_id))
+    get_device_info_internal(
+        &dev,
+        &default_device_ids(&enumerator, flow),
+        DeviceDetail::Format,
+    )
 }
 
 /// Internal function to get a device by its ID. Must be called in a COM-initialized environment.
@@ -112,64 +373,165 @@ pub(super) fn get_output_device_by_id_internal(id: &str) -> Result<IMMDevice> {
         unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
             .map_err(|e| anyhow!("CoCreateInstance MMDeviceEnumerator failed: {:?}", e))?;
 
-    let wide: Vec<u16> = OsStr::new(id)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
+    let wide = to_wide(id);
     let pwstr = windows::core::PCWSTR(wide.as_ptr());
 
     unsafe { enumerator.GetDevice(pwstr) }.map_err(|e| anyhow!("GetDevice failed: {:?}", e))
 }
 
+/// Internal function to make `id` the default device for `role`. Must be called in a
+/// COM-initialized environment.
+///
+/// # Errors
+/// Returns an error if `IPolicyConfig` is unavailable or rejects the device.
+fn set_default_device_internal(id: &str, role: DeviceRole) -> Result<()> {
+    let policy = policy_config_internal()?;
+    let wide = to_wide(id);
+    unsafe { policy.SetDefaultEndpoint(windows::core::PCWSTR(wide.as_ptr()), role.as_erole()) }
+        .ok()
+        .map_err(|e| anyhow!("SetDefaultEndpoint failed: {:?}", e))
+}
+
+/// Internal function to enable or disable endpoint `id`. Must be called in a COM-initialized
+/// environment.
+///
+/// # Errors
+/// Returns an error if `IPolicyConfig` is unavailable or rejects the device.
+fn set_device_enabled_internal(id: &str, enabled: bool) -> Result<()> {
+    let policy = policy_config_internal()?;
+    let wide = to_wide(id);
+    unsafe { policy.SetEndpointVisibility(windows::core::PCWSTR(wide.as_ptr()), enabled as i32) }
+        .ok()
+        .map_err(|e| anyhow!("SetEndpointVisibility failed: {:?}", e))
+}
+
+fn policy_config_internal() -> Result<IPolicyConfig> {
+    unsafe { CoCreateInstance(&CLSID_POLICY_CONFIG_CLIENT, None, CLSCTX_ALL) }
+        .map_err(|e| anyhow!("CoCreateInstance PolicyConfigClient failed: {:?}", e))
+}
+
+/// Internal function to activate the endpoint volume control of device `id`. Must be called in
+/// a COM-initialized environment.
+fn endpoint_volume_internal(id: &str) -> Result<IAudioEndpointVolume> {
+    let device = get_output_device_by_id_internal(id)?;
+    unsafe { device.Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None) }
+        .map_err(|e| anyhow!("Activate IAudioEndpointVolume failed: {:?}", e))
+}
+
+/// Internal function to read the endpoint volume of device `id`. Must be called in a
+/// COM-initialized environment.
+fn get_endpoint_volume_internal(id: &str) -> Result<EndpointVolume> {
+    let volume = endpoint_volume_internal(id)?;
+    let scalar = unsafe { volume.GetMasterVolumeLevelScalar() }
+        .map_err(|e| anyhow!("GetMasterVolumeLevelScalar failed: {:?}", e))?;
+    let muted = unsafe { volume.GetMute() }.map_err(|e| anyhow!("GetMute failed: {:?}", e))?;
+    Ok(EndpointVolume {
+        scalar,
+        muted: muted.as_bool(),
+    })
+}
+
+/// Forwards endpoint volume notifications to a channel.
+#[implement(IAudioEndpointVolumeCallback)]
+struct VolumeCallback {
+    sender: Sender<EndpointVolume>,
+}
+
+impl IAudioEndpointVolumeCallback_Impl for VolumeCallback {
+    fn OnNotify(&self, pnotify: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows::core::Result<()> {
+        if let Some(data) = unsafe { pnotify.as_ref() } {
+            let _ = self.sender.send(EndpointVolume {
+                scalar: data.fMasterVolume,
+                muted: data.bMuted.as_bool(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Nul-terminated UTF-16 copy of a device id.
+fn to_wide(id: &str) -> Vec<u16> {
+    OsStr::new(id)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
 /// Internal function to retrieve detailed information about a specific audio device.
 ///
 /// This function queries the device's properties, state, and audio format information.
 ///
 /// # Parameters
 /// - `device`: Reference to the `IMMDevice` interface.
-/// - `default_device_id`: Optional ID of the default device for comparison.
+/// - `defaults`: Ids of the default devices per role, from [`default_device_ids`].
+/// - `detail`: Whether to activate the device for its mix format.
 ///
 /// # Returns
 /// A `DeviceInfo` struct with the device's details.
 ///
 /// # Errors
 /// Returns an error if property queries or format retrieval fails.
-fn get_device_info_internal(
+pub(super) fn get_device_info_internal(
     device: &IMMDevice,
-    default_device_id: Option<&str>,
+    defaults: &[(DeviceRole, String)],
+    detail: DeviceDetail,
 ) -> Result<DeviceInfo> {
     let id_pwstr = unsafe { device.GetId() }.map_err(|e| anyhow!("GetId failed: {:?}", e))?;
     let id = unsafe { id_pwstr.to_string() }.unwrap_or_else(|_| String::new());
     let state = unsafe { device.GetState().unwrap_or(0) };
 
     let mut friendly_name = id.clone();
-    if let Ok(store) = unsafe { device.OpenPropertyStore(STGM_READ) }
-        && let Some(s) =
+    let mut form_factor = FormFactor::Unknown;
+    let mut icon_path = None;
+    let mut enumerator_name = None;
+    if let Ok(store) = unsafe { device.OpenPropertyStore(STGM_READ) } {
+        if let Some(s) =
             unsafe { win_helpers::read_property_string(&store, &win_helpers::PKEY_DEVICE_FRIENDLY) }
-    {
-        friendly_name = s;
+        {
+            friendly_name = s;
+        }
+        if let Some(raw) =
+            unsafe { win_helpers::read_property_u32(&store, &PKEY_AudioEndpoint_FormFactor) }
+        {
+            form_factor = FormFactor::from_raw(raw);
+        }
+        icon_path = unsafe {
+            win_helpers::read_property_string(&store, &win_helpers::PKEY_DEVICE_ICON_PATH)
+        };
+        enumerator_name = unsafe {
+            win_helpers::read_property_string(&store, &win_helpers::PKEY_DEVICE_ENUMERATOR_NAME)
+        };
     }
 
-    let mut channels = None;
-    let mut channel_mask = None;
-    if let Ok(audio_client) = unsafe { device.Activate::<IAudioClient>(CLSCTX_ALL, None) }
+    let mut mix_format = MixFormatInfo::default();
+    if detail == DeviceDetail::Format
+        && let Ok(audio_client) = unsafe { device.Activate::<IAudioClient>(CLSCTX_ALL, None) }
         && let Ok(pwf) = unsafe { audio_client.GetMixFormat() }
     {
-        let (ch, mask) = unsafe { crate::utils::parse_mix_format(pwf) };
-        channels = ch;
-        channel_mask = mask;
+        mix_format = unsafe { crate::utils::parse_mix_format(pwf) };
     }
 
-    // Determine if this is the default device by comparing IDs. Note that `default_device_id` may be None if we failed to get it, in which case we'll just mark all devices as non-default.
-    let is_default = default_device_id.is_some_and(|d| d == id);
+    // Determine the roles this device is default for by comparing IDs. Roles whose default could not be retrieved are missing from `defaults`, so no device holds them.
+    let default_roles: Vec<DeviceRole> = defaults
+        .iter()
+        .filter(|(_, default_id)| *default_id == id)
+        .map(|(role, _)| *role)
+        .collect();
+    let is_default = default_roles.contains(&DeviceRole::Console);
 
     Ok(DeviceInfo {
         id,
         friendly_name,
         state: map_state(state),
-        channels,
-        channel_mask,
+        channels: mix_format.channels,
+        channel_mask: mix_format.channel_mask,
         is_default,
+        default_roles,
+        sample_rate: mix_format.sample_rate,
+        bits_per_sample: mix_format.bits_per_sample,
+        form_factor,
+        icon_path,
+        enumerator_name,
     })
 }
 
@@ -186,6 +548,70 @@ pub fn get_all_output_devices() -> Result<Vec<DeviceInfo>> {
     get_all_output_devices_internal()
 }
 
+/// Async variant of [`get_all_output_devices`]: the enumeration is dispatched to the COM
+/// worker and the returned future resolves when it finishes, so async callers do not block a
+/// runtime thread while endpoints (Bluetooth ones in particular) are queried.
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+#[with_com]
+pub async fn get_all_output_devices_async() -> Result<Vec<DeviceInfo>> {
+    get_all_output_devices_internal()
+}
+
+/// Retrieves the audio output devices whose state is in `states`, e.g.
+/// `DeviceStateMask::ACTIVE | DeviceStateMask::UNPLUGGED` to keep showing a headset that is
+/// currently unplugged.
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+#[with_com]
+pub fn get_output_devices(states: DeviceStateMask) -> Result<Vec<DeviceInfo>> {
+    get_all_devices_internal(eRender, states, DeviceDetail::Format)
+}
+
+/// Retrieves the audio output devices whose state is in `states`, reading only what `detail`
+/// asks for. With [`DeviceDetail::Properties`] this is the fast path for device lists; fetch the
+/// format of a single device later with [`crate::com_service::jack::get_device_details`].
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+#[with_com]
+pub fn enumerate_output_devices(
+    states: DeviceStateMask,
+    detail: DeviceDetail,
+) -> Result<Vec<DeviceInfo>> {
+    get_all_devices_internal(eRender, states, detail)
+}
+
+/// Retrieves all active audio output devices, running the COM work on the
+/// long-lived thread for `apartment` instead of the shared `#[with_com]` worker.
+///
+/// Used when apartment calibration found that `apartment` dispatches faster on
+/// this machine (see [`crate::com_service::apartment::calibrate_apartments`]).
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+pub fn get_all_output_devices_in(
+    apartment: crate::com_service::apartment::Apartment,
+) -> Result<Vec<DeviceInfo>> {
+    crate::com_service::apartment::dispatch_in_apartment(apartment, get_all_output_devices_internal)
+}
+
+/// [`enumerate_output_devices`], running the COM work on the long-lived thread for `apartment`.
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+pub fn enumerate_output_devices_in(
+    apartment: crate::com_service::apartment::Apartment,
+    states: DeviceStateMask,
+    detail: DeviceDetail,
+) -> Result<Vec<DeviceInfo>> {
+    crate::com_service::apartment::dispatch_in_apartment(apartment, move || {
+        get_all_devices_internal(eRender, states, detail)
+    })
+}
+
 /// Retrieves information about the default audio output device.
 ///
 /// # Returns
@@ -195,7 +621,94 @@ pub fn get_all_output_devices() -> Result<Vec<DeviceInfo>> {
 /// Returns an error if the default device cannot be retrieved.
 #[with_com]
 pub fn get_default_output_device() -> Result<DeviceInfo> {
-    get_default_output_device_internal()
+    get_default_device_internal(eRender, DeviceRole::Console)
+}
+
+/// Retrieves the default audio output device for `role`, e.g. the communications device a
+/// headset is usually set as.
+///
+/// # Errors
+/// Returns an error if no device is default for `role` or it cannot be queried.
+#[with_com]
+pub fn get_default_output_device_for_role(role: DeviceRole) -> Result<DeviceInfo> {
+    get_default_device_internal(eRender, role)
+}
+
+/// Retrieves the default device of `flow` for `role`.
+///
+/// # Errors
+/// Returns an error if no device is default for `flow` and `role` or it cannot be queried.
+#[with_com]
+pub fn get_default_device(flow: DeviceFlow, role: DeviceRole) -> Result<DeviceInfo> {
+    get_default_device_internal(flow.as_edataflow(), role)
+}
+
+/// Retrieves a list of all active audio input (capture) devices on the system, such as
+/// microphones and line-in.
+///
+/// # Returns
+/// A vector of `DeviceInfo` structs; `channels` is the channel count of the capture mix format.
+///
+/// # Errors
+/// Returns an error if device enumeration fails or COM operations encounter issues.
+#[with_com]
+pub fn get_all_input_devices() -> Result<Vec<DeviceInfo>> {
+    get_all_devices_internal(eCapture, DeviceStateMask::ACTIVE, DeviceDetail::Format)
+}
+
+/// Retrieves information about the default audio input (capture) device.
+///
+/// # Returns
+/// A `DeviceInfo` struct for the default input device.
+///
+/// # Errors
+/// Returns an error if there is no input device or it cannot be queried.
+#[with_com]
+pub fn get_default_input_device() -> Result<DeviceInfo> {
+    get_default_device_internal(eCapture, DeviceRole::Console)
+}
+
+/// Makes `id` the Windows default output device for `role`, like "Set as Default Device" in
+/// the Sound control panel. The change is system-wide and persists after the app exits.
+///
+/// This goes through the undocumented `IPolicyConfig` interface, which has no public SDK
+/// definition but has kept the same layout since Windows 7.
+///
+/// # Errors
+/// Returns an error if the device does not exist or Windows rejects the change.
+#[with_com]
+pub fn set_default_output_device(id: &str, role: DeviceRole) -> Result<()> {
+    let id_str = id.to_string();
+    set_default_device_internal(&id_str, role)
+}
+
+/// Enables or disables endpoint `id` system-wide, like "Disable" in the Sound control panel.
+/// A disabled device is hidden from other applications and reported as
+/// [`DeviceState::Disabled`]; it stays disabled after the app exits until re-enabled.
+///
+/// Requires administrator rights on most systems.
+///
+/// # Errors
+/// Returns an error if the device does not exist or Windows rejects the change.
+#[with_com]
+pub fn set_device_enabled(id: &str, enabled: bool) -> Result<()> {
+    let id_str = id.to_string();
+    set_device_enabled_internal(&id_str, enabled)
+}
+
+/// Reads the current friendly name of device `id`, e.g. after the user renamed it in the Sound
+/// settings.
+///
+/// # Errors
+/// Returns an error if the device is not found or has no friendly name.
+#[with_com]
+pub fn get_device_friendly_name(id: &str) -> Result<String> {
+    let id_str = id.to_string();
+    let device = get_output_device_by_id_internal(&id_str)?;
+    let store = unsafe { device.OpenPropertyStore(STGM_READ) }
+        .map_err(|e| anyhow!("OpenPropertyStore failed: {:?}", e))?;
+    unsafe { win_helpers::read_property_string(&store, &win_helpers::PKEY_DEVICE_FRIENDLY) }
+        .ok_or_else(|| anyhow!("device {} has no friendly name", id_str))
 }
 
 /// Retrieves an audio device by its ID.
@@ -217,10 +730,154 @@ pub fn get_output_device_by_id(id: &str) -> Result<crate::utils::ComSend<IMMDevi
     get_output_device_by_id_internal(&id_str).map(crate::utils::ComSend::new)
 }
 
+/// Reads the hardware volume and mute state of device `id`.
+///
+/// # Errors
+/// Returns an error if the device is not found or has no volume control.
+#[with_com]
+pub fn get_endpoint_volume(id: &str) -> Result<EndpointVolume> {
+    let id_str = id.to_string();
+    get_endpoint_volume_internal(&id_str)
+}
+
+/// Sets the hardware volume of device `id` to `scalar` (0.0–1.0), like moving its Windows volume
+/// slider.
+///
+/// # Errors
+/// Returns an error if `scalar` is out of range or the device rejects the change.
+#[with_com]
+pub fn set_endpoint_volume(id: &str, scalar: f32) -> Result<()> {
+    if !(0.0..=1.0).contains(&scalar) {
+        return Err(anyhow!("volume {scalar} is out of range 0.0-1.0"));
+    }
+    let volume = endpoint_volume_internal(id)?;
+    unsafe { volume.SetMasterVolumeLevelScalar(scalar, std::ptr::null()) }
+        .map_err(|e| anyhow!("SetMasterVolumeLevelScalar failed: {:?}", e))
+}
+
+/// Mutes or unmutes device `id` at the endpoint.
+///
+/// # Errors
+/// Returns an error if the device is not found or rejects the change.
+#[with_com]
+pub fn set_endpoint_mute(id: &str, muted: bool) -> Result<()> {
+    let volume = endpoint_volume_internal(id)?;
+    unsafe { volume.SetMute(BOOL::from(muted), std::ptr::null()) }
+        .map_err(|e| anyhow!("SetMute failed: {:?}", e))
+}
+
+#[with_com]
+fn register_volume_callback(
+    id: &str,
+    sender: Sender<EndpointVolume>,
+) -> Result<ComSend<(IAudioEndpointVolume, IAudioEndpointVolumeCallback)>> {
+    let volume = endpoint_volume_internal(id)?;
+    let callback: IAudioEndpointVolumeCallback = VolumeCallback { sender }.into();
+    unsafe { volume.RegisterControlChangeNotify(&callback) }
+        .map_err(|e| anyhow!("RegisterControlChangeNotify failed: {:?}", e))?;
+    Ok(ComSend::new((volume, callback)))
+}
+
+#[with_com]
+fn unregister_volume_callback(
+    registration: ComSend<(IAudioEndpointVolume, IAudioEndpointVolumeCallback)>,
+) -> Result<ComSend<()>> {
+    let (volume, callback) = registration.take();
+    unsafe { volume.UnregisterControlChangeNotify(&callback) }
+        .map_err(|e| anyhow!("UnregisterControlChangeNotify failed: {:?}", e))
+        .map(ComSend::new)
+}
+
+/// Registration of a volume-changed callback; dropping it unregisters the callback.
+pub struct EndpointVolumeWatch {
+    registration: Option<ComSend<(IAudioEndpointVolume, IAudioEndpointVolumeCallback)>>,
+}
+
+impl Drop for EndpointVolumeWatch {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take()
+            && let Err(e) = unregister_volume_callback(registration)
+        {
+            log::warn!("Unregister volume callback failed: {e:?}");
+        }
+    }
+}
+
+/// Watches the hardware volume of device `id`. Every change, including ones made in the Windows
+/// volume mixer or by hardware keys, is sent on the returned receiver until the watch is dropped.
+///
+/// # Errors
+/// Returns an error if the device is not found or has no volume control.
+pub fn watch_endpoint_volume(id: &str) -> Result<(EndpointVolumeWatch, Receiver<EndpointVolume>)> {
+    let (sender, receiver) = mpsc::channel();
+    let registration = register_volume_callback(id, sender)?;
+    Ok((
+        EndpointVolumeWatch {
+            registration: Some(registration),
+        },
+        receiver,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    #[ignore = "requires real Windows audio devices"]
+    async fn async_enumeration_matches_sync() {
+        let ids = |devices: Vec<DeviceInfo>| devices.into_iter().map(|d| d.id).collect::<Vec<_>>();
+        let sync = get_all_output_devices().expect("sync enumeration failed");
+        let asynchronous = get_all_output_devices_async()
+            .await
+            .expect("async enumeration failed");
+        assert_eq!(ids(asynchronous), ids(sync));
+    }
+
+    #[test]
+    fn flow_and_role_map_from_windows_values() {
+        assert_eq!(
+            DeviceFlow::from_edataflow(eCapture),
+            Some(DeviceFlow::Capture)
+        );
+        assert_eq!(DeviceFlow::from_edataflow(EDataFlow(2)), None);
+        assert_eq!(
+            DeviceRole::from_erole(eCommunications),
+            Some(DeviceRole::Communications)
+        );
+    }
+
+    #[test]
+    fn form_factor_maps_endpoint_values() {
+        assert_eq!(FormFactor::from_raw(3), FormFactor::Headphones);
+        assert_eq!(FormFactor::from_raw(8), FormFactor::Spdif);
+        assert_eq!(FormFactor::from_raw(9), FormFactor::Hdmi);
+        // EndpointFormFactor_enum_count 及以上均视为未知
+        assert_eq!(FormFactor::from_raw(10), FormFactor::Unknown);
+        assert_eq!(FormFactor::from_raw(u32::MAX), FormFactor::Unknown);
+    }
+
+    #[test]
+    fn state_mask_combines_states() {
+        let mask = DeviceStateMask::ACTIVE | DeviceStateMask::UNPLUGGED;
+        assert!(mask.contains(DeviceStateMask::UNPLUGGED));
+        assert!(!mask.contains(DeviceStateMask::DISABLED));
+        assert!(DeviceStateMask::ALL.contains(mask | DeviceStateMask::NOT_PRESENT));
+    }
+
+    #[test]
+    fn bus_follows_enumerator_name() {
+        assert_eq!(DeviceBus::from_enumerator("USB"), DeviceBus::Usb);
+        assert_eq!(DeviceBus::from_enumerator("BTHENUM"), DeviceBus::Bluetooth);
+        assert_eq!(
+            DeviceBus::from_enumerator("BTHHFENUM"),
+            DeviceBus::Bluetooth
+        );
+        assert_eq!(DeviceBus::from_enumerator("HDAUDIO"), DeviceBus::HdAudio);
+        assert_eq!(DeviceBus::from_enumerator("swd"), DeviceBus::Software);
+        assert_eq!(DeviceBus::from_enumerator("PCI"), DeviceBus::Other);
+    }
+
     #[cfg(target_os = "windows")]
     #[test]
     #[ignore = "requires real Windows audio devices"]
@@ -234,8 +891,18 @@ mod tests {
             println!("Found {} audio output devices:", devices.len());
             for d in devices.iter() {
                 println!(
-                    " - id: {}, name: {}, state: {:?}, channels: {:?}, channel_mask: {:?}, is_default: {}",
-                    d.id, d.friendly_name, d.state, d.channels, d.channel_mask, d.is_default
+                    " - id: {}, name: {}, state: {:?}, channels: {:?}, channel_mask: {:?}, is_default: {}, format: {:?} Hz / {:?} bit, form factor: {:?}, bus: {:?}, icon: {:?}",
+                    d.id,
+                    d.friendly_name,
+                    d.state,
+                    d.channels,
+                    d.channel_mask,
+                    d.is_default,
+                    d.sample_rate,
+                    d.bits_per_sample,
+                    d.form_factor,
+                    d.bus(),
+                    d.icon_path
                 );
             }
         }
@@ -246,6 +913,32 @@ mod tests {
             d.id, d.friendly_name, d.state, d.channels, d.channel_mask, d.is_default
         );
 
+        for role in DeviceRole::ALL {
+            let d = get_default_output_device_for_role(role).expect("default for role");
+            assert!(d.default_roles.contains(&role));
+        }
+
+        // 包含非活动设备的枚举是活动设备的超集
+        let all = get_output_devices(DeviceStateMask::ALL).expect("list all devices");
+        assert!(devices.iter().all(|d| all.contains(d)));
+
+        let inputs = get_all_input_devices().expect("list input devices");
+        println!("Found {} audio input devices:", inputs.len());
+        for d in inputs.iter() {
+            println!(
+                " - id: {}, name: {}, state: {:?}, channels: {:?}, is_default: {}",
+                d.id, d.friendly_name, d.state, d.channels, d.is_default
+            );
+        }
+        // 默认输入设备必须出现在枚举结果中并被标记
+        if let Ok(default_input) = get_default_input_device() {
+            assert!(
+                inputs
+                    .iter()
+                    .any(|d| d.id == default_input.id && d.is_default)
+            );
+        }
+
         // Verify lookup by id for the first device
         let first_id = devices[0].id.clone();
         let found_dev = get_output_device_by_id(&first_id)
@@ -254,5 +947,8 @@ mod tests {
         let id_pwstr = unsafe { found_dev.GetId() }.expect("GetId");
         let id_str = unsafe { id_pwstr.to_string() }.unwrap_or_default();
         assert_eq!(id_str, first_id);
+
+        let volume = get_endpoint_volume(&first_id).expect("endpoint volume");
+        assert!((0.0..=1.0).contains(&volume.scalar));
     }
 }
@@ -0,0 +1,236 @@
+//! Probing which stream formats a device accepts, in shared and exclusive mode.
+
+use crate::com_service::playback::activate_endpoint;
+use crate::com_service::router::{SampleFormat, get_mix_format};
+use crate::router::mixer::default_channel_mask;
+use anyhow::Result;
+use callcomapi::with_com;
+use serde::{Deserialize, Serialize};
+use windows::Win32::Media::Audio::{
+    AUDCLNT_E_EXCLUSIVE_MODE_NOT_ALLOWED, AUDCLNT_SHAREMODE, AUDCLNT_SHAREMODE_EXCLUSIVE,
+    AUDCLNT_SHAREMODE_SHARED, IAudioClient, WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+};
+use windows::Win32::System::Com::CoTaskMemFree;
+use windows::core::GUID;
+
+/// Sample rates tried by [`probe_supported_formats`].
+pub const PROBE_SAMPLE_RATES: [u32; 6] = [44_100, 48_000, 88_200, 96_000, 176_400, 192_000];
+/// Channel counts tried by [`probe_supported_formats`].
+pub const PROBE_CHANNELS: [u16; 4] = [1, 2, 6, 8];
+
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+const KSDATAFORMAT_SUBTYPE_PCM: GUID = GUID::from_u128(0x00000001_0000_0010_8000_00aa00389b71);
+const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: GUID =
+    GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
+
+/// Sample encoding of a probed format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProbeSampleFormat {
+    Pcm16,
+    /// Packed 24-bit PCM (3 bytes per sample)
+    Pcm24,
+    Pcm32,
+    Float32,
+}
+
+impl ProbeSampleFormat {
+    pub const ALL: [ProbeSampleFormat; 4] = [
+        ProbeSampleFormat::Pcm16,
+        ProbeSampleFormat::Pcm24,
+        ProbeSampleFormat::Pcm32,
+        ProbeSampleFormat::Float32,
+    ];
+
+    pub fn bits_per_sample(self) -> u16 {
+        match self {
+            ProbeSampleFormat::Pcm16 => 16,
+            ProbeSampleFormat::Pcm24 => 24,
+            ProbeSampleFormat::Pcm32 | ProbeSampleFormat::Float32 => 32,
+        }
+    }
+
+    /// Whether the router can read and write this encoding directly.
+    pub fn is_routable(self) -> bool {
+        !matches!(self, ProbeSampleFormat::Pcm24)
+    }
+
+    fn from_sample_format(format: SampleFormat) -> Option<Self> {
+        match format {
+            SampleFormat::F32 => Some(ProbeSampleFormat::Float32),
+            SampleFormat::I16 => Some(ProbeSampleFormat::Pcm16),
+            SampleFormat::I32 => Some(ProbeSampleFormat::Pcm32),
+            SampleFormat::Unsupported => None,
+        }
+    }
+}
+
+/// Whether the device accepts one combination of rate, channels and encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbedFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: ProbeSampleFormat,
+    /// Accepted as-is in shared mode (normally only the mix format's rate)
+    pub shared: bool,
+    /// Accepted in exclusive mode
+    pub exclusive: bool,
+}
+
+/// Result of [`probe_supported_formats`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatCapabilities {
+    pub device_id: String,
+    /// The shared-mode mix format; `sample_format` is `None` when the router
+    /// cannot handle its encoding.
+    pub mix_sample_rate: u32,
+    pub mix_channels: u16,
+    pub mix_sample_format: Option<ProbeSampleFormat>,
+    /// `false` if the user disabled "Allow applications to take exclusive
+    /// control" for this device, in which case every `exclusive` is `false`.
+    pub exclusive_allowed: bool,
+    pub formats: Vec<ProbedFormat>,
+}
+
+impl FormatCapabilities {
+    /// Sample rates accepted in exclusive mode with any encoding, ascending.
+    pub fn exclusive_sample_rates(&self) -> Vec<u32> {
+        let mut rates: Vec<u32> = self
+            .formats
+            .iter()
+            .filter(|f| f.exclusive)
+            .map(|f| f.sample_rate)
+            .collect();
+        rates.sort_unstable();
+        rates.dedup();
+        rates
+    }
+}
+
+/// `WAVEFORMATEXTENSIBLE` for one probe combination, with the default
+/// channel layout for `channels`.
+fn probe_format(
+    sample_rate: u32,
+    channels: u16,
+    sample_format: ProbeSampleFormat,
+) -> WAVEFORMATEXTENSIBLE {
+    let bits = sample_format.bits_per_sample();
+    let block_align = bits / 8 * channels;
+    // SAFETY: WAVEFORMATEXTENSIBLE is plain data, all-zero is a valid value.
+    let mut ext: WAVEFORMATEXTENSIBLE = unsafe { std::mem::zeroed() };
+    ext.Format.wFormatTag = WAVE_FORMAT_EXTENSIBLE;
+    ext.Format.nChannels = channels;
+    ext.Format.nSamplesPerSec = sample_rate;
+    ext.Format.nBlockAlign = block_align;
+    ext.Format.nAvgBytesPerSec = sample_rate * block_align as u32;
+    ext.Format.wBitsPerSample = bits;
+    ext.Format.cbSize =
+        (std::mem::size_of::<WAVEFORMATEXTENSIBLE>() - std::mem::size_of::<WAVEFORMATEX>()) as u16;
+    ext.Samples.wValidBitsPerSample = bits;
+    ext.dwChannelMask = default_channel_mask(channels);
+    ext.SubFormat = match sample_format {
+        ProbeSampleFormat::Float32 => KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
+        _ => KSDATAFORMAT_SUBTYPE_PCM,
+    };
+    ext
+}
+
+/// Outcome of one `IsFormatSupported` call.
+enum Support {
+    Yes,
+    No,
+    ExclusiveNotAllowed,
+}
+
+fn is_format_supported(
+    client: &IAudioClient,
+    mode: AUDCLNT_SHAREMODE,
+    format: &WAVEFORMATEXTENSIBLE,
+) -> Support {
+    let pwf = format as *const WAVEFORMATEXTENSIBLE as *const WAVEFORMATEX;
+    let mut closest: *mut WAVEFORMATEX = std::ptr::null_mut();
+    // 共享模式必须提供 closest 指针；独占模式不允许提供
+    let closest_arg = (mode == AUDCLNT_SHAREMODE_SHARED).then_some(&mut closest as *mut _);
+    let hr = unsafe { client.IsFormatSupported(mode, pwf, closest_arg) };
+    if !closest.is_null() {
+        unsafe { CoTaskMemFree(Some(closest.cast())) };
+    }
+    // S_FALSE（只支持相近格式）也算不支持
+    if hr.0 == 0 {
+        Support::Yes
+    } else if hr == AUDCLNT_E_EXCLUSIVE_MODE_NOT_ALLOWED {
+        Support::ExclusiveNotAllowed
+    } else {
+        Support::No
+    }
+}
+
+/// Runs `IsFormatSupported` for every combination of [`PROBE_SAMPLE_RATES`],
+/// [`PROBE_CHANNELS`] and [`ProbeSampleFormat::ALL`] on device `device_id`,
+/// in shared and exclusive mode.
+///
+/// # Errors
+/// Returns an error if the device cannot be activated.
+#[with_com]
+pub fn probe_supported_formats(device_id: &str) -> Result<FormatCapabilities> {
+    let client = activate_endpoint(device_id)?;
+    let mix = get_mix_format(&client)?.stream_format();
+
+    let mut exclusive_allowed = true;
+    let mut formats = Vec::new();
+    for sample_rate in PROBE_SAMPLE_RATES {
+        for channels in PROBE_CHANNELS {
+            for sample_format in ProbeSampleFormat::ALL {
+                let format = probe_format(sample_rate, channels, sample_format);
+                let shared = matches!(
+                    is_format_supported(&client, AUDCLNT_SHAREMODE_SHARED, &format),
+                    Support::Yes
+                );
+                let exclusive = exclusive_allowed
+                    && match is_format_supported(&client, AUDCLNT_SHAREMODE_EXCLUSIVE, &format) {
+                        Support::Yes => true,
+                        Support::No => false,
+                        Support::ExclusiveNotAllowed => {
+                            exclusive_allowed = false;
+                            false
+                        }
+                    };
+                formats.push(ProbedFormat {
+                    sample_rate,
+                    channels,
+                    sample_format,
+                    shared,
+                    exclusive,
+                });
+            }
+        }
+    }
+
+    Ok(FormatCapabilities {
+        device_id: device_id.to_string(),
+        mix_sample_rate: mix.sample_rate,
+        mix_channels: mix.channels,
+        mix_sample_format: ProbeSampleFormat::from_sample_format(mix.sample_format),
+        exclusive_allowed,
+        formats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_format_describes_packed_samples() {
+        // 结构体是 packed 的，先拷贝字段再比较
+        let format = probe_format(96_000, 6, ProbeSampleFormat::Pcm24);
+        assert_eq!({ format.Format.nBlockAlign }, 18);
+        assert_eq!({ format.Format.nAvgBytesPerSec }, 96_000 * 18);
+        assert_eq!({ format.Format.cbSize }, 22);
+        assert_eq!({ format.dwChannelMask }, default_channel_mask(6));
+        assert_eq!({ format.SubFormat }, KSDATAFORMAT_SUBTYPE_PCM);
+
+        let float = probe_format(48_000, 2, ProbeSampleFormat::Float32);
+        assert_eq!({ float.Format.nBlockAlign }, 8);
+        assert_eq!({ float.SubFormat }, KSDATAFORMAT_SUBTYPE_IEEE_FLOAT);
+    }
+}
@@ -0,0 +1,296 @@
+//! Jack detection: which physical connectors of an endpoint have something
+//! plugged in, their color and where they are on the chassis.
+//!
+//! The data comes from the KS filter behind the endpoint (`IKsJackDescription`
+//! and, for HDMI/DisplayPort, `IKsJackSinkInformation`). Virtual and most USB
+//! or Bluetooth devices have no jack description, which is reported as an
+//! empty jack list rather than an error.
+
+use super::device::{
+    DeviceDetail, DeviceInfo, default_device_ids, get_device_info_internal,
+    get_output_device_by_id_internal,
+};
+use anyhow::{Result, anyhow};
+use callcomapi::with_com;
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use windows::Win32::Media::Audio::{
+    IDeviceTopology, IMMDevice, IMMDeviceEnumerator, IPart, MMDeviceEnumerator, eCapture, eRender,
+};
+use windows::Win32::Media::KernelStreaming::{
+    IKsJackDescription, IKsJackSinkInformation, KSJACK_DESCRIPTION,
+    KSJACK_SINK_CONNECTIONTYPE_DISPLAYPORT, KSJACK_SINK_CONNECTIONTYPE_HDMI,
+    KSJACK_SINK_INFORMATION,
+};
+use windows::Win32::System::Com::{CLSCTX_ALL, CLSCTX_INPROC_SERVER, CoCreateInstance};
+use windows::core::ComInterface;
+
+/// Physical connector type of a jack (`EPcxConnectionType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JackConnectionType {
+    Unknown,
+    /// 3.5 mm minijack
+    Mini35mm,
+    /// 6.35 mm (quarter-inch) jack
+    Quarter,
+    AtapiInternal,
+    Rca,
+    Optical,
+    OtherDigital,
+    OtherAnalog,
+    MultichannelAnalogDin,
+    Xlr,
+    Rj11,
+    Combination,
+}
+
+impl JackConnectionType {
+    pub fn from_raw(raw: i32) -> Self {
+        match raw {
+            1 => JackConnectionType::Mini35mm,
+            2 => JackConnectionType::Quarter,
+            3 => JackConnectionType::AtapiInternal,
+            4 => JackConnectionType::Rca,
+            5 => JackConnectionType::Optical,
+            6 => JackConnectionType::OtherDigital,
+            7 => JackConnectionType::OtherAnalog,
+            8 => JackConnectionType::MultichannelAnalogDin,
+            9 => JackConnectionType::Xlr,
+            10 => JackConnectionType::Rj11,
+            11 => JackConnectionType::Combination,
+            _ => JackConnectionType::Unknown,
+        }
+    }
+}
+
+/// Where a jack sits on the chassis (`EPcxGeoLocation`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JackLocation {
+    Unknown,
+    Rear,
+    Front,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    RearPanel,
+    Riser,
+    InsideMobileLid,
+    Drivebay,
+    Hdmi,
+    OutsideMobileLid,
+    Atapi,
+    NotApplicable,
+}
+
+impl JackLocation {
+    pub fn from_raw(raw: i32) -> Self {
+        match raw {
+            1 => JackLocation::Rear,
+            2 => JackLocation::Front,
+            3 => JackLocation::Left,
+            4 => JackLocation::Right,
+            5 => JackLocation::Top,
+            6 => JackLocation::Bottom,
+            7 => JackLocation::RearPanel,
+            8 => JackLocation::Riser,
+            9 => JackLocation::InsideMobileLid,
+            10 => JackLocation::Drivebay,
+            11 => JackLocation::Hdmi,
+            12 => JackLocation::OutsideMobileLid,
+            13 => JackLocation::Atapi,
+            14 => JackLocation::NotApplicable,
+            _ => JackLocation::Unknown,
+        }
+    }
+}
+
+/// One physical jack of an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JackInfo {
+    /// Whether the driver reports something plugged into the jack. Drivers
+    /// without jack presence detection always report `true`.
+    pub connected: bool,
+    /// Jack color as `0x00RRGGBB` (green `0x0000FF00` is the usual front-left/right
+    /// output)
+    pub color: u32,
+    pub connection_type: JackConnectionType,
+    pub location: JackLocation,
+    /// Speaker positions carried by the jack (`KSAUDIO_SPEAKER_*` mask)
+    pub channel_mapping: u32,
+}
+
+impl JackInfo {
+    /// The color as a CSS hex string, e.g. `#00ff00`.
+    pub fn color_hex(&self) -> String {
+        format!("#{:06x}", self.color & 0x00FF_FFFF)
+    }
+}
+
+/// Digital display sink type reported by `IKsJackSinkInformation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JackSinkConnection {
+    Hdmi,
+    DisplayPort,
+    Unknown,
+}
+
+/// The monitor or receiver behind an HDMI/DisplayPort endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JackSinkInfo {
+    pub connection: JackSinkConnection,
+    /// EDID manufacturer and product codes
+    pub manufacturer_id: u16,
+    pub product_id: u16,
+    /// Audio latency reported by the sink, in milliseconds (0 if unknown)
+    pub latency_ms: u16,
+    /// Monitor name from the EDID, e.g. `DELL U2720Q`
+    pub description: String,
+}
+
+/// [`DeviceInfo`] plus jack-detection data of one endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceDetails {
+    pub info: DeviceInfo,
+    /// Jacks of the endpoint; empty when the driver exposes no jack description
+    pub jacks: Vec<JackInfo>,
+    pub sink: Option<JackSinkInfo>,
+}
+
+impl DeviceDetails {
+    /// `Some(true)` if any jack has something plugged in, `None` if the device
+    /// has no jack information.
+    pub fn any_jack_connected(&self) -> Option<bool> {
+        (!self.jacks.is_empty()).then(|| self.jacks.iter().any(|j| j.connected))
+    }
+}
+
+/// The KS filter part the endpoint is connected to, which is what implements
+/// the jack interfaces. Must be called in a COM-initialized environment.
+fn jack_part_internal(device: &IMMDevice) -> Result<IPart> {
+    let topology = unsafe { device.Activate::<IDeviceTopology>(CLSCTX_ALL, None) }
+        .map_err(|e| anyhow!("Activate IDeviceTopology failed: {:?}", e))?;
+    let connector =
+        unsafe { topology.GetConnector(0) }.map_err(|e| anyhow!("GetConnector failed: {:?}", e))?;
+    let connected_to = unsafe { connector.GetConnectedTo() }
+        .map_err(|e| anyhow!("GetConnectedTo failed: {:?}", e))?;
+    connected_to
+        .cast::<IPart>()
+        .map_err(|e| anyhow!("QueryInterface IPart failed: {:?}", e))
+}
+
+/// Activates control interface `T` on `part`.
+fn activate_part<T: ComInterface>(part: &IPart) -> Result<T> {
+    let mut raw: *mut c_void = std::ptr::null_mut();
+    unsafe { part.Activate(CLSCTX_INPROC_SERVER.0, &T::IID, Some(&mut raw)) }
+        .map_err(|e| anyhow!("IPart::Activate failed: {:?}", e))?;
+    if raw.is_null() {
+        return Err(anyhow!("IPart::Activate returned no interface"));
+    }
+    Ok(unsafe { T::from_raw(raw) })
+}
+
+fn read_jacks(part: &IPart) -> Result<Vec<JackInfo>> {
+    let description: IKsJackDescription = activate_part(part)?;
+    let count = unsafe { description.GetJackCount() }
+        .map_err(|e| anyhow!("GetJackCount failed: {:?}", e))?;
+    let mut jacks = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        let mut desc = KSJACK_DESCRIPTION::default();
+        unsafe { description.GetJackDescription(index, &mut desc) }
+            .map_err(|e| anyhow!("GetJackDescription failed: {:?}", e))?;
+        jacks.push(JackInfo {
+            connected: desc.IsConnected.as_bool(),
+            color: desc.Color,
+            connection_type: JackConnectionType::from_raw(desc.ConnectionType.0),
+            location: JackLocation::from_raw(desc.GeoLocation.0),
+            channel_mapping: desc.ChannelMapping,
+        });
+    }
+    Ok(jacks)
+}
+
+fn read_sink(part: &IPart) -> Result<JackSinkInfo> {
+    let information: IKsJackSinkInformation = activate_part(part)?;
+    let mut sink = KSJACK_SINK_INFORMATION::default();
+    unsafe { information.GetJackSinkInformation(&mut sink) }
+        .map_err(|e| anyhow!("GetJackSinkInformation failed: {:?}", e))?;
+    let connection = match sink.ConnType {
+        KSJACK_SINK_CONNECTIONTYPE_HDMI => JackSinkConnection::Hdmi,
+        KSJACK_SINK_CONNECTIONTYPE_DISPLAYPORT => JackSinkConnection::DisplayPort,
+        _ => JackSinkConnection::Unknown,
+    };
+    let len = (sink.SinkDescriptionLength as usize).min(sink.SinkDescription.len());
+    Ok(JackSinkInfo {
+        connection,
+        manufacturer_id: sink.ManufacturerId,
+        product_id: sink.ProductId,
+        latency_ms: sink.AudioLatency,
+        description: String::from_utf16_lossy(&sink.SinkDescription[..len])
+            .trim_end_matches('\0')
+            .to_string(),
+    })
+}
+
+/// Retrieves [`DeviceInfo`] together with the jack-detection data of device `id`
+/// (output or input), e.g. to show whether the rear green jack actually has
+/// speakers plugged in.
+///
+/// # Errors
+/// Returns an error if the device is not found or cannot be queried. Missing
+/// jack information is not an error.
+#[with_com]
+pub fn get_device_details(id: &str) -> Result<DeviceDetails> {
+    let id_str = id.to_string();
+    let enumerator: IMMDeviceEnumerator =
+        unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
+            .map_err(|e| anyhow!("CoCreateInstance MMDeviceEnumerator failed: {:?}", e))?;
+    let device = get_output_device_by_id_internal(&id_str)?;
+    // 设备 id 在两个方向上唯一，合并两边的默认设备即可
+    let mut defaults = default_device_ids(&enumerator, eRender);
+    defaults.extend(default_device_ids(&enumerator, eCapture));
+    let info = get_device_info_internal(&device, &defaults, DeviceDetail::Format)?;
+
+    let part = jack_part_internal(&device);
+    let jacks = match &part {
+        Ok(part) => read_jacks(part).unwrap_or_else(|e| {
+            log::debug!("No jack description for {}: {}", id_str, e);
+            Vec::new()
+        }),
+        Err(e) => {
+            log::debug!("No jack topology for {}: {}", id_str, e);
+            Vec::new()
+        }
+    };
+    let sink = part.ok().and_then(|part| read_sink(&part).ok());
+
+    Ok(DeviceDetails { info, jacks, sink })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jack_values_map_and_format() {
+        assert_eq!(
+            JackConnectionType::from_raw(1),
+            JackConnectionType::Mini35mm
+        );
+        assert_eq!(
+            JackConnectionType::from_raw(99),
+            JackConnectionType::Unknown
+        );
+        assert_eq!(JackLocation::from_raw(1), JackLocation::Rear);
+        assert_eq!(JackLocation::from_raw(0), JackLocation::Unknown);
+
+        let jack = JackInfo {
+            connected: false,
+            color: 0x0000_FF00,
+            connection_type: JackConnectionType::Mini35mm,
+            location: JackLocation::Rear,
+            channel_mapping: 0x3,
+        };
+        assert_eq!(jack.color_hex(), "#00ff00");
+    }
+}
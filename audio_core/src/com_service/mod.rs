@@ -1,3 +1,8 @@
+pub mod apartment;
 pub mod device;
+pub mod formats;
+pub mod jack;
+pub mod playback;
+mod policy_config;
 pub mod router;
 pub mod watcher;
@@ -0,0 +1,353 @@
+//! Playback of generated test signals on a single output device, outside of
+//! a routing session, optionally recording the result for measurement.
+
+use crate::com_service::apartment::{Apartment, run_in_apartment};
+use crate::com_service::device::get_output_device_by_id_internal;
+use crate::com_service::router::{
+    MixFormat, SampleFormat, StreamFormat, capture_to_f32, err_code, get_mix_format,
+    initialize_capture_client_internal, initialize_render_client_internal, write_f32_samples,
+};
+use crate::dsp::{ChannelIdentification, LogSweep, SweepSettings, TestTone};
+use crate::router::mixer::SPEAKER_LOW_FREQUENCY;
+use crate::utils::decode_channel_mask;
+use anyhow::{Result, anyhow};
+use std::time::{Duration, Instant};
+use windows::Win32::Media::Audio::{AUDCLNT_BUFFERFLAGS_SILENT, IAudioCaptureClient, IAudioClient};
+use windows::Win32::System::Com::CLSCTX_ALL;
+
+/// 测试信号和测量使用的 WASAPI 缓冲区时长，不受 `[engine]` 设置影响。
+const BUFFER_DURATION: Duration = Duration::from_millis(50);
+/// 轮询输出/捕获缓冲区的间隔。
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long recording continues after the sweep has played out, so the
+/// output latency and the room's decay end up in the recording.
+pub const SWEEP_TAIL_SECS: f32 = 1.0;
+
+/// Where [`measure_sweep`] records the played sweep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MeasurementInput {
+    /// Loopback of the measured output: the signal as sent to the device,
+    /// so only the audio engine's latency is seen.
+    Loopback,
+    /// A capture endpoint, e.g. a measurement microphone.
+    Device(String),
+}
+
+/// Result of [`measure_sweep`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepMeasurement {
+    /// The sweep as played (mono, at `output_sample_rate`), the reference
+    /// for deconvolving `recording`.
+    pub excitation: Vec<f32>,
+    pub output_sample_rate: u32,
+    /// Interleaved recording at the input's own format. It starts before the
+    /// first sweep frame was queued, so the offset of the sweep in it is the
+    /// round-trip latency.
+    pub recording: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Plays a short tone burst on each channel of the output `device_id` in
+/// turn (the LFE channel gets a low tone), so the speaker wiring can be
+/// checked before choosing a mix mode. Blocks until the sequence has played.
+///
+/// Returns the speaker position of each channel in playback order, as named
+/// by [`decode_channel_mask`]; channels beyond the mask are "Channel N".
+///
+/// Runs on its own COM thread. The device must not be in exclusive use.
+pub fn play_channel_identification(device_id: &str) -> Result<Vec<String>> {
+    let device_id = device_id.to_string();
+    run_in_apartment(Apartment::Mta, move || {
+        let client = activate_endpoint(&device_id)?;
+        let mix_format = get_mix_format(&client)?;
+        let format = mix_format.stream_format();
+        let channels = format.channels as usize;
+        let lfe = (format.channel_mask & SPEAKER_LOW_FREQUENCY != 0)
+            .then(|| (format.channel_mask & (SPEAKER_LOW_FREQUENCY - 1)).count_ones() as usize)
+            .filter(|&index| index < channels);
+
+        let mut sequence = ChannelIdentification::new(channels, format.sample_rate, lfe);
+        render_generated(
+            &client,
+            &mix_format,
+            |buffer| sequence.fill(buffer),
+            || Ok(()),
+        )?;
+        Ok(channel_names(format.channel_mask, channels))
+    })
+}
+
+/// Plays a short test tone ([`crate::dsp::TEST_TONE_HZ`] for
+/// [`crate::dsp::TEST_TONE_SECS`]) on every channel of the output
+/// `device_id`, so the user can hear which device an entry is. Blocks until
+/// the tone has played.
+///
+/// Works whether or not the device is being routed to: the tone plays in its
+/// own shared-mode stream, mixed with the routed audio by the engine.
+/// Runs on its own COM thread. The device must not be in exclusive use.
+pub fn play_test_tone(device_id: &str) -> Result<()> {
+    let device_id = device_id.to_string();
+    run_in_apartment(Apartment::Mta, move || {
+        let client = activate_endpoint(&device_id)?;
+        let mix_format = get_mix_format(&client)?;
+        let format = mix_format.stream_format();
+        let mut tone = TestTone::new(format.channels as usize, format.sample_rate);
+        render_generated(&client, &mix_format, |buffer| tone.fill(buffer), || Ok(()))
+    })
+}
+
+/// Plays a log sine sweep on every channel of the output `device_id` while
+/// recording `input`, and returns both for latency and frequency response
+/// analysis. Blocks for the sweep duration plus [`SWEEP_TAIL_SECS`].
+///
+/// Runs on its own COM thread. Neither device may be in exclusive use.
+pub fn measure_sweep(
+    device_id: &str,
+    input: &MeasurementInput,
+    settings: &SweepSettings,
+) -> Result<SweepMeasurement> {
+    if !(settings.start_hz >= 1.0 && settings.end_hz > settings.start_hz) {
+        return Err(anyhow!(
+            "sweep range {} -> {} Hz must rise from at least 1 Hz",
+            settings.start_hz,
+            settings.end_hz
+        ));
+    }
+    if !(0.1..=60.0).contains(&settings.duration_secs) {
+        return Err(anyhow!(
+            "sweep duration {} s is outside 0.1..=60 s",
+            settings.duration_secs
+        ));
+    }
+    if !(-60.0..=0.0).contains(&settings.level_db) {
+        return Err(anyhow!(
+            "sweep level {} dBFS is outside -60..=0 dBFS",
+            settings.level_db
+        ));
+    }
+
+    let device_id = device_id.to_string();
+    let input = input.clone();
+    let settings = *settings;
+    run_in_apartment(Apartment::Mta, move || {
+        let client = activate_endpoint(&device_id)?;
+        let mix_format = get_mix_format(&client)?;
+        let format = mix_format.stream_format();
+        if settings.end_hz >= format.sample_rate as f32 / 2.0 {
+            return Err(anyhow!(
+                "sweep end {} Hz is above the Nyquist frequency of {} Hz output",
+                settings.end_hz,
+                format.sample_rate
+            ));
+        }
+
+        let (input_client, loopback) = match &input {
+            MeasurementInput::Loopback => (activate_endpoint(&device_id)?, true),
+            MeasurementInput::Device(input_id) => (activate_endpoint(input_id)?, false),
+        };
+        let input_format = get_mix_format(&input_client)?;
+        let mut recorder = Recorder::start(&input_client, &input_format, loopback)?;
+
+        let mut excitation = LogSweep::new(&settings, 1, format.sample_rate);
+        let mut reference = vec![0.0; excitation.total_frames()];
+        excitation.fill(&mut reference);
+
+        // 先开始录音再播放，录音中扫频的起点即为往返延迟
+        let mut recording = Vec::new();
+        let mut sweep = LogSweep::new(&settings, format.channels as usize, format.sample_rate);
+        render_generated(
+            &client,
+            &mix_format,
+            |buffer| sweep.fill(buffer),
+            || recorder.read(&mut recording),
+        )?;
+        let tail_end = Instant::now() + Duration::from_secs_f32(SWEEP_TAIL_SECS);
+        while Instant::now() < tail_end {
+            std::thread::sleep(POLL_INTERVAL);
+            recorder.read(&mut recording)?;
+        }
+
+        Ok(SweepMeasurement {
+            excitation: reference,
+            output_sample_rate: format.sample_rate,
+            recording,
+            sample_rate: recorder.format.sample_rate,
+            channels: recorder.format.channels,
+        })
+    })
+}
+
+/// Speaker name of each of the first `channels` channels.
+fn channel_names(mask: u32, channels: usize) -> Vec<String> {
+    let mut names: Vec<String> = decode_channel_mask(mask)
+        .into_iter()
+        .take(channels)
+        .map(str::to_string)
+        .collect();
+    for index in names.len()..channels {
+        names.push(format!("Channel {}", index + 1));
+    }
+    names
+}
+
+/// Activates a render or capture endpoint by its ID.
+pub(crate) fn activate_endpoint(device_id: &str) -> Result<IAudioClient> {
+    let device = get_output_device_by_id_internal(device_id)?;
+    unsafe { device.Activate(CLSCTX_ALL, None) }
+        .map_err(|e| anyhow!("Failed to activate IAudioClient: {}", err_code(&e)))
+}
+
+/// Streams interleaved f32 frames from `fill` to `client` in its mix format
+/// until `fill` returns `false`, then waits for the buffer to play out.
+/// `fill` must write the whole slice (silence once it has finished).
+///
+/// `poll` runs on every wake-up of the loop (e.g. to drain a recording).
+pub(crate) fn render_generated<F, P>(
+    client: &IAudioClient,
+    mix_format: &MixFormat,
+    mut fill: F,
+    mut poll: P,
+) -> Result<()>
+where
+    F: FnMut(&mut [f32]) -> bool,
+    P: FnMut() -> Result<()>,
+{
+    let format = mix_format.stream_format();
+    if format.sample_format == SampleFormat::Unsupported {
+        return Err(anyhow!("Unsupported output sample format"));
+    }
+    let channels = format.channels as usize;
+    // 初始化后即已 Start
+    let service = initialize_render_client_internal(client, mix_format.as_ptr(), BUFFER_DURATION)?;
+
+    let result = (|| -> Result<()> {
+        let buffer_frames = unsafe { client.GetBufferSize() }
+            .map_err(|e| anyhow!("GetBufferSize failed: {}", err_code(&e)))?;
+        let mut scratch = Vec::new();
+        let mut finished = false;
+        loop {
+            poll()?;
+            let padding = unsafe { client.GetCurrentPadding() }
+                .map_err(|e| anyhow!("GetCurrentPadding failed: {}", err_code(&e)))?;
+            if finished {
+                if padding == 0 {
+                    return Ok(());
+                }
+            } else if padding < buffer_frames {
+                let frames = buffer_frames - padding;
+                scratch.clear();
+                scratch.resize(frames as usize * channels, 0.0);
+                finished = !fill(&mut scratch);
+                unsafe {
+                    let buffer = service
+                        .GetBuffer(frames)
+                        .map_err(|e| anyhow!("GetBuffer failed: {}", err_code(&e)))?;
+                    write_f32_samples(&scratch, buffer, format.sample_format, None);
+                    service
+                        .ReleaseBuffer(frames, 0)
+                        .map_err(|e| anyhow!("ReleaseBuffer failed: {}", err_code(&e)))?;
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    })();
+
+    unsafe {
+        let _ = client.Stop();
+    }
+    result
+}
+
+/// Capture stream of a measurement; stops the client when dropped.
+struct Recorder {
+    client: IAudioClient,
+    service: IAudioCaptureClient,
+    format: StreamFormat,
+    /// 单个 packet 的 f32 副本。
+    scratch: Vec<f32>,
+}
+
+impl Recorder {
+    fn start(client: &IAudioClient, mix_format: &MixFormat, loopback: bool) -> Result<Self> {
+        let format = mix_format.stream_format();
+        if format.sample_format == SampleFormat::Unsupported {
+            return Err(anyhow!("Unsupported input sample format"));
+        }
+        let service = initialize_capture_client_internal(
+            client,
+            mix_format.as_ptr(),
+            loopback,
+            BUFFER_DURATION,
+            None,
+        )?;
+        unsafe { client.Start() }
+            .map_err(|e| anyhow!("IAudioClient::Start (capture) failed: {}", err_code(&e)))?;
+        Ok(Self {
+            client: client.clone(),
+            service,
+            format,
+            scratch: Vec::new(),
+        })
+    }
+
+    /// Appends every packet captured so far to `recording`.
+    fn read(&mut self, recording: &mut Vec<f32>) -> Result<()> {
+        loop {
+            let packet_size = unsafe { self.service.GetNextPacketSize() }
+                .map_err(|e| anyhow!("GetNextPacketSize failed: {}", err_code(&e)))?;
+            if packet_size == 0 {
+                return Ok(());
+            }
+            let mut data: *mut u8 = std::ptr::null_mut();
+            let mut frames: u32 = 0;
+            let mut flags: u32 = 0;
+            unsafe {
+                self.service
+                    .GetBuffer(&mut data, &mut frames, &mut flags, None, None)
+                    .map_err(|e| anyhow!("GetBuffer failed: {}", err_code(&e)))?;
+            }
+            let silent = (flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0 || data.is_null();
+            let captured = if silent {
+                &[][..]
+            } else {
+                let bytes = frames as usize * self.format.block_align as usize;
+                unsafe { std::slice::from_raw_parts(data as *const u8, bytes) }
+            };
+            capture_to_f32(
+                captured,
+                frames,
+                self.format.channels as usize,
+                self.format.sample_format,
+                silent,
+                &mut self.scratch,
+            );
+            recording.extend_from_slice(&self.scratch);
+            unsafe { self.service.ReleaseBuffer(frames) }
+                .map_err(|e| anyhow!("ReleaseBuffer failed: {}", err_code(&e)))?;
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.client.Stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_names_fall_back_beyond_the_mask() {
+        assert_eq!(
+            channel_names(0x3, 3),
+            ["Front Left", "Front Right", "Channel 3"]
+        );
+        assert_eq!(channel_names(0x3F, 2), ["Front Left", "Front Right"]);
+    }
+}
@@ -0,0 +1,55 @@
+//! `IPolicyConfig`: the undocumented interface the Sound control panel uses to
+//! change the default endpoint.
+//!
+//! It is not in the Windows SDK, but its layout has been stable since Windows 7
+//! and is what every "set default device" tool relies on. Only
+//! `SetDefaultEndpoint` and `SetEndpointVisibility` are called; the other slots
+//! are declared to keep the vtable layout intact.
+
+#![allow(non_snake_case)]
+
+use std::ffi::c_void;
+use windows::Win32::Media::Audio::ERole;
+use windows::core::{GUID, HRESULT, IUnknown, IUnknown_Vtbl, PCWSTR, interface};
+
+/// `CLSID_PolicyConfigClient`.
+pub(crate) const CLSID_POLICY_CONFIG_CLIENT: GUID =
+    GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+#[interface("f8679f50-850a-41cf-9c72-430f290290c8")]
+pub(crate) unsafe trait IPolicyConfig: IUnknown {
+    fn GetMixFormat(&self, device_id: PCWSTR, format: *mut *mut c_void) -> HRESULT;
+    fn GetDeviceFormat(&self, device_id: PCWSTR, default: i32, format: *mut *mut c_void)
+    -> HRESULT;
+    fn ResetDeviceFormat(&self, device_id: PCWSTR) -> HRESULT;
+    fn SetDeviceFormat(
+        &self,
+        device_id: PCWSTR,
+        endpoint_format: *mut c_void,
+        mix_format: *mut c_void,
+    ) -> HRESULT;
+    fn GetProcessingPeriod(
+        &self,
+        device_id: PCWSTR,
+        default: i32,
+        default_period: *mut i64,
+        min_period: *mut i64,
+    ) -> HRESULT;
+    fn SetProcessingPeriod(&self, device_id: PCWSTR, period: *mut i64) -> HRESULT;
+    fn GetShareMode(&self, device_id: PCWSTR, mode: *mut c_void) -> HRESULT;
+    fn SetShareMode(&self, device_id: PCWSTR, mode: *mut c_void) -> HRESULT;
+    fn GetPropertyValue(
+        &self,
+        device_id: PCWSTR,
+        key: *const c_void,
+        value: *mut c_void,
+    ) -> HRESULT;
+    fn SetPropertyValue(
+        &self,
+        device_id: PCWSTR,
+        key: *const c_void,
+        value: *mut c_void,
+    ) -> HRESULT;
+    pub(crate) fn SetDefaultEndpoint(&self, device_id: PCWSTR, role: ERole) -> HRESULT;
+    pub(crate) fn SetEndpointVisibility(&self, device_id: PCWSTR, visible: i32) -> HRESULT;
+}
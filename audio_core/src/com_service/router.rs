@@ -1,12 +1,36 @@
-use crate::com_service::device::get_output_device_by_id_internal;
-use crate::router::{ChannelMode, RouterConfig};
+use crate::com_service::device::{get_default_device_internal, get_output_device_by_id_internal};
+use crate::dsp::{
+    BassRole, CorrelationMeter, Crossfeed, CrossfeedPreset, DelayLine, Dither, DitherMode,
+    DspChain, EqPreset, Fade, GraphicEq, LIMITER_LOOKAHEAD_SECS, LevelMeter, Limiter,
+    LimiterSettings, LoudnessNormalizer, LoudnessSettings, MidSide, MidSideSettings, NoiseGate,
+    PINK_NOISE_LEVEL_RANGE_DB, PinkNoise, Resampler, ResamplerQuality, SmoothedGain, count_clipped,
+};
+use crate::plugin::{PluginSlot, load_chain};
+use crate::router::mixer::{
+    ChannelMixer, MODE_CROSSFADE_SECS, default_channel_mask, downmix_matrix, keeps_layout,
+    loudness_weights, mode_matrix, subwoofer_matrix, upmix_mixer,
+};
+use crate::router::tap::Taps;
+use crate::router::{
+    ChannelMatrix, ChannelMode, ChannelTrim, EngineMode, LatencySlots, LevelSlots, MixLevels,
+    OutputControl, OutputStats, OverflowPolicy, RouterConfig, RouterControls, RouterStats,
+    RouterTarget, StreamLatency,
+};
 use anyhow::{Result, anyhow};
+use std::cell::{Cell, RefCell};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
 use windows::Win32::Media::Audio::{
-    AUDCLNT_BUFFERFLAGS_SILENT, IAudioCaptureClient, IAudioClient, IAudioRenderClient, IMMDevice,
-    WAVEFORMATEX,
+    AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY, AUDCLNT_BUFFERFLAGS_SILENT,
+    AUDCLNT_BUFFERFLAGS_TIMESTAMP_ERROR, IAudioCaptureClient, IAudioClient, IAudioRenderClient,
+    IMMDevice, WAVEFORMATEX, WAVEFORMATEXTENSIBLE, eRender,
 };
 use windows::Win32::System::Com::{CLSCTX_ALL, CoTaskMemFree};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+use windows::core::PCWSTR;
+
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
 
 /// 设备 invalidated 相关的 HRESULT 代码。
 /// 这些错误都表示设备状态发生变化（格式改变、设备移除/禁用等），
@@ -22,7 +46,7 @@ const DEVICE_INVALIDATED_CODES: &[i32] = &[
 /// 将 windows::core::Error 转换为不含 message() 的字符串，
 /// 避免 windows 0.48.0 中 HRESULT::message() 在某些错误下
 /// 触发 slice::from_raw_parts 的 UB precondition 检查而 panic。
-fn err_code(e: &windows::core::Error) -> String {
+pub(crate) fn err_code(e: &windows::core::Error) -> String {
     let code = e.code();
     let code_u32 = code.0 as u32;
     format!("0x{:08X}", code_u32)
@@ -43,31 +67,272 @@ pub struct RouterSetupResult {
 }
 
 #[derive(Clone)]
-pub struct RouterOutputClient {
+pub struct RouterOutputClient<C = IAudioClient> {
     pub device_id: String,
     pub channel_mode: ChannelMode,
-    pub client: IAudioClient,
+    pub overflow_policy: OverflowPolicy,
+    pub channel_matrix: Option<ChannelMatrix>,
+    pub downmix_lfe: bool,
+    pub eq_preset: EqPreset,
+    pub gain_db: f32,
+    pub delay_ms: f32,
+    pub muted: bool,
+    pub phase_invert: u32,
+    pub channel_trim: ChannelTrim,
+    pub bass_role: BassRole,
+    pub limiter: LimiterSettings,
+    pub loudness: LoudnessSettings,
+    pub crossfeed: CrossfeedPreset,
+    pub mid_side: MidSideSettings,
+    pub dither: DitherMode,
+    pub resampler: ResamplerQuality,
+    pub plugins: Vec<PluginSlot>,
+    pub dsp_chain: DspChain,
+    /// 低音管理分频点（所有输出共用）。
+    pub crossover_hz: f32,
+    /// Downmix/Upmix 的声道电平（所有输出共用）。
+    pub mix_levels: MixLevels,
+    pub client: C,
 }
 
-#[derive(Clone)]
-pub struct RouterInitialized {
-    pub capture_service: IAudioCaptureClient,
-    pub render_services: Vec<RouterRenderClient>,
+impl<C> RouterOutputClient<C> {
+    pub(crate) fn new(target: &RouterTarget, cfg: &RouterConfig, client: C) -> Self {
+        Self {
+            device_id: target.device_id.clone(),
+            channel_mode: target.channel_mode,
+            overflow_policy: target.overflow_policy,
+            channel_matrix: target.channel_matrix.clone(),
+            downmix_lfe: target.downmix_lfe,
+            eq_preset: target.eq_preset,
+            gain_db: target.gain_db,
+            delay_ms: target.delay_ms,
+            muted: target.muted,
+            phase_invert: target.phase_invert,
+            channel_trim: target.channel_trim.clone(),
+            bass_role: target.bass_role,
+            limiter: target.limiter,
+            loudness: target.loudness,
+            crossfeed: target.crossfeed,
+            mid_side: target.mid_side,
+            dither: target.dither,
+            resampler: target.resampler,
+            plugins: target.plugins.clone(),
+            dsp_chain: target.dsp_chain.clone(),
+            crossover_hz: cfg.bass_management.crossover_hz,
+            mix_levels: cfg.mix_levels,
+            client,
+        }
+    }
+
+    /// 是否有必须在 f32 上进行的处理（EQ、增益、静音、延迟、反相、声道微调、分频、中置/侧向、交叉馈送、插件、响度、限幅）。
+    fn needs_dsp(&self) -> bool {
+        self.eq_preset != EqPreset::Flat
+            || self.gain_db != 0.0
+            || self.muted
+            || self.delay_ms > 0.0
+            || self.phase_invert != 0
+            || !self.channel_trim.is_flat()
+            || self.bass_role != BassRole::FullRange
+            || self.limiter.enabled
+            || self.loudness.enabled
+            || self.mid_side.enabled
+            || self.crossfeed != CrossfeedPreset::Off
+            || self.plugins.iter().any(|p| p.enabled)
+    }
+
+    /// 是否自行重采样到设备采样率（而不是交给引擎转换）。
+    fn resamples(&self, capture: StreamFormat) -> bool {
+        self.resampler != ResamplerQuality::System
+            && capture.sample_format != SampleFormat::Unsupported
+    }
+
+    /// 确定输出格式是否需要设备的 mix format：Upmix 需要设备实际的扬声器数量，
+    /// 自行重采样需要设备的采样率。
+    pub(crate) fn needs_device_format(&self, capture: StreamFormat) -> bool {
+        self.channel_mode == ChannelMode::Upmix || self.resamples(capture)
+    }
+
+    /// 输出端初始化使用的格式：与捕获端只可能在声道数和采样率上不同。
+    /// `device` 为设备的 mix format（见 [`Self::needs_device_format`]）。
+    pub(crate) fn render_format(
+        &self,
+        capture: StreamFormat,
+        device: Option<StreamFormat>,
+    ) -> StreamFormat {
+        let channels = render_channels(self, capture, device.map(|format| format.channels));
+        let sample_rate = device
+            .filter(|_| self.resamples(capture))
+            .map_or(capture.sample_rate, |format| format.sample_rate);
+        capture.with_layout(channels, sample_rate)
+    }
 }
 
-#[derive(Clone)]
-pub struct RouterRenderClient {
+/// 初始化完成后整个会话内不变的状态，由 worker 持有并在每个 packet 间复用。
+pub struct RouterInitialized<C = IAudioCaptureClient, R = WasapiRender> {
+    pub capture_service: C,
+    pub render_services: Vec<RouterRenderClient<R>>,
+    /// 事件驱动模式下捕获数据就绪时置位的事件；轮询模式为 None。
+    pub(crate) capture_event: Option<CaptureEvent>,
+    /// 捕获端格式，初始化时解析一次。
+    pub format: StreamFormat,
+    /// 尚未取到本会话的第一个捕获 packet。
+    first_packet: Cell<bool>,
+    /// 不计电平表时也需要捕获数据的 f32 副本（有噪声门或混音路径的输出）。
+    needs_f32: bool,
+    /// 上一个 packet 是否计了电平，用于在停止计量时清空读数。
+    metered: Cell<bool>,
+    /// 捕获数据的 f32 副本（供电平表、tap 和矩阵混音使用），跨 packet 复用避免实时循环中分配。
+    capture_scratch: RefCell<Vec<f32>>,
+    /// 矩阵混音输出缓冲区，各输出端依次复用。
+    mix_scratch: RefCell<Vec<f32>>,
+    /// 重采样输出缓冲区，各输出端依次复用。
+    resample_scratch: RefCell<Vec<f32>>,
+    /// 作用于捕获信号（tap 与所有输出之前）的噪声门。
+    gate: Option<RefCell<NoiseGate>>,
+    /// 捕获信号前置左右声道的相关性（单声道兼容性）表。
+    correlation: Option<RefCell<CorrelationMeter>>,
+    /// 会话开始时的淡入或停止前的淡出；进行中时作用于捕获数据的副本，
+    /// 所有输出路径都经过它。
+    fade: RefCell<Option<Fade>>,
+    /// 淡入淡出期间捕获数据（原始格式）的副本。
+    fade_scratch: RefCell<Vec<u8>>,
+    /// 电平校准用的粉红噪声源（捕获端声道数），同一时间只替代一个输出的信号。
+    pink_noise: RefCell<PinkNoise>,
+    /// 粉红噪声的输出缓冲区。
+    noise_scratch: RefCell<Vec<f32>>,
+    /// 捕获信号的峰值/RMS 表及其读数。
+    source_meter: RefCell<LevelMeter>,
+    source_levels: Arc<LevelSlots>,
+    /// 输出端已写入数据的 f32 副本（供其电平表使用），各输出端依次复用。
+    meter_scratch: RefCell<Vec<f32>>,
+}
+
+impl<C, R> RouterInitialized<C, R> {
+    /// 丢弃所有电平表的读数和未完成的窗口，读数归零。
+    fn clear_meters(&self, stats: &RouterStats) {
+        let mut source_meter = self.source_meter.borrow_mut();
+        source_meter.reset();
+        self.source_levels
+            .store(source_meter.peak(), source_meter.rms());
+        for render in &self.render_services {
+            let mut meter = render.meter.borrow_mut();
+            meter.reset();
+            render.levels.store(meter.peak(), meter.rms());
+        }
+        stats.record_correlation(None);
+    }
+
+    /// 从当前增益（淡入未完成时低于 1）淡出到静音，之后保持静音直到会话结束。
+    pub(crate) fn start_fade_out(&self, duration_ms: f32) {
+        let mut fade = self.fade.borrow_mut();
+        let from = fade.as_ref().map_or(1.0, Fade::gain);
+        *fade = Some(Fade::fade_out(from, duration_ms, self.format.sample_rate));
+    }
+}
+
+pub struct RouterRenderClient<R = WasapiRender> {
     pub channel_mode: ChannelMode,
-    pub client: IAudioClient,
-    pub service: IAudioRenderClient,
+    pub overflow_policy: OverflowPolicy,
+    /// 初始化时确定的写入路径，避免每个 packet 重复判断。
+    pub path: RenderPath,
+    /// 输出端初始化时使用的格式（与捕获端只可能在声道数和采样率上不同）。
+    pub format: StreamFormat,
+    /// `RenderPath::Mixed` 使用的混音器（Upmix 的滤波器带状态，因此可变）。
+    mixer: Option<RefCell<ChannelMixer>>,
+    /// 混音器当前矩阵对应的声道模式；与 `control` 中的不同时交叉淡化到新模式。
+    mixer_mode: Cell<ChannelMode>,
+    /// 混音结果写入 16 位格式时的抖动（其他格式为 None）。
+    dither: Option<RefCell<Dither>>,
+    /// 输出端采样率与捕获端不同时，混音后的重采样器。
+    resampler: Option<RefCell<Resampler>>,
+    pub endpoint: R,
+    /// `OverflowPolicy::Resync` 正在丢包等待缓冲区回落。
+    resyncing: Cell<bool>,
+    /// 已写入过数据；此后 padding 为 0 才算 underrun（启动/flush 后的空缓冲不算）。
+    primed: Cell<bool>,
+    stats: Arc<OutputStats>,
+    /// 运行中可调整的参数（增益、声道模式），每个 packet 读取一次。
+    control: Arc<OutputControl>,
+    /// 写入设备的数据（所有处理之后）的峰值/RMS 表及其读数。
+    meter: RefCell<LevelMeter>,
+    levels: Arc<LevelSlots>,
+    /// 延迟统计，每次写入前记录缓冲区占用。
+    latency: Arc<LatencySlots>,
 }
 
 pub struct MixFormat {
     ptr: *mut WAVEFORMATEX,
 }
 
+/// 与 `WAVEFORMATEX` 中影响写入路径的字段对应的简化描述。
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SampleFormat {
+pub struct StreamFormat {
+    pub(crate) sample_format: SampleFormat,
+    pub(crate) channels: u16,
+    pub(crate) sample_rate: u32,
+    pub(crate) block_align: u16,
+    /// 扬声器位置掩码（非 EXTENSIBLE 格式取该声道数的 Windows 默认布局）。
+    pub(crate) channel_mask: u32,
+}
+
+impl StreamFormat {
+    /// 采样格式相同、声道数为 `channels`、采样率为 `sample_rate` 的格式，
+    /// 声道数改变时布局取该声道数的 Windows 默认布局（与 [`MixFormat::with_layout`] 一致）。
+    pub(crate) fn with_layout(self, channels: u16, sample_rate: u32) -> Self {
+        if channels == self.channels && sample_rate == self.sample_rate {
+            return self;
+        }
+        let bytes_per_sample = self.block_align / self.channels.max(1);
+        Self {
+            channels,
+            sample_rate,
+            block_align: bytes_per_sample * channels,
+            channel_mask: default_channel_mask(channels),
+            ..self
+        }
+    }
+}
+
+/// 输出端的写入路径。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// 捕获格式与输出格式一致且无需任何处理：直接从捕获缓冲区拷贝到输出缓冲区，
+    /// 不经过中间 f32 缓冲。
+    Direct,
+    /// 需要按声道模式逐帧映射（仍在原始采样格式上进行，不做 f32 转换）。
+    ChannelMapped,
+    /// 转换为 f32 后经增益矩阵混音（及该输出的 DSP 处理），再写回输出格式。
+    Mixed,
+}
+
+impl RenderPath {
+    /// `dsp` 表示该输出有需要在 f32 上进行的处理（如 EQ、增益、分频）。
+    fn select(capture: StreamFormat, render: StreamFormat, mode: ChannelMode, dsp: bool) -> Self {
+        if capture.sample_format == SampleFormat::Unsupported {
+            return RenderPath::ChannelMapped;
+        }
+        if dsp
+            || mode == ChannelMode::Matrix
+            || capture.channels != render.channels
+            || capture.sample_rate != render.sample_rate
+        {
+            return RenderPath::Mixed;
+        }
+        // 单声道源没有可映射的左右声道；声道数未变的 Downmix/Upmix 无需处理
+        let identity_mapping = matches!(
+            mode,
+            ChannelMode::Stereo | ChannelMode::Downmix | ChannelMode::Upmix
+        ) || capture.channels < 2;
+        if capture == render && identity_mapping {
+            RenderPath::Direct
+        } else {
+            RenderPath::ChannelMapped
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SampleFormat {
     F32,
     I16,
     I32,
@@ -82,9 +347,34 @@ impl MixFormat {
         Ok(Self { ptr })
     }
 
-    fn as_ptr(&self) -> *const WAVEFORMATEX {
+    pub(crate) fn as_ptr(&self) -> *const WAVEFORMATEX {
         self.ptr.cast_const()
     }
+
+    pub(crate) fn stream_format(&self) -> StreamFormat {
+        stream_format_of(self.as_ptr())
+    }
+
+    /// 与捕获格式采样格式相同，但声道数为 `channels`、采样率为 `sample_rate` 的格式，
+    /// 声道布局取该声道数的 Windows 默认布局。
+    fn with_layout(&self, channels: u16, sample_rate: u32) -> WAVEFORMATEXTENSIBLE {
+        let pwf = self.as_ptr();
+        unsafe {
+            let mut ext: WAVEFORMATEXTENSIBLE = std::mem::zeroed();
+            if (*pwf).wFormatTag == WAVE_FORMAT_EXTENSIBLE {
+                ext = *(pwf as *const WAVEFORMATEXTENSIBLE);
+                ext.dwChannelMask = default_channel_mask(channels);
+            } else {
+                ext.Format = *pwf;
+            }
+            let bytes_per_sample = (*pwf).nBlockAlign / (*pwf).nChannels.max(1);
+            ext.Format.nChannels = channels;
+            ext.Format.nSamplesPerSec = sample_rate;
+            ext.Format.nBlockAlign = bytes_per_sample * channels;
+            ext.Format.nAvgBytesPerSec = ext.Format.nSamplesPerSec * ext.Format.nBlockAlign as u32;
+            ext
+        }
+    }
 }
 
 impl Drop for MixFormat {
@@ -95,27 +385,170 @@ impl Drop for MixFormat {
     }
 }
 
+fn stream_format_of(pwf: *const WAVEFORMATEX) -> StreamFormat {
+    unsafe {
+        let channels = (*pwf).nChannels;
+        let channel_mask = if (*pwf).wFormatTag == WAVE_FORMAT_EXTENSIBLE {
+            (*(pwf as *const WAVEFORMATEXTENSIBLE)).dwChannelMask
+        } else {
+            default_channel_mask(channels)
+        };
+        StreamFormat {
+            sample_format: detect_sample_format(pwf),
+            channels,
+            sample_rate: (*pwf).nSamplesPerSec,
+            block_align: (*pwf).nBlockAlign,
+            channel_mask,
+        }
+    }
+}
+
+/// 输出端需要的声道数：Downmix 输出立体声，立体声源 Upmix 按设备声道数，
+/// 带矩阵的 Matrix 模式按矩阵列数，其余模式与捕获端一致。
+fn render_channels<C>(
+    output: &RouterOutputClient<C>,
+    capture: StreamFormat,
+    device_channels: Option<u16>,
+) -> u16 {
+    match output.channel_mode {
+        ChannelMode::Downmix => capture.channels.min(2),
+        ChannelMode::Upmix if capture.channels == 2 => {
+            device_channels.unwrap_or(capture.channels).max(2)
+        }
+        ChannelMode::Matrix => output
+            .channel_matrix
+            .as_ref()
+            .map(|m| m.outputs() as u16)
+            .filter(|&n| n > 0)
+            .unwrap_or(capture.channels),
+        _ => capture.channels,
+    }
+}
+
+/// 构造 `RenderPath::Mixed` 输出端的混音器及其完整的 DSP 链。
+pub(crate) fn build_mixer<C>(
+    output: &RouterOutputClient<C>,
+    capture: StreamFormat,
+    render: StreamFormat,
+    gain_db: f32,
+) -> ChannelMixer {
+    let mixer = match output.channel_mode {
+        // 超低音输出忽略声道模式：所有声道的低频求和后送到每个声道
+        _ if output.bass_role == BassRole::Subwoofer => ChannelMixer::from_matrix(
+            &subwoofer_matrix(
+                capture.channel_mask,
+                capture.channels as usize,
+                render.channels as usize,
+            ),
+            capture.channels as usize,
+            render.channels as usize,
+        ),
+        ChannelMode::Upmix if capture.channels != render.channels => upmix_mixer(
+            render.channel_mask,
+            render.channels as usize,
+            render.sample_rate,
+            &output.mix_levels,
+        ),
+        _ => ChannelMixer::from_matrix(
+            &mix_matrix(output, capture),
+            capture.channels as usize,
+            render.channels as usize,
+        ),
+    };
+    mixer
+        .with_bass_role(output.bass_role, output.crossover_hz, render.sample_rate)
+        .with_phase_invert(output.phase_invert)
+        .with_channel_trim(&output.channel_trim.0)
+        .with_mid_side(MidSide::new(&output.mid_side, render.channels as usize))
+        .with_crossfeed(Crossfeed::new(
+            output.crossfeed,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_eq(GraphicEq::new(
+            output.eq_preset,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_plugins(load_chain(
+            &output.device_id,
+            &output.plugins,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_loudness(LoudnessNormalizer::new(
+            &output.loudness,
+            loudness_weights(render.channel_mask, render.channels as usize),
+            render.sample_rate,
+        ))
+        .with_gain(SmoothedGain::new(
+            gain_db,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_limiter(Limiter::new(
+            &output.limiter,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_delay(DelayLine::new(
+            output.delay_ms,
+            render.channels as usize,
+            render.sample_rate,
+        ))
+        .with_order(output.dsp_chain.stages())
+}
+
+/// 输出端使用的增益矩阵。
+fn mix_matrix<C>(output: &RouterOutputClient<C>, capture: StreamFormat) -> ChannelMatrix {
+    let channels = capture.channels as usize;
+    match output.channel_mode {
+        ChannelMode::Downmix => downmix_matrix(
+            capture.channel_mask,
+            channels,
+            output.downmix_lfe,
+            &output.mix_levels,
+        ),
+        ChannelMode::Matrix => output.channel_matrix.clone().unwrap_or_else(|| {
+            log::warn!(
+                "Render {} has no channel matrix; passing channels through",
+                output.device_id
+            );
+            ChannelMatrix::identity(channels)
+        }),
+        _ => mode_matrix(output.channel_mode, channels),
+    }
+}
+
 /// Internal function to create and initialize WASAPI audio clients for a router.
 /// Must be called in a COM-initialized environment.
 pub fn setup_router_clients(cfg: &RouterConfig) -> Result<RouterSetupResult> {
-    let source_id = cfg
-        .source_device_id
-        .as_ref()
-        .ok_or_else(|| anyhow!("source_device_id is required"))?;
+    let source_id = match cfg.source_role {
+        Some(role) => {
+            let device = get_default_device_internal(eRender, role.into())?;
+            log::info!("Source follows the {role:?} default: {}", device.id);
+            device.id
+        }
+        None => cfg
+            .source_device_id
+            .clone()
+            .ok_or_else(|| anyhow!("source_device_id is required"))?,
+    };
 
-    let source_device = get_output_device_by_id_internal(source_id)?;
+    let source_device = get_output_device_by_id_internal(&source_id)?;
     let source_client: IAudioClient = unsafe { source_device.Activate(CLSCTX_ALL, None) }
         .map_err(|e| anyhow!("Failed to activate source IAudioClient: {}", err_code(&e)))?;
 
     let mut output_clients = Vec::new();
     for target in &cfg.targets {
+        // 跟随默认设备时，源可能恰好是某个输出
+        if target.device_id == source_id {
+            log::warn!("Skipping output {}: it is the source", target.device_id);
+            continue;
+        }
         match get_output_device_by_id_internal(&target.device_id) {
             Ok(dev) => match unsafe { dev.Activate::<IAudioClient>(CLSCTX_ALL, None) } {
-                Ok(client) => output_clients.push(RouterOutputClient {
-                    device_id: target.device_id.clone(),
-                    channel_mode: target.channel_mode,
-                    client,
-                }),
+                Ok(client) => output_clients.push(RouterOutputClient::new(target, cfg, client)),
                 Err(e) => log::warn!(
                     "Failed to activate output device {}: {}",
                     target.device_id,
@@ -148,26 +581,104 @@ pub fn get_mix_format(client: &IAudioClient) -> Result<MixFormat> {
     MixFormat::new(pwf)
 }
 
-/// Initialize a capture client for loopback. Must be called in COM thread.
-fn initialize_capture_client_internal(
+/// Queries the audio engine's default device period (the interval at which the
+/// engine produces/consumes one packet). Must be called in COM thread.
+pub fn get_device_period(client: &IAudioClient) -> Result<Duration> {
+    let mut default_period_100ns: i64 = 0;
+    unsafe { client.GetDevicePeriod(Some(&mut default_period_100ns), None) }
+        .map_err(|e| anyhow!("GetDevicePeriod failed: {}", err_code(&e)))?;
+    Ok(Duration::from_nanos(
+        default_period_100ns.max(0) as u64 * 100,
+    ))
+}
+
+/// 引擎报告的流延迟（已初始化的客户端）；查询失败时按 0 计。
+fn stream_latency(client: &IAudioClient) -> Duration {
+    match unsafe { client.GetStreamLatency() } {
+        Ok(latency) => Duration::from_nanos(latency.max(0) as u64 * 100),
+        Err(e) => {
+            log::debug!("GetStreamLatency failed: {}", err_code(&e));
+            Duration::ZERO
+        }
+    }
+}
+
+/// WASAPI 以 100ns 为单位的缓冲区时长。
+fn buffer_duration_100ns(buffer: Duration) -> i64 {
+    i64::try_from(buffer.as_nanos() / 100).unwrap_or(i64::MAX)
+}
+
+/// 事件驱动模式下由 WASAPI 在捕获端有数据可读时置位的自动复位事件。
+pub(crate) struct CaptureEvent(HANDLE);
+
+impl CaptureEvent {
+    fn new() -> Result<Self> {
+        unsafe { CreateEventW(None, false, false, PCWSTR::null()) }
+            .map(Self)
+            .map_err(|e| anyhow!("CreateEventW failed: {}", err_code(&e)))
+    }
+
+    /// 等待事件置位，最长 `timeout`（不足 1ms 按 1ms 计）。
+    pub(crate) fn wait(&self, timeout: Duration) {
+        let millis = timeout.as_millis().clamp(1, u32::MAX.into()) as u32;
+        unsafe {
+            WaitForSingleObject(self.0, millis);
+        }
+    }
+}
+
+impl Drop for CaptureEvent {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// Initialize a capture client, in loopback mode for a render endpoint.
+/// With `event`, WASAPI signals it whenever a packet is ready.
+/// Must be called in COM thread.
+pub(crate) fn initialize_capture_client_internal(
     client: &IAudioClient,
     pwf: *const WAVEFORMATEX,
+    loopback: bool,
+    buffer: Duration,
+    event: Option<&CaptureEvent>,
 ) -> Result<IAudioCaptureClient> {
-    use windows::Win32::Media::Audio::{AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK};
+    use windows::Win32::Media::Audio::{
+        AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_EVENTCALLBACK, AUDCLNT_STREAMFLAGS_LOOPBACK,
+    };
+
+    let mut flags = if loopback {
+        AUDCLNT_STREAMFLAGS_LOOPBACK
+    } else {
+        0
+    };
+    if event.is_some() {
+        flags |= AUDCLNT_STREAMFLAGS_EVENTCALLBACK;
+    }
 
-    let buffer_duration_100ns: i64 = 50_000_000; // 50ms
     unsafe {
         client
             .Initialize(
                 windows::Win32::Media::Audio::AUDCLNT_SHAREMODE(AUDCLNT_SHAREMODE_SHARED.0),
-                AUDCLNT_STREAMFLAGS_LOOPBACK,
-                buffer_duration_100ns,
+                flags,
+                buffer_duration_100ns(buffer),
                 0,
                 pwf,
                 None,
             )
             .map_err(|e| anyhow!("IAudioClient::Initialize (capture) failed: {}", err_code(&e)))?;
 
+        if let Some(event) = event {
+            client.SetEventHandle(event.0).map_err(|e| {
+                anyhow!(
+                    "IAudioClient::SetEventHandle (capture) failed: {}",
+                    err_code(&e)
+                )
+            })?;
+        }
+
         client.GetService::<IAudioCaptureClient>().map_err(|e| {
             anyhow!(
                 "IAudioClient::GetService (IAudioCaptureClient) failed: {}",
@@ -178,22 +689,22 @@ fn initialize_capture_client_internal(
 }
 
 /// Initialize a render client. Must be called in COM thread.
-fn initialize_render_client_internal(
+pub(crate) fn initialize_render_client_internal(
     client: &IAudioClient,
     pwf: *const WAVEFORMATEX,
+    buffer: Duration,
 ) -> Result<IAudioRenderClient> {
     use windows::Win32::Media::Audio::{
         AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
         AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
     };
 
-    let buffer_duration_100ns: i64 = 50_000_000; // 50ms
     unsafe {
         client
             .Initialize(
                 windows::Win32::Media::Audio::AUDCLNT_SHAREMODE(AUDCLNT_SHAREMODE_SHARED.0),
                 AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
-                buffer_duration_100ns,
+                buffer_duration_100ns(buffer),
                 0,
                 pwf,
                 None,
@@ -220,21 +731,71 @@ pub fn initialize_router(
     capture: &IAudioClient,
     render_clients: &[RouterOutputClient],
     mix_format: &MixFormat,
+    stats: &RouterStats,
+    controls: &RouterControls,
+    cfg: &RouterConfig,
 ) -> Result<RouterInitialized> {
+    // 重启后未能恢复的输出端不应保留旧读数
+    stats.clear_readings();
     let pwf = mix_format.as_ptr();
+    let capture_format = mix_format.stream_format();
+    if capture_format.sample_format == SampleFormat::Unsupported {
+        let w_format = unsafe { (*pwf).wFormatTag };
+        log::warn!("Unsupported audio format tag: {w_format}");
+    }
 
-    let capture_service = initialize_capture_client_internal(capture, pwf)?;
+    let buffer = cfg.engine.buffer_duration();
+    let capture_event = match cfg.engine.mode {
+        EngineMode::Polling => None,
+        EngineMode::EventDriven => Some(CaptureEvent::new()?),
+    };
+    let capture_service =
+        initialize_capture_client_internal(capture, pwf, true, buffer, capture_event.as_ref())?;
+    let session = SessionSetup {
+        capture_format,
+        capture_latency: stream_latency(capture),
+        stats,
+        cfg,
+    };
 
     let mut render_services = Vec::new();
     for render_client in render_clients {
-        match initialize_render_client_internal(&render_client.client, pwf) {
-            Ok(service) => {
-                render_services.push(RouterRenderClient {
-                    channel_mode: render_client.channel_mode,
+        // 运行中切换过的声道模式在设备重启后继续生效
+        let control = controls.register_output(
+            &render_client.device_id,
+            render_client.gain_db,
+            render_client.channel_mode,
+        );
+        control.set_muted(render_client.muted);
+        let render_client = &RouterOutputClient {
+            channel_mode: control.mode(),
+            ..render_client.clone()
+        };
+        // 输出端以捕获端的 mix format 初始化（由引擎负责转换到设备格式），
+        // 需要改变声道数的模式只替换声道数
+        let device_format = render_client
+            .needs_device_format(capture_format)
+            .then(|| get_mix_format(&render_client.client).ok())
+            .flatten()
+            .map(|format| format.stream_format());
+        let render_format = render_client.render_format(capture_format, device_format);
+        let resized = (render_format != capture_format)
+            .then(|| mix_format.with_layout(render_format.channels, render_format.sample_rate));
+        let render_pwf = resized.as_ref().map_or(pwf, |ext| {
+            ext as *const WAVEFORMATEXTENSIBLE as *const WAVEFORMATEX
+        });
+        match initialize_render_client_internal(&render_client.client, render_pwf, buffer) {
+            Ok(service) => render_services.push(RouterRenderClient::new(
+                &session,
+                render_client,
+                control,
+                WasapiRender {
                     client: render_client.client.clone(),
                     service,
-                });
-            }
+                },
+                render_format,
+                stream_latency(&render_client.client),
+            )),
             Err(e) => log::warn!(
                 "Failed to initialize render client {}: {e}",
                 render_client.device_id
@@ -252,22 +813,262 @@ pub fn initialize_router(
             .map_err(|e| anyhow!("IAudioClient::Start (capture) failed: {}", err_code(&e)))?;
     }
 
-    Ok(RouterInitialized {
+    Ok(RouterInitialized::new(
+        &session,
         capture_service,
+        capture_event,
         render_services,
-    })
+    ))
+}
+
+/// 构造会话状态时用到的会话级参数。
+pub(crate) struct SessionSetup<'a> {
+    pub(crate) capture_format: StreamFormat,
+    pub(crate) capture_latency: Duration,
+    pub(crate) stats: &'a RouterStats,
+    pub(crate) cfg: &'a RouterConfig,
+}
+
+impl<R> RouterRenderClient<R> {
+    /// 为已初始化的输出端构造逐 packet 处理所需的状态（写入路径、混音器、
+    /// 重采样、抖动、统计和电平表）。与设备无关，基准测试用它构造同样的输出端。
+    pub(crate) fn new<C>(
+        session: &SessionSetup,
+        render_client: &RouterOutputClient<C>,
+        control: Arc<OutputControl>,
+        endpoint: R,
+        render_format: StreamFormat,
+        engine_latency: Duration,
+    ) -> Self {
+        let capture_format = session.capture_format;
+        let cfg = session.cfg;
+        let path = RenderPath::select(
+            capture_format,
+            render_format,
+            render_client.channel_mode,
+            // 噪声门作用于 f32 副本，所有输出都必须从它写入；
+            // 跟随源音量时所有输出都需要增益级
+            render_client.needs_dsp() || cfg.noise_gate.enabled || cfg.mirror_source_volume,
+        );
+        log::info!(
+            "Render {} uses {path:?} path ({} -> {} channels, {} -> {} Hz)",
+            render_client.device_id,
+            capture_format.channels,
+            render_format.channels,
+            capture_format.sample_rate,
+            render_format.sample_rate
+        );
+        // 超低音输出忽略声道模式，不提供实时切换
+        control.set_live(
+            path == RenderPath::Mixed,
+            path == RenderPath::Mixed
+                && render_client.bass_role != BassRole::Subwoofer
+                && keeps_layout(render_client.channel_mode),
+        );
+        // DSP 在捕获端采样率上进行，重采样放在最后
+        let mixer = (path == RenderPath::Mixed).then(|| {
+            RefCell::new(build_mixer(
+                render_client,
+                capture_format,
+                StreamFormat {
+                    sample_rate: capture_format.sample_rate,
+                    ..render_format
+                },
+                control.total_gain_db(),
+            ))
+        });
+        let resampler = (path == RenderPath::Mixed)
+            .then(|| {
+                Resampler::new(
+                    render_client.resampler,
+                    render_format.channels as usize,
+                    capture_format.sample_rate,
+                    render_format.sample_rate,
+                )
+            })
+            .flatten()
+            .map(RefCell::new);
+        let dither = (path == RenderPath::Mixed
+            && render_format.sample_format == SampleFormat::I16)
+            .then(|| Dither::new(render_client.dither, render_format.channels as usize))
+            .flatten()
+            .map(RefCell::new);
+        let output_stats = session.stats.register_output(&render_client.device_id);
+        let levels = output_stats.attach_levels(render_format.channels as usize);
+        // 延迟、限幅器、插件和重采样只在混音路径上生效
+        let mut processing = 0.0;
+        if path == RenderPath::Mixed {
+            processing += render_client.delay_ms.max(0.0) / 1000.0;
+            if render_client.limiter.enabled {
+                processing += LIMITER_LOOKAHEAD_SECS;
+            }
+        }
+        if let Some(mixer) = &mixer {
+            // 插件运行在捕获端采样率上
+            processing +=
+                mixer.borrow().plugin_latency_frames() as f32 / capture_format.sample_rate as f32;
+        }
+        if let Some(resampler) = &resampler {
+            processing += resampler.borrow().latency_secs();
+        }
+        let latency = output_stats.attach_latency(
+            StreamLatency {
+                capture: session.capture_latency,
+                processing: Duration::from_secs_f32(processing),
+                engine: engine_latency,
+            },
+            render_format.sample_rate,
+        );
+        Self {
+            channel_mode: render_client.channel_mode,
+            overflow_policy: render_client.overflow_policy,
+            path,
+            format: render_format,
+            mixer,
+            mixer_mode: Cell::new(render_client.channel_mode),
+            dither,
+            resampler,
+            endpoint,
+            resyncing: Cell::new(false),
+            primed: Cell::new(false),
+            stats: output_stats,
+            control,
+            meter: RefCell::new(LevelMeter::new(
+                render_format.channels as usize,
+                render_format.sample_rate,
+                cfg.meter_window_ms,
+            )),
+            levels,
+            latency,
+        }
+    }
+}
+
+impl<C, R> RouterInitialized<C, R> {
+    /// 由已初始化的捕获端和输出端组装会话状态。
+    pub(crate) fn new(
+        session: &SessionSetup,
+        capture_service: C,
+        capture_event: Option<CaptureEvent>,
+        render_services: Vec<RouterRenderClient<R>>,
+    ) -> Self {
+        let capture_format = session.capture_format;
+        let cfg = session.cfg;
+        let needs_f32 = cfg.noise_gate.enabled
+            || render_services
+                .iter()
+                .any(|render| render.path == RenderPath::Mixed);
+        Self {
+            capture_service,
+            render_services,
+            capture_event,
+            format: capture_format,
+            first_packet: Cell::new(true),
+            needs_f32,
+            metered: Cell::new(false),
+            capture_scratch: RefCell::new(Vec::new()),
+            mix_scratch: RefCell::new(Vec::new()),
+            resample_scratch: RefCell::new(Vec::new()),
+            gate: NoiseGate::new(
+                &cfg.noise_gate,
+                capture_format.channels as usize,
+                capture_format.sample_rate,
+            )
+            .map(RefCell::new),
+            correlation: CorrelationMeter::new(
+                capture_format.channels as usize,
+                capture_format.sample_rate,
+            )
+            .map(RefCell::new),
+            fade: RefCell::new(Fade::fade_in(cfg.fade_in_ms, capture_format.sample_rate)),
+            fade_scratch: RefCell::new(Vec::new()),
+            pink_noise: RefCell::new(PinkNoise::new(
+                capture_format.channels as usize,
+                *PINK_NOISE_LEVEL_RANGE_DB.end(),
+            )),
+            noise_scratch: RefCell::new(Vec::new()),
+            source_meter: RefCell::new(LevelMeter::new(
+                capture_format.channels as usize,
+                capture_format.sample_rate,
+                cfg.meter_window_ms,
+            )),
+            source_levels: session
+                .stats
+                .attach_source_levels(capture_format.channels as usize),
+            meter_scratch: RefCell::new(Vec::new()),
+        }
+    }
 }
 
 /// 目标缓冲延迟占总缓冲区大小的比例 (0.2 = 20%)。
 /// 较低的目标延迟可以减少整体延迟，但太低会增加 underrun 风险。
 const TARGET_BUFFER_RATIO: f64 = 0.2;
 
-/// 判断是否应该跳过本次写入以降低累积延迟。
-/// 当输出端 padding 超过目标阈值时，跳过整个 packet（而不是部分截断），
-/// 这样可以避免波形断裂导致的噪点。
-/// 返回 Ok(true) 表示跳过本次写入，Ok(false) 表示正常写入。
+/// `OverflowPolicy::DropOldest` 每个 packet 最多丢弃的帧数比例。
+/// 分多个 packet 逐步回落到目标延迟，每次只是一处很短的跳变。
+const MAX_TRIM_RATIO: f64 = 0.25;
+
+/// 输出端缓冲区溢出时对当前 packet 的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverflowAction {
+    /// 正常写入
+    Write,
+    /// 跳过整个 packet（而不是部分截断），避免波形断裂导致的噪点
+    Skip,
+    /// 清空输出端已缓冲的数据后写入
+    Flush,
+    /// 丢弃 packet 开头（最旧）的若干帧，写入其余部分
+    Trim(u32),
+}
+
+/// 根据输出端策略和缓冲区状态决定如何处理当前 packet。
+///
+/// `resyncing` 是 `OverflowPolicy::Resync` 的滞回状态：超过目标后持续丢包，
+/// 直到 padding 回落到目标的一半以下，避免在阈值附近反复丢包/写入。
+fn overflow_action(
+    policy: OverflowPolicy,
+    padding: u32,
+    buffer_size: u32,
+    frames: u32,
+    resyncing: &Cell<bool>,
+) -> OverflowAction {
+    let target_padding = (buffer_size as f64 * TARGET_BUFFER_RATIO) as u32;
+    let over_target = padding > target_padding;
+    let fits = buffer_size.saturating_sub(padding) >= frames;
+
+    match policy {
+        OverflowPolicy::DropNewest if over_target || !fits => OverflowAction::Skip,
+        // 只有放不下整个 packet 时才重置流；超出目标时逐步裁掉 packet 开头
+        OverflowPolicy::DropOldest if !fits => OverflowAction::Flush,
+        OverflowPolicy::DropOldest if over_target => {
+            let max_trim = (frames as f64 * MAX_TRIM_RATIO) as u32;
+            match (padding - target_padding).min(max_trim) {
+                0 => OverflowAction::Write,
+                trim => OverflowAction::Trim(trim),
+            }
+        }
+        // 不在实时线程上等待：放不下时只能跳过
+        OverflowPolicy::Block if !fits => OverflowAction::Skip,
+        OverflowPolicy::Resync if resyncing.get() => {
+            if padding <= target_padding / 2 && fits {
+                resyncing.set(false);
+                OverflowAction::Write
+            } else {
+                OverflowAction::Skip
+            }
+        }
+        OverflowPolicy::Resync if over_target || !fits => {
+            resyncing.set(true);
+            OverflowAction::Skip
+        }
+        _ => OverflowAction::Write,
+    }
+}
+
+/// 查询输出端当前 padding 和缓冲区大小。
+/// 返回 Ok(None) 表示查询失败但设备仍有效，调用方按正常写入处理。
 /// 返回 Err 表示设备 invalidated，调用方应传播错误触发重启。
-fn should_skip_write(render_client: &IAudioClient) -> Result<bool> {
+fn render_buffer_state(render_client: &IAudioClient) -> Result<Option<(u32, u32)>> {
     unsafe {
         let padding = match render_client.GetCurrentPadding() {
             Ok(p) => p,
@@ -278,7 +1079,7 @@ fn should_skip_write(render_client: &IAudioClient) -> Result<bool> {
                         err_code(&e)
                     ));
                 }
-                return Ok(false);
+                return Ok(None);
             }
         };
 
@@ -291,175 +1092,475 @@ fn should_skip_write(render_client: &IAudioClient) -> Result<bool> {
                         err_code(&e)
                     ));
                 }
-                return Ok(false);
+                return Ok(None);
             }
         };
 
         if buffer_size == 0 {
-            return Ok(false);
+            return Ok(None);
         }
 
-        let target_padding = (buffer_size as f64 * TARGET_BUFFER_RATIO) as u32;
-        Ok(padding > target_padding)
+        Ok(Some((padding, buffer_size)))
     }
 }
 
-/// Process a single audio packet. Must be called in COM environment.
-pub fn process_next_packet<F>(
-    state: &RouterInitialized,
-    mix_format: &MixFormat,
-    cb: Arc<F>,
-) -> Result<bool>
-where
-    F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
-{
-    let capture = &state.capture_service;
-    let renders = &state.render_services;
-    let pwf = mix_format.as_ptr();
+/// 按输出端的溢出策略决定本次是否写入，只查询一次 padding，从不等待。
+/// 返回 Err 表示设备 invalidated，调用方应传播错误触发重启。
+fn resolve_overflow<R: RenderEndpoint>(
+    render: &RouterRenderClient<R>,
+    frames: u32,
+) -> Result<OverflowAction> {
+    let Some((padding, buffer_size)) = render.endpoint.buffer_state()? else {
+        return Ok(OverflowAction::Write);
+    };
+    if padding == 0 && render.primed.replace(false) {
+        render.stats.record_underrun();
+    }
+    render.latency.record_buffered(padding);
+    Ok(overflow_action(
+        render.overflow_policy,
+        padding,
+        buffer_size,
+        frames,
+        &render.resyncing,
+    ))
+}
 
-    unsafe {
-        let packet_size = match capture.GetNextPacketSize() {
-            Ok(s) => s,
+/// 丢弃输出端已缓冲但尚未播放的数据（Stop → Reset → Start）。
+fn flush_render_client(render_client: &IAudioClient) -> Result<()> {
+    let check = |result: windows::core::Result<()>, step: &str| -> Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if is_device_invalidated(&e) => Err(anyhow!(
+                "Render device invalidated during {step}: {}",
+                err_code(&e)
+            )),
             Err(e) => {
-                if is_device_invalidated(&e) {
-                    return Err(anyhow!(
-                        "Capture device invalidated (format changed or device removed): {}",
-                        err_code(&e)
-                    ));
-                }
-                return Err(anyhow!("GetNextPacketSize failed: {}", err_code(&e)));
+                log::warn!("Render {step} failed while flushing: {}", err_code(&e));
+                Ok(())
             }
-        };
-
-        if packet_size == 0 {
-            return Ok(false);
         }
+    };
+    unsafe {
+        check(render_client.Stop(), "Stop")?;
+        check(render_client.Reset(), "Reset")?;
+        check(render_client.Start(), "Start")
+    }
+}
+
+/// 逐 packet 处理用到的捕获端操作。会话使用 WASAPI 的 `IAudioCaptureClient`，
+/// 基准测试（`bench` feature）用内存中的数据代替，驱动同一份处理代码。
+pub(crate) trait CaptureEndpoint {
+    /// 下一个 packet 的帧数，0 表示暂无数据。Err 表示设备失效。
+    fn next_packet_size(&self) -> Result<u32>;
+    /// 取得下一个 packet：数据指针、帧数和缓冲区标志。
+    /// 数据在 [`Self::release_buffer`] 之前有效。
+    fn get_buffer(&self) -> Result<(*mut u8, u32, u32)>;
+    fn release_buffer(&self, frames: u32);
+}
+
+/// 逐 packet 处理用到的输出端操作，见 [`CaptureEndpoint`]。
+pub(crate) trait RenderEndpoint {
+    /// 当前 padding 和缓冲区大小；Ok(None) 表示查询失败但设备仍有效。
+    fn buffer_state(&self) -> Result<Option<(u32, u32)>>;
+    /// 丢弃已缓冲但尚未播放的数据。
+    fn flush(&self) -> Result<()>;
+    /// 取得可写入 `frames` 帧的缓冲区；Ok(None) 表示本次取不到但设备仍有效。
+    fn get_buffer(&self, frames: u32) -> Result<Option<*mut u8>>;
+    fn release_buffer(&self, frames: u32) -> Result<()>;
+}
 
+impl CaptureEndpoint for IAudioCaptureClient {
+    fn next_packet_size(&self) -> Result<u32> {
+        unsafe { self.GetNextPacketSize() }.map_err(|e| {
+            if is_device_invalidated(&e) {
+                anyhow!(
+                    "Capture device invalidated (format changed or device removed): {}",
+                    err_code(&e)
+                )
+            } else {
+                anyhow!("GetNextPacketSize failed: {}", err_code(&e))
+            }
+        })
+    }
+
+    fn get_buffer(&self) -> Result<(*mut u8, u32, u32)> {
         let mut buf_ptr: *mut u8 = std::ptr::null_mut();
         let mut frames: u32 = 0;
         let mut flags: u32 = 0;
+        unsafe { self.GetBuffer(&mut buf_ptr, &mut frames, &mut flags, None, None) }.map_err(
+            |e| {
+                if is_device_invalidated(&e) {
+                    anyhow!(
+                        "Capture device invalidated during GetBuffer: {}",
+                        err_code(&e)
+                    )
+                } else {
+                    anyhow!("GetBuffer failed: {}", err_code(&e))
+                }
+            },
+        )?;
+        Ok((buf_ptr, frames, flags))
+    }
 
-        if let Err(e) = capture.GetBuffer(&mut buf_ptr, &mut frames, &mut flags, None, None) {
-            if is_device_invalidated(&e) {
-                return Err(anyhow!(
-                    "Capture device invalidated during GetBuffer: {}",
-                    err_code(&e)
-                ));
+    fn release_buffer(&self, frames: u32) {
+        unsafe {
+            let _ = self.ReleaseBuffer(frames);
+        }
+    }
+}
+
+/// 已初始化的 WASAPI 输出端。
+pub struct WasapiRender {
+    pub client: IAudioClient,
+    pub service: IAudioRenderClient,
+}
+
+impl RenderEndpoint for WasapiRender {
+    fn buffer_state(&self) -> Result<Option<(u32, u32)>> {
+        render_buffer_state(&self.client)
+    }
+
+    fn flush(&self) -> Result<()> {
+        flush_render_client(&self.client)
+    }
+
+    fn get_buffer(&self, frames: u32) -> Result<Option<*mut u8>> {
+        match unsafe { self.service.GetBuffer(frames) } {
+            Ok(ptr) => Ok(Some(ptr)),
+            Err(e) if is_device_invalidated(&e) => Err(anyhow!(
+                "Render device invalidated during GetBuffer: {}",
+                err_code(&e)
+            )),
+            Err(e) => {
+                log::warn!("Failed to get render buffer: {}", err_code(&e));
+                Ok(None)
             }
-            return Err(anyhow!("GetBuffer failed: {}", err_code(&e)));
         }
+    }
 
-        struct CaptureBufferGuard<'a> {
-            capture: &'a IAudioCaptureClient,
+    fn release_buffer(&self, frames: u32) -> Result<()> {
+        match unsafe { self.service.ReleaseBuffer(frames, 0) } {
+            Ok(()) => Ok(()),
+            Err(e) if is_device_invalidated(&e) => Err(anyhow!(
+                "Render device invalidated during ReleaseBuffer: {}",
+                err_code(&e)
+            )),
+            Err(e) => {
+                log::warn!("ReleaseBuffer failed: {}", err_code(&e));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 单次唤醒最多处理的 packet 数。正常情况下每次唤醒只有 1~2 个 packet，
+/// 上限只在调度卡顿后追赶时生效，保证 stop 信号仍能被及时检查。
+pub const MAX_PACKETS_PER_BATCH: usize = 64;
+
+/// Drain all currently available capture packets in one call.
+/// Returns the number of packets processed. Must be called in COM environment.
+pub(crate) fn process_available_packets<C: CaptureEndpoint, R: RenderEndpoint>(
+    state: &RouterInitialized<C, R>,
+    taps: &Taps,
+    stats: &RouterStats,
+    controls: &RouterControls,
+) -> Result<usize> {
+    let mut processed = 0;
+    while processed < MAX_PACKETS_PER_BATCH {
+        let started = Instant::now();
+        if !process_next_packet(state, taps, stats, controls)? {
+            break;
+        }
+        stats.record_processing(started.elapsed());
+        processed += 1;
+    }
+    if processed > 2 {
+        log::debug!("Caught up {processed} capture packets in one batch");
+    }
+    Ok(processed)
+}
+
+/// 捕获 packet 是否带有数据不连续标志。流启动后的第一个 packet 总会带上
+/// 该标志，不算 glitch。
+fn is_capture_glitch(flags: u32, first_packet: bool) -> bool {
+    !first_packet && flags & AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32 != 0
+}
+
+/// Process a single audio packet. Must be called in COM environment.
+///
+/// 捕获数据只在有消费者（电平表、tap、频谱、噪声门或混音路径的输出）时
+/// 转换为 f32 副本；Direct/ChannelMapped 输出端直接从捕获缓冲区写入。
+fn process_next_packet<C: CaptureEndpoint, R: RenderEndpoint>(
+    state: &RouterInitialized<C, R>,
+    taps: &Taps,
+    stats: &RouterStats,
+    controls: &RouterControls,
+) -> Result<bool> {
+    let capture = &state.capture_service;
+    let renders = &state.render_services;
+    let format = state.format;
+
+    unsafe {
+        if capture.next_packet_size()? == 0 {
+            return Ok(false);
+        }
+
+        let (buf_ptr, frames, flags) = capture.get_buffer()?;
+
+        struct CaptureBufferGuard<'a, C: CaptureEndpoint> {
+            capture: &'a C,
             frames: u32,
         }
 
-        impl Drop for CaptureBufferGuard<'_> {
+        impl<C: CaptureEndpoint> Drop for CaptureBufferGuard<'_, C> {
             fn drop(&mut self) {
-                unsafe {
-                    let _ = self.capture.ReleaseBuffer(self.frames);
-                }
+                self.capture.release_buffer(self.frames);
             }
         }
 
         let _release_capture = CaptureBufferGuard { capture, frames };
 
-        if frames > 0 && !buf_ptr.is_null() {
-            let block_align = (*pwf).nBlockAlign as usize;
-            let bytes = frames as usize * block_align;
-            let slice = std::slice::from_raw_parts(buf_ptr as *const u8, bytes);
-
-            let channels_count = (*pwf).nChannels as usize;
-            let sample_rate = (*pwf).nSamplesPerSec;
-
-            let mut out_f32 = Vec::with_capacity(frames as usize * channels_count);
-
-            let w_format = (*pwf).wFormatTag;
-            let sample_format = detect_sample_format(pwf);
-            let mut handled = false;
+        // 数据不连续/时间戳错误说明捕获端出现了 glitch（可听见的断续），
+        // 计入统计以便诊断。
+        stats.record_packet(frames);
+        if is_capture_glitch(flags, state.first_packet.replace(false)) {
+            stats.record_discontinuity();
+        }
+        if (flags & AUDCLNT_BUFFERFLAGS_TIMESTAMP_ERROR.0 as u32) != 0 {
+            stats.record_timestamp_error();
+        }
 
+        if frames > 0 && !buf_ptr.is_null() {
+            let bytes = frames as usize * format.block_align as usize;
+            let captured = std::slice::from_raw_parts(buf_ptr as *const u8, bytes);
+
+            let channels_count = format.channels as usize;
+            let sample_format = format.sample_format;
+
+            // 淡入淡出期间所有路径（包括直接拷贝）都从处理后的副本写入
+            let mut faded = state.fade_scratch.borrow_mut();
+            let slice = match state.fade.borrow_mut().as_mut() {
+                Some(fade) if fade.is_active() => {
+                    faded.clear();
+                    faded.extend_from_slice(captured);
+                    fade_raw_samples(&mut faded, channels_count, sample_format, fade);
+                    &faded[..]
+                }
+                _ => captured,
+            };
             let silent = (flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0;
 
-            if silent {
-                out_f32.resize(frames as usize * channels_count, 0.0);
-                handled = true;
-            } else if sample_format == SampleFormat::F32 {
-                let samples = bytes / 4;
-                let f32_slice: &[f32] =
-                    std::slice::from_raw_parts(slice.as_ptr() as *const f32, samples);
-                out_f32.extend_from_slice(f32_slice);
-                handled = true;
-            } else if sample_format == SampleFormat::I16 {
-                let samples = bytes / 2;
-                for i in 0..samples {
-                    let b1 = slice[i * 2];
-                    let b2 = slice[i * 2 + 1];
-                    let val = i16::from_le_bytes([b1, b2]);
-                    out_f32.push(val as f32 / 32768.0_f32);
+            // 电平表（含相关性表）只在有人读取时计量；停止读取后清空读数
+            let metering = controls.metering();
+            if state.metered.replace(metering) && !metering {
+                state.clear_meters(stats);
+            }
+
+            // f32 副本只构造一次，电平表、tap、相关性表、矩阵混音和噪声门共享；
+            // 都不需要时整个跳过，保持直接拷贝路径零转换。
+            let mut scratch = state.capture_scratch.borrow_mut();
+            scratch.clear();
+            if metering || state.needs_f32 || taps.pcm.is_some() || taps.spectrum.is_some() {
+                capture_to_f32(
+                    slice,
+                    frames,
+                    channels_count,
+                    sample_format,
+                    silent,
+                    &mut scratch,
+                );
+            }
+            if let Some(gate) = &state.gate {
+                gate.borrow_mut().process(&mut scratch);
+            }
+            if metering {
+                let mut source_meter = state.source_meter.borrow_mut();
+                if source_meter.process(&scratch) {
+                    state
+                        .source_levels
+                        .store(source_meter.peak(), source_meter.rms());
                 }
-                handled = true;
-            } else if sample_format == SampleFormat::I32 {
-                let samples = bytes / 4;
-                for i in 0..samples {
-                    let b1 = slice[i * 4];
-                    let b2 = slice[i * 4 + 1];
-                    let b3 = slice[i * 4 + 2];
-                    let b4 = slice[i * 4 + 3];
-                    let val = i32::from_le_bytes([b1, b2, b3, b4]);
-                    out_f32.push(val as f32 / 2147483648.0_f32);
+                if let Some(correlation) = &state.correlation {
+                    let mut correlation = correlation.borrow_mut();
+                    correlation.process(&scratch);
+                    stats.record_correlation(correlation.correlation());
                 }
-                handled = true;
             }
 
-            if !handled {
-                log::warn!("Unsupported audio format tag: {w_format}");
+            // 回调不在实时循环中执行：推入无锁队列，
+            // 队列满时丢弃本 packet 的 tap 数据，不影响路由。
+            if let Some(tap) = &taps.pcm
+                && !scratch.is_empty()
+            {
+                tap.set_format(format.sample_rate, format.channels);
+                if !tap.push(&scratch) {
+                    stats.record_tap_drop(scratch.len());
+                }
             }
-
-            let channels = channels_count as u16;
-
-            if !out_f32.is_empty() {
-                cb(&out_f32, sample_rate, channels);
+            // 频谱分析只需要最近的样本，队列满时直接丢弃，不计入 tap 丢包
+            if let Some(spectrum) = &taps.spectrum
+                && !scratch.is_empty()
+            {
+                spectrum.set_format(format.sample_rate, format.channels);
+                spectrum.push(&scratch);
             }
 
             for render in renders.iter() {
-                // 检查输出端累积延迟，padding 过高时跳过整个 packet，
-                // 让输出端消化已缓冲数据。整个 packet 跳过可以避免
-                // 部分截断导致的波形断裂和噪点。
-                // should_skip_write 返回 Err 表示设备 invalidated，需传播错误触发重启。
-                if should_skip_write(&render.client)? {
-                    continue;
-                }
+                // 重采样的输出端每个 packet 写入的帧数与捕获端不同
+                let render_frames = render.resampler.as_ref().map_or(frames, |resampler| {
+                    resampler.borrow().output_frames(frames as usize) as u32
+                });
 
-                match render.service.GetBuffer(frames) {
-                    Ok(render_buf_ptr) => {
-                        copy_with_channel_mode(
-                            slice,
+                // 检查输出端累积延迟，按该输出的溢出策略处理：
+                // 跳过、清空后写入、裁掉开头或重新同步。
+                // resolve_overflow 返回 Err 表示设备 invalidated，需传播错误触发重启。
+                let mut skip_frames = 0;
+                match resolve_overflow(render, render_frames)? {
+                    OverflowAction::Write => {}
+                    OverflowAction::Flush => {
+                        stats.record_overflow();
+                        render.stats.record_overflow();
+                        render.endpoint.flush()?;
+                    }
+                    OverflowAction::Trim(trim) => {
+                        stats.record_overflow();
+                        render.stats.record_overflow();
+                        skip_frames = trim;
+                    }
+                    OverflowAction::Skip => {
+                        stats.record_overflow();
+                        render.stats.record_overflow();
+                        continue;
+                    }
+                }
+                // 实际写入的帧数；被裁掉的帧仍经过混音器等有状态的处理，保持连续
+                let write_frames = render_frames - skip_frames;
+                let skip_samples = skip_frames as usize * render.format.channels as usize;
+                let skip_bytes = skip_frames as usize * render.format.block_align as usize;
+
+                // 粉红噪声替代该输出的捕获信号；Mixed 路径仍经过增益和 DSP
+                let noise_db = render
+                    .control
+                    .pink_noise_db()
+                    .filter(|_| sample_format != SampleFormat::Unsupported);
+                let mut noise = state.noise_scratch.borrow_mut();
+                if let Some(level_db) = noise_db {
+                    let mut pink_noise = state.pink_noise.borrow_mut();
+                    pink_noise.set_level_db(level_db);
+                    noise.clear();
+                    noise.resize(frames as usize * channels_count, 0.0);
+                    pink_noise.fill(&mut noise);
+                }
+                let source: &[f32] = if noise_db.is_some() {
+                    &noise[..]
+                } else {
+                    &scratch[..]
+                };
+
+                if let Some(render_buf_ptr) = render.endpoint.get_buffer(write_frames)? {
+                    match render.path {
+                        // 非 Mixed 路径的布局与捕获端一致
+                        RenderPath::Direct | RenderPath::ChannelMapped if noise_db.is_some() => {
+                            write_f32_samples(
+                                &source[skip_samples..],
+                                render_buf_ptr,
+                                sample_format,
+                                None,
+                            );
+                        }
+                        RenderPath::Direct if silent => {
+                            std::ptr::write_bytes(render_buf_ptr, 0, bytes - skip_bytes);
+                        }
+                        RenderPath::Direct => {
+                            std::ptr::copy_nonoverlapping(
+                                slice[skip_bytes..].as_ptr(),
+                                render_buf_ptr,
+                                bytes - skip_bytes,
+                            );
+                        }
+                        RenderPath::ChannelMapped => copy_with_channel_mode(
+                            &slice[skip_bytes..],
                             render_buf_ptr,
-                            bytes,
+                            bytes - skip_bytes,
                             channels_count,
                             sample_format,
                             render.channel_mode,
                             silent,
-                        );
-                        if let Err(e) = render.service.ReleaseBuffer(frames, 0) {
-                            if is_device_invalidated(&e) {
-                                return Err(anyhow!(
-                                    "Render device invalidated during ReleaseBuffer: {}",
-                                    err_code(&e)
-                                ));
+                        ),
+                        RenderPath::Mixed => match &render.mixer {
+                            Some(mixer) => {
+                                let mut mixed = state.mix_scratch.borrow_mut();
+                                let mut mixer = mixer.borrow_mut();
+                                mixer.set_gain_db(render.control.total_gain_db());
+                                let mode = render.control.mode();
+                                if mode != render.mixer_mode.get() {
+                                    render.mixer_mode.set(mode);
+                                    // 只在用户切换时发生一次，构造矩阵的分配可以接受
+                                    mixer.crossfade_to(
+                                        &mode_matrix(mode, render.format.channels as usize),
+                                        (MODE_CROSSFADE_SECS * format.sample_rate as f32) as u32,
+                                    );
+                                }
+                                mixer.process(source, &mut mixed);
+                                let mut resampled = state.resample_scratch.borrow_mut();
+                                let output = match &render.resampler {
+                                    Some(resampler) => {
+                                        resampler.borrow_mut().process(&mixed, &mut resampled);
+                                        &resampled
+                                    }
+                                    None => &mixed,
+                                };
+                                let output = &output[skip_samples.min(output.len())..];
+                                // 计量时由下面的读回统计削波
+                                if !metering {
+                                    let clipped = count_clipped(output);
+                                    if clipped > 0 {
+                                        render.stats.record_clipping(clipped);
+                                    }
+                                }
+                                let mut dither = render.dither.as_ref().map(|d| d.borrow_mut());
+                                write_f32_samples(
+                                    output,
+                                    render_buf_ptr,
+                                    sample_format,
+                                    dither.as_deref_mut(),
+                                );
                             }
-                            log::warn!("ReleaseBuffer failed: {}", err_code(&e));
-                        }
+                            None => std::ptr::write_bytes(
+                                render_buf_ptr,
+                                0,
+                                write_frames as usize * render.format.block_align as usize,
+                            ),
+                        },
                     }
-                    Err(e) => {
-                        if is_device_invalidated(&e) {
-                            return Err(anyhow!(
-                                "Render device invalidated during GetBuffer: {}",
-                                err_code(&e)
-                            ));
+                    // 计量时从输出缓冲区读回实际写入的数据，所有路径一致。
+                    // 不计量时只有混音路径会产生削波，已在写入前统计。
+                    if metering {
+                        let written = std::slice::from_raw_parts(
+                            render_buf_ptr,
+                            write_frames as usize * render.format.block_align as usize,
+                        );
+                        let mut metered = state.meter_scratch.borrow_mut();
+                        capture_to_f32(
+                            written,
+                            write_frames,
+                            render.format.channels as usize,
+                            render.format.sample_format,
+                            false,
+                            &mut metered,
+                        );
+                        let clipped = count_clipped(&metered);
+                        if clipped > 0 {
+                            render.stats.record_clipping(clipped);
+                        }
+                        let mut meter = render.meter.borrow_mut();
+                        if meter.process(&metered) {
+                            render.levels.store(meter.peak(), meter.rms());
                         }
-                        log::warn!("Failed to get render buffer: {}", err_code(&e));
                     }
+                    render.primed.set(true);
+                    render.endpoint.release_buffer(write_frames)?;
                 }
             }
 
@@ -470,10 +1571,115 @@ where
     }
 }
 
+/// 将捕获缓冲区转换为交错 f32 样本写入 `out`（复用其容量）。格式不支持时 `out` 为空。
+pub(crate) fn capture_to_f32(
+    slice: &[u8],
+    frames: u32,
+    channels: usize,
+    sample_format: SampleFormat,
+    silent: bool,
+    out: &mut Vec<f32>,
+) {
+    out.clear();
+    if silent {
+        out.resize(frames as usize * channels, 0.0);
+        return;
+    }
+    match sample_format {
+        SampleFormat::F32 => out.extend(
+            slice
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        ),
+        SampleFormat::I16 => out.extend(
+            slice
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0_f32),
+        ),
+        SampleFormat::I32 => out.extend(
+            slice
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0_f32),
+        ),
+        SampleFormat::Unsupported => {}
+    }
+}
+
+/// 对原始格式的交错帧施加淡入淡出增益。不支持的格式保持原样。
+fn fade_raw_samples(
+    bytes: &mut [u8],
+    channels: usize,
+    sample_format: SampleFormat,
+    fade: &mut Fade,
+) {
+    let sample_bytes = match sample_format {
+        SampleFormat::F32 | SampleFormat::I32 => 4,
+        SampleFormat::I16 => 2,
+        SampleFormat::Unsupported => return,
+    };
+    for frame in bytes.chunks_exact_mut(sample_bytes * channels.max(1)) {
+        if !fade.is_active() {
+            return;
+        }
+        let gain = fade.next_gain();
+        for sample in frame.chunks_exact_mut(sample_bytes) {
+            match sample_format {
+                SampleFormat::F32 => {
+                    let value = f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                    sample.copy_from_slice(&(value * gain).to_le_bytes());
+                }
+                SampleFormat::I16 => {
+                    let value = i16::from_le_bytes([sample[0], sample[1]]);
+                    sample.copy_from_slice(&((value as f32 * gain) as i16).to_le_bytes());
+                }
+                SampleFormat::I32 => {
+                    let value = i32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                    sample.copy_from_slice(&((value as f64 * gain as f64) as i32).to_le_bytes());
+                }
+                SampleFormat::Unsupported => {}
+            }
+        }
+    }
+}
+
+/// 将交错 f32 样本按 `sample_format` 写入输出缓冲区（整数格式会截断到满幅）。
+/// 16 位格式在给出 `dither` 时加抖动量化，否则直接截断。
+/// 调用方保证 `target` 至少能容纳 `samples.len()` 个样本。
+pub(crate) fn write_f32_samples(
+    samples: &[f32],
+    target: *mut u8,
+    sample_format: SampleFormat,
+    dither: Option<&mut Dither>,
+) {
+    let len = samples.len();
+    match sample_format {
+        SampleFormat::F32 => unsafe {
+            std::ptr::copy_nonoverlapping(samples.as_ptr(), target as *mut f32, len);
+        },
+        SampleFormat::I16 => {
+            let output = unsafe { std::slice::from_raw_parts_mut(target as *mut i16, len) };
+            match dither {
+                Some(dither) => dither.quantize_i16(samples, output),
+                None => {
+                    for (dst, &src) in output.iter_mut().zip(samples) {
+                        *dst = (src.clamp(-1.0, 1.0) * 32767.0) as i16;
+                    }
+                }
+            }
+        }
+        SampleFormat::I32 => {
+            let output = unsafe { std::slice::from_raw_parts_mut(target as *mut i32, len) };
+            for (dst, &src) in output.iter_mut().zip(samples) {
+                *dst = (src.clamp(-1.0, 1.0) as f64 * 2147483647.0) as i32;
+            }
+        }
+        SampleFormat::Unsupported => {}
+    }
+}
+
 fn detect_sample_format(pwf: *const WAVEFORMATEX) -> SampleFormat {
     const WAVE_FORMAT_PCM: u16 = 1;
     const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
-    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
 
     unsafe {
         match ((*pwf).wFormatTag, (*pwf).wBitsPerSample) {
@@ -518,7 +1724,7 @@ pub fn finalize_router(res: &RouterSetupResult) -> Result<()> {
     Ok(())
 }
 
-fn copy_with_channel_mode(
+pub(crate) fn copy_with_channel_mode(
     source: &[u8],
     target: *mut u8,
     bytes: usize,
@@ -532,15 +1738,15 @@ fn copy_with_channel_mode(
         return;
     }
 
-    if channels != 2 || mode == ChannelMode::Stereo {
+    if channels < 2 || mode == ChannelMode::Stereo {
         unsafe { std::ptr::copy_nonoverlapping(source.as_ptr(), target, bytes) };
         return;
     }
 
     match sample_format {
-        SampleFormat::F32 => copy_f32_stereo(source, target, mode),
-        SampleFormat::I16 => copy_i16_stereo(source, target, mode),
-        SampleFormat::I32 => copy_i32_stereo(source, target, mode),
+        SampleFormat::F32 => copy_f32_frames(source, target, channels, mode),
+        SampleFormat::I16 => copy_i16_frames(source, target, channels, mode),
+        SampleFormat::I32 => copy_i32_frames(source, target, channels, mode),
         SampleFormat::Unsupported => {
             log::warn!(
                 "Channel mode {:?} is unsupported for this format; using stereo",
@@ -556,7 +1762,9 @@ where
     T: Copy + Average,
 {
     match mode {
-        ChannelMode::Stereo => (left, right),
+        ChannelMode::Stereo | ChannelMode::Downmix | ChannelMode::Upmix | ChannelMode::Matrix => {
+            (left, right)
+        }
         ChannelMode::LeftMono => (left, left),
         ChannelMode::RightMono => (right, right),
         ChannelMode::Mono => {
@@ -591,35 +1799,46 @@ impl Average for i32 {
     }
 }
 
-fn copy_f32_stereo(source: &[u8], target: *mut u8, mode: ChannelMode) {
+fn copy_f32_frames(source: &[u8], target: *mut u8, channels: usize, mode: ChannelMode) {
     let samples = source.len() / 4;
     let input = unsafe { std::slice::from_raw_parts(source.as_ptr() as *const f32, samples) };
     let output = unsafe { std::slice::from_raw_parts_mut(target as *mut f32, samples) };
-    apply_stereo_frames(input, output, 0.0, mode);
+    apply_channel_mode_frames(input, output, channels, 0.0, mode);
 }
 
-fn copy_i16_stereo(source: &[u8], target: *mut u8, mode: ChannelMode) {
+fn copy_i16_frames(source: &[u8], target: *mut u8, channels: usize, mode: ChannelMode) {
     let samples = source.len() / 2;
     let input = unsafe { std::slice::from_raw_parts(source.as_ptr() as *const i16, samples) };
     let output = unsafe { std::slice::from_raw_parts_mut(target as *mut i16, samples) };
-    apply_stereo_frames(input, output, 0, mode);
+    apply_channel_mode_frames(input, output, channels, 0, mode);
 }
 
-fn copy_i32_stereo(source: &[u8], target: *mut u8, mode: ChannelMode) {
+fn copy_i32_frames(source: &[u8], target: *mut u8, channels: usize, mode: ChannelMode) {
     let samples = source.len() / 4;
     let input = unsafe { std::slice::from_raw_parts(source.as_ptr() as *const i32, samples) };
     let output = unsafe { std::slice::from_raw_parts_mut(target as *mut i32, samples) };
-    apply_stereo_frames(input, output, 0, mode);
+    apply_channel_mode_frames(input, output, channels, 0, mode);
 }
 
-fn apply_stereo_frames<T>(input: &[T], output: &mut [T], zero: T, mode: ChannelMode)
-where
+/// 按声道模式逐帧映射。声道模式作用于每帧的前两个声道（多声道布局中的 FL/FR），
+/// 其余声道（C、LFE、环绕等）原样保留。调用方保证 `channels >= 2`。
+fn apply_channel_mode_frames<T>(
+    input: &[T],
+    output: &mut [T],
+    channels: usize,
+    zero: T,
+    mode: ChannelMode,
+) where
     T: Copy + Average,
 {
-    for (src, dst) in input.chunks_exact(2).zip(output.chunks_exact_mut(2)) {
+    for (src, dst) in input
+        .chunks_exact(channels)
+        .zip(output.chunks_exact_mut(channels))
+    {
         let (left, right) = map_stereo_frame(src[0], src[1], zero, mode);
         dst[0] = left;
         dst[1] = right;
+        dst[2..].copy_from_slice(&src[2..]);
     }
 }
 
@@ -627,6 +1846,177 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn render_path_is_direct_only_for_unprocessed_matching_formats() {
+        let stereo_f32 = StreamFormat {
+            sample_format: SampleFormat::F32,
+            channels: 2,
+            sample_rate: 48_000,
+            block_align: 8,
+            channel_mask: 0x3,
+        };
+        let surround = StreamFormat {
+            channels: 6,
+            block_align: 24,
+            channel_mask: 0x3F,
+            ..stereo_f32
+        };
+        let unsupported = StreamFormat {
+            sample_format: SampleFormat::Unsupported,
+            ..stereo_f32
+        };
+
+        assert_eq!(
+            RenderPath::select(stereo_f32, stereo_f32, ChannelMode::Stereo, false),
+            RenderPath::Direct
+        );
+        assert_eq!(
+            RenderPath::select(stereo_f32, stereo_f32, ChannelMode::Swap, false),
+            RenderPath::ChannelMapped
+        );
+        // 多声道源同样按模式映射前置左右声道；单声道源无需映射
+        assert_eq!(
+            RenderPath::select(surround, surround, ChannelMode::Mono, false),
+            RenderPath::ChannelMapped
+        );
+        let mono = StreamFormat {
+            channels: 1,
+            block_align: 4,
+            channel_mask: 0x4,
+            ..stereo_f32
+        };
+        assert_eq!(
+            RenderPath::select(mono, mono, ChannelMode::Swap, false),
+            RenderPath::Direct
+        );
+        // 声道数不同只能经矩阵混音
+        assert_eq!(
+            RenderPath::select(stereo_f32, surround, ChannelMode::Stereo, false),
+            RenderPath::Mixed
+        );
+        assert_eq!(
+            RenderPath::select(surround, stereo_f32, ChannelMode::Downmix, false),
+            RenderPath::Mixed
+        );
+        assert_eq!(
+            RenderPath::select(stereo_f32, stereo_f32, ChannelMode::Downmix, false),
+            RenderPath::Direct
+        );
+        assert_eq!(
+            RenderPath::select(stereo_f32, surround, ChannelMode::Upmix, false),
+            RenderPath::Mixed
+        );
+        assert_eq!(
+            RenderPath::select(surround, surround, ChannelMode::Upmix, false),
+            RenderPath::Direct
+        );
+        // 自行重采样的输出必须走 f32 处理
+        let stereo_44k1 = StreamFormat {
+            sample_rate: 44_100,
+            ..stereo_f32
+        };
+        assert_eq!(
+            RenderPath::select(stereo_44k1, stereo_f32, ChannelMode::Stereo, false),
+            RenderPath::Mixed
+        );
+        // 有 EQ 的输出必须走 f32 处理
+        assert_eq!(
+            RenderPath::select(stereo_f32, stereo_f32, ChannelMode::Stereo, true),
+            RenderPath::Mixed
+        );
+        assert_eq!(
+            RenderPath::select(unsupported, unsupported, ChannelMode::Stereo, false),
+            RenderPath::ChannelMapped
+        );
+        assert_eq!(
+            RenderPath::select(stereo_f32, stereo_f32, ChannelMode::Matrix, false),
+            RenderPath::Mixed
+        );
+        assert_eq!(
+            RenderPath::select(unsupported, unsupported, ChannelMode::Matrix, false),
+            RenderPath::ChannelMapped
+        );
+    }
+
+    #[test]
+    fn discontinuity_on_the_first_packet_is_not_a_glitch() {
+        let flags = AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32;
+        assert!(!is_capture_glitch(flags, true));
+        assert!(is_capture_glitch(flags, false));
+        let silent = AUDCLNT_BUFFERFLAGS_SILENT.0 as u32;
+        assert!(!is_capture_glitch(silent, false));
+    }
+
+    #[test]
+    fn overflow_policies_handle_full_buffer() {
+        // buffer 1000 帧，目标 padding 200 帧，packet 100 帧
+        let idle = Cell::new(false);
+        let act = |policy, padding| overflow_action(policy, padding, 1000, 100, &idle);
+
+        for policy in [
+            OverflowPolicy::DropNewest,
+            OverflowPolicy::DropOldest,
+            OverflowPolicy::Block,
+            OverflowPolicy::Resync,
+        ] {
+            assert_eq!(act(policy, 150), OverflowAction::Write, "{policy:?}");
+        }
+
+        assert_eq!(act(OverflowPolicy::DropNewest, 300), OverflowAction::Skip);
+        // DropOldest 超出目标时每个 packet 最多裁掉 1/4，放不下时才清空
+        let drop_oldest = |padding| act(OverflowPolicy::DropOldest, padding);
+        assert_eq!(drop_oldest(210), OverflowAction::Trim(10));
+        assert_eq!(drop_oldest(300), OverflowAction::Trim(25));
+        assert_eq!(drop_oldest(950), OverflowAction::Flush);
+        // Block 忽略延迟目标，放不下整个 packet 时跳过而不是等待
+        assert_eq!(act(OverflowPolicy::Block, 300), OverflowAction::Write);
+        assert_eq!(act(OverflowPolicy::Block, 950), OverflowAction::Skip);
+    }
+
+    #[test]
+    fn resync_drops_until_buffer_drains_below_half_target() {
+        let resyncing = Cell::new(false);
+        let act = |padding| overflow_action(OverflowPolicy::Resync, padding, 1000, 100, &resyncing);
+
+        assert_eq!(act(300), OverflowAction::Skip);
+        assert!(resyncing.get());
+        // 回落到目标以下但仍高于一半，继续丢包
+        assert_eq!(act(150), OverflowAction::Skip);
+        assert_eq!(act(100), OverflowAction::Write);
+        assert!(!resyncing.get());
+        assert_eq!(act(150), OverflowAction::Write);
+    }
+
+    #[test]
+    fn fades_raw_i16_frames_in() {
+        let mut fade = Fade::fade_in(1.0, 4_000).unwrap();
+        let mut bytes: Vec<u8> = [1000_i16; 2 * 6]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        fade_raw_samples(&mut bytes, 2, SampleFormat::I16, &mut fade);
+        let samples: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        // 1 ms @ 4 kHz = 4 帧
+        assert_eq!(samples[..8], [250, 250, 500, 500, 750, 750, 1000, 1000]);
+        assert!(samples[8..].iter().all(|&s| s == 1000));
+    }
+
+    #[test]
+    fn writes_mixed_samples_with_clipping() {
+        let samples = [0.5_f32, -1.5, 1.5];
+        let mut output = [0_i16; 3];
+        write_f32_samples(
+            &samples,
+            output.as_mut_ptr() as *mut u8,
+            SampleFormat::I16,
+            None,
+        );
+        assert_eq!(output, [16383, -32767, 32767]);
+    }
+
     #[test]
     fn maps_f32_stereo_modes() {
         let input = [0.8_f32, 0.2_f32, -0.4_f32, 0.6_f32];
@@ -642,10 +2032,48 @@ mod tests {
 
         for (mode, expected) in cases {
             let mut output = vec![0.0_f32; input.len()];
-            apply_stereo_frames(&input, &mut output, 0.0, mode);
+            apply_channel_mode_frames(&input, &mut output, 2, 0.0, mode);
             for (actual, expected) in output.iter().zip(expected) {
                 assert!((actual - expected).abs() < f32::EPSILON);
             }
         }
     }
+
+    #[test]
+    fn maps_front_pair_of_surround_frames_and_keeps_other_channels() {
+        // 两帧 5.1（FL FR C LFE SL SR）
+        let input: [i16; 12] = [100, 200, 300, 400, 500, 600, -1, -2, -3, -4, -5, -6];
+        let mut output = [0_i16; 12];
+        copy_with_channel_mode(
+            as_bytes(&input),
+            output.as_mut_ptr() as *mut u8,
+            input.len() * 2,
+            6,
+            SampleFormat::I16,
+            ChannelMode::Swap,
+            false,
+        );
+        assert_eq!(
+            output,
+            [200, 100, 300, 400, 500, 600, -2, -1, -3, -4, -5, -6]
+        );
+
+        copy_with_channel_mode(
+            as_bytes(&input),
+            output.as_mut_ptr() as *mut u8,
+            input.len() * 2,
+            6,
+            SampleFormat::I16,
+            ChannelMode::Mono,
+            false,
+        );
+        assert_eq!(
+            output,
+            [150, 150, 300, 400, 500, 600, -1, -1, -3, -4, -5, -6]
+        );
+    }
+
+    fn as_bytes(samples: &[i16]) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(samples.as_ptr() as *const u8, samples.len() * 2) }
+    }
 }
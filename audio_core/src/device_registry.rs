@@ -0,0 +1,200 @@
+//! Cached output device list, invalidated by the device watcher.
+//!
+//! Enumerating devices creates an enumerator and opens every endpoint's property
+//! store, which is too slow to repeat on every UI refresh. [`DeviceRegistry`] keeps the last
+//! result and only enumerates again after [`DeviceWatcher`] has reported a
+//! change. Without a running watcher every call enumerates, as before.
+//!
+//! The registry owns the watcher, so other consumers get its events through
+//! [`DeviceRegistry::subscribe`].
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::com_service::apartment::Apartment;
+use crate::com_service::device::{
+    DeviceDetail, DeviceInfo, DeviceStateMask, enumerate_output_devices,
+    enumerate_output_devices_in,
+};
+use crate::device_watcher::{DeviceEvent, DeviceWatcher};
+
+struct Shared {
+    /// 每收到一次设备事件加一。
+    generation: AtomicU64,
+    /// 监听线程在运行时为 true；否则缓存不可信。
+    watching: AtomicBool,
+    /// 缓存的设备列表及其枚举开始时的 generation。
+    cache: Mutex<Option<(u64, Vec<DeviceInfo>)>>,
+    subscribers: Mutex<Vec<Sender<DeviceEvent>>>,
+}
+
+impl Shared {
+    /// Renames device `id` in the cache; `false` if it is not cached.
+    fn rename_cached(&self, id: &str, name: &str) -> bool {
+        let mut cache = self.cache.lock();
+        let Some(device) = cache
+            .as_mut()
+            .and_then(|(_, devices)| devices.iter_mut().find(|d| d.id == id))
+        else {
+            return false;
+        };
+        device.friendly_name = name.to_string();
+        true
+    }
+}
+
+/// Output device list cache, refreshed when the device watcher reports a change.
+pub struct DeviceRegistry {
+    shared: Arc<Shared>,
+    watcher: Option<DeviceWatcher>,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeviceRegistry {
+    /// Creates a registry that does not cache until [`Self::start_watching`] is called.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                generation: AtomicU64::new(0),
+                watching: AtomicBool::new(false),
+                cache: Mutex::new(None),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+            watcher: None,
+        }
+    }
+
+    /// Starts a [`DeviceWatcher`] whose events invalidate the cache. Idempotent.
+    ///
+    /// # Errors
+    /// Returns an error if the watcher cannot be started.
+    pub fn start_watching(&mut self) -> Result<()> {
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+        let (watcher, rx) = DeviceWatcher::start()?;
+        let shared = self.shared.clone();
+        shared.watching.store(true, Ordering::Release);
+        thread::spawn(move || {
+            for event in rx {
+                // 改名只需更新缓存中的名称，不必重新枚举
+                let renamed = matches!(&event, DeviceEvent::FriendlyNameChanged(id, name)
+                    if shared.rename_cached(id, name));
+                // 其他事件都可能改变列表或其中的默认设备标记
+                if !renamed {
+                    shared.generation.fetch_add(1, Ordering::AcqRel);
+                }
+                // 缓存失效后才通知，订阅者收到事件时读到的一定是新列表
+                shared
+                    .subscribers
+                    .lock()
+                    .retain(|tx| tx.send(event.clone()).is_ok());
+            }
+            shared.watching.store(false, Ordering::Release);
+        });
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Stops the watcher; later calls enumerate every time.
+    pub fn stop_watching(&mut self) {
+        self.shared.watching.store(false, Ordering::Release);
+        if let Some(mut watcher) = self.watcher.take() {
+            watcher.stop();
+        }
+        *self.shared.cache.lock() = None;
+    }
+
+    /// Receives every event of the registry's watcher from now on. By the time an event is
+    /// received, [`Self::devices`] already reflects it.
+    pub fn subscribe(&self) -> Receiver<DeviceEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.shared.subscribers.lock().push(tx);
+        rx
+    }
+
+    /// Forces the next [`Self::devices`] call to enumerate.
+    pub fn invalidate(&self) {
+        self.shared.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// All active output devices, from the cache if no change was reported since it was
+    /// filled. Enumeration runs in `apartment` if given, otherwise on the `#[with_com]` worker.
+    ///
+    /// Only device properties are read ([`DeviceDetail::Properties`]), so the format fields of
+    /// the returned devices are `None`.
+    ///
+    /// # Errors
+    /// Returns an error if enumeration is needed and fails; the cache is left untouched.
+    pub fn devices(&self, apartment: Option<Apartment>) -> Result<Vec<DeviceInfo>> {
+        let (states, detail) = (DeviceStateMask::ACTIVE, DeviceDetail::Properties);
+        self.devices_with(|| match apartment {
+            Some(apartment) => enumerate_output_devices_in(apartment, states, detail),
+            None => enumerate_output_devices(states, detail),
+        })
+    }
+
+    fn devices_with(
+        &self,
+        enumerate: impl FnOnce() -> Result<Vec<DeviceInfo>>,
+    ) -> Result<Vec<DeviceInfo>> {
+        if !self.shared.watching.load(Ordering::Acquire) {
+            return enumerate();
+        }
+        // 先读 generation 再枚举：枚举期间到达的事件会让下一次调用重新枚举
+        let generation = self.shared.generation.load(Ordering::Acquire);
+        if let Some((cached_generation, devices)) = &*self.shared.cache.lock()
+            && *cached_generation == generation
+        {
+            return Ok(devices.clone());
+        }
+        let devices = enumerate()?;
+        *self.shared.cache.lock() = Some((generation, devices.clone()));
+        Ok(devices)
+    }
+}
+
+impl Drop for DeviceRegistry {
+    fn drop(&mut self) {
+        self.stop_watching();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn enumerates_again_only_after_invalidation() {
+        let registry = DeviceRegistry::new();
+        registry.shared.watching.store(true, Ordering::Release);
+        let calls = Cell::new(0);
+        let enumerate = || {
+            calls.set(calls.get() + 1);
+            Ok(Vec::new())
+        };
+
+        registry.devices_with(enumerate).unwrap();
+        registry.devices_with(enumerate).unwrap();
+        assert_eq!(calls.get(), 1);
+
+        registry.invalidate();
+        registry.devices_with(enumerate).unwrap();
+        assert_eq!(calls.get(), 2);
+
+        // 没有监听时不缓存
+        registry.shared.watching.store(false, Ordering::Release);
+        registry.devices_with(enumerate).unwrap();
+        assert_eq!(calls.get(), 3);
+    }
+}
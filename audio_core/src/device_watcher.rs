@@ -4,14 +4,21 @@
 //! such as device addition, removal, state changes, and default device changes.
 //! It uses Windows COM APIs to register for notifications and forwards events
 //! through a channel for easy consumption by other parts of the application.
+//!
+//! Windows fires a burst of notifications for a single hotplug, so events are
+//! coalesced over a short debounce window before they are forwarded.
 
 use anyhow::Result;
-use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use windows::core::implement;
 
-use crate::com_service::device::{DeviceInfo, get_default_output_device};
+use crate::com_service::device::{
+    DeviceFlow, DeviceInfo, DeviceRole, DeviceState, get_default_device, get_device_friendly_name,
+};
+use crate::utils::map_state;
+use crate::utils::win_helpers::PKEY_DEVICE_FRIENDLY;
 
 /// Event types for device changes.
 ///
@@ -25,12 +32,13 @@ use crate::com_service::device::{DeviceInfo, get_default_output_device};
 /// // Receive one event (initial default device will be sent on start)
 /// if let Ok(evt) = rx.recv() {
 ///     match evt {
-///         DeviceEvent::DefaultChanged(opt) => {
-///             println!("Default device changed: {:?}", opt);
+///         DeviceEvent::DefaultChanged { flow, role, device } => {
+///             println!("Default {flow:?}/{role:?} device changed: {:?}", device);
 ///         }
-///         DeviceEvent::Changed => {
-///             println!("Device topology changed; call list_output_devices() to refresh");
+///         DeviceEvent::StateChanged(id, state) => {
+///             println!("{id} is now {state:?}");
 ///         }
+///         other => println!("{:?}", other),
 ///     }
 /// }
 /// // Stop when done
@@ -38,10 +46,23 @@ use crate::com_service::device::{DeviceInfo, get_default_output_device};
 /// ```
 #[derive(Debug, Clone)]
 pub enum DeviceEvent {
-    /// Something changed in device topology (add/remove/state).
-    Changed,
-    /// Default device changed; contains current default device.
-    DefaultChanged(DeviceInfo),
+    /// A device with this id was added to the system.
+    Added(String),
+    /// The device with this id was removed from the system.
+    Removed(String),
+    /// The device was enabled, disabled, plugged or unplugged.
+    StateChanged(String, DeviceState),
+    /// A property of the device (format, icon, ...) changed.
+    PropertyChanged(String),
+    /// The device was renamed; carries the id and the new friendly name.
+    FriendlyNameChanged(String, String),
+    /// The default device of `flow` for `role` changed; `device` is `None` when no device
+    /// of that direction is left.
+    DefaultChanged {
+        flow: DeviceFlow,
+        role: DeviceRole,
+        device: Option<DeviceInfo>,
+    },
 }
 
 /// Notification client for Windows COM device events.
@@ -60,48 +81,180 @@ impl NotificationClient {
 impl windows::Win32::Media::Audio::IMMNotificationClient_Impl for NotificationClient {
     fn OnDeviceStateChanged(
         &self,
-        _pwstrdeviceid: &windows::core::PCWSTR,
-        _dwnewstate: u32,
+        pwstrdeviceid: &windows::core::PCWSTR,
+        dwnewstate: u32,
     ) -> windows::core::Result<()> {
-        let _ = self.sender.send(DeviceEvent::Changed);
+        let _ = self.sender.send(DeviceEvent::StateChanged(
+            device_id(pwstrdeviceid),
+            map_state(dwnewstate),
+        ));
         Ok(())
     }
 
-    fn OnDeviceAdded(&self, _pwstrdeviceid: &windows::core::PCWSTR) -> windows::core::Result<()> {
-        let _ = self.sender.send(DeviceEvent::Changed);
+    fn OnDeviceAdded(&self, pwstrdeviceid: &windows::core::PCWSTR) -> windows::core::Result<()> {
+        let _ = self
+            .sender
+            .send(DeviceEvent::Added(device_id(pwstrdeviceid)));
         Ok(())
     }
 
-    fn OnDeviceRemoved(&self, _pwstrdeviceid: &windows::core::PCWSTR) -> windows::core::Result<()> {
-        let _ = self.sender.send(DeviceEvent::Changed);
+    fn OnDeviceRemoved(&self, pwstrdeviceid: &windows::core::PCWSTR) -> windows::core::Result<()> {
+        let _ = self
+            .sender
+            .send(DeviceEvent::Removed(device_id(pwstrdeviceid)));
         Ok(())
     }
 
     fn OnDefaultDeviceChanged(
         &self,
-        _flow: windows::Win32::Media::Audio::EDataFlow,
-        _role: windows::Win32::Media::Audio::ERole,
-        _pwstrdefaultdeviceid: &windows::core::PCWSTR,
+        flow: windows::Win32::Media::Audio::EDataFlow,
+        role: windows::Win32::Media::Audio::ERole,
+        pwstrdefaultdeviceid: &windows::core::PCWSTR,
     ) -> windows::core::Result<()> {
-        match get_default_output_device() {
-            Ok(d) => {
-                let _ = self.sender.send(DeviceEvent::DefaultChanged(d));
+        let (Some(flow), Some(role)) = (
+            DeviceFlow::from_edataflow(flow),
+            DeviceRole::from_erole(role),
+        ) else {
+            return Ok(());
+        };
+        // 空 id 表示该方向已没有任何设备
+        let device = if pwstrdefaultdeviceid.is_null() {
+            None
+        } else {
+            match get_default_device(flow, role) {
+                Ok(d) => Some(d),
+                Err(e) => {
+                    log::error!("get_default_device failed in callback: {:?}", e);
+                    return Ok(());
+                }
             }
-            Err(e) => log::error!("get_default_output_device failed in callback: {:?}", e),
-        }
+        };
+        let _ = self
+            .sender
+            .send(DeviceEvent::DefaultChanged { flow, role, device });
         Ok(())
     }
 
     fn OnPropertyValueChanged(
         &self,
-        _pwstrdeviceid: &windows::core::PCWSTR,
-        _key: &windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY,
+        pwstrdeviceid: &windows::core::PCWSTR,
+        key: &windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY,
     ) -> windows::core::Result<()> {
-        let _ = self.sender.send(DeviceEvent::Changed);
+        let id = device_id(pwstrdeviceid);
+        let event = if *key == PKEY_DEVICE_FRIENDLY {
+            match get_device_friendly_name(&id) {
+                Ok(name) => DeviceEvent::FriendlyNameChanged(id, name),
+                Err(e) => {
+                    log::warn!("Reading renamed device {} failed: {:?}", id, e);
+                    DeviceEvent::PropertyChanged(id)
+                }
+            }
+        } else {
+            DeviceEvent::PropertyChanged(id)
+        };
+        let _ = self.sender.send(event);
         Ok(())
     }
 }
 
+/// Copies the device id passed to a notification callback.
+fn device_id(id: &windows::core::PCWSTR) -> String {
+    if id.is_null() {
+        return String::new();
+    }
+    unsafe { id.to_string() }.unwrap_or_default()
+}
+
+/// Debounce window used by [`DeviceWatcher::start`].
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A burst is flushed at the latest this many debounce windows after its first
+/// event, even if notifications keep arriving.
+const MAX_DEBOUNCE_WINDOWS: u32 = 5;
+
+/// How often the watcher thread checks for the stop signal while idle.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Events of one device (or the default device) collapse into one slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CoalesceKey {
+    Device(String),
+    /// 改名单独成组，不会被同一设备的状态变化吞掉
+    Name(String),
+    Default(DeviceFlow, DeviceRole),
+}
+
+impl CoalesceKey {
+    fn of(event: &DeviceEvent) -> Self {
+        match event {
+            DeviceEvent::Added(id)
+            | DeviceEvent::Removed(id)
+            | DeviceEvent::StateChanged(id, _)
+            | DeviceEvent::PropertyChanged(id) => CoalesceKey::Device(id.clone()),
+            DeviceEvent::FriendlyNameChanged(id, _) => CoalesceKey::Name(id.clone()),
+            DeviceEvent::DefaultChanged { flow, role, .. } => CoalesceKey::Default(*flow, *role),
+        }
+    }
+}
+
+/// Higher is more specific: an add/remove implies state and property changes.
+fn specificity(event: &DeviceEvent) -> u8 {
+    match event {
+        DeviceEvent::Added(_) | DeviceEvent::Removed(_) => 2,
+        DeviceEvent::StateChanged(..) => 1,
+        DeviceEvent::PropertyChanged(_)
+        | DeviceEvent::FriendlyNameChanged(..)
+        | DeviceEvent::DefaultChanged { .. } => 0,
+    }
+}
+
+/// Collects a burst of events and keeps one per device: the most specific, the
+/// latest among equally specific ones.
+struct EventCoalescer {
+    window: Duration,
+    pending: Vec<(CoalesceKey, DeviceEvent)>,
+    first_at: Option<Instant>,
+    last_at: Option<Instant>,
+}
+
+impl EventCoalescer {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Vec::new(),
+            first_at: None,
+            last_at: None,
+        }
+    }
+
+    fn push(&mut self, event: DeviceEvent, now: Instant) {
+        self.first_at.get_or_insert(now);
+        self.last_at = Some(now);
+        let key = CoalesceKey::of(&event);
+        match self.pending.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => {
+                if specificity(&event) >= specificity(existing) {
+                    *existing = event;
+                }
+            }
+            None => self.pending.push((key, event)),
+        }
+    }
+
+    /// When the pending burst should be flushed, `None` if nothing is pending.
+    fn deadline(&self) -> Option<Instant> {
+        let (first, last) = (self.first_at?, self.last_at?);
+        Some((last + self.window).min(first + self.window * MAX_DEBOUNCE_WINDOWS))
+    }
+
+    /// Takes the pending events in order of first appearance.
+    fn drain(&mut self) -> Vec<DeviceEvent> {
+        self.first_at = None;
+        self.last_at = None;
+        self.pending.drain(..).map(|(_, event)| event).collect()
+    }
+}
+
 /// Handle for the device watcher.
 ///
 /// Drop or call `stop()` to unregister and stop the background thread.
@@ -114,7 +267,9 @@ impl DeviceWatcher {
     /// Starts a device watcher and returns the handle and event receiver.
     ///
     /// This spawns a background thread that registers for audio device notifications.
-    /// An initial `DefaultChanged` event is sent immediately with the current default device.
+    /// Notifications cover render and capture endpoints alike. Initial `DefaultChanged` events
+    /// are sent immediately with the current console default output and input devices.
+    /// Bursts are coalesced over [`DEFAULT_DEBOUNCE`].
     ///
     /// # Returns
     /// A tuple of `(DeviceWatcher, Receiver<DeviceEvent>)`.
@@ -122,11 +277,22 @@ impl DeviceWatcher {
     /// # Errors
     /// Returns an error if COM setup fails.
     pub fn start() -> Result<(DeviceWatcher, Receiver<DeviceEvent>)> {
+        Self::start_with_debounce(DEFAULT_DEBOUNCE)
+    }
+
+    /// Like [`Self::start`], but notifications arriving within `debounce` of each other are
+    /// coalesced into one event per device. `Duration::ZERO` forwards every notification.
+    ///
+    /// # Errors
+    /// Returns an error if COM setup fails.
+    pub fn start_with_debounce(
+        debounce: Duration,
+    ) -> Result<(DeviceWatcher, Receiver<DeviceEvent>)> {
         let (event_tx, event_rx) = mpsc::channel::<DeviceEvent>();
         let (stop_tx, stop_rx) = mpsc::channel::<()>();
 
         let join_handle = thread::spawn(move || {
-            if let Err(e) = watcher_thread(event_tx, stop_rx) {
+            if let Err(e) = watcher_thread(event_tx, stop_rx, debounce) {
                 log::error!("Watcher thread error: {:?}", e);
             }
         });
@@ -160,12 +326,17 @@ impl Drop for DeviceWatcher {
 }
 
 /// Main watcher thread function.
-fn watcher_thread(event_tx: Sender<DeviceEvent>, stop_rx: Receiver<()>) -> Result<()> {
+fn watcher_thread(
+    event_tx: Sender<DeviceEvent>,
+    stop_rx: Receiver<()>,
+    debounce: Duration,
+) -> Result<()> {
     let enumerator = crate::com_service::watcher::create_enumerator()?;
 
-    // Create the COM notification client
+    // Create the COM notification client; its raw events go through the coalescer
+    let (raw_tx, raw_rx) = mpsc::channel::<DeviceEvent>();
     let client: windows::Win32::Media::Audio::IMMNotificationClient =
-        NotificationClient::new(event_tx.clone()).into();
+        NotificationClient::new(raw_tx).into();
 
     // Register for notifications
     crate::com_service::watcher::register_notification(
@@ -173,13 +344,21 @@ fn watcher_thread(event_tx: Sender<DeviceEvent>, stop_rx: Receiver<()>) -> Resul
         crate::utils::ComSend::new(client.clone()),
     )?;
 
-    // Send initial default device event
-    if let Ok(d) = get_default_output_device() {
-        event_tx.send(DeviceEvent::DefaultChanged(d))?;
+    // Send initial default device events; the multimedia default follows the console one
+    for flow in DeviceFlow::ALL {
+        for role in [DeviceRole::Console, DeviceRole::Communications] {
+            if let Ok(d) = get_default_device(flow, role) {
+                event_tx.send(DeviceEvent::DefaultChanged {
+                    flow,
+                    role,
+                    device: Some(d),
+                })?;
+            }
+        }
     }
 
-    // Wait for stop signal
-    watcher_event_loop(&stop_rx)?;
+    // Forward coalesced events until the stop signal
+    watcher_event_loop(&stop_rx, &raw_rx, &event_tx, debounce);
 
     // Unregister callback
     let _ = crate::com_service::watcher::unregister_notification(
@@ -190,15 +369,40 @@ fn watcher_thread(event_tx: Sender<DeviceEvent>, stop_rx: Receiver<()>) -> Resul
     Ok(())
 }
 
-/// Event loop that waits for stop signal.
-fn watcher_event_loop(stop_rx: &Receiver<()>) -> Result<()> {
+/// Event loop that coalesces raw notifications and forwards them until the stop signal.
+fn watcher_event_loop(
+    stop_rx: &Receiver<()>,
+    raw_rx: &Receiver<DeviceEvent>,
+    event_tx: &Sender<DeviceEvent>,
+    debounce: Duration,
+) {
+    let mut coalescer = EventCoalescer::new(debounce);
     loop {
-        match stop_rx.recv_timeout(Duration::from_millis(500)) {
-            Ok(_) | Err(RecvTimeoutError::Disconnected) => break,
-            Err(RecvTimeoutError::Timeout) => continue,
+        match stop_rx.try_recv() {
+            Ok(_) | Err(TryRecvError::Disconnected) => break,
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let now = Instant::now();
+        let timeout = coalescer
+            .deadline()
+            .map_or(STOP_POLL_INTERVAL, |d| d.saturating_duration_since(now))
+            .min(STOP_POLL_INTERVAL);
+        match raw_rx.recv_timeout(timeout) {
+            Ok(event) if debounce.is_zero() => {
+                let _ = event_tx.send(event);
+            }
+            Ok(event) => coalescer.push(event, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if coalescer.deadline().is_some_and(|d| d <= Instant::now()) {
+            for event in coalescer.drain() {
+                let _ = event_tx.send(event);
+            }
         }
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -212,11 +416,102 @@ mod tests {
 
         // We should receive the initial DefaultChanged event
         match rx.recv_timeout(Duration::from_secs(2)) {
-            Ok(DeviceEvent::DefaultChanged(_)) => (),
+            Ok(DeviceEvent::DefaultChanged { .. }) => (),
             Ok(other) => panic!("expected DefaultChanged, got {:?}", other),
             Err(e) => panic!("did not receive initial event: {:?}", e),
         }
 
         watcher.stop();
     }
+
+    #[test]
+    fn coalescer_keeps_most_specific_event_per_device() {
+        let window = Duration::from_millis(200);
+        let mut coalescer = EventCoalescer::new(window);
+        let start = Instant::now();
+        assert_eq!(coalescer.deadline(), None);
+
+        coalescer.push(DeviceEvent::PropertyChanged("a".into()), start);
+        coalescer.push(DeviceEvent::Added("a".into()), start);
+        coalescer.push(
+            DeviceEvent::StateChanged("a".into(), DeviceState::Active),
+            start,
+        );
+        coalescer.push(DeviceEvent::PropertyChanged("b".into()), start);
+        let later = start + Duration::from_millis(150);
+        coalescer.push(DeviceEvent::PropertyChanged("a".into()), later);
+        assert_eq!(coalescer.deadline(), Some(later + window));
+
+        let events = coalescer.drain();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], DeviceEvent::Added(id) if id == "a"));
+        assert!(matches!(&events[1], DeviceEvent::PropertyChanged(id) if id == "b"));
+        assert_eq!(coalescer.deadline(), None);
+    }
+
+    #[test]
+    fn coalescer_keeps_rename_next_to_state_change() {
+        let mut coalescer = EventCoalescer::new(Duration::from_millis(200));
+        let now = Instant::now();
+        coalescer.push(
+            DeviceEvent::FriendlyNameChanged("a".into(), "Desk".into()),
+            now,
+        );
+        coalescer.push(
+            DeviceEvent::StateChanged("a".into(), DeviceState::Active),
+            now,
+        );
+        coalescer.push(
+            DeviceEvent::FriendlyNameChanged("a".into(), "Desk speakers".into()),
+            now,
+        );
+
+        let events = coalescer.drain();
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(&events[0], DeviceEvent::FriendlyNameChanged(_, name) if name == "Desk speakers")
+        );
+        assert!(matches!(&events[1], DeviceEvent::StateChanged(..)));
+    }
+
+    #[test]
+    fn coalescer_deadline_is_capped() {
+        let window = Duration::from_millis(100);
+        let mut coalescer = EventCoalescer::new(window);
+        let start = Instant::now();
+        for i in 0..20 {
+            coalescer.push(
+                DeviceEvent::PropertyChanged("a".into()),
+                start + Duration::from_millis(50 * i),
+            );
+        }
+        assert_eq!(
+            coalescer.deadline(),
+            Some(start + window * MAX_DEBOUNCE_WINDOWS)
+        );
+    }
+
+    #[test]
+    fn callbacks_send_detailed_events() {
+        use windows::Win32::Media::Audio::{DEVICE_STATE_UNPLUGGED, IMMNotificationClient_Impl};
+
+        let (tx, rx) = mpsc::channel();
+        let client = NotificationClient::new(tx);
+        let wide: Vec<u16> = "{0.0.0.00000000}.{abc}".encode_utf16().chain([0]).collect();
+        let id = windows::core::PCWSTR(wide.as_ptr());
+
+        client.OnDeviceAdded(&id).unwrap();
+        client
+            .OnDeviceStateChanged(&id, DEVICE_STATE_UNPLUGGED)
+            .unwrap();
+
+        match rx.try_recv() {
+            Ok(DeviceEvent::Added(added)) => assert!(!added.is_empty()),
+            other => panic!("expected Added, got {:?}", other),
+        }
+        match rx.try_recv() {
+            Ok(DeviceEvent::StateChanged(_, state)) => assert_eq!(state, DeviceState::Unplugged),
+            other => panic!("expected StateChanged, got {:?}", other),
+        }
+    }
 }
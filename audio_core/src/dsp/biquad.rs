@@ -0,0 +1,78 @@
+//! Second-order IIR sections (RBJ Audio EQ Cookbook).
+
+/// Butterworth Q, used for the crossover filters.
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// One biquad section in transposed direct form II.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    pub(crate) fn low_pass(freq: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::prewarp(freq, BUTTERWORTH_Q, sample_rate);
+        let b1 = 1.0 - cos;
+        Self::normalized(
+            [b1 / 2.0, b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub(crate) fn high_pass(freq: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::prewarp(freq, BUTTERWORTH_Q, sample_rate);
+        let b1 = -(1.0 + cos);
+        Self::normalized(
+            [-b1 / 2.0, b1, -b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Bell filter boosting or cutting `gain_db` around `freq`.
+    pub(crate) fn peaking(freq: f32, q: f32, gain_db: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::prewarp(freq, q, sample_rate);
+        let a = 10f32.powf(gain_db / 40.0);
+        Self::normalized(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    /// Section from raw coefficients; `a[0]` is normalized to 1.
+    pub(crate) fn from_coefficients(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self::normalized(b, a)
+    }
+
+    /// `(cos(w0), alpha)` for a center/cutoff frequency.
+    fn prewarp(freq: f32, q: f32, sample_rate: u32) -> (f32, f32) {
+        // 截止频率不能超过奈奎斯特频率，否则系数不稳定
+        let nyquist = sample_rate.max(1) as f32 / 2.0;
+        let w0 = std::f32::consts::TAU * freq.min(nyquist * 0.95) / sample_rate.max(1) as f32;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub(crate) fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
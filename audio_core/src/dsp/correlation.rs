@@ -0,0 +1,88 @@
+//! Inter-channel correlation of the front pair (mono compatibility).
+
+/// Time constant of the running averages.
+pub const CORRELATION_WINDOW_SECS: f32 = 0.3;
+
+/// Running correlation coefficient of the first two channels: +1 for mono,
+/// 0 for unrelated channels, −1 for content that cancels when summed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CorrelationMeter {
+    channels: usize,
+    /// 单极点平滑系数（每帧）。
+    decay: f32,
+    left_power: f32,
+    right_power: f32,
+    cross: f32,
+}
+
+impl CorrelationMeter {
+    /// Returns `None` when there is no stereo pair.
+    pub(crate) fn new(channels: usize, sample_rate: u32) -> Option<Self> {
+        if channels < 2 {
+            return None;
+        }
+        let frames = CORRELATION_WINDOW_SECS * sample_rate.max(1) as f32;
+        Some(Self {
+            channels,
+            decay: (-1.0 / frames).exp(),
+            left_power: 0.0,
+            right_power: 0.0,
+            cross: 0.0,
+        })
+    }
+
+    pub(crate) fn process(&mut self, samples: &[f32]) {
+        let (mut ll, mut rr, mut lr) = (self.left_power, self.right_power, self.cross);
+        for frame in samples.chunks_exact(self.channels) {
+            let (l, r) = (frame[0], frame[1]);
+            ll = self.decay * ll + (1.0 - self.decay) * l * l;
+            rr = self.decay * rr + (1.0 - self.decay) * r * r;
+            lr = self.decay * lr + (1.0 - self.decay) * l * r;
+        }
+        self.left_power = ll;
+        self.right_power = rr;
+        self.cross = lr;
+    }
+
+    /// The current coefficient, or `None` while the pair is silent.
+    pub(crate) fn correlation(&self) -> Option<f32> {
+        // 约 -100 dBFS 以下视为无信号
+        const SILENCE_POWER: f32 = 1e-10;
+        if self.left_power + self.right_power < SILENCE_POWER {
+            return None;
+        }
+        let norm = (self.left_power * self.right_power)
+            .sqrt()
+            .max(SILENCE_POWER);
+        Some((self.cross / norm).clamp(-1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(left: impl Fn(f32) -> f32, right: impl Fn(f32) -> f32) -> Option<f32> {
+        let mut meter = CorrelationMeter::new(2, 48_000).unwrap();
+        let samples: Vec<f32> = (0..48_000)
+            .flat_map(|i| {
+                let t = i as f32 / 48_000.0;
+                [left(t), right(t)]
+            })
+            .collect();
+        meter.process(&samples);
+        meter.correlation()
+    }
+
+    #[test]
+    fn mono_inverted_and_unrelated_content() {
+        let sine = |f: f32| move |t: f32| (std::f32::consts::TAU * f * t).sin();
+        let mono = measure(sine(440.0), sine(440.0)).unwrap();
+        assert!((mono - 1.0).abs() < 1e-3, "{mono}");
+        let inverted = measure(sine(440.0), |t| -sine(440.0)(t)).unwrap();
+        assert!((inverted + 1.0).abs() < 1e-3, "{inverted}");
+        let unrelated = measure(sine(440.0), sine(1_000.0)).unwrap();
+        assert!(unrelated.abs() < 0.1, "{unrelated}");
+        assert_eq!(measure(|_| 0.0, |_| 0.0), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Bauer stereophonic-to-binaural crossfeed for headphone outputs.
+
+pub use ::config::config::CrossfeedPreset;
+
+/// `(cutoff Hz, feed level dB)` of a preset, as in the bs2b library.
+pub fn crossfeed_params(preset: CrossfeedPreset) -> Option<(f32, f32)> {
+    match preset {
+        CrossfeedPreset::Off => None,
+        CrossfeedPreset::Default => Some((700.0, 4.5)),
+        CrossfeedPreset::ChuMoy => Some((700.0, 6.0)),
+        CrossfeedPreset::JanMeier => Some((650.0, 9.5)),
+    }
+}
+
+/// First-order filters of one channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ChannelState {
+    low: f32,
+    high: f32,
+    last_input: f32,
+}
+
+/// Feeds a low-passed copy of each front channel into the other one and
+/// shelves down the direct lows to match, like a speaker pair heard from
+/// the sweet spot. Only the first two channels are processed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Crossfeed {
+    channels: usize,
+    // 交叉通道：一阶低通
+    lo_a0: f32,
+    lo_b1: f32,
+    // 直达通道：一阶高架
+    hi_a0: f32,
+    hi_a1: f32,
+    hi_b1: f32,
+    left: ChannelState,
+    right: ChannelState,
+}
+
+impl Crossfeed {
+    /// Returns `None` when the preset is off or there is no stereo pair.
+    pub(crate) fn new(preset: CrossfeedPreset, channels: usize, sample_rate: u32) -> Option<Self> {
+        let (cutoff, feed_db) = crossfeed_params(preset)?;
+        if channels < 2 {
+            return None;
+        }
+        let sample_rate = sample_rate.max(1) as f32;
+        let gb_lo = feed_db * -5.0 / 6.0 - 3.0;
+        let gb_hi = feed_db / 6.0 - 3.0;
+        let g_lo = 10f32.powf(gb_lo / 20.0);
+        let g_hi = 1.0 - 10f32.powf(gb_hi / 20.0);
+        let cutoff_hi = cutoff * 2f32.powf((gb_lo - 20.0 * g_hi.log10()) / 12.0);
+
+        let x = (-std::f32::consts::TAU * cutoff / sample_rate).exp();
+        let (lo_a0, lo_b1) = (g_lo * (1.0 - x), x);
+        let x = (-std::f32::consts::TAU * cutoff_hi / sample_rate).exp();
+        Some(Self {
+            channels,
+            lo_a0,
+            lo_b1,
+            hi_a0: 1.0 - g_hi * (1.0 - x),
+            hi_a1: -x,
+            hi_b1: x,
+            left: ChannelState::default(),
+            right: ChannelState::default(),
+        })
+    }
+
+    fn step(&self, state: &mut ChannelState, input: f32) {
+        state.low = self.lo_a0 * input + self.lo_b1 * state.low;
+        state.high = self.hi_a0 * input + self.hi_a1 * state.last_input + self.hi_b1 * state.high;
+        state.last_input = input;
+    }
+
+    /// Crossfeeds the front pair of interleaved frames in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        let (mut left, mut right) = (self.left, self.right);
+        for frame in samples.chunks_exact_mut(self.channels) {
+            self.step(&mut left, frame[0]);
+            self.step(&mut right, frame[1]);
+            frame[0] = left.high + right.low;
+            frame[1] = right.high + left.low;
+        }
+        self.left = left;
+        self.right = right;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只有左声道有信号时，稳态下左右输出的峰值。
+    fn hard_left(freq: f32) -> (f32, f32) {
+        let mut crossfeed = Crossfeed::new(CrossfeedPreset::Default, 2, 48_000).unwrap();
+        let mut samples: Vec<f32> = (0..48_000)
+            .flat_map(|i| {
+                [
+                    (std::f32::consts::TAU * freq * i as f32 / 48_000.0).sin(),
+                    0.0,
+                ]
+            })
+            .collect();
+        crossfeed.process(&mut samples);
+        samples[48_000..]
+            .chunks_exact(2)
+            .fold((0.0, 0.0), |(l, r), f| {
+                (f32::max(l, f[0].abs()), f32::max(r, f[1].abs()))
+            })
+    }
+
+    #[test]
+    fn lows_cross_over_and_highs_stay_separated() {
+        let (left, right) = hard_left(100.0);
+        assert!(right > 0.4 && right < left, "{left} {right}");
+        let (left, right) = hard_left(10_000.0);
+        assert!(right < 0.05 && left > 0.9, "{left} {right}");
+    }
+
+    #[test]
+    fn off_or_mono_needs_no_processing() {
+        assert!(Crossfeed::new(CrossfeedPreset::Off, 2, 48_000).is_none());
+        assert!(Crossfeed::new(CrossfeedPreset::Default, 1, 48_000).is_none());
+    }
+}
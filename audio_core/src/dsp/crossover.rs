@@ -0,0 +1,58 @@
+//! Linkwitz-Riley crossover used for bass management.
+
+pub use ::config::config::{BassManagement, BassRole};
+
+use super::Biquad;
+
+/// 4th-order Linkwitz-Riley low-pass: two cascaded Butterworth sections.
+///
+/// Its sum with [`linkwitz_riley_high`] at the same frequency is flat in
+/// magnitude, so a subwoofer and its satellites add back up to the original.
+pub(crate) fn linkwitz_riley_low(freq: f32, sample_rate: u32) -> [Biquad; 2] {
+    [
+        Biquad::low_pass(freq, sample_rate),
+        Biquad::low_pass(freq, sample_rate),
+    ]
+}
+
+/// 4th-order Linkwitz-Riley high-pass, the complement of [`linkwitz_riley_low`].
+pub(crate) fn linkwitz_riley_high(freq: f32, sample_rate: u32) -> [Biquad; 2] {
+    [
+        Biquad::high_pass(freq, sample_rate),
+        Biquad::high_pass(freq, sample_rate),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 稳态正弦经过低通、高通两路后各自与求和的幅度。
+    fn split(freq: f32) -> (f32, f32, f32) {
+        let sample_rate = 48_000;
+        let mut low = linkwitz_riley_low(80.0, sample_rate);
+        let mut high = linkwitz_riley_high(80.0, sample_rate);
+        let (mut peak_low, mut peak_high, mut peak_sum) = (0.0_f32, 0.0_f32, 0.0_f32);
+        for i in 0..96_000 {
+            let x = (std::f32::consts::TAU * freq * i as f32 / sample_rate as f32).sin();
+            let l = low.iter_mut().fold(x, |s, f| f.process(s));
+            let h = high.iter_mut().fold(x, |s, f| f.process(s));
+            if i >= 48_000 {
+                peak_low = peak_low.max(l.abs());
+                peak_high = peak_high.max(h.abs());
+                peak_sum = peak_sum.max((l + h).abs());
+            }
+        }
+        (peak_low, peak_high, peak_sum)
+    }
+
+    #[test]
+    fn bands_split_and_sum_flat() {
+        let (low, high, _) = split(30.0);
+        assert!(low > 0.95 && high < 0.1);
+        let (low, high, _) = split(1_000.0);
+        assert!(low < 0.01 && high > 0.99);
+        let (_, _, sum) = split(80.0);
+        assert!((sum - 1.0).abs() < 0.02, "sum {sum}");
+    }
+}
@@ -0,0 +1,58 @@
+//! Fixed per-output delay, used to time-align outputs whose speakers sit at
+//! different distances or whose devices have different latency.
+
+/// Delays interleaved frames by a fixed number of frames.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DelayLine {
+    channels: usize,
+    /// 环形缓冲区：`frames` 帧交错样本。
+    buffer: Vec<f32>,
+    pos: usize,
+}
+
+impl DelayLine {
+    /// Returns `None` when the delay rounds to zero frames.
+    pub(crate) fn new(delay_ms: f32, channels: usize, sample_rate: u32) -> Option<Self> {
+        let frames = (delay_ms.max(0.0) / 1000.0 * sample_rate as f32).round() as usize;
+        if frames == 0 || channels == 0 {
+            return None;
+        }
+        Some(Self {
+            channels,
+            buffer: vec![0.0; frames * channels],
+            pos: 0,
+        })
+    }
+
+    /// Delays interleaved frames in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        let frames = self.buffer.len() / self.channels;
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let slot = &mut self.buffer[self.pos * self.channels..][..self.channels];
+            for (sample, delayed) in frame.iter_mut().zip(slot) {
+                std::mem::swap(sample, delayed);
+            }
+            self.pos = (self.pos + 1) % frames;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_come_out_late_by_the_delay() {
+        assert!(DelayLine::new(0.0, 2, 48_000).is_none());
+
+        // 1 ms at 3 kHz = 3 frames
+        let mut delay = DelayLine::new(1.0, 2, 3_000).unwrap();
+        let mut first: Vec<f32> = (1..=4).flat_map(|f| [f as f32, -f as f32]).collect();
+        delay.process(&mut first);
+        assert_eq!(first, [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, -1.0]);
+
+        let mut second = vec![5.0, -5.0, 6.0, -6.0];
+        delay.process(&mut second);
+        assert_eq!(second, [2.0, -2.0, 3.0, -3.0]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Dither for float → 16-bit conversion.
+
+pub use ::config::config::DitherMode;
+
+/// Requantizes interleaved f32 frames to 16-bit with TPDF dither, optionally
+/// pushing the quantization noise towards high frequencies (first-order
+/// error feedback) where it is less audible.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Dither {
+    shaped: bool,
+    channels: usize,
+    /// xorshift32 状态：实时循环中不能用需要加锁或分配的随机数源。
+    rng: u32,
+    /// 每声道上一帧的量化误差（噪声整形用）。
+    error: Vec<f32>,
+}
+
+impl Dither {
+    /// Returns `None` for [`DitherMode::Off`] (plain truncation).
+    pub(crate) fn new(mode: DitherMode, channels: usize) -> Option<Self> {
+        let shaped = match mode {
+            DitherMode::Off => return None,
+            DitherMode::Tpdf => false,
+            DitherMode::NoiseShaped => true,
+        };
+        Some(Self {
+            shaped,
+            channels: channels.max(1),
+            rng: 0x9E37_79B9,
+            error: vec![0.0; channels.max(1)],
+        })
+    }
+
+    /// Uniform in [0, 1).
+    fn uniform(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub(crate) fn quantize_i16(&mut self, samples: &[f32], output: &mut [i16]) {
+        for (i, (dst, &src)) in output.iter_mut().zip(samples).enumerate() {
+            let channel = i % self.channels;
+            let mut x = src.clamp(-1.0, 1.0) * 32767.0;
+            if self.shaped {
+                x -= self.error[channel];
+            }
+            // 两个均匀分布之差：±1 LSB 的三角分布
+            let tpdf = self.uniform() - self.uniform();
+            let quantized = (x + tpdf).round().clamp(-32768.0, 32767.0);
+            if self.shaped {
+                self.error[channel] = quantized - x;
+            }
+            *dst = quantized as i16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 0.25 LSB 的直流截断后为 0，加抖动后平均值应保留下来。
+    fn dc_mean(mode: DitherMode) -> f32 {
+        let mut dither = Dither::new(mode, 2).unwrap();
+        let samples = vec![0.25 / 32767.0; 100_000];
+        let mut output = vec![0i16; samples.len()];
+        dither.quantize_i16(&samples, &mut output);
+        output.iter().map(|&s| s as f32).sum::<f32>() / output.len() as f32
+    }
+
+    #[test]
+    fn dither_preserves_sub_lsb_level() {
+        assert!((dc_mean(DitherMode::Tpdf) - 0.25).abs() < 0.02);
+        assert!((dc_mean(DitherMode::NoiseShaped) - 0.25).abs() < 0.02);
+        assert!(Dither::new(DitherMode::Off, 2).is_none());
+    }
+
+    #[test]
+    fn dither_stays_within_range() {
+        let mut dither = Dither::new(DitherMode::NoiseShaped, 1).unwrap();
+        let mut output = [0i16; 4];
+        dither.quantize_i16(&[1.5, -1.5, 1.0, -1.0], &mut output);
+        assert!(output[0] >= 32766 && output[1] <= -32766);
+    }
+}
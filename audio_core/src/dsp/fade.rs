@@ -0,0 +1,83 @@
+//! Fade-in at the start of a routing session and fade-out before stopping.
+
+/// Linear gain ramp between two gains over a fixed number of frames; the
+/// target gain holds once the ramp ends.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Fade {
+    frames: u32,
+    position: u32,
+    from: f32,
+    to: f32,
+}
+
+impl Fade {
+    /// Ramp from silence to unity. Returns `None` for a zero duration.
+    pub(crate) fn fade_in(duration_ms: f32, sample_rate: u32) -> Option<Self> {
+        let frames = duration_frames(duration_ms, sample_rate);
+        (frames > 0).then_some(Self {
+            frames,
+            position: 0,
+            from: 0.0,
+            to: 1.0,
+        })
+    }
+
+    /// Ramp from `from` to silence; a zero duration silences at once.
+    pub(crate) fn fade_out(from: f32, duration_ms: f32, sample_rate: u32) -> Self {
+        Self {
+            frames: duration_frames(duration_ms, sample_rate).max(1),
+            position: 0,
+            from,
+            to: 0.0,
+        }
+    }
+
+    /// The fade still changes the signal: the ramp is running or has
+    /// ended below unity gain.
+    pub(crate) fn is_active(&self) -> bool {
+        self.position < self.frames || self.to < 1.0
+    }
+
+    /// Gain of the last frame, or the starting gain before the first.
+    pub(crate) fn gain(&self) -> f32 {
+        self.from + (self.to - self.from) * self.position as f32 / self.frames as f32
+    }
+
+    /// Gain of the next frame; advances the ramp.
+    pub(crate) fn next_gain(&mut self) -> f32 {
+        if self.position < self.frames {
+            self.position += 1;
+        }
+        self.gain()
+    }
+}
+
+fn duration_frames(duration_ms: f32, sample_rate: u32) -> u32 {
+    (duration_ms / 1000.0 * sample_rate as f32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramps_to_unity_then_stays() {
+        // 1 ms @ 4 kHz = 4 帧
+        let mut fade = Fade::fade_in(1.0, 4_000).unwrap();
+        let gains: Vec<f32> = (0..6).map(|_| fade.next_gain()).collect();
+        assert_eq!(gains, [0.25, 0.5, 0.75, 1.0, 1.0, 1.0]);
+        assert!(!fade.is_active());
+        assert!(Fade::fade_in(0.0, 48_000).is_none());
+    }
+
+    #[test]
+    fn fades_out_from_the_current_gain_and_stays_silent() {
+        let mut fade = Fade::fade_out(0.5, 1.0, 4_000);
+        let gains: Vec<f32> = (0..6).map(|_| fade.next_gain()).collect();
+        assert_eq!(gains, [0.375, 0.25, 0.125, 0.0, 0.0, 0.0]);
+        assert!(fade.is_active());
+
+        let mut instant = Fade::fade_out(1.0, 0.0, 48_000);
+        assert_eq!(instant.next_gain(), 0.0);
+    }
+}
@@ -0,0 +1,98 @@
+//! Radix-2 FFT for spectrum analysis.
+
+/// In-place complex FFT of a fixed power-of-two size.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Fft {
+    /// `e^(−2πik/N)`，k < N/2。
+    twiddles: Vec<(f32, f32)>,
+    /// 位反转置换的下标。
+    bit_reversed: Vec<usize>,
+}
+
+impl Fft {
+    /// `size` must be a power of two.
+    pub(crate) fn new(size: usize) -> Self {
+        assert!(
+            size.is_power_of_two(),
+            "FFT size {size} is not a power of two"
+        );
+        let bits = size.trailing_zeros();
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let angle = -std::f64::consts::TAU * k as f64 / size as f64;
+                (angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        let bit_reversed = (0..size)
+            .map(|i| {
+                if bits == 0 {
+                    0
+                } else {
+                    i.reverse_bits() >> (usize::BITS - bits)
+                }
+            })
+            .collect();
+        Self {
+            twiddles,
+            bit_reversed,
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.bit_reversed.len()
+    }
+
+    /// Transforms `re`/`im` (each [`Self::size`] long) in place.
+    pub(crate) fn process(&self, re: &mut [f32], im: &mut [f32]) {
+        let size = self.size();
+        for i in 0..size {
+            let j = self.bit_reversed[i];
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+        let mut half = 1;
+        while half < size {
+            let stride = size / (half * 2);
+            for start in (0..size).step_by(half * 2) {
+                for k in 0..half {
+                    let (wr, wi) = self.twiddles[k * stride];
+                    let (a, b) = (start + k, start + k + half);
+                    let tr = re[b] * wr - im[b] * wi;
+                    let ti = re[b] * wi + im[b] * wr;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            half *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_lands_in_its_bin() {
+        let fft = Fft::new(64);
+        let mut re: Vec<f32> = (0..64)
+            .map(|n| (std::f32::consts::TAU * 5.0 * n as f32 / 64.0).cos())
+            .collect();
+        let mut im = vec![0.0; 64];
+        fft.process(&mut re, &mut im);
+
+        let magnitude = |k: usize| (re[k] * re[k] + im[k] * im[k]).sqrt();
+        // 实余弦的能量平分到 k 与 N−k 两个频点
+        assert!((magnitude(5) - 32.0).abs() < 1e-3);
+        assert!((magnitude(59) - 32.0).abs() < 1e-3);
+        assert!(
+            (0..64)
+                .filter(|&k| k != 5 && k != 59)
+                .all(|k| magnitude(k) < 1e-3)
+        );
+    }
+}
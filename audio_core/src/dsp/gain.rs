@@ -0,0 +1,118 @@
+//! Output gain with click-free changes.
+
+use ::config::config::Output;
+
+/// How long a gain change takes to reach its new value.
+pub const GAIN_RAMP_SECS: f32 = 0.02;
+
+/// Range offered for output gain; lower values are effectively mute.
+pub const MIN_GAIN_DB: f32 = *Output::GAIN_RANGE_DB.start();
+pub const MAX_GAIN_DB: f32 = *Output::GAIN_RANGE_DB.end();
+
+/// Converts a gain in dB to a linear amplitude factor.
+pub fn db_to_linear(gain_db: f32) -> f32 {
+    10.0_f32.powf(gain_db / 20.0)
+}
+
+/// Converts a Windows volume slider position (0.0–1.0) to the gain that
+/// scales the signal by the same factor; muted or 0 is `-inf` dB (silence).
+pub fn volume_scalar_to_db(scalar: f32, muted: bool) -> f32 {
+    if muted || scalar <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    20.0 * scalar.min(1.0).log10()
+}
+
+/// Gain stage that ramps linearly to a new target instead of jumping,
+/// which would be audible as zipper noise while a slider is dragged.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SmoothedGain {
+    channels: usize,
+    ramp_frames: u32,
+    target_db: f32,
+    current: f32,
+    target: f32,
+    /// 每帧的增量，剩余帧数为 0 时不再变化。
+    step: f32,
+    remaining: u32,
+}
+
+impl SmoothedGain {
+    /// Starts at `gain_db` without ramping.
+    pub(crate) fn new(gain_db: f32, channels: usize, sample_rate: u32) -> Self {
+        let gain = db_to_linear(gain_db);
+        Self {
+            channels,
+            ramp_frames: ((sample_rate as f32 * GAIN_RAMP_SECS) as u32).max(1),
+            target_db: gain_db,
+            current: gain,
+            target: gain,
+            step: 0.0,
+            remaining: 0,
+        }
+    }
+
+    /// Ramps towards `gain_db`; a no-op if that is already the target.
+    pub(crate) fn set_target_db(&mut self, gain_db: f32) {
+        if gain_db == self.target_db {
+            return;
+        }
+        self.target_db = gain_db;
+        self.target = db_to_linear(gain_db);
+        self.step = (self.target - self.current) / self.ramp_frames as f32;
+        self.remaining = self.ramp_frames;
+    }
+
+    /// Applies the gain to interleaved frames in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        if self.channels == 0 || (self.remaining == 0 && self.current == 1.0) {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(self.channels) {
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                self.current = if self.remaining == 0 {
+                    self.target
+                } else {
+                    self.current + self.step
+                };
+            }
+            for sample in frame {
+                *sample *= self.current;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_conversion() {
+        assert_eq!(db_to_linear(0.0), 1.0);
+        assert!((db_to_linear(-6.0) - 0.501).abs() < 0.001);
+        assert!((db_to_linear(20.0) - 10.0).abs() < 1e-4);
+        assert_eq!(volume_scalar_to_db(1.0, false), 0.0);
+        assert!((volume_scalar_to_db(0.5, false) + 6.02).abs() < 0.01);
+        assert_eq!(db_to_linear(volume_scalar_to_db(0.5, true)), 0.0);
+        assert_eq!(db_to_linear(volume_scalar_to_db(0.0, false)), 0.0);
+    }
+
+    #[test]
+    fn gain_change_ramps_without_jumps() {
+        let mut gain = SmoothedGain::new(0.0, 2, 48_000);
+        gain.set_target_db(-20.0);
+        // 两个 10ms packet：第一个处于渐变中，第二个结束时到达目标
+        let mut first = vec![1.0_f32; 480 * 2];
+        gain.process(&mut first);
+        let steps: Vec<f32> = first.windows(2).map(|w| (w[0] - w[1]).abs()).collect();
+        assert!(steps.iter().all(|&d| d < 0.01));
+        assert!(first[958] > 0.4 && first[958] < 0.6);
+
+        let mut second = vec![1.0_f32; 480 * 2];
+        gain.process(&mut second);
+        assert!((second[959] - 0.1).abs() < 1e-6);
+        assert_eq!(second[0], second[1]);
+    }
+}
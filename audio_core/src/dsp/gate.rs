@@ -0,0 +1,102 @@
+//! Noise gate for the captured signal.
+
+pub use ::config::config::NoiseGateSettings;
+
+use super::db_to_linear;
+
+/// How fast the gate opens once the signal crosses the threshold.
+const GATE_ATTACK_SECS: f32 = 0.001;
+
+/// Mutes interleaved frames while the signal stays below a threshold.
+///
+/// After the last frame above the threshold the gate stays open for the
+/// hold time, then fades out over the release time.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NoiseGate {
+    channels: usize,
+    threshold: f32,
+    hold_frames: u32,
+    /// 剩余的保持帧数，为 0 后开始释放。
+    hold_remaining: u32,
+    attack_step: f32,
+    release_step: f32,
+    gain: f32,
+}
+
+impl NoiseGate {
+    /// Returns `None` for disabled settings, so callers can skip the stage.
+    pub(crate) fn new(
+        settings: &NoiseGateSettings,
+        channels: usize,
+        sample_rate: u32,
+    ) -> Option<Self> {
+        if !settings.enabled || channels == 0 {
+            return None;
+        }
+        let frames = |secs: f32| (secs * sample_rate as f32).max(1.0);
+        Some(Self {
+            channels,
+            threshold: db_to_linear(settings.threshold_db),
+            hold_frames: frames(settings.hold_ms / 1000.0) as u32,
+            hold_remaining: 0,
+            attack_step: 1.0 / frames(GATE_ATTACK_SECS),
+            release_step: 1.0 / frames(settings.release_ms / 1000.0),
+            // 初始关闭：启动时的底噪不会先漏出来
+            gain: 0.0,
+        })
+    }
+
+    /// Gates interleaved frames in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+            if peak >= self.threshold {
+                self.hold_remaining = self.hold_frames;
+                self.gain = (self.gain + self.attack_step).min(1.0);
+            } else if self.hold_remaining > 0 {
+                self.hold_remaining -= 1;
+                self.gain = (self.gain + self.attack_step).min(1.0);
+            } else {
+                self.gain = (self.gain - self.release_step).max(0.0);
+            }
+            if self.gain < 1.0 {
+                for sample in frame.iter_mut() {
+                    *sample *= self.gain;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_passes_signal_holds_then_releases() {
+        let settings = NoiseGateSettings {
+            enabled: true,
+            threshold_db: -40.0,
+            hold_ms: 10.0,
+            release_ms: 10.0,
+        };
+        let mut gate = NoiseGate::new(&settings, 1, 48_000).unwrap();
+
+        // 低于阈值的底噪被静音
+        let mut noise = vec![0.001_f32; 480];
+        gate.process(&mut noise);
+        assert!(noise.iter().all(|&s| s == 0.0));
+
+        // 信号通过（1 ms 淡入后不变）
+        let mut signal = vec![0.5_f32; 480];
+        gate.process(&mut signal);
+        assert_eq!(signal[479], 0.5);
+
+        // 保持 10 ms，再用 10 ms 淡出
+        let mut tail = vec![0.001_f32; 1_440];
+        gate.process(&mut tail);
+        assert_eq!(tail[479], 0.001);
+        assert!(tail[720] < 0.001 && tail[720] > 0.0);
+        assert_eq!(tail[1_439], 0.0);
+    }
+}
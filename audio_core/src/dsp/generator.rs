@@ -0,0 +1,394 @@
+//! Test signal generators.
+
+use super::db_to_linear;
+
+/// Length of each channel's tone burst in the identification sequence.
+pub const IDENTIFY_BURST_SECS: f32 = 0.6;
+/// Silence after each burst, so consecutive channels are easy to tell apart.
+pub const IDENTIFY_GAP_SECS: f32 = 0.4;
+/// Level of the identification bursts in dBFS.
+pub const IDENTIFY_LEVEL_DB: f32 = -12.0;
+
+const IDENTIFY_TONE_HZ: f32 = 1_000.0;
+/// LFE 声道通常在 120 Hz 以上被滤除，改用低频音。
+const IDENTIFY_LFE_TONE_HZ: f32 = 60.0;
+/// 起止的升余弦斜坡，避免咔嗒声。
+const EDGE_RAMP_SECS: f32 = 0.01;
+
+/// Raised-cosine gain `edge` frames from the nearer end of a burst.
+fn edge_envelope(edge: usize, ramp_frames: usize) -> f32 {
+    if edge < ramp_frames {
+        0.5 - 0.5 * (std::f32::consts::PI * edge as f32 / ramp_frames as f32).cos()
+    } else {
+        1.0
+    }
+}
+
+/// A tone burst on each channel in turn, then silence.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ChannelIdentification {
+    channels: usize,
+    sample_rate: f32,
+    /// LFE 声道的下标（播放低频音）。
+    lfe: Option<usize>,
+    burst_frames: usize,
+    slot_frames: usize,
+    ramp_frames: usize,
+    amplitude: f32,
+    /// 下一个要生成的帧。
+    position: usize,
+}
+
+impl ChannelIdentification {
+    pub(crate) fn new(channels: usize, sample_rate: u32, lfe: Option<usize>) -> Self {
+        let rate = sample_rate.max(1) as f32;
+        let burst_frames = (IDENTIFY_BURST_SECS * rate) as usize;
+        Self {
+            channels: channels.max(1),
+            sample_rate: rate,
+            lfe,
+            burst_frames,
+            slot_frames: burst_frames + (IDENTIFY_GAP_SECS * rate) as usize,
+            ramp_frames: ((EDGE_RAMP_SECS * rate) as usize).max(1),
+            amplitude: db_to_linear(IDENTIFY_LEVEL_DB),
+            position: 0,
+        }
+    }
+
+    /// Total length of the sequence in frames.
+    pub(crate) fn total_frames(&self) -> usize {
+        self.slot_frames * self.channels
+    }
+
+    /// Fills interleaved `output` with the next frames; returns `false` once
+    /// the whole sequence has been generated (the rest is silence).
+    pub(crate) fn fill(&mut self, output: &mut [f32]) -> bool {
+        output.fill(0.0);
+        for frame in output.chunks_exact_mut(self.channels) {
+            if self.position >= self.total_frames() {
+                break;
+            }
+            let channel = self.position / self.slot_frames;
+            let offset = self.position % self.slot_frames;
+            if offset < self.burst_frames {
+                let freq = if self.lfe == Some(channel) {
+                    IDENTIFY_LFE_TONE_HZ
+                } else {
+                    IDENTIFY_TONE_HZ
+                };
+                let edge = offset.min(self.burst_frames - 1 - offset);
+                let envelope = edge_envelope(edge, self.ramp_frames);
+                let phase = std::f32::consts::TAU * freq * offset as f32 / self.sample_rate;
+                frame[channel] = self.amplitude * envelope * phase.sin();
+            }
+            self.position += 1;
+        }
+        self.position < self.total_frames()
+    }
+}
+
+/// Frequency of the device test tone.
+pub const TEST_TONE_HZ: f32 = 440.0;
+/// Length of the device test tone.
+pub const TEST_TONE_SECS: f32 = 1.0;
+/// Peak level of the device test tone in dBFS.
+pub const TEST_TONE_LEVEL_DB: f32 = -18.0;
+
+/// A short sine tone on every channel, then silence, to check which
+/// physical device an endpoint is.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TestTone {
+    channels: usize,
+    sample_rate: f32,
+    total_frames: usize,
+    ramp_frames: usize,
+    amplitude: f32,
+    position: usize,
+}
+
+impl TestTone {
+    pub(crate) fn new(channels: usize, sample_rate: u32) -> Self {
+        let rate = sample_rate.max(1) as f32;
+        Self {
+            channels: channels.max(1),
+            sample_rate: rate,
+            total_frames: (TEST_TONE_SECS * rate) as usize,
+            ramp_frames: ((EDGE_RAMP_SECS * rate) as usize).max(1),
+            amplitude: db_to_linear(TEST_TONE_LEVEL_DB),
+            position: 0,
+        }
+    }
+
+    /// Fills interleaved `output` with the next frames; returns `false` once
+    /// the whole tone has been generated (the rest is silence).
+    pub(crate) fn fill(&mut self, output: &mut [f32]) -> bool {
+        output.fill(0.0);
+        for frame in output.chunks_exact_mut(self.channels) {
+            if self.position >= self.total_frames {
+                break;
+            }
+            let edge = self.position.min(self.total_frames - 1 - self.position);
+            let phase =
+                std::f32::consts::TAU * TEST_TONE_HZ * self.position as f32 / self.sample_rate;
+            frame.fill(self.amplitude * edge_envelope(edge, self.ramp_frames) * phase.sin());
+            self.position += 1;
+        }
+        self.position < self.total_frames
+    }
+}
+
+/// Levels offered for the pink-noise source, in dBFS RMS.
+pub const PINK_NOISE_LEVEL_RANGE_DB: std::ops::RangeInclusive<f32> = -60.0..=-10.0;
+
+/// RMS of the unscaled Kellett filter output for uniform white input.
+const PINK_FILTER_RMS: f32 = 1.745;
+
+/// Pink (−3 dB/octave) noise at a calibrated RMS level, the same signal on
+/// every channel: Paul Kellett's refined filter over xorshift white noise.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PinkNoise {
+    channels: usize,
+    /// 线性幅度：RMS 归一化后乘以目标电平。
+    scale: f32,
+    /// xorshift32 状态，实时循环中不能使用带锁的随机数源。
+    rng: u32,
+    state: [f32; 7],
+}
+
+impl PinkNoise {
+    pub(crate) fn new(channels: usize, level_db: f32) -> Self {
+        Self {
+            channels: channels.max(1),
+            scale: db_to_linear(level_db) / PINK_FILTER_RMS,
+            rng: 0x2545_F491,
+            state: [0.0; 7],
+        }
+    }
+
+    /// Changes the RMS level; the filter keeps running, so there is no click.
+    pub(crate) fn set_level_db(&mut self, level_db: f32) {
+        self.scale = db_to_linear(level_db) / PINK_FILTER_RMS;
+    }
+
+    /// Uniform in [-1, 1).
+    fn white(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+    }
+
+    fn next(&mut self) -> f32 {
+        let white = self.white();
+        let b = &mut self.state;
+        b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+        b[2] = 0.969 * b[2] + white * 0.153_852;
+        b[3] = 0.8665 * b[3] + white * 0.310_485_6;
+        b[4] = 0.55 * b[4] + white * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - white * 0.016_898;
+        let pink = b[..6].iter().sum::<f32>() + b[6] + white * 0.5362;
+        b[6] = white * 0.115_926;
+        pink * self.scale
+    }
+
+    /// Overwrites interleaved `output` with noise.
+    pub(crate) fn fill(&mut self, output: &mut [f32]) {
+        for frame in output.chunks_exact_mut(self.channels) {
+            frame.fill(self.next());
+        }
+    }
+}
+
+/// Parameters of a logarithmic (exponential) sine sweep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepSettings {
+    pub start_hz: f32,
+    pub end_hz: f32,
+    pub duration_secs: f32,
+    /// Peak level in dBFS.
+    pub level_db: f32,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        Self {
+            start_hz: 20.0,
+            end_hz: 20_000.0,
+            duration_secs: 5.0,
+            level_db: -12.0,
+        }
+    }
+}
+
+/// A log sine sweep (equal time per octave), the same signal on every
+/// channel, then silence. Its pink spectrum keeps the energy per octave
+/// constant, and harmonic distortion separates from the linear response when
+/// the recording is deconvolved with the sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LogSweep {
+    channels: usize,
+    total_frames: usize,
+    ramp_frames: usize,
+    amplitude: f32,
+    /// 相位公式 `k·(e^(t/L) − 1)` 的系数（以帧为时间单位），
+    /// 用 f64 计算，避免长扫频末端的相位误差。
+    phase_scale: f64,
+    frames_per_e: f64,
+    position: usize,
+}
+
+impl LogSweep {
+    pub(crate) fn new(settings: &SweepSettings, channels: usize, sample_rate: u32) -> Self {
+        let rate = sample_rate.max(1) as f64;
+        let total_frames = (settings.duration_secs as f64 * rate) as usize;
+        let octaves_e = (settings.end_hz as f64 / settings.start_hz as f64).ln();
+        let frames_per_e = total_frames.max(1) as f64 / octaves_e;
+        Self {
+            channels: channels.max(1),
+            total_frames,
+            ramp_frames: ((EDGE_RAMP_SECS * sample_rate as f32) as usize).max(1),
+            amplitude: db_to_linear(settings.level_db),
+            phase_scale: std::f64::consts::TAU * settings.start_hz as f64 / rate * frames_per_e,
+            frames_per_e,
+            position: 0,
+        }
+    }
+
+    /// Length of the sweep in frames.
+    pub(crate) fn total_frames(&self) -> usize {
+        self.total_frames
+    }
+
+    /// Fills interleaved `output` with the next frames; returns `false` once
+    /// the whole sweep has been generated (the rest is silence).
+    pub(crate) fn fill(&mut self, output: &mut [f32]) -> bool {
+        output.fill(0.0);
+        for frame in output.chunks_exact_mut(self.channels) {
+            if self.position >= self.total_frames {
+                break;
+            }
+            let t = self.position as f64;
+            let phase = self.phase_scale * ((t / self.frames_per_e).exp() - 1.0);
+            let edge = self.position.min(self.total_frames - 1 - self.position);
+            let sample =
+                self.amplitude * edge_envelope(edge, self.ramp_frames) * (phase.sin() as f32);
+            frame.fill(sample);
+            self.position += 1;
+        }
+        self.position < self.total_frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::Biquad;
+
+    #[test]
+    fn identification_plays_each_channel_in_turn() {
+        let mut sequence = ChannelIdentification::new(3, 8_000, Some(2));
+        let mut output = vec![0.0; sequence.total_frames() * 3];
+        assert!(!sequence.fill(&mut output));
+
+        let peak = |channel: usize, slot: usize| {
+            output
+                .chunks_exact(3)
+                .skip(slot * 8_000)
+                .take(8_000)
+                .fold(0.0_f32, |m, f| m.max(f[channel].abs()))
+        };
+        for slot in 0..3 {
+            for channel in 0..3 {
+                let level = peak(channel, slot);
+                if channel == slot {
+                    assert!(level > 0.2 && level <= 0.26, "{channel} {level}");
+                } else {
+                    assert_eq!(level, 0.0, "{channel} in slot {slot}");
+                }
+            }
+        }
+
+        // 结束后只输出静音
+        let mut tail = vec![1.0; 6];
+        assert!(!sequence.fill(&mut tail));
+        assert_eq!(tail, [0.0; 6]);
+    }
+
+    #[test]
+    fn test_tone_plays_on_every_channel_then_stops() {
+        let mut tone = TestTone::new(2, 8_000);
+        let mut output = vec![0.0; 2 * 8_000];
+        assert!(!tone.fill(&mut output));
+        assert!(output.chunks_exact(2).all(|f| f[0] == f[1]));
+        let peak = output.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        let expected = db_to_linear(TEST_TONE_LEVEL_DB);
+        assert!((peak - expected).abs() < 0.01, "{peak}");
+        // 起止有斜坡，不会从满幅开始
+        assert_eq!(output[0], 0.0);
+        assert!(output[output.len() - 2].abs() < 0.01);
+
+        let mut tail = vec![1.0; 4];
+        assert!(!tone.fill(&mut tail));
+        assert_eq!(tail, [0.0; 4]);
+    }
+
+    #[test]
+    fn pink_noise_is_calibrated_with_equal_energy_per_octave() {
+        let mut noise = PinkNoise::new(2, -20.0);
+        let mut output = vec![0.0; 2 * 480_000];
+        noise.fill(&mut output);
+        assert!(output.chunks_exact(2).all(|f| f[0] == f[1]));
+
+        let rms = (output.iter().map(|s| s * s).sum::<f32>() / output.len() as f32).sqrt();
+        let level_db = 20.0 * rms.log10();
+        assert!((level_db + 20.0).abs() < 0.3, "{level_db}");
+
+        // 恒定 Q 带通：粉红噪声各倍频程能量相等（白噪声相隔两个倍频程差 6 dB）
+        let band_power = |freq: f32| {
+            let w0 = std::f32::consts::TAU * freq / 48_000.0;
+            let alpha = w0.sin() / (2.0 * 4.0);
+            let mut band = Biquad::from_coefficients(
+                [alpha, 0.0, -alpha],
+                [1.0 + alpha, -2.0 * w0.cos(), 1.0 - alpha],
+            );
+            output
+                .iter()
+                .step_by(2)
+                .map(|&s| band.process(s).powi(2))
+                .sum::<f32>()
+        };
+        let ratio_db = 10.0 * (band_power(500.0) / band_power(2_000.0)).log10();
+        assert!(ratio_db.abs() < 1.0, "{ratio_db}");
+    }
+
+    #[test]
+    fn sweep_rises_from_start_to_end_frequency() {
+        let settings = SweepSettings {
+            start_hz: 100.0,
+            end_hz: 10_000.0,
+            duration_secs: 1.0,
+            level_db: -6.0,
+        };
+        let mut sweep = LogSweep::new(&settings, 1, 48_000);
+        let mut output = vec![0.0; sweep.total_frames() + 100];
+        assert!(!sweep.fill(&mut output));
+        assert!(output[sweep.total_frames()..].iter().all(|&s| s == 0.0));
+        let peak = output.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.501).abs() < 0.01, "{peak}");
+
+        // 过零次数估计局部频率：开头约 100 Hz，中点（对数中值）约 1 kHz
+        let frequency_at = |start: usize| {
+            let window = &output[start..start + 2_400];
+            let crossings = window
+                .windows(2)
+                .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+                .count();
+            crossings as f32 / 2.0 / 0.05
+        };
+        let low = frequency_at(480);
+        assert!((90.0..=130.0).contains(&low), "{low}");
+        let mid = frequency_at(24_000 - 1_200);
+        assert!((900.0..=1_100.0).contains(&mid), "{mid}");
+    }
+}
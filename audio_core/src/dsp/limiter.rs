@@ -0,0 +1,120 @@
+//! Look-ahead brickwall limiter.
+
+pub use ::config::config::LimiterSettings;
+
+/// How far ahead the limiter looks; also the latency it adds to the output.
+pub const LIMITER_LOOKAHEAD_SECS: f32 = 0.0015;
+
+/// Keeps interleaved frames within ±threshold.
+///
+/// Frames are delayed by the look-ahead so the gain is already down when a
+/// peak leaves the limiter; whatever the attack smoothing leaves above the
+/// threshold is clamped, so the output never exceeds it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Limiter {
+    channels: usize,
+    threshold: f32,
+    attack: f32,
+    release: f32,
+    /// 延迟线：`lookahead` 帧交错样本。
+    delay: Vec<f32>,
+    /// 延迟线中每帧所需的增益。
+    required: Vec<f32>,
+    pos: usize,
+    envelope: f32,
+}
+
+impl Limiter {
+    /// Returns `None` for disabled settings, so callers can skip the stage.
+    pub(crate) fn new(
+        settings: &LimiterSettings,
+        channels: usize,
+        sample_rate: u32,
+    ) -> Option<Self> {
+        if !settings.enabled || channels == 0 {
+            return None;
+        }
+        let lookahead = ((sample_rate as f32 * LIMITER_LOOKAHEAD_SECS) as usize).max(1);
+        let release_frames = (settings.release_ms / 1000.0 * sample_rate as f32).max(1.0);
+        Some(Self {
+            channels,
+            threshold: 10.0_f32.powf(settings.threshold_db / 20.0),
+            // 单极点平滑在 look-ahead 时间内走完约 99%，剩余部分由最终的钳位处理
+            attack: 1.0 - (-5.0 / lookahead as f32).exp(),
+            release: 1.0 - (-1.0 / release_frames).exp(),
+            delay: vec![0.0; lookahead * channels],
+            required: vec![1.0; lookahead],
+            pos: 0,
+            envelope: 1.0,
+        })
+    }
+
+    /// Limits interleaved frames in place (output is delayed by the look-ahead).
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+            self.required[self.pos] = if peak > self.threshold {
+                self.threshold / peak
+            } else {
+                1.0
+            };
+            let target = self.required.iter().fold(1.0_f32, |m, &g| m.min(g));
+            let coef = if target < self.envelope {
+                self.attack
+            } else {
+                self.release
+            };
+            self.envelope += (target - self.envelope) * coef;
+
+            let delayed = &mut self.delay[self.pos * self.channels..][..self.channels];
+            for (sample, slot) in frame.iter_mut().zip(delayed) {
+                let out = (*slot * self.envelope).clamp(-self.threshold, self.threshold);
+                *slot = *sample;
+                *sample = out;
+            }
+            self.pos = (self.pos + 1) % self.required.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(threshold_db: f32) -> LimiterSettings {
+        LimiterSettings {
+            enabled: true,
+            threshold_db,
+            release_ms: 50.0,
+        }
+    }
+
+    #[test]
+    fn disabled_limiter_is_skipped() {
+        let off = LimiterSettings {
+            enabled: false,
+            ..settings(-1.0)
+        };
+        assert!(Limiter::new(&off, 2, 48_000).is_none());
+    }
+
+    #[test]
+    fn peaks_stay_below_threshold_and_quiet_audio_passes() {
+        let mut limiter = Limiter::new(&settings(-6.0), 1, 48_000).unwrap();
+        let threshold = 10.0_f32.powf(-6.0 / 20.0);
+        let mut loud: Vec<f32> = (0..4_800)
+            .map(|i| 1.5 * (std::f32::consts::TAU * 440.0 * i as f32 / 48_000.0).sin())
+            .collect();
+        limiter.process(&mut loud);
+        assert!(loud.iter().all(|s| s.abs() <= threshold + 1e-6));
+        let steps = loud.windows(2).map(|w| (w[1] - w[0]).abs());
+        assert!(steps.fold(0.0_f32, f32::max) < 0.1);
+
+        let mut limiter = Limiter::new(&settings(-1.0), 1, 48_000).unwrap();
+        let mut quiet = vec![0.25_f32; 480];
+        limiter.process(&mut quiet);
+        // 延迟 72 帧（1.5 ms）后原样输出
+        assert!(quiet[..72].iter().all(|&s| s == 0.0));
+        assert!(quiet[72..].iter().all(|&s| s == 0.25));
+    }
+}
@@ -0,0 +1,233 @@
+//! Loudness normalization (ITU-R BS.1770 / EBU R128).
+
+pub use ::config::config::LoudnessSettings;
+
+use super::{Biquad, db_to_linear};
+
+/// Length of one measurement block; momentary loudness uses four of them.
+pub const LOUDNESS_BLOCK_SECS: f32 = 0.1;
+
+/// Span of the sliding integrated loudness the gain rider follows.
+pub const LOUDNESS_HISTORY_SECS: f32 = 10.0;
+
+/// Time constant of the gain rider, slow enough not to pump on transients.
+const RIDE_SECS: f32 = 1.0;
+
+/// EBU R128 absolute gate; quieter blocks (silence, pauses) are ignored.
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+/// Relative gate below the ungated loudness.
+const RELATIVE_GATE_LU: f32 = 10.0;
+
+const MOMENTARY_BLOCKS: usize = 4;
+
+fn energy_to_lufs(energy: f64) -> f32 {
+    (-0.691 + 10.0 * energy.log10()) as f32
+}
+
+fn lufs_to_energy(lufs: f32) -> f64 {
+    10f64.powf((lufs as f64 + 0.691) / 10.0)
+}
+
+/// BS.1770 K-weighting (high shelf + RLB high-pass) for any sample rate,
+/// using the analog prototypes of the 48 kHz reference coefficients.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate.max(1) as f64;
+
+    let k = (std::f64::consts::PI * 1681.974450955533 / fs).tan();
+    let q = 0.7071752369554196;
+    let vh = 10f64.powf(3.999843853973347 / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let shelf = Biquad::from_coefficients(
+        [
+            (vh + vb * k / q + k * k) as f32,
+            (2.0 * (k * k - vh)) as f32,
+            (vh - vb * k / q + k * k) as f32,
+        ],
+        [
+            (1.0 + k / q + k * k) as f32,
+            (2.0 * (k * k - 1.0)) as f32,
+            (1.0 - k / q + k * k) as f32,
+        ],
+    );
+
+    let k = (std::f64::consts::PI * 38.13547087602444 / fs).tan();
+    let q = 0.5003270373238773;
+    let high_pass = Biquad::from_coefficients(
+        [1.0, -2.0, 1.0],
+        [
+            (1.0 + k / q + k * k) as f32,
+            (2.0 * (k * k - 1.0)) as f32,
+            (1.0 - k / q + k * k) as f32,
+        ],
+    );
+    [shelf, high_pass]
+}
+
+/// Gated loudness of momentary values, `None` if all are below the absolute gate.
+fn gated_loudness(values: &[f32]) -> Option<f32> {
+    let mean = |threshold: f32| {
+        let (sum, count) = values
+            .iter()
+            .filter(|&&l| l > threshold)
+            .fold((0.0, 0usize), |(sum, count), &l| {
+                (sum + lufs_to_energy(l), count + 1)
+            });
+        (count > 0).then(|| sum / count as f64)
+    };
+    let ungated = energy_to_lufs(mean(ABSOLUTE_GATE_LUFS)?);
+    let threshold = ABSOLUTE_GATE_LUFS.max(ungated - RELATIVE_GATE_LU);
+    mean(threshold).map(energy_to_lufs)
+}
+
+/// Measures the loudness of an output and slowly rides its gain towards
+/// the target, so quiet and loud sources end up at a similar level.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LoudnessNormalizer {
+    channels: usize,
+    /// BS.1770 声道权重（LFE 为 0，环绕声道 1.41）。
+    weights: Vec<f32>,
+    filters: Vec<[Biquad; 2]>,
+    block_frames: usize,
+    block_pos: usize,
+    block_energy: f64,
+    /// 最近 4 个块的平均能量，组成 400 ms 瞬时响度窗口。
+    recent: [f64; MOMENTARY_BLOCKS],
+    recent_count: usize,
+    /// 最近 `LOUDNESS_HISTORY_SECS` 内的瞬时响度（LUFS），环形缓冲。
+    history: Vec<f32>,
+    history_pos: usize,
+    history_count: usize,
+    target_lufs: f32,
+    max_gain_db: f32,
+    target_gain: f32,
+    gain: f32,
+    smoothing: f32,
+}
+
+impl LoudnessNormalizer {
+    /// `weights` holds the BS.1770 weight of each interleaved channel.
+    /// Returns `None` for disabled settings.
+    pub(crate) fn new(
+        settings: &LoudnessSettings,
+        weights: Vec<f32>,
+        sample_rate: u32,
+    ) -> Option<Self> {
+        if !settings.enabled || weights.is_empty() {
+            return None;
+        }
+        let block_frames = ((sample_rate as f32 * LOUDNESS_BLOCK_SECS) as usize).max(1);
+        let history_len = (LOUDNESS_HISTORY_SECS / LOUDNESS_BLOCK_SECS) as usize;
+        Some(Self {
+            channels: weights.len(),
+            filters: vec![k_weighting(sample_rate); weights.len()],
+            weights,
+            block_frames,
+            block_pos: 0,
+            block_energy: 0.0,
+            recent: [0.0; MOMENTARY_BLOCKS],
+            recent_count: 0,
+            history: vec![0.0; history_len],
+            history_pos: 0,
+            history_count: 0,
+            target_lufs: settings.target_lufs,
+            max_gain_db: settings.max_gain_db,
+            target_gain: 1.0,
+            gain: 1.0,
+            smoothing: 1.0 - (-1.0 / (RIDE_SECS * sample_rate.max(1) as f32)).exp(),
+        })
+    }
+
+    /// Sliding integrated loudness of the input, once enough audio was seen.
+    pub(crate) fn integrated_lufs(&self) -> Option<f32> {
+        gated_loudness(&self.history[..self.history_count])
+    }
+
+    /// Measures and normalizes interleaved frames in place.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            for ((sample, filters), &weight) in
+                frame.iter_mut().zip(&mut self.filters).zip(&self.weights)
+            {
+                let weighted = filters.iter_mut().fold(*sample, |s, f| f.process(s));
+                self.block_energy += (weight * weighted * weighted) as f64;
+            }
+            self.gain += (self.target_gain - self.gain) * self.smoothing;
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+
+            self.block_pos += 1;
+            if self.block_pos == self.block_frames {
+                self.finish_block();
+            }
+        }
+    }
+
+    fn finish_block(&mut self) {
+        self.recent.rotate_left(1);
+        self.recent[MOMENTARY_BLOCKS - 1] = self.block_energy / self.block_frames as f64;
+        self.block_energy = 0.0;
+        self.block_pos = 0;
+        self.recent_count = (self.recent_count + 1).min(MOMENTARY_BLOCKS);
+        if self.recent_count < MOMENTARY_BLOCKS {
+            return;
+        }
+
+        let momentary = self.recent.iter().sum::<f64>() / MOMENTARY_BLOCKS as f64;
+        self.history[self.history_pos] = energy_to_lufs(momentary);
+        self.history_pos = (self.history_pos + 1) % self.history.len();
+        self.history_count = (self.history_count + 1).min(self.history.len());
+
+        // 静音时保持当前增益，避免把底噪拉高
+        if let Some(loudness) = self.integrated_lufs() {
+            let correction =
+                (self.target_lufs - loudness).clamp(-self.max_gain_db, self.max_gain_db);
+            self.target_gain = db_to_linear(correction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, secs: f32) -> Vec<f32> {
+        (0..(48_000.0 * secs) as usize)
+            .map(|i| amplitude * (std::f32::consts::TAU * 1_000.0 * i as f32 / 48_000.0).sin())
+            .collect()
+    }
+
+    fn settings(target_lufs: f32) -> LoudnessSettings {
+        LoudnessSettings {
+            enabled: true,
+            target_lufs,
+            max_gain_db: 12.0,
+        }
+    }
+
+    #[test]
+    fn full_scale_sine_reads_minus_3_lufs() {
+        // BS.1770：单声道 0 dBFS 1 kHz 正弦为 -3.01 LUFS
+        let mut normalizer = LoudnessNormalizer::new(&settings(-3.0), vec![1.0], 48_000).unwrap();
+        normalizer.process(&mut sine(1.0, 2.0));
+        let loudness = normalizer.integrated_lufs().unwrap();
+        assert!((loudness + 3.01).abs() < 0.1, "{loudness}");
+    }
+
+    #[test]
+    fn quiet_input_is_raised_towards_target_and_silence_is_not() {
+        let mut normalizer = LoudnessNormalizer::new(&settings(-23.0), vec![1.0], 48_000).unwrap();
+        // -33 LUFS 的输入应被提升约 10 dB
+        let mut quiet = sine(10f32.powf(-30.0 / 20.0), 8.0);
+        normalizer.process(&mut quiet);
+        let peak = quiet[quiet.len() - 4_800..]
+            .iter()
+            .fold(0.0_f32, |m, s| m.max(s.abs()));
+        let boost_db = 20.0 * (peak / 10f32.powf(-30.0 / 20.0)).log10();
+        assert!((boost_db - 10.0).abs() < 1.0, "{boost_db}");
+
+        let gain = normalizer.gain;
+        normalizer.process(&mut vec![0.0; 48_000]);
+        assert!((normalizer.gain - gain).abs() < 0.05);
+    }
+}
@@ -0,0 +1,128 @@
+//! Windowed peak and RMS levels.
+
+/// Metering window used when none is configured.
+pub const DEFAULT_METER_WINDOW_MS: f32 = 300.0;
+
+/// Magnitude counted as clipped. Integer formats saturate just below 1.0
+/// (32767/32768 for 16-bit), so full scale itself can never be reached.
+const CLIP_LEVEL: f32 = 0.9999;
+
+/// Number of `samples` at or beyond full scale.
+pub(crate) fn count_clipped(samples: &[f32]) -> usize {
+    samples.iter().filter(|s| s.abs() >= CLIP_LEVEL).count()
+}
+
+/// Peak and RMS of each channel over consecutive windows of fixed length.
+/// The readings of the last completed window stay available until the next
+/// one completes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LevelMeter {
+    channels: usize,
+    window_frames: usize,
+    /// 当前窗口已累计的帧数。
+    frames: usize,
+    window_peak: Vec<f32>,
+    window_sum_squares: Vec<f32>,
+    /// 上一个完整窗口的读数。
+    peak: Vec<f32>,
+    rms: Vec<f32>,
+}
+
+impl LevelMeter {
+    /// A non-positive `window_ms` selects [`DEFAULT_METER_WINDOW_MS`].
+    pub(crate) fn new(channels: usize, sample_rate: u32, window_ms: f32) -> Self {
+        let channels = channels.max(1);
+        let window_ms = if window_ms > 0.0 {
+            window_ms
+        } else {
+            DEFAULT_METER_WINDOW_MS
+        };
+        Self {
+            channels,
+            window_frames: ((window_ms / 1000.0 * sample_rate as f32) as usize).max(1),
+            frames: 0,
+            window_peak: vec![0.0; channels],
+            window_sum_squares: vec![0.0; channels],
+            peak: vec![0.0; channels],
+            rms: vec![0.0; channels],
+        }
+    }
+
+    /// Accumulates interleaved `samples`; returns `true` if at least one
+    /// window completed, i.e. [`Self::peak`] and [`Self::rms`] changed.
+    pub(crate) fn process(&mut self, samples: &[f32]) -> bool {
+        let mut completed = false;
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                self.window_peak[channel] = self.window_peak[channel].max(sample.abs());
+                self.window_sum_squares[channel] += sample * sample;
+            }
+            self.frames += 1;
+            if self.frames == self.window_frames {
+                self.peak.copy_from_slice(&self.window_peak);
+                for (rms, &sum) in self.rms.iter_mut().zip(&self.window_sum_squares) {
+                    *rms = (sum / self.window_frames as f32).sqrt();
+                }
+                self.window_peak.fill(0.0);
+                self.window_sum_squares.fill(0.0);
+                self.frames = 0;
+                completed = true;
+            }
+        }
+        completed
+    }
+
+    /// Drops the current window and the last readings, which read silent
+    /// until the next window completes.
+    pub(crate) fn reset(&mut self) {
+        self.frames = 0;
+        self.window_peak.fill(0.0);
+        self.window_sum_squares.fill(0.0);
+        self.peak.fill(0.0);
+        self.rms.fill(0.0);
+    }
+
+    /// Linear peak of each channel in the last completed window.
+    pub(crate) fn peak(&self) -> &[f32] {
+        &self.peak
+    }
+
+    /// Linear RMS of each channel in the last completed window.
+    pub(crate) fn rms(&self) -> &[f32] {
+        &self.rms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_completed_window() {
+        // 1 ms @ 4 kHz = 4 帧
+        let mut meter = LevelMeter::new(2, 4_000, 1.0);
+        assert!(!meter.process(&[0.5, 0.0, -0.5, 0.0, 0.5, 0.0]));
+        assert_eq!(meter.peak(), [0.0, 0.0]);
+
+        assert!(meter.process(&[-0.5, 1.0, 0.1, 0.0]));
+        assert_eq!(meter.peak(), [0.5, 1.0]);
+        assert_eq!(meter.rms(), [0.5, 0.5]);
+
+        // 下一个窗口未满，读数保持
+        assert!(!meter.process(&[0.0, 0.0]));
+        assert_eq!(meter.peak(), [0.5, 1.0]);
+
+        // 重置后读数归零，未完成的窗口也被丢弃
+        meter.reset();
+        assert_eq!(meter.peak(), [0.0, 0.0]);
+        assert!(!meter.process(&[0.5, 0.5, 0.5, 0.5, 0.5, 0.5]));
+        assert!(meter.process(&[0.5, 0.5]));
+        assert_eq!(meter.peak(), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn counts_saturated_integer_samples_as_clipped() {
+        let i16_max = i16::MAX as f32 / 32768.0;
+        assert_eq!(count_clipped(&[0.99, i16_max, -1.0, 1.5, 0.0]), 3);
+    }
+}
@@ -0,0 +1,91 @@
+//! Mid/side gains for the front pair (stereo width).
+
+pub use ::config::config::MidSideSettings;
+
+/// Splits the front pair into mid `(L+R)/2` and side `(L−R)/2`, scales each
+/// and converts back, so unity gains leave the signal unchanged. Channels
+/// after the first two pass through.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MidSide {
+    channels: usize,
+    mid: f32,
+    side: f32,
+}
+
+/// Linear gain for `gain_db`; the bottom of the range is silence rather than
+/// −60 dB, so "mid only" really is mono.
+fn component_gain(gain_db: f32) -> f32 {
+    if gain_db <= *MidSideSettings::GAIN_RANGE_DB.start() {
+        0.0
+    } else {
+        super::db_to_linear(gain_db)
+    }
+}
+
+impl MidSide {
+    /// Returns `None` when disabled, neutral or there is no stereo pair.
+    pub(crate) fn new(settings: &MidSideSettings, channels: usize) -> Option<Self> {
+        if !settings.enabled || channels < 2 {
+            return None;
+        }
+        let (mid, side) = (
+            component_gain(settings.mid_db),
+            component_gain(settings.side_db),
+        );
+        if mid == 1.0 && side == 1.0 {
+            return None;
+        }
+        Some(Self {
+            channels,
+            mid,
+            side,
+        })
+    }
+
+    pub(crate) fn process(&self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let mid = (frame[0] + frame[1]) * 0.5 * self.mid;
+            let side = (frame[0] - frame[1]) * 0.5 * self.side;
+            frame[0] = mid + side;
+            frame[1] = mid - side;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mid_db: f32, side_db: f32) -> MidSideSettings {
+        MidSideSettings {
+            enabled: true,
+            mid_db,
+            side_db,
+        }
+    }
+
+    #[test]
+    fn muted_side_folds_to_mono_and_keeps_other_channels() {
+        let mid_side = MidSide::new(&settings(0.0, -60.0), 3).unwrap();
+        let mut samples = [1.0, 0.0, 0.7, 0.25, 0.75, -0.3];
+        mid_side.process(&mut samples);
+        assert_eq!(samples, [0.5, 0.5, 0.7, 0.5, 0.5, -0.3]);
+    }
+
+    #[test]
+    fn side_boost_widens_the_image() {
+        let mid_side = MidSide::new(&settings(0.0, 6.0), 2).unwrap();
+        let mut samples = [1.0, 0.0];
+        mid_side.process(&mut samples);
+        // S 提升约 2 倍：L' ≈ 0.5 + 1.0，R' ≈ 0.5 − 1.0
+        assert!((samples[0] - 1.498).abs() < 0.01, "{samples:?}");
+        assert!((samples[1] + 0.498).abs() < 0.01, "{samples:?}");
+    }
+
+    #[test]
+    fn neutral_or_mono_needs_no_processing() {
+        assert!(MidSide::new(&settings(0.0, 0.0), 2).is_none());
+        assert!(MidSide::new(&settings(0.0, -60.0), 1).is_none());
+        assert!(MidSide::new(&MidSideSettings::default(), 2).is_none());
+    }
+}
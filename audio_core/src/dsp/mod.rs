@@ -0,0 +1,59 @@
+//! Signal processing blocks (filters, FFT, crossovers, crossfeed, equalizers,
+//! mid/side, correlation and level metering, gain, loudness normalization,
+//! limiter, delay, noise gate, dither, resampling, fades, test signals).
+//!
+//! Everything here works on interleaved f32 frames and keeps its own filter
+//! state, so one instance belongs to exactly one stream.
+
+mod biquad;
+mod correlation;
+mod crossfeed;
+mod crossover;
+mod delay;
+mod dither;
+mod eq;
+mod fade;
+mod fft;
+mod gain;
+mod gate;
+mod generator;
+mod limiter;
+mod loudness;
+mod meter;
+mod midside;
+mod resample;
+
+pub use ::config::config::{DspChain, DspStage};
+pub(crate) use biquad::Biquad;
+pub use correlation::CORRELATION_WINDOW_SECS;
+pub(crate) use correlation::CorrelationMeter;
+pub(crate) use crossfeed::Crossfeed;
+pub use crossfeed::{CrossfeedPreset, crossfeed_params};
+pub use crossover::{BassManagement, BassRole};
+pub(crate) use crossover::{linkwitz_riley_high, linkwitz_riley_low};
+pub(crate) use delay::DelayLine;
+pub(crate) use dither::Dither;
+pub use dither::DitherMode;
+pub(crate) use eq::GraphicEq;
+pub use eq::{EqPreset, GRAPHIC_EQ_BANDS_HZ, preset_gains};
+pub(crate) use fade::Fade;
+pub(crate) use fft::Fft;
+pub(crate) use gain::SmoothedGain;
+pub use gain::{GAIN_RAMP_SECS, MAX_GAIN_DB, MIN_GAIN_DB, db_to_linear, volume_scalar_to_db};
+pub(crate) use gate::NoiseGate;
+pub use gate::NoiseGateSettings;
+pub(crate) use generator::{ChannelIdentification, LogSweep, PinkNoise, TestTone};
+pub use generator::{
+    IDENTIFY_BURST_SECS, IDENTIFY_GAP_SECS, IDENTIFY_LEVEL_DB, PINK_NOISE_LEVEL_RANGE_DB,
+    SweepSettings, TEST_TONE_HZ, TEST_TONE_LEVEL_DB, TEST_TONE_SECS,
+};
+pub(crate) use limiter::Limiter;
+pub use limiter::{LIMITER_LOOKAHEAD_SECS, LimiterSettings};
+pub(crate) use loudness::LoudnessNormalizer;
+pub use loudness::{LOUDNESS_BLOCK_SECS, LOUDNESS_HISTORY_SECS, LoudnessSettings};
+pub use meter::DEFAULT_METER_WINDOW_MS;
+pub(crate) use meter::{LevelMeter, count_clipped};
+pub(crate) use midside::MidSide;
+pub use midside::MidSideSettings;
+pub(crate) use resample::Resampler;
+pub use resample::ResamplerQuality;
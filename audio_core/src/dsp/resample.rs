@@ -0,0 +1,235 @@
+//! Polyphase windowed-sinc sample-rate conversion.
+
+pub use ::config::config::ResamplerQuality;
+
+/// `(half kernel length in input frames, Kaiser beta, cutoff relative to Nyquist)`.
+fn quality_params(quality: ResamplerQuality) -> Option<(usize, f64, f64)> {
+    match quality {
+        ResamplerQuality::System => None,
+        ResamplerQuality::Fast => Some((8, 6.0, 0.90)),
+        ResamplerQuality::Balanced => Some((24, 8.6, 0.94)),
+        ResamplerQuality::High => Some((64, 10.0, 0.97)),
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Zeroth-order modified Bessel function (power series), for the Kaiser window.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..50 {
+        term *= (half / k as f64) * (half / k as f64);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Converts interleaved frames from one sample rate to another.
+///
+/// The ratio is kept as an exact fraction, so one kernel row per output
+/// phase is precomputed and no drift accumulates over long sessions. Output
+/// lags the input by half the kernel length.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Resampler {
+    channels: usize,
+    from_rate: u32,
+    half_taps: usize,
+    /// 输出相位数（约分后的目标采样率）。
+    phases: usize,
+    /// 每个输出帧前进的相位数（约分后的源采样率）。
+    step: usize,
+    /// `phases` 行、每行 `2 * half_taps` 个系数。
+    kernel: Vec<f32>,
+    /// 尚未完全用完的输入帧（交错）。
+    history: Vec<f32>,
+    /// 下一个输出帧在 `history` 中的整数帧位置与相位。
+    frame: usize,
+    phase: usize,
+}
+
+impl Resampler {
+    /// Returns `None` when the engine converts (`System`) or the rates match.
+    pub(crate) fn new(
+        quality: ResamplerQuality,
+        channels: usize,
+        from_rate: u32,
+        to_rate: u32,
+    ) -> Option<Self> {
+        let (half_taps, beta, cutoff) = quality_params(quality)?;
+        if channels == 0 || from_rate == 0 || to_rate == 0 || from_rate == to_rate {
+            return None;
+        }
+        let divisor = gcd(from_rate, to_rate);
+        let phases = (to_rate / divisor) as usize;
+        let step = (from_rate / divisor) as usize;
+        // 降采样时截止频率跟随目标采样率的 Nyquist，避免混叠
+        let cutoff = cutoff * f64::min(1.0, to_rate as f64 / from_rate as f64) / 2.0;
+
+        let taps = 2 * half_taps;
+        let mut kernel = Vec::with_capacity(phases * taps);
+        for phase in 0..phases {
+            let offset = phase as f64 / phases as f64;
+            let row: Vec<f64> = (0..taps)
+                .map(|j| {
+                    let d = j as f64 - (half_taps - 1) as f64 - offset;
+                    let x = 2.0 * cutoff * d;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+                    };
+                    let ratio = (d / half_taps as f64).clamp(-1.0, 1.0);
+                    let window = bessel_i0(beta * (1.0 - ratio * ratio).sqrt()) / bessel_i0(beta);
+                    sinc * window
+                })
+                .collect();
+            // 每行归一化，保证直流增益精确为 1
+            let sum: f64 = row.iter().sum();
+            kernel.extend(row.iter().map(|&h| (h / sum) as f32));
+        }
+
+        Some(Self {
+            channels,
+            from_rate,
+            half_taps,
+            phases,
+            step,
+            kernel,
+            history: vec![0.0; (half_taps - 1) * channels],
+            frame: half_taps - 1,
+            phase: 0,
+        })
+    }
+
+    /// Group delay in seconds: an output frame needs `half_taps` input
+    /// frames after it before it can be produced.
+    pub(crate) fn latency_secs(&self) -> f32 {
+        self.half_taps as f32 / self.from_rate as f32
+    }
+
+    /// Number of frames the next [`process`](Self::process) call produces for
+    /// `input_frames` new frames.
+    pub(crate) fn output_frames(&self, input_frames: usize) -> usize {
+        let available = self.history.len() / self.channels + input_frames;
+        let (mut frame, mut phase, mut count) = (self.frame, self.phase, 0);
+        while frame + self.half_taps < available {
+            count += 1;
+            phase += self.step;
+            frame += phase / self.phases;
+            phase %= self.phases;
+        }
+        count
+    }
+
+    /// Resamples interleaved frames into `output` (its capacity is reused).
+    pub(crate) fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        output.clear();
+        self.history.extend_from_slice(input);
+        let available = self.history.len() / self.channels;
+        let taps = 2 * self.half_taps;
+
+        while self.frame + self.half_taps < available {
+            let row = &self.kernel[self.phase * taps..][..taps];
+            let start = (self.frame + 1 - self.half_taps) * self.channels;
+            for channel in 0..self.channels {
+                let sum = row
+                    .iter()
+                    .zip(
+                        self.history[start + channel..]
+                            .iter()
+                            .step_by(self.channels),
+                    )
+                    .map(|(h, x)| h * x)
+                    .sum();
+                output.push(sum);
+            }
+            self.phase += self.step;
+            self.frame += self.phase / self.phases;
+            self.phase %= self.phases;
+        }
+
+        // 丢弃之后的输出不再需要的帧
+        let consumed = (self.frame + 1 - self.half_taps).min(available);
+        self.history.drain(..consumed * self.channels);
+        self.frame -= consumed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| (std::f32::consts::TAU * freq * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn reports_the_input_needed_before_the_first_output_frame() {
+        let resampler = Resampler::new(ResamplerQuality::Balanced, 1, 48_000, 44_100).unwrap();
+        let lag_frames = (resampler.latency_secs() * 48_000.0).round() as usize;
+        assert_eq!(lag_frames, 24);
+        // 第一个输出帧对应第一个输入帧，要等到其后 `lag_frames` 帧到达
+        assert_eq!(resampler.output_frames(lag_frames), 0);
+        assert_eq!(resampler.output_frames(lag_frames + 1), 1);
+    }
+
+    #[test]
+    fn converts_44k1_to_48k_in_uneven_chunks() {
+        let mut resampler = Resampler::new(ResamplerQuality::Balanced, 1, 44_100, 48_000).unwrap();
+        let input = sine(1_000.0, 44_100, 44_100);
+        let mut output = Vec::new();
+        let mut chunk_out = Vec::new();
+        for chunk in input.chunks(441 + 7) {
+            let expected = resampler.output_frames(chunk.len());
+            resampler.process(chunk, &mut chunk_out);
+            assert_eq!(chunk_out.len(), expected);
+            output.extend_from_slice(&chunk_out);
+        }
+        // 一秒输入得到一秒输出（减去内核的延迟）
+        assert!(
+            (output.len() as i64 - 48_000).abs() <= 32,
+            "{}",
+            output.len()
+        );
+
+        // 与理想的 48 kHz 正弦比较（首个输出帧对应第一个输入帧）
+        let max_error = output[1_000..40_000]
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let t = (i as f32 + 1_000.0) / 48_000.0;
+                (s - (std::f32::consts::TAU * 1_000.0 * t).sin()).abs()
+            })
+            .fold(0.0_f32, f32::max);
+        assert!(max_error < 0.01, "{max_error}");
+    }
+
+    #[test]
+    fn downsampling_removes_content_above_new_nyquist() {
+        let mut resampler = Resampler::new(ResamplerQuality::High, 2, 96_000, 48_000).unwrap();
+        // 30 kHz 在 48 kHz 下会混叠到 18 kHz
+        let input: Vec<f32> = sine(30_000.0, 96_000, 9_600)
+            .into_iter()
+            .flat_map(|s| [s, s])
+            .collect();
+        let mut output = Vec::new();
+        resampler.process(&input, &mut output);
+        let peak = output[400..].iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!(peak < 0.01, "{peak}");
+    }
+
+    #[test]
+    fn system_quality_or_equal_rates_need_no_resampler() {
+        assert!(Resampler::new(ResamplerQuality::System, 2, 44_100, 48_000).is_none());
+        assert!(Resampler::new(ResamplerQuality::High, 2, 48_000, 48_000).is_none());
+    }
+}
@@ -1,7 +1,15 @@
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod com_service;
+pub mod device_registry;
 pub mod device_watcher;
+pub mod dsp;
+pub mod plugin;
 pub mod router;
 pub mod utils;
 
 // Re-export common types
 pub use router::{Router, RouterConfig};
+
+/// Version of the audio engine crate, for diagnostics.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,229 @@
+//! The subset of the CLAP 1.x C ABI the host uses (entry, factory, plugin,
+//! process, audio-ports, latency and state extensions).
+
+// 与 C 头文件一一对应：保留未使用的字段以保证布局
+#![allow(non_camel_case_types, dead_code)]
+
+use std::ffi::{c_char, c_void};
+
+pub const CLAP_VERSION: clap_version = clap_version {
+    major: 1,
+    minor: 2,
+    revision: 0,
+};
+
+pub const CLAP_PLUGIN_FACTORY_ID: &std::ffi::CStr = c"clap.plugin-factory";
+pub const CLAP_EXT_AUDIO_PORTS: &std::ffi::CStr = c"clap.audio-ports";
+pub const CLAP_EXT_STATE: &std::ffi::CStr = c"clap.state";
+pub const CLAP_EXT_LATENCY: &std::ffi::CStr = c"clap.latency";
+
+pub const CLAP_PROCESS_ERROR: i32 = 0;
+
+const CLAP_NAME_SIZE: usize = 256;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct clap_version {
+    pub major: u32,
+    pub minor: u32,
+    pub revision: u32,
+}
+
+impl clap_version {
+    /// Only plugins built against CLAP 1.x are ABI-compatible with this host.
+    pub fn is_compatible(&self) -> bool {
+        self.major == 1
+    }
+}
+
+#[repr(C)]
+pub struct clap_plugin_entry {
+    pub clap_version: clap_version,
+    pub init: Option<unsafe extern "C" fn(plugin_path: *const c_char) -> bool>,
+    pub deinit: Option<unsafe extern "C" fn()>,
+    pub get_factory: Option<unsafe extern "C" fn(factory_id: *const c_char) -> *const c_void>,
+}
+
+#[repr(C)]
+pub struct clap_plugin_descriptor {
+    pub clap_version: clap_version,
+    pub id: *const c_char,
+    pub name: *const c_char,
+    pub vendor: *const c_char,
+    pub url: *const c_char,
+    pub manual_url: *const c_char,
+    pub support_url: *const c_char,
+    pub version: *const c_char,
+    pub description: *const c_char,
+    pub features: *const *const c_char,
+}
+
+#[repr(C)]
+pub struct clap_plugin_factory {
+    pub get_plugin_count: Option<unsafe extern "C" fn(factory: *const clap_plugin_factory) -> u32>,
+    pub get_plugin_descriptor: Option<
+        unsafe extern "C" fn(
+            factory: *const clap_plugin_factory,
+            index: u32,
+        ) -> *const clap_plugin_descriptor,
+    >,
+    pub create_plugin: Option<
+        unsafe extern "C" fn(
+            factory: *const clap_plugin_factory,
+            host: *const clap_host,
+            plugin_id: *const c_char,
+        ) -> *const clap_plugin,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_host {
+    pub clap_version: clap_version,
+    pub host_data: *mut c_void,
+    pub name: *const c_char,
+    pub vendor: *const c_char,
+    pub url: *const c_char,
+    pub version: *const c_char,
+    pub get_extension: Option<
+        unsafe extern "C" fn(host: *const clap_host, extension_id: *const c_char) -> *const c_void,
+    >,
+    pub request_restart: Option<unsafe extern "C" fn(host: *const clap_host)>,
+    pub request_process: Option<unsafe extern "C" fn(host: *const clap_host)>,
+    pub request_callback: Option<unsafe extern "C" fn(host: *const clap_host)>,
+}
+
+#[repr(C)]
+pub struct clap_plugin {
+    pub desc: *const clap_plugin_descriptor,
+    pub plugin_data: *mut c_void,
+    pub init: Option<unsafe extern "C" fn(plugin: *const clap_plugin) -> bool>,
+    pub destroy: Option<unsafe extern "C" fn(plugin: *const clap_plugin)>,
+    pub activate: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            sample_rate: f64,
+            min_frames_count: u32,
+            max_frames_count: u32,
+        ) -> bool,
+    >,
+    pub deactivate: Option<unsafe extern "C" fn(plugin: *const clap_plugin)>,
+    pub start_processing: Option<unsafe extern "C" fn(plugin: *const clap_plugin) -> bool>,
+    pub stop_processing: Option<unsafe extern "C" fn(plugin: *const clap_plugin)>,
+    pub reset: Option<unsafe extern "C" fn(plugin: *const clap_plugin)>,
+    pub process: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, process: *const clap_process) -> i32,
+    >,
+    pub get_extension: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, id: *const c_char) -> *const c_void,
+    >,
+    pub on_main_thread: Option<unsafe extern "C" fn(plugin: *const clap_plugin)>,
+}
+
+#[repr(C)]
+pub struct clap_audio_buffer {
+    pub data32: *mut *mut f32,
+    pub data64: *mut *mut f64,
+    pub channel_count: u32,
+    pub latency: u32,
+    pub constant_mask: u64,
+}
+
+/// Events are not used: the list passed to the plugin is always empty.
+#[repr(C)]
+pub struct clap_event_header {
+    pub size: u32,
+    pub time: u32,
+    pub space_id: u16,
+    pub type_: u16,
+    pub flags: u32,
+}
+
+#[repr(C)]
+pub struct clap_input_events {
+    pub ctx: *mut c_void,
+    pub size: Option<unsafe extern "C" fn(list: *const clap_input_events) -> u32>,
+    pub get: Option<
+        unsafe extern "C" fn(
+            list: *const clap_input_events,
+            index: u32,
+        ) -> *const clap_event_header,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_output_events {
+    pub ctx: *mut c_void,
+    pub try_push: Option<
+        unsafe extern "C" fn(
+            list: *const clap_output_events,
+            event: *const clap_event_header,
+        ) -> bool,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_process {
+    pub steady_time: i64,
+    pub frames_count: u32,
+    pub transport: *const c_void,
+    pub audio_inputs: *const clap_audio_buffer,
+    pub audio_outputs: *mut clap_audio_buffer,
+    pub audio_inputs_count: u32,
+    pub audio_outputs_count: u32,
+    pub in_events: *const clap_input_events,
+    pub out_events: *const clap_output_events,
+}
+
+#[repr(C)]
+pub struct clap_audio_port_info {
+    pub id: u32,
+    pub name: [c_char; CLAP_NAME_SIZE],
+    pub flags: u32,
+    pub channel_count: u32,
+    pub port_type: *const c_char,
+    pub in_place_pair: u32,
+}
+
+#[repr(C)]
+pub struct clap_plugin_audio_ports {
+    pub count: Option<unsafe extern "C" fn(plugin: *const clap_plugin, is_input: bool) -> u32>,
+    pub get: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            index: u32,
+            is_input: bool,
+            info: *mut clap_audio_port_info,
+        ) -> bool,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_istream {
+    pub ctx: *mut c_void,
+    pub read: Option<
+        unsafe extern "C" fn(stream: *const clap_istream, buffer: *mut c_void, size: u64) -> i64,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_ostream {
+    pub ctx: *mut c_void,
+    pub write: Option<
+        unsafe extern "C" fn(stream: *const clap_ostream, buffer: *const c_void, size: u64) -> i64,
+    >,
+}
+
+#[repr(C)]
+pub struct clap_plugin_latency {
+    pub get: Option<unsafe extern "C" fn(plugin: *const clap_plugin) -> u32>,
+}
+
+#[repr(C)]
+pub struct clap_plugin_state {
+    pub save: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool,
+    >,
+    pub load: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, stream: *const clap_istream) -> bool,
+    >,
+}
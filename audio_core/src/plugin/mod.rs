@@ -0,0 +1,459 @@
+//! Hosting of third-party CLAP effect plugins in an output's DSP chain.
+//!
+//! The router worker thread is both the plugins' main thread and their audio
+//! thread: instances are created, activated and processed there, and the
+//! main-thread callbacks they request run right after a processed packet.
+//! Plugins get no editor window; their settings come from the state saved in
+//! the config.
+//!
+//! Only CLAP is hosted. VST3 is out of scope for now: its C++ COM-style
+//! interfaces need a separate binding layer.
+
+mod ffi;
+
+pub use ::config::config::PluginSlot;
+
+use anyhow::{Context, Result, anyhow};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Weak};
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::System::LibraryLoader::{FreeLibrary, GetProcAddress, LoadLibraryW};
+use windows::core::{HSTRING, s};
+
+/// Largest block handed to a plugin at once; longer packets are split.
+pub const PLUGIN_MAX_BLOCK_FRAMES: usize = 4096;
+
+/// A loaded `.clap` file. Shared by all instances created from it, so the
+/// entry is initialized once however many outputs use the plugin.
+struct ClapLibrary {
+    module: HMODULE,
+    entry: *const ffi::clap_plugin_entry,
+}
+
+// CLAP 要求 entry 及 factory 的函数是线程安全的
+unsafe impl Send for ClapLibrary {}
+unsafe impl Sync for ClapLibrary {}
+
+static LIBRARIES: LazyLock<Mutex<HashMap<String, Weak<ClapLibrary>>>> =
+    LazyLock::new(Default::default);
+
+impl ClapLibrary {
+    fn load(path: &str) -> Result<Arc<Self>> {
+        let mut libraries = LIBRARIES.lock();
+        if let Some(library) = libraries.get(path).and_then(Weak::upgrade) {
+            return Ok(library);
+        }
+
+        let c_path = CString::new(path).context("plugin path contains a NUL byte")?;
+        let module = unsafe { LoadLibraryW(&HSTRING::from(path)) }
+            .map_err(|e| anyhow!("Failed to load plugin {path}: {e}"))?;
+        let entry = unsafe { GetProcAddress(module, s!("clap_entry")) }
+            .map(|symbol| symbol as *const ffi::clap_plugin_entry)
+            .filter(|&entry| unsafe { (*entry).clap_version.is_compatible() });
+        let initialized = entry
+            .and_then(|entry| unsafe { (*entry).init })
+            .is_some_and(|init| unsafe { init(c_path.as_ptr()) });
+        let Some(entry) = entry.filter(|_| initialized) else {
+            let _ = unsafe { FreeLibrary(module) };
+            anyhow::bail!("{path} is not a compatible CLAP plugin");
+        };
+
+        let library = Arc::new(Self { module, entry });
+        libraries.insert(path.to_string(), Arc::downgrade(&library));
+        Ok(library)
+    }
+
+    fn factory(&self) -> Result<&ffi::clap_plugin_factory> {
+        let get_factory = unsafe { (*self.entry).get_factory }.context("missing get_factory")?;
+        let factory = unsafe { get_factory(ffi::CLAP_PLUGIN_FACTORY_ID.as_ptr()) };
+        unsafe { (factory as *const ffi::clap_plugin_factory).as_ref() }
+            .context("plugin has no plugin factory")
+    }
+}
+
+impl Drop for ClapLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+            let _ = FreeLibrary(self.module);
+        }
+    }
+}
+
+/// Flags set by host callbacks (possibly from plugin threads).
+#[derive(Default)]
+struct HostState {
+    callback_requested: AtomicBool,
+}
+
+/// `clap_host` plus its state; boxed so the pointers handed out stay valid.
+struct Host {
+    clap: ffi::clap_host,
+    state: HostState,
+}
+
+impl Host {
+    fn new() -> Box<Self> {
+        let mut host = Box::new(Self {
+            clap: ffi::clap_host {
+                clap_version: ffi::CLAP_VERSION,
+                host_data: std::ptr::null_mut(),
+                name: c"AudioRouter".as_ptr(),
+                vendor: c"fangfuzha".as_ptr(),
+                url: c"".as_ptr(),
+                version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+                get_extension: Some(host_get_extension),
+                request_restart: Some(host_request_ignored),
+                request_process: Some(host_request_ignored),
+                request_callback: Some(host_request_callback),
+            },
+            state: HostState::default(),
+        });
+        host.clap.host_data = &host.state as *const HostState as *mut c_void;
+        host
+    }
+}
+
+/// No host extensions are offered.
+unsafe extern "C" fn host_get_extension(
+    _host: *const ffi::clap_host,
+    _extension_id: *const c_char,
+) -> *const c_void {
+    std::ptr::null()
+}
+
+/// Plugins are always processing and never restarted mid-session.
+unsafe extern "C" fn host_request_ignored(_host: *const ffi::clap_host) {}
+
+unsafe extern "C" fn host_request_callback(host: *const ffi::clap_host) {
+    let state = unsafe { &*((*host).host_data as *const HostState) };
+    state.callback_requested.store(true, Ordering::Release);
+}
+
+unsafe extern "C" fn no_events_size(_list: *const ffi::clap_input_events) -> u32 {
+    0
+}
+
+unsafe extern "C" fn no_events_get(
+    _list: *const ffi::clap_input_events,
+    _index: u32,
+) -> *const ffi::clap_event_header {
+    std::ptr::null()
+}
+
+unsafe extern "C" fn discard_event(
+    _list: *const ffi::clap_output_events,
+    _event: *const ffi::clap_event_header,
+) -> bool {
+    true
+}
+
+/// `clap_istream` over a byte slice; `ctx` points at the unread `&[u8]`.
+unsafe extern "C" fn read_state(
+    stream: *const ffi::clap_istream,
+    buffer: *mut c_void,
+    size: u64,
+) -> i64 {
+    let remaining = unsafe { &mut *((*stream).ctx as *mut &[u8]) };
+    let len = remaining.len().min(size as usize);
+    unsafe { std::ptr::copy_nonoverlapping(remaining.as_ptr(), buffer as *mut u8, len) };
+    *remaining = &remaining[len..];
+    len as i64
+}
+
+type ProcessFn =
+    unsafe extern "C" fn(plugin: *const ffi::clap_plugin, process: *const ffi::clap_process) -> i32;
+
+/// An activated CLAP plugin instance processing interleaved frames in place.
+pub(crate) struct ClapPlugin {
+    name: String,
+    plugin: *const ffi::clap_plugin,
+    process: ProcessFn,
+    channels: usize,
+    /// 每声道一个缓冲区（CLAP 使用非交错数据）。
+    inputs: Vec<Vec<f32>>,
+    outputs: Vec<Vec<f32>>,
+    input_ptrs: Vec<*mut f32>,
+    output_ptrs: Vec<*mut f32>,
+    in_events: ffi::clap_input_events,
+    out_events: ffi::clap_output_events,
+    steady_time: i64,
+    activated: bool,
+    processing: bool,
+    host: Box<Host>,
+    /// 最后释放：插件销毁之前库必须保持加载。
+    _library: Arc<ClapLibrary>,
+}
+
+impl std::fmt::Debug for ClapPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClapPlugin")
+            .field("name", &self.name)
+            .field("channels", &self.channels)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClapPlugin {
+    /// Loads, activates and starts the plugin in `slot` for a stream of
+    /// `channels` at `sample_rate`. The plugin's main input and output
+    /// ports must have exactly that many channels.
+    pub(crate) fn new(slot: &PluginSlot, channels: usize, sample_rate: u32) -> Result<Self> {
+        let library = ClapLibrary::load(&slot.path)?;
+        let factory = library.factory()?;
+        let count = factory
+            .get_plugin_count
+            .map_or(0, |count| unsafe { count(factory) });
+        let descriptor = (0..count)
+            .filter_map(|i| {
+                let get = factory.get_plugin_descriptor?;
+                unsafe { get(factory, i).as_ref() }
+            })
+            .find(|d| {
+                slot.plugin_id.is_empty()
+                    || unsafe { CStr::from_ptr(d.id) }.to_bytes() == slot.plugin_id.as_bytes()
+            })
+            .with_context(|| format!("{} has no plugin {:?}", slot.path, slot.plugin_id))?;
+        let name = unsafe { CStr::from_ptr(descriptor.name) }
+            .to_string_lossy()
+            .into_owned();
+
+        let host = Host::new();
+        let create = factory.create_plugin.context("missing create_plugin")?;
+        let plugin = unsafe { create(factory, &host.clap, descriptor.id) };
+        if plugin.is_null() {
+            anyhow::bail!("{name}: create_plugin failed");
+        }
+        let Some(process) = (unsafe { (*plugin).process }) else {
+            if let Some(destroy) = unsafe { (*plugin).destroy } {
+                unsafe { destroy(plugin) };
+            }
+            anyhow::bail!("{name}: plugin has no process function");
+        };
+
+        let mut inputs = vec![vec![0.0; PLUGIN_MAX_BLOCK_FRAMES]; channels];
+        let mut outputs = inputs.clone();
+        // 之后各缓冲区不再重新分配，指针保持有效
+        let input_ptrs = inputs.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let output_ptrs = outputs.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let mut instance = Self {
+            name,
+            plugin,
+            process,
+            channels,
+            inputs,
+            outputs,
+            input_ptrs,
+            output_ptrs,
+            in_events: ffi::clap_input_events {
+                ctx: std::ptr::null_mut(),
+                size: Some(no_events_size),
+                get: Some(no_events_get),
+            },
+            out_events: ffi::clap_output_events {
+                ctx: std::ptr::null_mut(),
+                try_push: Some(discard_event),
+            },
+            steady_time: 0,
+            activated: false,
+            processing: false,
+            host,
+            _library: library,
+        };
+
+        // 以下失败时由 Drop 负责销毁实例
+        let plugin = unsafe { &*instance.plugin };
+        if !plugin
+            .init
+            .is_some_and(|init| unsafe { init(instance.plugin) })
+        {
+            anyhow::bail!("{}: init failed", instance.name);
+        }
+        let ports = (
+            instance.main_port_channels(true),
+            instance.main_port_channels(false),
+        );
+        if ports != (Some(channels as u32), Some(channels as u32)) {
+            anyhow::bail!(
+                "{}: main ports have {}/{} channels, the output has {channels}",
+                instance.name,
+                ports.0.unwrap_or(0),
+                ports.1.unwrap_or(0)
+            );
+        }
+        instance.load_state(&slot.state_bytes()?)?;
+        let activate = plugin.activate.context("missing activate")?;
+        if !unsafe {
+            activate(
+                instance.plugin,
+                sample_rate as f64,
+                1,
+                PLUGIN_MAX_BLOCK_FRAMES as u32,
+            )
+        } {
+            anyhow::bail!("{}: activate failed", instance.name);
+        }
+        instance.activated = true;
+        if !plugin
+            .start_processing
+            .is_some_and(|start| unsafe { start(instance.plugin) })
+        {
+            anyhow::bail!("{}: start_processing failed", instance.name);
+        }
+        instance.processing = true;
+        Ok(instance)
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Delay the plugin adds, in frames; 0 when it does not report one.
+    pub(crate) fn latency_frames(&self) -> u32 {
+        self.extension::<ffi::clap_plugin_latency>(ffi::CLAP_EXT_LATENCY)
+            .and_then(|ext| ext.get)
+            .map_or(0, |get| unsafe { get(self.plugin) })
+    }
+
+    fn extension<T>(&self, id: &CStr) -> Option<&T> {
+        let get = unsafe { (*self.plugin).get_extension }?;
+        unsafe { (get(self.plugin, id.as_ptr()) as *const T).as_ref() }
+    }
+
+    /// Channel count of the first input or output port, if there is one.
+    fn main_port_channels(&self, is_input: bool) -> Option<u32> {
+        let ports = self.extension::<ffi::clap_plugin_audio_ports>(ffi::CLAP_EXT_AUDIO_PORTS)?;
+        let (count, get) = (ports.count?, ports.get?);
+        if unsafe { count(self.plugin, is_input) } == 0 {
+            return None;
+        }
+        let mut info = std::mem::MaybeUninit::<ffi::clap_audio_port_info>::zeroed();
+        unsafe { get(self.plugin, 0, is_input, info.as_mut_ptr()) }
+            .then(|| unsafe { info.assume_init() }.channel_count)
+    }
+
+    fn load_state(&self, state: &[u8]) -> Result<()> {
+        if state.is_empty() {
+            return Ok(());
+        }
+        let load = self
+            .extension::<ffi::clap_plugin_state>(ffi::CLAP_EXT_STATE)
+            .and_then(|ext| ext.load)
+            .with_context(|| format!("{}: plugin cannot load a saved state", self.name))?;
+        let mut remaining = state;
+        let stream = ffi::clap_istream {
+            ctx: &mut remaining as *mut &[u8] as *mut c_void,
+            read: Some(read_state),
+        };
+        if !unsafe { load(self.plugin, &stream) } {
+            anyhow::bail!("{}: plugin rejected the saved state", self.name);
+        }
+        Ok(())
+    }
+
+    /// Processes interleaved frames in place. A block the plugin fails to
+    /// process passes through unchanged.
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.channels;
+        for block in samples.chunks_mut(PLUGIN_MAX_BLOCK_FRAMES * channels) {
+            let frames = block.len() / channels;
+            for (i, frame) in block.chunks_exact(channels).enumerate() {
+                for (input, &sample) in self.inputs.iter_mut().zip(frame) {
+                    input[i] = sample;
+                }
+            }
+            let audio_inputs = ffi::clap_audio_buffer {
+                data32: self.input_ptrs.as_mut_ptr(),
+                data64: std::ptr::null_mut(),
+                channel_count: channels as u32,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let mut audio_outputs = ffi::clap_audio_buffer {
+                data32: self.output_ptrs.as_mut_ptr(),
+                ..audio_inputs
+            };
+            let process = ffi::clap_process {
+                steady_time: self.steady_time,
+                frames_count: frames as u32,
+                transport: std::ptr::null(),
+                audio_inputs: &audio_inputs,
+                audio_outputs: &mut audio_outputs,
+                audio_inputs_count: 1,
+                audio_outputs_count: 1,
+                in_events: &self.in_events,
+                out_events: &self.out_events,
+            };
+            let status = unsafe { (self.process)(self.plugin, &process) };
+            self.steady_time += frames as i64;
+            if status == ffi::CLAP_PROCESS_ERROR {
+                continue;
+            }
+            for (i, frame) in block.chunks_exact_mut(channels).enumerate() {
+                for (sample, output) in frame.iter_mut().zip(&self.outputs) {
+                    *sample = output[i];
+                }
+            }
+        }
+
+        if self
+            .host
+            .state
+            .callback_requested
+            .swap(false, Ordering::AcqRel)
+            && let Some(on_main_thread) = unsafe { (*self.plugin).on_main_thread }
+        {
+            unsafe { on_main_thread(self.plugin) };
+        }
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        let plugin = unsafe { &*self.plugin };
+        unsafe {
+            if self.processing
+                && let Some(stop) = plugin.stop_processing
+            {
+                stop(self.plugin);
+            }
+            if self.activated
+                && let Some(deactivate) = plugin.deactivate
+            {
+                deactivate(self.plugin);
+            }
+            if let Some(destroy) = plugin.destroy {
+                destroy(self.plugin);
+            }
+        }
+    }
+}
+
+/// Instantiates the enabled plugins of an output, skipping (and logging)
+/// those that fail to load.
+pub(crate) fn load_chain(
+    device_id: &str,
+    slots: &[PluginSlot],
+    channels: usize,
+    sample_rate: u32,
+) -> Vec<ClapPlugin> {
+    slots
+        .iter()
+        .filter(|slot| slot.enabled)
+        .filter_map(|slot| match ClapPlugin::new(slot, channels, sample_rate) {
+            Ok(plugin) => {
+                log::info!("Render {device_id} hosts plugin {}", plugin.name());
+                Some(plugin)
+            }
+            Err(e) => {
+                log::warn!("Render {device_id} skips plugin {}: {e:#}", slot.path);
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+//! CPU placement of the router worker thread.
+//!
+//! On hybrid CPUs (P-cores + E-cores) the scheduler may park the routing loop
+//! on an efficiency core and cause periodic glitches. Pinning uses Windows CPU
+//! sets, which are soft constraints that still respect the process affinity.
+
+pub use ::config::config::ThreadAffinity;
+
+use anyhow::{Result, anyhow};
+use windows::Win32::System::SystemInformation::{
+    CpuSetInformation, GetSystemCpuSetInformation, SYSTEM_CPU_SET_INFORMATION,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, GetCurrentThread, SetThreadSelectedCpuSets,
+};
+
+/// One entry of the system CPU set list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CpuSet {
+    id: u32,
+    /// Logical processor index across groups (`group * 64 + index`).
+    logical_index: u32,
+    /// Higher is faster; all cores share the same class on non-hybrid CPUs.
+    efficiency_class: u8,
+}
+
+/// Applies `affinity` to the calling thread. Failures are logged and ignored
+/// so routing still works on systems without CPU set support.
+pub(crate) fn apply_to_current_thread(affinity: &ThreadAffinity) {
+    if affinity.is_unrestricted() {
+        return;
+    }
+    let result = query_cpu_sets().and_then(|sets| {
+        let selected = select_cpu_sets(&sets, affinity);
+        if selected.is_empty() {
+            return Err(anyhow!("no CPU matches {affinity:?}"));
+        }
+        unsafe { SetThreadSelectedCpuSets(GetCurrentThread(), &selected) }
+            .ok()
+            .map_err(|e| anyhow!("SetThreadSelectedCpuSets failed: {e:?}"))?;
+        Ok(selected)
+    });
+    match result {
+        Ok(selected) => log::info!("Router worker pinned to CPU sets {selected:?}"),
+        Err(e) => log::warn!("Failed to apply thread affinity, using default scheduling: {e}"),
+    }
+}
+
+/// Picks CPU set ids matching the configured cores, optionally keeping only
+/// the highest efficiency class (performance cores).
+fn select_cpu_sets(sets: &[CpuSet], affinity: &ThreadAffinity) -> Vec<u32> {
+    let fastest_class = sets.iter().map(|s| s.efficiency_class).max().unwrap_or(0);
+    sets.iter()
+        .filter(|s| affinity.cores.is_empty() || affinity.cores.contains(&s.logical_index))
+        .filter(|s| !affinity.exclude_efficiency_cores || s.efficiency_class == fastest_class)
+        .map(|s| s.id)
+        .collect()
+}
+
+fn query_cpu_sets() -> Result<Vec<CpuSet>> {
+    let process = unsafe { GetCurrentProcess() };
+    let mut needed: u32 = 0;
+    // 第一次调用只获取所需缓冲区大小（预期返回 FALSE / ERROR_INSUFFICIENT_BUFFER）
+    let _ = unsafe { GetSystemCpuSetInformation(None, 0, &mut needed, process, 0) };
+    if needed == 0 {
+        return Err(anyhow!("GetSystemCpuSetInformation returned no data"));
+    }
+
+    // 以 u64 为单位分配，保证记录按 8 字节对齐
+    let mut buffer = vec![0u64; (needed as usize).div_ceil(8)];
+    let ptr = buffer.as_mut_ptr() as *mut SYSTEM_CPU_SET_INFORMATION;
+    unsafe { GetSystemCpuSetInformation(Some(ptr), needed, &mut needed, process, 0) }
+        .ok()
+        .map_err(|e| anyhow!("GetSystemCpuSetInformation failed: {e:?}"))?;
+
+    let bytes = buffer.as_ptr() as *const u8;
+    let mut sets = Vec::new();
+    let mut offset = 0usize;
+    while offset + std::mem::size_of::<SYSTEM_CPU_SET_INFORMATION>() <= needed as usize {
+        let info = unsafe { &*(bytes.add(offset) as *const SYSTEM_CPU_SET_INFORMATION) };
+        if info.Size == 0 {
+            break;
+        }
+        if info.Type == CpuSetInformation {
+            let cpu = unsafe { info.Anonymous.CpuSet };
+            sets.push(CpuSet {
+                id: cpu.Id,
+                logical_index: cpu.Group as u32 * 64 + cpu.LogicalProcessorIndex as u32,
+                efficiency_class: cpu.EfficiencyClass,
+            });
+        }
+        offset += info.Size as usize;
+    }
+    Ok(sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hybrid_cpu() -> Vec<CpuSet> {
+        // 2 个 P-core（class 1）+ 2 个 E-core（class 0）
+        (0..4)
+            .map(|i| CpuSet {
+                id: 256 + i,
+                logical_index: i,
+                efficiency_class: if i < 2 { 1 } else { 0 },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_configured_cores_and_excludes_efficiency_cores() {
+        let sets = hybrid_cpu();
+
+        let p_cores = ThreadAffinity {
+            cores: vec![],
+            exclude_efficiency_cores: true,
+        };
+        assert_eq!(select_cpu_sets(&sets, &p_cores), vec![256, 257]);
+
+        let pinned = ThreadAffinity {
+            cores: vec![1, 3],
+            exclude_efficiency_cores: false,
+        };
+        assert_eq!(select_cpu_sets(&sets, &pinned), vec![257, 259]);
+
+        let both = ThreadAffinity {
+            cores: vec![1, 3],
+            exclude_efficiency_cores: true,
+        };
+        assert_eq!(select_cpu_sets(&sets, &both), vec![257]);
+
+        // 非混合架构：所有核心同一 class，排除 E-core 不影响结果
+        let uniform: Vec<CpuSet> = sets
+            .iter()
+            .map(|s| CpuSet {
+                efficiency_class: 0,
+                ..*s
+            })
+            .collect();
+        assert_eq!(select_cpu_sets(&uniform, &p_cores).len(), 4);
+    }
+}
@@ -1,16 +1,143 @@
 //! Router configuration.
 
-pub use ::config::config::ChannelMode;
+use super::affinity::ThreadAffinity;
+use crate::dsp::{
+    BassManagement, BassRole, CrossfeedPreset, DitherMode, DspChain, EqPreset, LimiterSettings,
+    LoudnessSettings, MidSideSettings, NoiseGateSettings, ResamplerQuality,
+};
+use crate::plugin::PluginSlot;
+use ::config::config::Output;
+pub use ::config::config::{
+    ChannelMatrix, ChannelMode, ChannelTrim, EngineMode, EngineSettings, MixLevels, OverflowPolicy,
+    SourceRole,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RouterConfig {
     pub source_device_id: Option<String>,
+    /// Capture the default output device for this role instead of
+    /// `source_device_id`. Resolved whenever the worker (re)opens its clients;
+    /// targets that turn out to be the source are skipped.
+    #[serde(default)]
+    pub source_role: Option<SourceRole>,
     pub targets: Vec<RouterTarget>,
+    /// CPU placement of the streaming worker thread.
+    #[serde(default)]
+    pub affinity: ThreadAffinity,
+    /// Buffer size, wake-up mode and thread priority of the session.
+    #[serde(default)]
+    pub engine: EngineSettings,
+    /// Crossover for outputs with a satellite or subwoofer bass role.
+    #[serde(default)]
+    pub bass_management: BassManagement,
+    /// Channel levels used by Downmix and Upmix.
+    #[serde(default)]
+    pub mix_levels: MixLevels,
+    /// Gate applied to the captured signal before it reaches any output.
+    #[serde(default)]
+    pub noise_gate: NoiseGateSettings,
+    /// Fade-in at the start of every session, in milliseconds.
+    #[serde(default)]
+    pub fade_in_ms: f32,
+    /// Window of the peak/RMS meters in milliseconds; 0 selects
+    /// [`crate::dsp::DEFAULT_METER_WINDOW_MS`].
+    #[serde(default)]
+    pub meter_window_ms: f32,
+    /// Publish the spectrum of the captured signal as
+    /// [`super::WorkerEvent::Spectrum`] events.
+    #[serde(default)]
+    pub spectrum: bool,
+    /// Route every output through its gain stage so
+    /// [`super::Router::set_master_gain`] can follow the source's volume.
+    #[serde(default)]
+    pub mirror_source_volume: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouterTarget {
     pub device_id: String,
     pub channel_mode: ChannelMode,
+    #[serde(default)]
+    pub overflow_policy: OverflowPolicy,
+    /// Gains for `ChannelMode::Matrix`; identity when absent.
+    #[serde(default)]
+    pub channel_matrix: Option<ChannelMatrix>,
+    /// Whether `ChannelMode::Downmix` folds the LFE channel into L/R.
+    #[serde(default)]
+    pub downmix_lfe: bool,
+    /// Graphic EQ applied to this output.
+    #[serde(default)]
+    pub eq_preset: EqPreset,
+    /// Output gain in dB.
+    #[serde(default)]
+    pub gain_db: f32,
+    /// Delay of the output in milliseconds, for time alignment.
+    #[serde(default)]
+    pub delay_ms: f32,
+    /// Start the output muted; see [`super::Router::set_output_muted`].
+    #[serde(default)]
+    pub muted: bool,
+    /// Render channels with inverted polarity, one bit per channel.
+    #[serde(default)]
+    pub phase_invert: u32,
+    /// Level trim of each render channel in dB.
+    #[serde(default)]
+    pub channel_trim: ChannelTrim,
+    /// Whether this output gets the full range, only the highs or only the lows.
+    #[serde(default)]
+    pub bass_role: BassRole,
+    /// Brickwall limiter at the end of the output's processing.
+    #[serde(default)]
+    pub limiter: LimiterSettings,
+    /// Loudness normalization of the output.
+    #[serde(default)]
+    pub loudness: LoudnessSettings,
+    /// Headphone crossfeed of the front pair.
+    #[serde(default)]
+    pub crossfeed: CrossfeedPreset,
+    /// Mid/side gains of the front pair.
+    #[serde(default)]
+    pub mid_side: MidSideSettings,
+    /// Dither for processed audio written to a 16-bit stream.
+    #[serde(default)]
+    pub dither: DitherMode,
+    /// Sample-rate conversion to the device rate.
+    #[serde(default)]
+    pub resampler: ResamplerQuality,
+    /// CLAP effect plugins after the EQ.
+    #[serde(default)]
+    pub plugins: Vec<PluginSlot>,
+    /// Order of the DSP stages.
+    #[serde(default)]
+    pub dsp_chain: DspChain,
+}
+
+impl RouterTarget {
+    /// Builds a router target from a persisted output entry; settings the
+    /// output leaves open come from `engine`.
+    pub fn from_output(output: &Output, engine: &EngineSettings) -> Self {
+        Self {
+            device_id: output.device_id.clone(),
+            channel_mode: ChannelMode::from_config(output.channel_mode.as_deref()),
+            overflow_policy: output.overflow_policy.unwrap_or(engine.overflow_policy),
+            channel_matrix: output.channel_matrix.clone(),
+            downmix_lfe: output.downmix_lfe,
+            eq_preset: output.eq_preset,
+            gain_db: output.gain_db,
+            delay_ms: output.delay_ms,
+            muted: output.muted,
+            phase_invert: output.phase_invert,
+            channel_trim: output.channel_trim.clone(),
+            bass_role: output.bass_role,
+            limiter: output.limiter,
+            loudness: output.loudness,
+            crossfeed: output.crossfeed,
+            mid_side: output.mid_side,
+            dither: output.dither,
+            resampler: output.resampler.unwrap_or(engine.resampler),
+            plugins: output.plugins.clone(),
+            dsp_chain: output.dsp_chain.clone(),
+        }
+    }
 }
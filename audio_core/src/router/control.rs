@@ -0,0 +1,359 @@
+//! Per-output parameters that can change while routing is running.
+
+use super::config::ChannelMode;
+use super::mixer::keeps_layout;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+/// 声道模式以其在此表中的下标存入原子变量。
+const MODES: [ChannelMode; 10] = [
+    ChannelMode::Stereo,
+    ChannelMode::LeftMono,
+    ChannelMode::RightMono,
+    ChannelMode::Mono,
+    ChannelMode::Swap,
+    ChannelMode::LeftOnly,
+    ChannelMode::RightOnly,
+    ChannelMode::Downmix,
+    ChannelMode::Upmix,
+    ChannelMode::Matrix,
+];
+
+fn mode_index(mode: ChannelMode) -> u8 {
+    MODES.iter().position(|&m| m == mode).unwrap_or(0) as u8
+}
+
+/// Live controls shared between the router handle and its worker thread.
+///
+/// The handle writes, the worker reads once per packet; values are plain
+/// atomics so the real-time loop never takes a lock.
+#[derive(Debug, Default)]
+pub struct RouterControls {
+    outputs: Mutex<Vec<Arc<OutputControl>>>,
+    /// 叠加在所有输出增益上的主增益（dB，f32 位模式），各输出共享。
+    master_gain_db: Arc<AtomicU32>,
+    /// 请求 worker 切换到的源设备；`source_switch_pending` 置位时才加锁读取。
+    source_switch: Mutex<Option<String>>,
+    source_switch_pending: AtomicBool,
+    /// 请求的淡出时长（ms，f32 位模式）；`fade_out_pending` 置位时有效。
+    fade_out_ms: AtomicU32,
+    fade_out_pending: AtomicBool,
+    /// 有消费者在读取电平时置位；由 [`super::Router`] 持有，跨会话保留。
+    metering: Arc<AtomicBool>,
+}
+
+/// Controls for one output, registered by the worker for every render client.
+#[derive(Debug)]
+pub(crate) struct OutputControl {
+    device_id: String,
+    /// f32 位模式存储的增益（dB）。
+    gain_db: AtomicU32,
+    /// 该输出经过混音器（`RenderPath::Mixed`），增益可以实时生效。
+    live: AtomicBool,
+    /// `MODES` 中的下标。
+    mode: AtomicU8,
+    /// 混音器的矩阵可以实时替换（声道布局不随模式变化）。
+    live_mode: AtomicBool,
+    /// 静音：增益级输出静音，会话继续运行。
+    muted: AtomicBool,
+    /// 替代捕获信号播放的粉红噪声电平（dBFS RMS，f32 位模式）；NaN 表示关闭。
+    pink_noise_db: AtomicU32,
+    master_gain_db: Arc<AtomicU32>,
+}
+
+impl OutputControl {
+    pub(crate) fn gain_db(&self) -> f32 {
+        f32::from_bits(self.gain_db.load(Ordering::Relaxed))
+    }
+
+    /// The output's own gain plus the master gain shared by all outputs;
+    /// -inf while the output is muted.
+    pub(crate) fn total_gain_db(&self) -> f32 {
+        if self.muted.load(Ordering::Relaxed) {
+            return f32::NEG_INFINITY;
+        }
+        self.gain_db() + f32::from_bits(self.master_gain_db.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub(crate) fn mode(&self) -> ChannelMode {
+        MODES[self.mode.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Level of the pink noise replacing the captured signal, if it is on.
+    pub(crate) fn pink_noise_db(&self) -> Option<f32> {
+        let level = f32::from_bits(self.pink_noise_db.load(Ordering::Relaxed));
+        (!level.is_nan()).then_some(level)
+    }
+
+    /// Records which parameters the initialized render path can change live.
+    pub(crate) fn set_live(&self, gain: bool, mode: bool) {
+        self.live.store(gain, Ordering::Relaxed);
+        self.live_mode.store(mode, Ordering::Relaxed);
+    }
+}
+
+impl RouterControls {
+    /// Controls of a new session that meters while `metering` is set.
+    pub(crate) fn new(metering: Arc<AtomicBool>) -> Self {
+        Self {
+            metering,
+            ..Self::default()
+        }
+    }
+
+    /// Whether someone reads the levels, so the worker has to meter.
+    pub(crate) fn metering(&self) -> bool {
+        self.metering.load(Ordering::Relaxed)
+    }
+
+    /// Returns the controls for `device_id`, creating them with `gain_db`
+    /// and `mode` on first use. Nothing is live until
+    /// [`OutputControl::set_live`] is called.
+    ///
+    /// Re-registering after a device restart keeps the last gain and mode set
+    /// through [`RouterControls::set_gain_db`] and
+    /// [`RouterControls::set_mode`], which may be newer than the config.
+    pub(crate) fn register_output(
+        &self,
+        device_id: &str,
+        gain_db: f32,
+        mode: ChannelMode,
+    ) -> Arc<OutputControl> {
+        let mut outputs = self.outputs.lock();
+        if let Some(existing) = outputs.iter().find(|o| o.device_id == device_id) {
+            existing.set_live(false, false);
+            return Arc::clone(existing);
+        }
+        let output = Arc::new(OutputControl {
+            device_id: device_id.to_string(),
+            gain_db: AtomicU32::new(gain_db.to_bits()),
+            live: AtomicBool::new(false),
+            mode: AtomicU8::new(mode_index(mode)),
+            live_mode: AtomicBool::new(false),
+            muted: AtomicBool::new(false),
+            pink_noise_db: AtomicU32::new(f32::NAN.to_bits()),
+            master_gain_db: Arc::clone(&self.master_gain_db),
+        });
+        outputs.push(Arc::clone(&output));
+        output
+    }
+
+    /// Updates the gain of a running output.
+    ///
+    /// Returns `false` if the output is not running or its render path has
+    /// no gain stage, in which case the session must be restarted to apply it.
+    pub(crate) fn set_gain_db(&self, device_id: &str, gain_db: f32) -> bool {
+        let outputs = self.outputs.lock();
+        match outputs.iter().find(|o| o.device_id == device_id) {
+            Some(output) if output.live.load(Ordering::Relaxed) => {
+                output.gain_db.store(gain_db.to_bits(), Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Mutes or unmutes a running output through its gain stage.
+    ///
+    /// Returns `false` under the same conditions as
+    /// [`RouterControls::set_gain_db`].
+    pub(crate) fn set_muted(&self, device_id: &str, muted: bool) -> bool {
+        let outputs = self.outputs.lock();
+        match outputs.iter().find(|o| o.device_id == device_id) {
+            Some(output) if output.live.load(Ordering::Relaxed) => {
+                output.set_muted(muted);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the gain added to every output's own gain. Like
+    /// [`RouterControls::set_gain_db`] it only affects outputs whose render
+    /// path has a gain stage.
+    pub(crate) fn set_master_gain_db(&self, gain_db: f32) {
+        self.master_gain_db
+            .store(gain_db.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Switches the channel mode of a running output; the worker crossfades
+    /// to the new mapping.
+    ///
+    /// Returns `false` if the output is not running, is not mixed in f32, or
+    /// either mode changes the channel layout; restart the session then.
+    pub(crate) fn set_mode(&self, device_id: &str, mode: ChannelMode) -> bool {
+        let outputs = self.outputs.lock();
+        match outputs.iter().find(|o| o.device_id == device_id) {
+            Some(output) if output.live_mode.load(Ordering::Relaxed) && keeps_layout(mode) => {
+                output.mode.store(mode_index(mode), Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the signal of output `device_id` with pink noise at
+    /// `level_db` dBFS RMS and turns the noise off on every other output.
+    ///
+    /// Returns `false` if the output is not running.
+    pub(crate) fn play_pink_noise(&self, device_id: &str, level_db: f32) -> bool {
+        let outputs = self.outputs.lock();
+        if !outputs.iter().any(|o| o.device_id == device_id) {
+            return false;
+        }
+        for output in outputs.iter() {
+            let level = if output.device_id == device_id {
+                level_db
+            } else {
+                f32::NAN
+            };
+            output
+                .pink_noise_db
+                .store(level.to_bits(), Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Turns the pink noise off; every output plays the captured signal again.
+    pub(crate) fn stop_pink_noise(&self) {
+        for output in self.outputs.lock().iter() {
+            output
+                .pink_noise_db
+                .store(f32::NAN.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Asks the worker to capture from `device_id` instead of the current
+    /// source. A later request replaces one the worker has not picked up.
+    pub(crate) fn request_source_switch(&self, device_id: &str) {
+        *self.source_switch.lock() = Some(device_id.to_string());
+        self.source_switch_pending.store(true, Ordering::Release);
+    }
+
+    /// The source requested since the last call, if any. Called by the
+    /// worker once per loop, so it only locks when a switch is pending.
+    pub(crate) fn take_source_switch(&self) -> Option<String> {
+        if !self.source_switch_pending.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        self.source_switch.lock().take()
+    }
+
+    /// Asks the worker to fade every output to silence over `duration_ms`.
+    pub(crate) fn request_fade_out(&self, duration_ms: f32) {
+        self.fade_out_ms
+            .store(duration_ms.to_bits(), Ordering::Relaxed);
+        self.fade_out_pending.store(true, Ordering::Release);
+    }
+
+    /// The fade-out duration requested since the last call, if any.
+    pub(crate) fn take_fade_out(&self) -> Option<f32> {
+        self.fade_out_pending
+            .swap(false, Ordering::Acquire)
+            .then(|| f32::from_bits(self.fade_out_ms.load(Ordering::Relaxed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_gain_survives_reregistration() {
+        let controls = RouterControls::default();
+        let output = controls.register_output("dev", -3.0, ChannelMode::Stereo);
+        output.set_live(true, false);
+        assert!(controls.set_gain_db("dev", -12.0));
+        assert_eq!(output.gain_db(), -12.0);
+
+        let output = controls.register_output("dev", -3.0, ChannelMode::Stereo);
+        output.set_live(true, false);
+        assert_eq!(output.gain_db(), -12.0);
+
+        controls.register_output("dev", -3.0, ChannelMode::Stereo);
+        assert!(!controls.set_gain_db("dev", 0.0));
+        assert!(!controls.set_gain_db("other", 0.0));
+    }
+
+    #[test]
+    fn master_gain_adds_to_every_output() {
+        let controls = RouterControls::default();
+        let first = controls.register_output("first", -3.0, ChannelMode::Stereo);
+        controls.set_master_gain_db(-6.0);
+        let second = controls.register_output("second", 0.0, ChannelMode::Stereo);
+        assert_eq!(first.total_gain_db(), -9.0);
+        assert_eq!(second.total_gain_db(), -6.0);
+        assert_eq!(first.gain_db(), -3.0);
+    }
+
+    #[test]
+    fn muting_silences_only_live_outputs() {
+        let controls = RouterControls::default();
+        let output = controls.register_output("dev", -3.0, ChannelMode::Stereo);
+        assert!(!controls.set_muted("dev", true));
+        assert_eq!(output.total_gain_db(), -3.0);
+
+        output.set_live(true, false);
+        assert!(controls.set_muted("dev", true));
+        assert_eq!(output.total_gain_db(), f32::NEG_INFINITY);
+        assert!(controls.set_muted("dev", false));
+        assert_eq!(output.total_gain_db(), -3.0);
+    }
+
+    #[test]
+    fn live_mode_only_between_layout_preserving_modes() {
+        let controls = RouterControls::default();
+        let output = controls.register_output("dev", 0.0, ChannelMode::Stereo);
+        assert!(!controls.set_mode("dev", ChannelMode::Swap));
+
+        output.set_live(true, true);
+        assert!(controls.set_mode("dev", ChannelMode::Swap));
+        assert!(!controls.set_mode("dev", ChannelMode::Downmix));
+        assert_eq!(output.mode(), ChannelMode::Swap);
+
+        // 重启后保留实时设置的模式
+        let output = controls.register_output("dev", 0.0, ChannelMode::Stereo);
+        assert_eq!(output.mode(), ChannelMode::Swap);
+    }
+
+    #[test]
+    fn only_the_latest_source_switch_is_taken() {
+        let controls = RouterControls::default();
+        assert_eq!(controls.take_source_switch(), None);
+        controls.request_source_switch("first");
+        controls.request_source_switch("second");
+        assert_eq!(controls.take_source_switch().as_deref(), Some("second"));
+        assert_eq!(controls.take_source_switch(), None);
+    }
+
+    #[test]
+    fn fade_out_request_is_taken_once() {
+        let controls = RouterControls::default();
+        assert_eq!(controls.take_fade_out(), None);
+        controls.request_fade_out(150.0);
+        assert_eq!(controls.take_fade_out(), Some(150.0));
+        assert_eq!(controls.take_fade_out(), None);
+    }
+
+    #[test]
+    fn pink_noise_plays_on_one_output_at_a_time() {
+        let controls = RouterControls::default();
+        let first = controls.register_output("first", 0.0, ChannelMode::Stereo);
+        let second = controls.register_output("second", 0.0, ChannelMode::Stereo);
+        assert_eq!(first.pink_noise_db(), None);
+        assert!(!controls.play_pink_noise("other", -20.0));
+
+        assert!(controls.play_pink_noise("first", -20.0));
+        assert!(controls.play_pink_noise("second", -30.0));
+        assert_eq!(first.pink_noise_db(), None);
+        assert_eq!(second.pink_noise_db(), Some(-30.0));
+
+        controls.stop_pink_noise();
+        assert_eq!(second.pink_noise_db(), None);
+    }
+}
@@ -0,0 +1,746 @@
+//! Channel mixing on interleaved f32 frames.
+//!
+//! The fixed stereo modes are applied directly on the raw sample format in
+//! `com_service::router`; everything that needs real gains (custom matrices,
+//! downmix, upmix, bass management) goes through a [`ChannelMixer`] after the
+//! capture packet was converted to f32.
+
+use super::config::{ChannelMatrix, ChannelMode, MixLevels};
+use crate::dsp::{
+    BassRole, Biquad, Crossfeed, DelayLine, DspStage, GraphicEq, Limiter, LoudnessNormalizer,
+    MidSide, SmoothedGain, db_to_linear, linkwitz_riley_high, linkwitz_riley_low,
+};
+use crate::plugin::ClapPlugin;
+
+// WAVEFORMATEXTENSIBLE::dwChannelMask 中的扬声器位（ksmedia.h）。
+// 交错数据中的声道按掩码位从低到高排列。
+pub(crate) const SPEAKER_FRONT_LEFT: u32 = 0x1;
+pub(crate) const SPEAKER_FRONT_RIGHT: u32 = 0x2;
+pub(crate) const SPEAKER_FRONT_CENTER: u32 = 0x4;
+pub(crate) const SPEAKER_LOW_FREQUENCY: u32 = 0x8;
+pub(crate) const SPEAKER_BACK_LEFT: u32 = 0x10;
+pub(crate) const SPEAKER_BACK_RIGHT: u32 = 0x20;
+pub(crate) const SPEAKER_FRONT_LEFT_OF_CENTER: u32 = 0x40;
+pub(crate) const SPEAKER_FRONT_RIGHT_OF_CENTER: u32 = 0x80;
+pub(crate) const SPEAKER_BACK_CENTER: u32 = 0x100;
+pub(crate) const SPEAKER_SIDE_LEFT: u32 = 0x200;
+pub(crate) const SPEAKER_SIDE_RIGHT: u32 = 0x400;
+
+/// Upmix 时中置（高通）与 LFE（低通）的分频点。
+const UPMIX_CROSSOVER_HZ: f32 = 120.0;
+
+/// Length of the crossfade when the channel mode of a running output changes.
+pub(crate) const MODE_CROSSFADE_SECS: f32 = 0.05;
+
+/// Windows' default speaker mask for a channel count (KSAUDIO_SPEAKER_*),
+/// used when the format does not carry one.
+pub(crate) fn default_channel_mask(channels: u16) -> u32 {
+    match channels {
+        1 => SPEAKER_FRONT_CENTER,
+        2 => SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT,
+        4 => SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT | SPEAKER_BACK_LEFT | SPEAKER_BACK_RIGHT,
+        6 => 0x3F,  // 5.1: FL FR C LFE BL BR
+        8 => 0x63F, // 7.1: FL FR C LFE BL BR SL SR
+        _ => 0,
+    }
+}
+
+/// Speaker position of each interleaved channel (0 for channels beyond the mask).
+fn channel_speakers(mask: u32, channels: usize) -> Vec<u32> {
+    let mut speakers: Vec<u32> = (0..32)
+        .map(|bit| 1u32 << bit)
+        .filter(|speaker| mask & speaker != 0)
+        .take(channels)
+        .collect();
+    speakers.resize(channels, 0);
+    speakers
+}
+
+/// ITU-R BS.775 downmix of `inputs` channels laid out per `mask` to stereo:
+/// `Lo = L + c·C + s·Ls`, `Ro = R + c·C + s·Rs`, with the center and surround
+/// levels from `levels` (-3 dB by default).
+/// The LFE channel is dropped unless `include_lfe`.
+/// Channels without a known position are dropped.
+pub(crate) fn downmix_matrix(
+    mask: u32,
+    inputs: usize,
+    include_lfe: bool,
+    levels: &MixLevels,
+) -> ChannelMatrix {
+    let center = levels.downmix_center;
+    let surround = levels.downmix_surround;
+    let lfe = if include_lfe { levels.downmix_lfe } else { 0.0 };
+    let rows = channel_speakers(mask, inputs)
+        .into_iter()
+        .map(|speaker| match speaker {
+            SPEAKER_FRONT_LEFT => vec![1.0, 0.0],
+            SPEAKER_FRONT_RIGHT => vec![0.0, 1.0],
+            SPEAKER_FRONT_CENTER | SPEAKER_BACK_CENTER => vec![center, center],
+            SPEAKER_LOW_FREQUENCY => vec![lfe, lfe],
+            SPEAKER_BACK_LEFT | SPEAKER_SIDE_LEFT | SPEAKER_FRONT_LEFT_OF_CENTER => {
+                vec![surround, 0.0]
+            }
+            SPEAKER_BACK_RIGHT | SPEAKER_SIDE_RIGHT | SPEAKER_FRONT_RIGHT_OF_CENTER => {
+                vec![0.0, surround]
+            }
+            _ => vec![0.0, 0.0],
+        })
+        .collect();
+    ChannelMatrix(rows)
+}
+
+/// A gain matrix flattened for the per-packet loop.
+#[derive(Debug)]
+pub(crate) struct ChannelMixer {
+    inputs: usize,
+    outputs: usize,
+    /// 按输入声道优先存储：`gains[input * outputs + output]`
+    gains: Vec<f32>,
+    /// 各输出声道的系数（反相、电平微调），已乘进 `gains`，替换矩阵时需重新施加。
+    output_scale: Vec<f32>,
+    /// 切换矩阵时的旧增益，交叉淡化期间与 `gains` 插值。
+    previous_gains: Vec<f32>,
+    crossfade_frames: u32,
+    crossfade_remaining: u32,
+    /// 混音后对各输出声道依次施加的滤波器链。
+    filters: Vec<Vec<Biquad>>,
+    /// 前置左右声道的中置/侧向增益。
+    mid_side: Option<MidSide>,
+    /// 耳机交叉馈送（只处理前置左右声道）。
+    crossfeed: Option<Crossfeed>,
+    /// 对所有输出声道施加的图形均衡器。
+    eq: Option<GraphicEq>,
+    /// 第三方 CLAP 效果插件，按配置顺序处理。
+    plugins: Vec<ClapPlugin>,
+    /// 响度归一化（测量 EQ 之后的信号）。
+    loudness: Option<LoudnessNormalizer>,
+    /// 输出增益；运行中调整时平滑过渡。
+    gain: Option<SmoothedGain>,
+    /// 防止削波的限幅器（默认为最后一级）。
+    limiter: Option<Limiter>,
+    /// 时间对齐用的延迟，在所有处理级之后。
+    delay: Option<DelayLine>,
+    /// 以上各级（延迟除外）的处理顺序。
+    order: Vec<DspStage>,
+}
+
+impl ChannelMixer {
+    /// Builds a mixer for `inputs` capture channels and `outputs` render
+    /// channels. Matrix entries outside that shape are ignored and missing
+    /// ones are treated as silence.
+    pub(crate) fn from_matrix(matrix: &ChannelMatrix, inputs: usize, outputs: usize) -> Self {
+        if matrix.inputs() != inputs || matrix.outputs() != outputs {
+            log::warn!(
+                "Channel matrix is {}x{} but the stream is {inputs}x{outputs}; missing gains are 0",
+                matrix.inputs(),
+                matrix.outputs()
+            );
+        }
+        Self {
+            inputs,
+            outputs,
+            gains: flatten_gains(matrix, inputs, outputs, &[]),
+            output_scale: vec![1.0; outputs],
+            previous_gains: Vec::new(),
+            crossfade_frames: 0,
+            crossfade_remaining: 0,
+            filters: vec![Vec::new(); outputs],
+            mid_side: None,
+            crossfeed: None,
+            eq: None,
+            plugins: Vec::new(),
+            loudness: None,
+            gain: None,
+            limiter: None,
+            delay: None,
+            order: DspStage::DEFAULT_ORDER.to_vec(),
+        }
+    }
+
+    /// Inverts the polarity of every render channel whose bit is set in `mask`.
+    pub(crate) fn with_phase_invert(self, mask: u32) -> Self {
+        let scale = (0..self.outputs)
+            .map(|o| {
+                if o < 32 && mask & (1 << o) != 0 {
+                    -1.0
+                } else {
+                    1.0
+                }
+            })
+            .collect::<Vec<_>>();
+        self.with_output_scale(&scale)
+    }
+
+    /// Trims the level of each render channel by `trim_db` (indexed by
+    /// channel; missing entries stay at 0 dB).
+    pub(crate) fn with_channel_trim(self, trim_db: &[f32]) -> Self {
+        let scale = (0..self.outputs)
+            .map(|o| trim_db.get(o).map_or(1.0, |&db| db_to_linear(db)))
+            .collect::<Vec<_>>();
+        self.with_output_scale(&scale)
+    }
+
+    /// Multiplies every gain feeding render channel `o` by `scale[o]`.
+    fn with_output_scale(mut self, scale: &[f32]) -> Self {
+        scale_outputs(&mut self.gains, self.outputs, scale);
+        for (total, &factor) in self.output_scale.iter_mut().zip(scale) {
+            *total *= factor;
+        }
+        self
+    }
+
+    /// Replaces the gain matrix, crossfading from the current gains over
+    /// `frames` frames. Filters and the DSP chain keep their state. A matrix
+    /// of a different shape is ignored, since the stream layout is fixed.
+    pub(crate) fn crossfade_to(&mut self, matrix: &ChannelMatrix, frames: u32) {
+        if matrix.inputs() != self.inputs || matrix.outputs() != self.outputs {
+            log::warn!(
+                "Ignoring {}x{} matrix for a {}x{} mixer",
+                matrix.inputs(),
+                matrix.outputs(),
+                self.inputs,
+                self.outputs
+            );
+            return;
+        }
+        // 从当前实际使用的增益开始（上一次淡化可能尚未结束）
+        let t = self.crossfade_position();
+        let current: Vec<f32> = match t {
+            Some(t) => self
+                .previous_gains
+                .iter()
+                .zip(&self.gains)
+                .map(|(&old, &new)| old + (new - old) * t)
+                .collect(),
+            None => self.gains.clone(),
+        };
+        self.previous_gains = current;
+        self.gains = flatten_gains(matrix, self.inputs, self.outputs, &self.output_scale);
+        self.crossfade_frames = frames.max(1);
+        self.crossfade_remaining = self.crossfade_frames;
+    }
+
+    /// Progress (0–1) of a running crossfade.
+    fn crossfade_position(&self) -> Option<f32> {
+        (self.crossfade_remaining > 0)
+            .then(|| 1.0 - self.crossfade_remaining as f32 / self.crossfade_frames as f32)
+    }
+
+    /// Filters render channel `output` after mixing, after any filter
+    /// added before.
+    pub(crate) fn with_filter(mut self, output: usize, filter: Biquad) -> Self {
+        if let Some(chain) = self.filters.get_mut(output) {
+            chain.push(filter);
+        }
+        self
+    }
+
+    /// Splits off the lows (subwoofer) or highs (satellite) of every render
+    /// channel at `crossover_hz`; full-range outputs are left untouched.
+    pub(crate) fn with_bass_role(
+        self,
+        role: BassRole,
+        crossover_hz: f32,
+        sample_rate: u32,
+    ) -> Self {
+        let crossover = match role {
+            BassRole::FullRange => return self,
+            BassRole::Satellite => linkwitz_riley_high,
+            BassRole::Subwoofer => linkwitz_riley_low,
+        };
+        (0..self.outputs).fold(self, |mixer, output| {
+            crossover(crossover_hz, sample_rate)
+                .into_iter()
+                .fold(mixer, |mixer, filter| mixer.with_filter(output, filter))
+        })
+    }
+
+    pub(crate) fn with_mid_side(mut self, mid_side: Option<MidSide>) -> Self {
+        self.mid_side = mid_side;
+        self
+    }
+
+    pub(crate) fn with_crossfeed(mut self, crossfeed: Option<Crossfeed>) -> Self {
+        self.crossfeed = crossfeed;
+        self
+    }
+
+    pub(crate) fn with_eq(mut self, eq: Option<GraphicEq>) -> Self {
+        self.eq = eq;
+        self
+    }
+
+    pub(crate) fn with_plugins(mut self, plugins: Vec<ClapPlugin>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Total delay the hosted plugins report, in frames.
+    pub(crate) fn plugin_latency_frames(&self) -> u32 {
+        self.plugins.iter().map(ClapPlugin::latency_frames).sum()
+    }
+
+    pub(crate) fn with_gain(mut self, gain: SmoothedGain) -> Self {
+        self.gain = Some(gain);
+        self
+    }
+
+    pub(crate) fn with_loudness(mut self, loudness: Option<LoudnessNormalizer>) -> Self {
+        self.loudness = loudness;
+        self
+    }
+
+    /// Sets the order of the DSP stages that follow the matrix and filters.
+    pub(crate) fn with_order(mut self, order: Vec<DspStage>) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub(crate) fn with_limiter(mut self, limiter: Option<Limiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    pub(crate) fn with_delay(mut self, delay: Option<DelayLine>) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Ramps the output gain towards `gain_db` over the next packets.
+    pub(crate) fn set_gain_db(&mut self, gain_db: f32) {
+        if let Some(gain) = &mut self.gain {
+            gain.set_target_db(gain_db);
+        }
+    }
+
+    /// Mixes whole input frames into `output` (cleared first, capacity reused).
+    pub(crate) fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        output.clear();
+        if self.inputs == 0 {
+            return;
+        }
+        let frames = input.len() / self.inputs;
+        output.resize(frames * self.outputs, 0.0);
+        for (src, dst) in input
+            .chunks_exact(self.inputs)
+            .zip(output.chunks_exact_mut(self.outputs))
+        {
+            if self.crossfade_remaining > 0 {
+                self.crossfade_remaining -= 1;
+                let t = 1.0 - self.crossfade_remaining as f32 / self.crossfade_frames as f32;
+                let rows = self
+                    .gains
+                    .chunks_exact(self.outputs)
+                    .zip(self.previous_gains.chunks_exact(self.outputs));
+                for (&sample, (row, old_row)) in src.iter().zip(rows) {
+                    for ((out, &gain), &old) in dst.iter_mut().zip(row).zip(old_row) {
+                        *out += sample * (old + (gain - old) * t);
+                    }
+                }
+            } else {
+                for (&sample, row) in src.iter().zip(self.gains.chunks_exact(self.outputs)) {
+                    for (out, &gain) in dst.iter_mut().zip(row) {
+                        *out += sample * gain;
+                    }
+                }
+            }
+            for (out, chain) in dst.iter_mut().zip(&mut self.filters) {
+                for filter in chain {
+                    *out = filter.process(*out);
+                }
+            }
+        }
+        for stage in &self.order {
+            match stage {
+                DspStage::MidSide => {
+                    if let Some(mid_side) = &self.mid_side {
+                        mid_side.process(output);
+                    }
+                }
+                DspStage::Crossfeed => {
+                    if let Some(crossfeed) = &mut self.crossfeed {
+                        crossfeed.process(output);
+                    }
+                }
+                DspStage::Eq => {
+                    if let Some(eq) = &mut self.eq {
+                        eq.process(output);
+                    }
+                }
+                DspStage::Plugins => {
+                    for plugin in &mut self.plugins {
+                        plugin.process(output);
+                    }
+                }
+                DspStage::Loudness => {
+                    if let Some(loudness) = &mut self.loudness {
+                        loudness.process(output);
+                    }
+                }
+                DspStage::Gain => {
+                    if let Some(gain) = &mut self.gain {
+                        gain.process(output);
+                    }
+                }
+                DspStage::Limiter => {
+                    if let Some(limiter) = &mut self.limiter {
+                        limiter.process(output);
+                    }
+                }
+            }
+        }
+        if let Some(delay) = &mut self.delay {
+            delay.process(output);
+        }
+    }
+}
+
+/// `matrix` as an input-major gain list of shape `inputs`×`outputs`, with
+/// the gains of render channel `o` multiplied by `output_scale[o]`.
+fn flatten_gains(
+    matrix: &ChannelMatrix,
+    inputs: usize,
+    outputs: usize,
+    output_scale: &[f32],
+) -> Vec<f32> {
+    let mut gains: Vec<f32> = (0..inputs)
+        .flat_map(|i| (0..outputs).map(move |o| (i, o)))
+        .map(|(i, o)| matrix.gain(i, o))
+        .collect();
+    scale_outputs(&mut gains, outputs, output_scale);
+    gains
+}
+
+fn scale_outputs(gains: &mut [f32], outputs: usize, scale: &[f32]) {
+    for row in gains.chunks_exact_mut(outputs.max(1)) {
+        for (gain, &factor) in row.iter_mut().zip(scale) {
+            *gain *= factor;
+        }
+    }
+}
+
+/// Modes that keep the capture layout and differ only in how the front pair
+/// is mapped, so a running output can switch between them without
+/// reinitializing the device.
+pub(crate) fn keeps_layout(mode: ChannelMode) -> bool {
+    !matches!(
+        mode,
+        ChannelMode::Downmix | ChannelMode::Upmix | ChannelMode::Matrix
+    )
+}
+
+/// The fixed channel modes as a gain matrix, for outputs that need the f32
+/// path anyway (e.g. because of an EQ). Like the raw-format path, the mode
+/// acts on the front pair and every other channel passes through; modes that
+/// change the layout map to identity here.
+pub(crate) fn mode_matrix(mode: ChannelMode, channels: usize) -> ChannelMatrix {
+    let mut matrix = ChannelMatrix::identity(channels);
+    if channels < 2 {
+        return matrix;
+    }
+    // (L→L, L→R, R→L, R→R)
+    let (ll, lr, rl, rr) = match mode {
+        ChannelMode::LeftMono => (1.0, 1.0, 0.0, 0.0),
+        ChannelMode::RightMono => (0.0, 0.0, 1.0, 1.0),
+        ChannelMode::Mono => (0.5, 0.5, 0.5, 0.5),
+        ChannelMode::Swap => (0.0, 1.0, 1.0, 0.0),
+        ChannelMode::LeftOnly => (1.0, 0.0, 0.0, 0.0),
+        ChannelMode::RightOnly => (0.0, 0.0, 0.0, 1.0),
+        ChannelMode::Stereo | ChannelMode::Downmix | ChannelMode::Upmix | ChannelMode::Matrix => {
+            return matrix;
+        }
+    };
+    matrix.0[0][0] = ll;
+    matrix.0[0][1] = lr;
+    matrix.0[1][0] = rl;
+    matrix.0[1][1] = rr;
+    matrix
+}
+
+/// ITU-R BS.1770 weight of each channel for loudness measurement:
+/// surrounds count +1.5 dB, the LFE is not measured.
+pub(crate) fn loudness_weights(mask: u32, channels: usize) -> Vec<f32> {
+    channel_speakers(mask, channels)
+        .into_iter()
+        .map(|speaker| match speaker {
+            SPEAKER_LOW_FREQUENCY => 0.0,
+            SPEAKER_BACK_LEFT | SPEAKER_BACK_RIGHT | SPEAKER_SIDE_LEFT | SPEAKER_SIDE_RIGHT => 1.41,
+            _ => 1.0,
+        })
+        .collect()
+}
+
+/// Feeds every render channel of a subwoofer output with all capture channels
+/// summed to mono; the low-pass is added by [`ChannelMixer::with_bass_role`].
+/// The LFE channel is added at unity, the others share unity gain between them
+/// so fully correlated bass keeps its level.
+pub(crate) fn subwoofer_matrix(mask: u32, inputs: usize, outputs: usize) -> ChannelMatrix {
+    let speakers = channel_speakers(mask, inputs);
+    let main = speakers
+        .iter()
+        .filter(|&&speaker| speaker != SPEAKER_LOW_FREQUENCY)
+        .count()
+        .max(1);
+    let rows = speakers
+        .into_iter()
+        .map(|speaker| {
+            let gain = if speaker == SPEAKER_LOW_FREQUENCY {
+                1.0
+            } else {
+                1.0 / main as f32
+            };
+            vec![gain; outputs]
+        })
+        .collect();
+    ChannelMatrix(rows)
+}
+
+/// Stereo → surround upmix for a render layout given by `mask`:
+/// L/R stay on the fronts, the difference signal (ambience) goes to the
+/// surrounds in opposite polarity, and the mono sum feeds the center
+/// (high-passed) and the LFE (low-passed) at the crossover frequency.
+/// The derived channels use the upmix levels from `levels` (0.5 by default).
+pub(crate) fn upmix_mixer(
+    mask: u32,
+    outputs: usize,
+    sample_rate: u32,
+    levels: &MixLevels,
+) -> ChannelMixer {
+    let speakers = channel_speakers(mask, outputs);
+    let center = levels.upmix_center;
+    let surround = levels.upmix_surround;
+    let lfe = levels.upmix_lfe;
+    let mut rows = vec![vec![0.0; outputs]; 2];
+    for (output, &speaker) in speakers.iter().enumerate() {
+        let (left, right) = match speaker {
+            SPEAKER_FRONT_LEFT => (1.0, 0.0),
+            SPEAKER_FRONT_RIGHT => (0.0, 1.0),
+            SPEAKER_FRONT_CENTER => (center, center),
+            SPEAKER_LOW_FREQUENCY => (lfe, lfe),
+            SPEAKER_BACK_LEFT | SPEAKER_SIDE_LEFT => (surround, -surround),
+            SPEAKER_BACK_RIGHT | SPEAKER_SIDE_RIGHT => (-surround, surround),
+            _ => (0.0, 0.0),
+        };
+        rows[0][output] = left;
+        rows[1][output] = right;
+    }
+
+    let mut mixer = ChannelMixer::from_matrix(&ChannelMatrix(rows), 2, outputs);
+    for (output, &speaker) in speakers.iter().enumerate() {
+        match speaker {
+            SPEAKER_FRONT_CENTER => {
+                mixer =
+                    mixer.with_filter(output, Biquad::high_pass(UPMIX_CROSSOVER_HZ, sample_rate));
+            }
+            SPEAKER_LOW_FREQUENCY => {
+                mixer =
+                    mixer.with_filter(output, Biquad::low_pass(UPMIX_CROSSOVER_HZ, sample_rate));
+            }
+            _ => {}
+        }
+    }
+    mixer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn matrix_routes_stereo_to_five_channels_with_center_sum() {
+        // L→0+2, R→1+3, (L+R)*0.5→4
+        let matrix = ChannelMatrix(vec![
+            vec![1.0, 0.0, 1.0, 0.0, 0.5],
+            vec![0.0, 1.0, 0.0, 1.0, 0.5],
+        ]);
+        let mut mixer = ChannelMixer::from_matrix(&matrix, 2, 5);
+
+        let mut out = Vec::new();
+        mixer.process(&[0.8, 0.2, -0.4, 0.6], &mut out);
+        assert_close(&out, &[0.8, 0.2, 0.8, 0.2, 0.5, -0.4, 0.6, -0.4, 0.6, 0.1]);
+    }
+
+    #[test]
+    fn downmixes_5_1_with_itu_coefficients() {
+        let g = std::f32::consts::FRAC_1_SQRT_2;
+        // FL FR C LFE BL BR
+        let frame = [0.1, 0.2, 0.4, 0.8, 0.3, 0.5];
+
+        let mut mixer =
+            ChannelMixer::from_matrix(&downmix_matrix(0x3F, 6, false, &MixLevels::default()), 6, 2);
+        let mut out = Vec::new();
+        mixer.process(&frame, &mut out);
+        assert_close(&out, &[0.1 + 0.4 * g + 0.3 * g, 0.2 + 0.4 * g + 0.5 * g]);
+
+        let mut mixer =
+            ChannelMixer::from_matrix(&downmix_matrix(0x3F, 6, true, &MixLevels::default()), 6, 2);
+        mixer.process(&frame, &mut out);
+        assert_close(&out, &[0.1 + 1.2 * g + 0.3 * g, 0.2 + 1.2 * g + 0.5 * g]);
+    }
+
+    #[test]
+    fn downmixes_7_1_side_and_back_channels() {
+        let levels = MixLevels::default();
+        let matrix = downmix_matrix(default_channel_mask(8), 8, false, &levels);
+        assert_eq!(matrix.inputs(), 8);
+        // SL / SR 分别只进入左 / 右声道
+        assert_eq!(matrix.0[6], vec![levels.downmix_surround, 0.0]);
+        assert_eq!(matrix.0[7], vec![0.0, levels.downmix_surround]);
+    }
+
+    #[test]
+    fn custom_mix_levels_replace_defaults() {
+        let levels = MixLevels {
+            downmix_center: 1.0,
+            downmix_lfe: 0.25,
+            upmix_surround: 0.0,
+            ..MixLevels::default()
+        };
+        let matrix = downmix_matrix(0x3F, 6, true, &levels);
+        assert_eq!(matrix.0[2], vec![1.0, 1.0]);
+        assert_eq!(matrix.0[3], vec![0.25, 0.25]);
+
+        let mut mixer = upmix_mixer(0x3F, 6, 48_000, &levels);
+        let mut out = Vec::new();
+        mixer.process(&[0.4, 0.0], &mut out);
+        assert_close(&out[4..], &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn upmix_sends_ambience_to_rears_and_bass_to_lfe() {
+        // 5.1：FL FR C LFE BL BR
+        let mut mixer = upmix_mixer(0x3F, 6, 48_000, &MixLevels::default());
+
+        // 左右相同的直流信号：无环绕成分，中置被高通滤除，LFE 完整通过
+        let input = vec![0.5_f32; 2 * 4800];
+        let mut out = Vec::new();
+        mixer.process(&input, &mut out);
+        let last = &out[out.len() - 6..];
+        assert_close(&last[..2], &[0.5, 0.5]);
+        assert!(last[2].abs() < 1e-3, "center {}", last[2]);
+        assert!((last[3] - 0.5).abs() < 1e-3, "lfe {}", last[3]);
+        assert_close(&last[4..], &[0.0, 0.0]);
+
+        // 只有左声道：环绕声道反相
+        let mut mixer = upmix_mixer(default_channel_mask(8), 8, 48_000, &MixLevels::default());
+        mixer.process(&[0.4, 0.0], &mut out);
+        assert_close(&out[4..], &[0.2, -0.2, 0.2, -0.2]);
+    }
+
+    #[test]
+    fn mode_matrix_matches_raw_channel_modes() {
+        let mut out = Vec::new();
+        for (mode, expected) in [
+            (ChannelMode::Mono, [0.5, 0.5, 0.9]),
+            (ChannelMode::Swap, [0.2, 0.8, 0.9]),
+            (ChannelMode::RightOnly, [0.0, 0.2, 0.9]),
+        ] {
+            let mut mixer = ChannelMixer::from_matrix(&mode_matrix(mode, 3), 3, 3);
+            mixer.process(&[0.8, 0.2, 0.9], &mut out);
+            assert_close(&out, &expected);
+        }
+    }
+
+    #[test]
+    fn phase_invert_flips_selected_channels() {
+        let mixer =
+            ChannelMixer::from_matrix(&downmix_matrix(0x3F, 6, false, &MixLevels::default()), 6, 2);
+        let mut mixer = mixer.with_phase_invert(0b10);
+        let mut out = Vec::new();
+        mixer.process(&[0.5, 0.25, 0.0, 0.0, 0.0, 0.0], &mut out);
+        assert_close(&out, &[0.5, -0.25]);
+    }
+
+    #[test]
+    fn subwoofer_sums_lows_of_all_channels() {
+        let matrix = subwoofer_matrix(0x3F, 6, 2);
+        assert_eq!(matrix.0[0], vec![0.2, 0.2]);
+        assert_eq!(matrix.0[3], vec![1.0, 1.0]);
+
+        let mut mixer = ChannelMixer::from_matrix(&subwoofer_matrix(0x3, 2, 2), 2, 2)
+            .with_bass_role(BassRole::Subwoofer, 80.0, 48_000);
+        let mut out = Vec::new();
+        // 1 kHz 只在左声道：经过低通后几乎无输出
+        let input: Vec<f32> = (0..4_800)
+            .flat_map(|i| {
+                [
+                    (std::f32::consts::TAU * 1_000.0 * i as f32 / 48_000.0).sin(),
+                    0.0,
+                ]
+            })
+            .collect();
+        mixer.process(&input, &mut out);
+        assert!(out[4_800..].iter().all(|s| s.abs() < 0.01));
+        // 直流（低频极限）在两路输出上等于两声道的平均
+        mixer.process(&[0.6, 0.2].repeat(9_600), &mut out);
+        assert!(out[out.len() - 2..].iter().all(|s| (s - 0.4).abs() < 1e-3));
+    }
+
+    #[test]
+    fn undersized_matrix_is_padded_with_silence() {
+        let mut mixer = ChannelMixer::from_matrix(&ChannelMatrix(vec![vec![0.5]]), 2, 2);
+
+        let mut out = Vec::new();
+        mixer.process(&[0.8, 0.2], &mut out);
+        assert_close(&out, &[0.4, 0.0]);
+    }
+
+    #[test]
+    fn mode_change_crossfades_and_keeps_phase_invert() {
+        let mut mixer = ChannelMixer::from_matrix(&mode_matrix(ChannelMode::Stereo, 2), 2, 2)
+            .with_phase_invert(0b10);
+        mixer.crossfade_to(&mode_matrix(ChannelMode::Swap, 2), 4);
+
+        let mut out = Vec::new();
+        mixer.process(&[1.0, 0.0].repeat(6), &mut out);
+        // L 从左声道逐渐移到（反相的）右声道
+        assert_close(
+            &out,
+            &[
+                0.75, -0.25, 0.5, -0.5, 0.25, -0.75, 0.0, -1.0, 0.0, -1.0, 0.0, -1.0,
+            ],
+        );
+        assert!(keeps_layout(ChannelMode::Swap));
+        assert!(!keeps_layout(ChannelMode::Downmix));
+    }
+
+    #[test]
+    fn channel_trim_scales_render_channels_after_mixing() {
+        let mut mixer = ChannelMixer::from_matrix(&ChannelMatrix::identity(4), 4, 4)
+            .with_phase_invert(0b100)
+            .with_channel_trim(&[0.0, -6.0206, 6.0206]);
+        let mut out = Vec::new();
+        mixer.process(&[0.5, 0.5, 0.25, 0.5], &mut out);
+        assert_close(&out, &[0.5, 0.25, -0.5, 0.5]);
+    }
+
+    #[test]
+    fn stages_run_in_configured_order() {
+        let limiter = crate::dsp::LimiterSettings {
+            enabled: true,
+            threshold_db: -6.0,
+            release_ms: 50.0,
+        };
+        let mixer = |order: Vec<DspStage>| {
+            ChannelMixer::from_matrix(&ChannelMatrix::identity(1), 1, 1)
+                .with_gain(SmoothedGain::new(12.0, 1, 48_000))
+                .with_limiter(Limiter::new(&limiter, 1, 48_000))
+                .with_order(order)
+        };
+        let peak = |mut mixer: ChannelMixer| {
+            let mut out = Vec::new();
+            mixer.process(&[0.4; 480], &mut out);
+            out.iter().fold(0.0_f32, |m, s| m.max(s.abs()))
+        };
+        // 默认顺序：先增益后限幅
+        assert!(peak(mixer(DspStage::DEFAULT_ORDER.to_vec())) <= 0.502);
+        assert!(peak(mixer(vec![DspStage::Limiter, DspStage::Gain])) > 1.0);
+    }
+}
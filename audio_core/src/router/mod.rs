@@ -3,20 +3,60 @@
 //! Handles routing audio from a source device to target devices with configurable
 //! channel mixing modes.
 
+mod affinity;
 mod config;
+mod control;
+pub(crate) mod mixer;
+mod priority;
+mod spectrum;
 mod state;
+mod stats;
+pub(crate) mod tap;
 mod worker;
 
-pub use config::{ChannelMode, RouterConfig, RouterTarget};
+pub use affinity::ThreadAffinity;
+pub use config::{
+    ChannelMatrix, ChannelMode, ChannelTrim, EngineMode, EngineSettings, MixLevels, OverflowPolicy,
+    RouterConfig, RouterTarget, SourceRole,
+};
+pub(crate) use control::OutputControl;
+pub use control::RouterControls;
+pub use priority::ThreadPriority;
+pub use spectrum::{SPECTRUM_FFT_SIZE, SPECTRUM_FRAMES_PER_SEC, SpectrumFrame};
 pub use state::RouterState;
+pub use stats::{
+    ChannelLevels, LEVEL_FLOOR_DB, OutputLatency, OutputLevels, OutputStatus, RouterLevels,
+    RouterMeters, RouterPerformance, RouterStats, RouterStatsSnapshot, RouterStatus,
+};
+pub(crate) use stats::{LatencySlots, LevelSlots, OutputStats, StreamLatency};
 pub use worker::WorkerEvent;
 
+use crate::dsp::PINK_NOISE_LEVEL_RANGE_DB;
 use anyhow::{Result, anyhow};
 use parking_lot::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, mpsc};
 use std::thread;
 use std::time::Duration;
 
+/// How long the worker may take to open its streams before start gives up.
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Extra readiness time per enabled plugin: the worker instantiates and
+/// activates plugins before it reports ready, and loading a DLL can be slow.
+const PLUGIN_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Readiness deadline for `cfg`, covering plugin loading on the worker.
+fn ready_timeout(cfg: &RouterConfig) -> Duration {
+    let plugins = cfg
+        .targets
+        .iter()
+        .flat_map(|target| &target.plugins)
+        .filter(|slot| slot.enabled)
+        .count() as u32;
+    READY_TIMEOUT + PLUGIN_LOAD_TIMEOUT * plugins
+}
+
 /// Main router interface for audio routing operations.
 #[derive(Debug, Clone)]
 pub struct Router {
@@ -34,6 +74,8 @@ impl Router {
     /// Starts routing with a callback to receive captured PCM frames.
     ///
     /// This spawns a worker thread that runs WASAPI capture+render operations.
+    /// The callback runs on a separate tap thread fed through a lock-free ring,
+    /// so a slow callback drops tap data instead of stalling the audio path.
     ///
     /// # Arguments
     /// * `cfg` - Routing configuration
@@ -45,6 +87,28 @@ impl Router {
     where
         F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
     {
+        self.spawn_worker(cfg, Some(cb))
+    }
+
+    /// Starts routing without a PCM callback.
+    ///
+    /// Without a callback, and while nobody reads [`Self::levels`], the worker
+    /// only builds an f32 copy of the captured audio for outputs that need
+    /// processing; outputs whose format matches the capture format are fed
+    /// directly from the capture buffer.
+    /// Prefer `start_with_callback` if you need to process the audio frames.
+    pub fn start(&self, cfg: RouterConfig) -> Result<()> {
+        self.spawn_worker::<fn(&[f32], u32, u16)>(cfg, None)
+    }
+
+    fn spawn_worker<F>(&self, cfg: RouterConfig, cb: Option<Arc<F>>) -> Result<()>
+    where
+        F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
+    {
+        let stats = Arc::new(RouterStats::default());
+        let metering = Arc::clone(&self.inner.read().metering);
+        let controls = Arc::new(RouterControls::new(metering));
+        let spectrum_pending = Arc::new(AtomicUsize::new(0));
         {
             let mut st = self.inner.write();
             if st.running {
@@ -52,18 +116,52 @@ impl Router {
             }
             st.running = true;
             st.cfg = cfg.clone();
+            st.stats = Arc::clone(&stats);
+            st.controls = Arc::clone(&controls);
+            st.spectrum_pending = Arc::clone(&spectrum_pending);
         }
 
         let (stop_tx, stop_rx) = mpsc::channel();
         let (ready_tx, ready_rx) = mpsc::channel();
         let (event_tx, event_rx) = mpsc::channel();
         let cfg_for_worker = cfg.clone();
+        let ready_timeout = ready_timeout(&cfg);
+
+        let (pcm, tap_join) = match cb {
+            Some(cb) => {
+                let (producer, consumer) = tap::tap_channel(tap::TAP_CAPACITY_SAMPLES);
+                (Some(producer), Some(tap::spawn_tap_thread(consumer, cb)))
+            }
+            None => (None, None),
+        };
+        let (spectrum, spectrum_join) = if cfg.spectrum {
+            let (producer, consumer) = tap::tap_channel(tap::TAP_CAPACITY_SAMPLES);
+            let join =
+                spectrum::spawn_spectrum_thread(consumer, event_tx.clone(), spectrum_pending);
+            (Some(producer), Some(join))
+        } else {
+            (None, None)
+        };
+        {
+            let mut st = self.inner.write();
+            st.tap_join = tap_join;
+            st.spectrum_join = spectrum_join;
+        }
+        let taps = tap::Taps { pcm, spectrum };
 
         let handle = thread::spawn(move || {
-            worker::run_worker(cfg_for_worker, cb, stop_rx, ready_tx, event_tx)
+            worker::run_worker(
+                cfg_for_worker,
+                taps,
+                stats,
+                controls,
+                stop_rx,
+                ready_tx,
+                event_tx,
+            )
         });
 
-        match ready_rx.recv_timeout(Duration::from_secs(5)) {
+        match ready_rx.recv_timeout(ready_timeout) {
             Ok(Ok(())) => {
                 let mut st = self.inner.write();
                 st.worker_stop_tx = Some(stop_tx);
@@ -82,6 +180,12 @@ impl Router {
             }
             Err(mpsc::RecvTimeoutError::Timeout) => {
                 let _ = stop_tx.send(());
+                // 卡住的 worker 可能仍在加载插件，在后台回收，不阻塞调用方
+                thread::spawn(move || {
+                    if let Err(panic) = handle.join() {
+                        log::error!("Timed-out router worker panicked: {panic:?}");
+                    }
+                });
                 self.reset_state();
                 Err(anyhow!("router worker did not report readiness in time"))
             }
@@ -97,14 +201,6 @@ impl Router {
         }
     }
 
-    /// Starts routing with a no-op callback.
-    ///
-    /// Prefer `start_with_callback` if you need to process the audio frames.
-    pub fn start(&self, cfg: RouterConfig) -> Result<()> {
-        let noop = Arc::new(|_samples: &[f32], _sr: u32, _ch: u16| {});
-        self.start_with_callback(cfg, noop)
-    }
-
     /// Stops the router and waits for the worker thread to exit.
     ///
     /// # Errors
@@ -117,6 +213,10 @@ impl Router {
             }
             (st.worker_stop_tx.take(), st.worker_join.take())
         };
+        let (tap_join, spectrum_join) = {
+            let mut st = self.inner.write();
+            (st.tap_join.take(), st.spectrum_join.take())
+        };
 
         if let Some(tx) = tx {
             let _ = tx.send(());
@@ -132,6 +232,14 @@ impl Router {
             Ok(())
         };
 
+        // worker 退出后 tap 生产端已释放，tap 线程送完剩余数据即退出
+        if let Some(tap_join) = tap_join {
+            let _ = tap_join.join();
+        }
+        if let Some(spectrum_join) = spectrum_join {
+            let _ = spectrum_join.join();
+        }
+
         self.reset_state();
 
         result?;
@@ -139,26 +247,197 @@ impl Router {
         Ok(())
     }
 
+    /// Fades every output to silence over `fade_out_ms` without blocking.
+    ///
+    /// The worker sends [`WorkerEvent::FadedOut`] once the fade has played
+    /// out; the outputs then stay silent until [`Router::stop`]. Returns
+    /// `false` if the router is not running.
+    pub fn fade_out(&self, fade_out_ms: f32) -> bool {
+        let st = self.inner.read();
+        if st.running {
+            st.controls.request_fade_out(fade_out_ms);
+        }
+        st.running
+    }
+
+    /// Changes the gain of a running output, ramping to it over ~20 ms.
+    ///
+    /// Returns `false` if the output is not running or cannot change gain
+    /// live (it was started at 0 dB without other DSP); restart the router
+    /// with an updated config in that case.
+    pub fn set_output_gain(&self, device_id: &str, gain_db: f32) -> bool {
+        let st = self.inner.read();
+        st.running && st.controls.set_gain_db(device_id, gain_db)
+    }
+
+    /// Mutes or unmutes a running output without restarting the session.
+    ///
+    /// Like [`Router::set_output_gain`] this only works for outputs with a
+    /// gain stage, which is every output whose config has `muted` set.
+    /// Returns `false` otherwise.
+    pub fn set_output_muted(&self, device_id: &str, muted: bool) -> bool {
+        let st = self.inner.read();
+        st.running && st.controls.set_muted(device_id, muted)
+    }
+
+    /// Sets a gain added to every output's own gain, ramping like
+    /// [`Router::set_output_gain`]; it resets to 0 dB when a session starts.
+    ///
+    /// Only outputs with a gain stage follow it, which is every output when
+    /// [`RouterConfig::mirror_source_volume`] is set. Returns `false` if the
+    /// router is not running.
+    pub fn set_master_gain(&self, gain_db: f32) -> bool {
+        let st = self.inner.read();
+        if st.running {
+            st.controls.set_master_gain_db(gain_db);
+        }
+        st.running
+    }
+
+    /// Switches the channel mode of a running output with a short crossfade
+    /// instead of a restart.
+    ///
+    /// Only outputs mixed in f32 (e.g. because they have DSP enabled) can
+    /// switch live, and only between modes that keep the channel layout
+    /// (not Downmix, Upmix or Matrix). Returns `false` otherwise; restart the
+    /// router with an updated config in that case.
+    pub fn set_output_mode(&self, device_id: &str, mode: ChannelMode) -> bool {
+        let st = self.inner.read();
+        st.running && st.controls.set_mode(device_id, mode)
+    }
+
+    /// Switches a running session to capture from `device_id` without
+    /// stopping the router. The worker reopens its devices in place, keeping
+    /// the live output settings (gain, mute, mode), taps and statistics.
+    ///
+    /// The switch happens asynchronously; the worker reports the outcome with
+    /// [`WorkerEvent::SourceSwitched`] or, having gone back to the previous
+    /// source, [`WorkerEvent::SourceSwitchFailed`]. `device_id` must not be
+    /// one of the outputs. Returns `false` if the router is not running.
+    pub fn switch_source(&self, device_id: &str) -> bool {
+        let st = self.inner.read();
+        if st.running {
+            st.controls.request_source_switch(device_id);
+        }
+        st.running
+    }
+
+    /// Plays calibrated pink noise at `level_db` dBFS RMS on output
+    /// `device_id` in place of the captured signal, for level matching.
+    /// Any other output playing noise goes back to the captured signal.
+    ///
+    /// The level is clamped to [`PINK_NOISE_LEVEL_RANGE_DB`]. Outputs mixed
+    /// in f32 apply their gain, trim and DSP to the noise; the others play it
+    /// as is. Returns `false` if the output is not running.
+    pub fn play_pink_noise(&self, device_id: &str, level_db: f32) -> bool {
+        let level_db = level_db.clamp(
+            *PINK_NOISE_LEVEL_RANGE_DB.start(),
+            *PINK_NOISE_LEVEL_RANGE_DB.end(),
+        );
+        let st = self.inner.read();
+        st.running && st.controls.play_pink_noise(device_id, level_db)
+    }
+
+    /// Stops the pink noise started by [`Router::play_pink_noise`].
+    pub fn stop_pink_noise(&self) {
+        self.inner.read().controls.stop_pink_noise();
+    }
+
     /// Returns whether the router is currently running.
     pub fn is_running(&self) -> bool {
         self.inner.read().running
     }
 
+    /// Returns the statistics of the current session.
+    ///
+    /// Counters are kept after the router stops so the last session can
+    /// still be inspected; they reset when the next session starts.
+    pub fn stats(&self) -> RouterStatsSnapshot {
+        self.inner.read().stats.snapshot()
+    }
+
+    /// Returns the live meters of the captured signal (e.g. the stereo
+    /// correlation, to spot content that cancels on a mono speaker).
+    ///
+    /// Starts metering like [`Self::levels`].
+    pub fn meters(&self) -> RouterMeters {
+        self.start_metering();
+        self.inner.read().stats.meters()
+    }
+
+    /// Returns the peak and RMS levels of the captured signal and of what
+    /// each running output is sent, over the last meter window (see
+    /// [`RouterConfig::meter_window_ms`]). Meant to be polled by the UI.
+    ///
+    /// The worker only meters once levels have been read, so the first call
+    /// reports silence; metering continues, also across sessions, until
+    /// [`Self::stop_metering`].
+    pub fn levels(&self) -> RouterLevels {
+        self.start_metering();
+        self.inner.read().stats.levels()
+    }
+
+    /// Makes the worker meter the source and every output, so levels read
+    /// by a consumer that starts later are already filled in.
+    pub fn start_metering(&self) {
+        self.inner.read().metering.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops metering started by [`Self::levels`] or [`Self::meters`], so
+    /// the worker no longer converts every packet for the meters. Levels read
+    /// silent afterwards.
+    pub fn stop_metering(&self) {
+        self.inner.read().metering.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns the approximate latency the router adds on the way to each
+    /// running output, split into capture, processing, render buffer and
+    /// engine parts. Empty while the router is stopped.
+    pub fn latency(&self) -> Vec<OutputLatency> {
+        self.inner.read().stats.latency()
+    }
+
+    /// Returns whether the router is running together with its statistics,
+    /// processing cost (per-packet time, duty cycle) and per-output underruns.
+    pub fn status(&self) -> RouterStatus {
+        let st = self.inner.read();
+        RouterStatus {
+            running: st.running,
+            stats: st.stats.snapshot(),
+            performance: st.stats.performance(),
+            meters: st.stats.meters(),
+        }
+    }
+
     /// 轮询 worker 事件。应定期调用（如 GUI 定时器）以同步状态。
     ///
     /// 返回所有待处理的事件。如果 worker 已退出（Failed 事件之后），
-    /// 会自动清理 running 状态。
+    /// 会自动清理 running 状态；未发送 Failed 就退出时追加一个
+    /// [`WorkerEvent::Exited`]。
     pub fn poll_events(&self) -> Vec<WorkerEvent> {
         let mut events = Vec::new();
         let mut should_reset = false;
+        let mut disconnected = false;
 
         {
             let st = self.inner.read();
             if let Some(rx) = &st.worker_event_rx {
                 if let Ok(rx) = rx.lock() {
-                    while let Ok(ev) = rx.try_recv() {
-                        if matches!(ev, WorkerEvent::Failed(_)) {
-                            should_reset = true;
+                    loop {
+                        let ev = match rx.try_recv() {
+                            Ok(ev) => ev,
+                            Err(mpsc::TryRecvError::Empty) => break,
+                            Err(mpsc::TryRecvError::Disconnected) => {
+                                disconnected = true;
+                                break;
+                            }
+                        };
+                        match ev {
+                            WorkerEvent::Failed(_) => should_reset = true,
+                            WorkerEvent::Spectrum(_) => {
+                                st.spectrum_pending.fetch_sub(1, Ordering::Relaxed);
+                            }
+                            _ => {}
                         }
                         events.push(ev);
                     }
@@ -166,6 +445,10 @@ impl Router {
             }
         }
 
+        if disconnected && !should_reset {
+            events.push(WorkerEvent::Exited(self.join_exited_worker()));
+            should_reset = true;
+        }
         if should_reset {
             self.reset_state();
         }
@@ -173,6 +456,23 @@ impl Router {
         events
     }
 
+    /// 等待已断开事件通道的 worker 线程结束，返回其退出原因。
+    fn join_exited_worker(&self) -> String {
+        let handle = self.inner.write().worker_join.take();
+        match handle.map(thread::JoinHandle::join) {
+            Some(Ok(Err(e))) => format!("{e:#}"),
+            Some(Err(panic)) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".into());
+                format!("router worker panicked: {message}")
+            }
+            Some(Ok(Ok(()))) | None => "router worker exited unexpectedly".into(),
+        }
+    }
+
     fn reset_state(&self) {
         let mut st = self.inner.write();
         st.running = false;
@@ -180,6 +480,9 @@ impl Router {
         st.worker_stop_tx = None;
         st.worker_join = None;
         st.worker_event_rx = None;
+        // tap/频谱线程在 worker 退出后自行结束，这里不等待
+        st.tap_join = None;
+        st.spectrum_join = None;
     }
 }
 
@@ -196,6 +499,76 @@ mod tests {
     use std::time::Duration;
     use tokio::time::sleep;
 
+    #[test]
+    fn reading_levels_turns_metering_on_for_every_session() {
+        let router = Router::new();
+        let controls = RouterControls::new(Arc::clone(&router.inner.read().metering));
+        assert!(!controls.metering());
+
+        router.levels();
+        assert!(controls.metering());
+        let next_session = RouterControls::new(Arc::clone(&router.inner.read().metering));
+        assert!(next_session.metering());
+
+        router.stop_metering();
+        assert!(!controls.metering());
+    }
+
+    #[test]
+    fn fade_out_is_only_requested_while_running() {
+        let router = Router::new();
+        assert!(!router.fade_out(150.0));
+        let controls = router.inner.read().controls.clone();
+        assert_eq!(controls.take_fade_out(), None);
+    }
+
+    #[test]
+    fn readiness_waits_longer_for_enabled_plugins() {
+        let mut output = ::config::config::Output::new("out".into());
+        output.plugins = vec![
+            crate::plugin::PluginSlot::default(),
+            crate::plugin::PluginSlot {
+                enabled: false,
+                ..Default::default()
+            },
+        ];
+        let mut cfg = RouterConfig::default();
+        assert_eq!(ready_timeout(&cfg), READY_TIMEOUT);
+
+        cfg.targets = vec![RouterTarget::from_output(
+            &output,
+            &EngineSettings::default(),
+        )];
+        assert_eq!(ready_timeout(&cfg), READY_TIMEOUT + PLUGIN_LOAD_TIMEOUT);
+    }
+
+    #[test]
+    fn worker_that_dies_silently_is_reported() {
+        let router = Router::new();
+        let (event_tx, event_rx) = mpsc::channel();
+        let handle = thread::spawn(move || -> Result<()> {
+            let _tx: mpsc::Sender<WorkerEvent> = event_tx;
+            panic!("boom");
+        });
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        {
+            let mut st = router.inner.write();
+            st.running = true;
+            st.worker_join = Some(handle);
+            st.worker_event_rx = Some(std::sync::Mutex::new(event_rx));
+        }
+
+        let events = router.poll_events();
+        assert!(
+            matches!(&events[..], [WorkerEvent::Exited(reason)] if reason.contains("boom")),
+            "{events:?}"
+        );
+        assert!(!router.is_running());
+        assert!(router.poll_events().is_empty());
+    }
+
     #[tokio::test]
     #[ignore = "requires real Windows audio devices"]
     async fn test_clone_default_to_all_outputs() {
@@ -234,8 +607,27 @@ mod tests {
                 .map(|device_id| RouterTarget {
                     device_id,
                     channel_mode: ChannelMode::Stereo,
+                    overflow_policy: OverflowPolicy::default(),
+                    channel_matrix: None,
+                    downmix_lfe: false,
+                    eq_preset: Default::default(),
+                    gain_db: 0.0,
+                    delay_ms: 0.0,
+                    muted: false,
+                    phase_invert: 0,
+                    channel_trim: Default::default(),
+                    bass_role: Default::default(),
+                    limiter: Default::default(),
+                    loudness: Default::default(),
+                    crossfeed: Default::default(),
+                    mid_side: Default::default(),
+                    dither: Default::default(),
+                    resampler: Default::default(),
+                    plugins: Default::default(),
+                    dsp_chain: Default::default(),
                 })
                 .collect(),
+            ..Default::default()
         };
 
         let router = Router::new();
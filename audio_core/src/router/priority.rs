@@ -0,0 +1,58 @@
+//! Scheduling priority of the router worker thread.
+//!
+//! `ProAudio` registers the thread with the Multimedia Class Scheduler
+//! Service, which boosts it above normal applications while keeping a small
+//! share of CPU for the rest of the system. The plain Win32 priorities are
+//! for systems where MMCSS is disabled.
+
+pub use ::config::config::ThreadPriority;
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Threading::{
+    AvRevertMmThreadCharacteristics, AvSetMmThreadCharacteristicsW, GetCurrentThread,
+    SetThreadPriority, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_TIME_CRITICAL,
+};
+
+/// Keeps the MMCSS registration of the current thread; reverted on drop.
+pub(crate) struct PriorityGuard(Option<HANDLE>);
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.take() {
+            unsafe {
+                AvRevertMmThreadCharacteristics(handle);
+            }
+        }
+    }
+}
+
+/// Applies `priority` to the calling thread. Failures are logged and ignored
+/// so routing still runs at normal priority.
+pub(crate) fn apply_to_current_thread(priority: ThreadPriority) -> PriorityGuard {
+    let level = match priority {
+        ThreadPriority::Normal => return PriorityGuard(None),
+        ThreadPriority::High => THREAD_PRIORITY_HIGHEST,
+        ThreadPriority::TimeCritical => THREAD_PRIORITY_TIME_CRITICAL,
+        ThreadPriority::ProAudio => {
+            let mut task_index = 0;
+            return match unsafe {
+                AvSetMmThreadCharacteristicsW(windows::w!("Pro Audio"), &mut task_index)
+            } {
+                Ok(handle) => {
+                    log::info!("Router worker registered with MMCSS as Pro Audio");
+                    PriorityGuard(Some(handle))
+                }
+                Err(e) => {
+                    log::warn!("Failed to register with MMCSS, using normal priority: {e:?}");
+                    PriorityGuard(None)
+                }
+            };
+        }
+    };
+    if unsafe { SetThreadPriority(GetCurrentThread(), level) }.as_bool() {
+        log::info!("Router worker priority set to {priority:?}");
+    } else {
+        log::warn!("Failed to set router worker priority to {priority:?}");
+    }
+    PriorityGuard(None)
+}
@@ -0,0 +1,187 @@
+//! Spectrum analysis tap: FFT magnitudes of the captured signal, published as
+//! worker events.
+//!
+//! Like the PCM tap, the worker only pushes into a lock-free ring; the FFT
+//! runs on its own thread, so analysis can never stall the audio path.
+
+use super::stats::LEVEL_FLOOR_DB;
+use super::tap::{TAP_CAPACITY_SAMPLES, TapConsumer};
+use super::worker::WorkerEvent;
+use crate::dsp::Fft;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Points of each FFT (frequency resolution `sample_rate / SPECTRUM_FFT_SIZE`).
+pub const SPECTRUM_FFT_SIZE: usize = 2048;
+/// Spectrum frames published per second of audio.
+pub const SPECTRUM_FRAMES_PER_SEC: u32 = 30;
+/// Frames left unpolled before the analysis thread skips publishing, so a
+/// caller that never polls events does not grow the event queue.
+const MAX_PENDING_FRAMES: usize = 4;
+
+/// How often the analysis thread checks the ring when it is empty.
+const SPECTRUM_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Magnitude spectrum of the captured signal (channels averaged).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectrumFrame {
+    pub sample_rate: u32,
+    pub fft_size: u32,
+    /// Level of bins `0..=fft_size / 2` in dBFS (a full-scale sine reads
+    /// about 0 dB), floored at [`LEVEL_FLOOR_DB`]. Bin `k` is centered on
+    /// `k * sample_rate / fft_size` Hz.
+    pub magnitudes_db: Vec<f32>,
+}
+
+/// Hann-windowed FFT over the latest [`SPECTRUM_FFT_SIZE`] mono samples,
+/// producing a frame every `sample_rate / SPECTRUM_FRAMES_PER_SEC` frames.
+pub(crate) struct SpectrumAnalyzer {
+    fft: Fft,
+    window: Vec<f32>,
+    /// 窗函数之和，用于把幅度归一化为 dBFS。
+    window_gain: f32,
+    /// 最近的单声道样本（环形缓冲）。
+    history: Vec<f32>,
+    write: usize,
+    /// 距上一帧已累计的帧数。
+    since_frame: usize,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    pub(crate) fn new() -> Self {
+        let size = SPECTRUM_FFT_SIZE;
+        let window: Vec<f32> = (0..size)
+            .map(|n| 0.5 - 0.5 * (std::f32::consts::TAU * n as f32 / size as f32).cos())
+            .collect();
+        Self {
+            fft: Fft::new(size),
+            window_gain: window.iter().sum(),
+            window,
+            history: vec![0.0; size],
+            write: 0,
+            since_frame: 0,
+            re: vec![0.0; size],
+            im: vec![0.0; size],
+        }
+    }
+
+    /// Feeds interleaved `samples`; calls `publish` for every frame due.
+    pub(crate) fn push(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u16,
+        mut publish: impl FnMut(SpectrumFrame),
+    ) {
+        let channels = channels.max(1) as usize;
+        let hop = (sample_rate / SPECTRUM_FRAMES_PER_SEC).max(1) as usize;
+        for frame in samples.chunks_exact(channels) {
+            self.history[self.write] = frame.iter().sum::<f32>() / channels as f32;
+            self.write = (self.write + 1) % self.history.len();
+            self.since_frame += 1;
+            if self.since_frame >= hop {
+                self.since_frame = 0;
+                publish(self.analyze(sample_rate));
+            }
+        }
+    }
+
+    fn analyze(&mut self, sample_rate: u32) -> SpectrumFrame {
+        let size = self.history.len();
+        for i in 0..size {
+            // 从最旧的样本开始
+            let sample = self.history[(self.write + i) % size];
+            self.re[i] = sample * self.window[i];
+            self.im[i] = 0.0;
+        }
+        self.fft.process(&mut self.re, &mut self.im);
+        let scale = 2.0 / self.window_gain;
+        let magnitudes_db = (0..=size / 2)
+            .map(|k| {
+                let magnitude = (self.re[k] * self.re[k] + self.im[k] * self.im[k]).sqrt() * scale;
+                if magnitude > 0.0 {
+                    (20.0 * magnitude.log10()).max(LEVEL_FLOOR_DB)
+                } else {
+                    LEVEL_FLOOR_DB
+                }
+            })
+            .collect();
+        SpectrumFrame {
+            sample_rate,
+            fft_size: size as u32,
+            magnitudes_db,
+        }
+    }
+}
+
+/// Spawns the analysis thread. It sends [`WorkerEvent::Spectrum`] on
+/// `events` while `pending` (decremented by the poller) stays below
+/// [`MAX_PENDING_FRAMES`], and exits once the producer is dropped.
+pub(crate) fn spawn_spectrum_thread(
+    consumer: TapConsumer,
+    events: mpsc::Sender<WorkerEvent>,
+    pending: Arc<AtomicUsize>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut analyzer = SpectrumAnalyzer::new();
+        let mut chunk = Vec::with_capacity(TAP_CAPACITY_SAMPLES);
+        loop {
+            let closed = consumer.is_closed();
+            let (sample_rate, channels) = consumer.pop_into(&mut chunk, TAP_CAPACITY_SAMPLES);
+            if !chunk.is_empty() {
+                analyzer.push(&chunk, sample_rate, channels, |frame| {
+                    if pending.load(Ordering::Relaxed) >= MAX_PENDING_FRAMES {
+                        return;
+                    }
+                    // 先计数再发送，接收方减计数时不会下溢
+                    pending.fetch_add(1, Ordering::Relaxed);
+                    if events.send(WorkerEvent::Spectrum(frame)).is_err() {
+                        pending.fetch_sub(1, Ordering::Relaxed);
+                    }
+                });
+            } else if closed {
+                break;
+            } else {
+                thread::sleep(SPECTRUM_POLL_INTERVAL);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_peaks_at_its_bin_at_full_scale() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        // 48 kHz / 2048 点：bin 64 = 1500 Hz
+        let samples: Vec<f32> = (0..48_000)
+            .flat_map(|n| {
+                let s = (std::f32::consts::TAU * 1_500.0 * n as f32 / 48_000.0).sin();
+                [s, s]
+            })
+            .collect();
+        let mut frames = Vec::new();
+        analyzer.push(&samples, 48_000, 2, |frame| frames.push(frame));
+
+        assert_eq!(frames.len(), 30);
+        let last = frames.last().unwrap();
+        assert_eq!(last.magnitudes_db.len(), SPECTRUM_FFT_SIZE / 2 + 1);
+        let (peak_bin, &peak_db) = last
+            .magnitudes_db
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        assert_eq!(peak_bin, 64);
+        assert!(peak_db.abs() < 0.1, "{peak_db}");
+        assert!(last.magnitudes_db[200] < -60.0);
+    }
+}
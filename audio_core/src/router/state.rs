@@ -1,8 +1,11 @@
 //! Router internal state management.
 
 use super::config::RouterConfig;
+use super::control::RouterControls;
+use super::stats::RouterStats;
 use super::worker::WorkerEvent;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
 
 /// Internal router state tracking.
@@ -18,6 +21,18 @@ pub struct RouterState {
     /// Channel to receive events from worker thread (restart/fail).
     /// 用 Mutex 包装使 Receiver 满足 Sync（mpsc::Receiver 本身不是 Sync）。
     pub worker_event_rx: Option<Mutex<mpsc::Receiver<WorkerEvent>>>,
+    /// Counters of the current (or last) session, shared with the worker.
+    pub stats: Arc<RouterStats>,
+    /// Live per-output parameters of the current session.
+    pub controls: Arc<RouterControls>,
+    /// Thread delivering captured PCM to the user callback, if any.
+    pub tap_join: Option<std::thread::JoinHandle<()>>,
+    /// Spectrum analysis thread, if `cfg.spectrum` is enabled.
+    pub spectrum_join: Option<std::thread::JoinHandle<()>>,
+    /// Spectrum events sent but not yet polled.
+    pub spectrum_pending: Arc<AtomicUsize>,
+    /// Set while someone reads the levels; kept across sessions.
+    pub metering: Arc<AtomicBool>,
 }
 
 impl std::fmt::Debug for RouterState {
@@ -28,6 +43,9 @@ impl std::fmt::Debug for RouterState {
             .field("has_stop_tx", &self.worker_stop_tx.is_some())
             .field("has_join", &self.worker_join.is_some())
             .field("has_event_rx", &self.worker_event_rx.is_some())
+            .field("stats", &self.stats.snapshot())
+            .field("has_tap", &self.tap_join.is_some())
+            .field("has_spectrum", &self.spectrum_join.is_some())
             .finish()
     }
 }
@@ -40,6 +58,12 @@ impl Default for RouterState {
             worker_stop_tx: None,
             worker_join: None,
             worker_event_rx: None,
+            stats: Arc::new(RouterStats::default()),
+            controls: Arc::new(RouterControls::default()),
+            tap_join: None,
+            spectrum_join: None,
+            spectrum_pending: Arc::new(AtomicUsize::new(0)),
+            metering: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -0,0 +1,737 @@
+//! Router runtime statistics.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters shared between the router handle and its worker thread.
+///
+/// The worker only ever increments; readers take a [`RouterStatsSnapshot`].
+#[derive(Debug)]
+pub struct RouterStats {
+    packets: AtomicU64,
+    frames: AtomicU64,
+    data_discontinuities: AtomicU64,
+    timestamp_errors: AtomicU64,
+    overflows: AtomicU64,
+    tap_dropped_samples: AtomicU64,
+    /// Time spent inside packet processing (capture read + all render writes).
+    processing_nanos: AtomicU64,
+    max_packet_nanos: AtomicU64,
+    started: Instant,
+    /// Wall-clock length of the session once the worker exits, 0 while running.
+    finished_nanos: AtomicU64,
+    /// Latest capture correlation as f32 bits; NaN when there is none.
+    correlation_bits: AtomicU32,
+    /// Levels of the captured signal; `None` while no session is initialized.
+    source_levels: Mutex<Option<Arc<LevelSlots>>>,
+    outputs: Mutex<Vec<Arc<OutputStats>>>,
+}
+
+impl Default for RouterStats {
+    fn default() -> Self {
+        Self {
+            packets: AtomicU64::new(0),
+            frames: AtomicU64::new(0),
+            data_discontinuities: AtomicU64::new(0),
+            timestamp_errors: AtomicU64::new(0),
+            overflows: AtomicU64::new(0),
+            tap_dropped_samples: AtomicU64::new(0),
+            processing_nanos: AtomicU64::new(0),
+            max_packet_nanos: AtomicU64::new(0),
+            started: Instant::now(),
+            finished_nanos: AtomicU64::new(0),
+            correlation_bits: AtomicU32::new(f32::NAN.to_bits()),
+            source_levels: Mutex::new(None),
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// Per-output counters, registered by the worker for every initialized render client.
+#[derive(Debug)]
+pub struct OutputStats {
+    device_id: String,
+    underruns: AtomicU64,
+    overflows: AtomicU64,
+    clipped_samples: AtomicU64,
+    /// Levels written to the device; `None` while the output is not running.
+    levels: Mutex<Option<Arc<LevelSlots>>>,
+    /// Latency of the output's stream; `None` while the output is not running.
+    latency: Mutex<Option<Arc<LatencySlots>>>,
+}
+
+impl OutputStats {
+    /// The render buffer ran dry before the next packet arrived (audible gap).
+    pub(crate) fn record_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_overflow(&self) {
+        self.overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `samples` written at or beyond full scale, after all processing.
+    pub(crate) fn record_clipping(&self, samples: usize) {
+        self.clipped_samples
+            .fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    /// Starts metering a stream of `channels` channels on this output.
+    pub(crate) fn attach_levels(&self, channels: usize) -> Arc<LevelSlots> {
+        let slots = Arc::new(LevelSlots::new(channels));
+        *self.levels.lock() = Some(Arc::clone(&slots));
+        slots
+    }
+
+    /// Starts tracking the latency of a stream running at `sample_rate`.
+    pub(crate) fn attach_latency(
+        &self,
+        fixed: StreamLatency,
+        sample_rate: u32,
+    ) -> Arc<LatencySlots> {
+        let slots = Arc::new(LatencySlots::new(fixed, sample_rate));
+        *self.latency.lock() = Some(Arc::clone(&slots));
+        slots
+    }
+}
+
+/// Parts of an output's latency that are fixed once its stream is initialized.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct StreamLatency {
+    /// Loopback capture stream latency reported by the engine.
+    pub capture: Duration,
+    /// Samples held back by the output's processing (delay, limiter look-ahead).
+    pub processing: Duration,
+    /// Render stream latency reported by the engine.
+    pub engine: Duration,
+}
+
+/// 每次记录缓冲区占用时新读数的权重（按 packet 平滑，约 10 个 packet）。
+const BUFFER_SMOOTHING: f32 = 0.1;
+
+/// Latency of one output stream: the fixed parts and the smoothed number
+/// of frames waiting in its render buffer.
+#[derive(Debug)]
+pub(crate) struct LatencySlots {
+    fixed: StreamLatency,
+    sample_rate: u32,
+    /// 平滑后的缓冲帧数（f32 位模式），尚无读数时为 NaN。
+    buffered_bits: AtomicU32,
+}
+
+impl LatencySlots {
+    fn new(fixed: StreamLatency, sample_rate: u32) -> Self {
+        Self {
+            fixed,
+            sample_rate,
+            buffered_bits: AtomicU32::new(f32::NAN.to_bits()),
+        }
+    }
+
+    /// Records the render buffer padding seen before a write. Only the
+    /// worker writes, so a plain load and store is enough.
+    pub(crate) fn record_buffered(&self, frames: u32) {
+        let previous = f32::from_bits(self.buffered_bits.load(Ordering::Relaxed));
+        let smoothed = if previous.is_nan() {
+            frames as f32
+        } else {
+            previous + (frames as f32 - previous) * BUFFER_SMOOTHING
+        };
+        self.buffered_bits
+            .store(smoothed.to_bits(), Ordering::Relaxed);
+    }
+
+    fn latency(&self, device_id: &str) -> OutputLatency {
+        let ms = |d: Duration| d.as_secs_f64() * 1e3;
+        let buffered = f32::from_bits(self.buffered_bits.load(Ordering::Relaxed));
+        let buffer_ms = if buffered.is_nan() || self.sample_rate == 0 {
+            0.0
+        } else {
+            buffered as f64 * 1e3 / self.sample_rate as f64
+        };
+        let capture_ms = ms(self.fixed.capture);
+        let processing_ms = ms(self.fixed.processing);
+        let engine_ms = ms(self.fixed.engine);
+        OutputLatency {
+            device_id: device_id.to_string(),
+            capture_ms,
+            processing_ms,
+            buffer_ms,
+            engine_ms,
+            total_ms: capture_ms + processing_ms + buffer_ms + engine_ms,
+        }
+    }
+}
+
+/// Readings below this level are reported as it (digital silence).
+pub const LEVEL_FLOOR_DB: f32 = -120.0;
+
+/// Latest linear peak and RMS of each channel of one stream, as f32 bits.
+///
+/// Allocated when the stream is initialized, so the worker only stores
+/// atomics, once per meter window.
+#[derive(Debug)]
+pub(crate) struct LevelSlots {
+    peak: Box<[AtomicU32]>,
+    rms: Box<[AtomicU32]>,
+}
+
+impl LevelSlots {
+    fn new(channels: usize) -> Self {
+        let silent = || (0..channels).map(|_| AtomicU32::new(0)).collect();
+        Self {
+            peak: silent(),
+            rms: silent(),
+        }
+    }
+
+    pub(crate) fn store(&self, peak: &[f32], rms: &[f32]) {
+        for (slot, value) in self.peak.iter().zip(peak) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+        }
+        for (slot, value) in self.rms.iter().zip(rms) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    fn levels(&self) -> ChannelLevels {
+        let to_db = |slots: &[AtomicU32]| {
+            slots
+                .iter()
+                .map(|slot| {
+                    let linear = f32::from_bits(slot.load(Ordering::Relaxed));
+                    if linear > 0.0 {
+                        (20.0 * linear.log10()).max(LEVEL_FLOOR_DB)
+                    } else {
+                        LEVEL_FLOOR_DB
+                    }
+                })
+                .collect()
+        };
+        ChannelLevels {
+            peak_db: to_db(&self.peak),
+            rms_db: to_db(&self.rms),
+        }
+    }
+}
+
+/// Point-in-time copy of [`RouterStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouterStatsSnapshot {
+    /// Capture packets processed since the session started.
+    pub packets: u64,
+    /// Capture frames processed since the session started.
+    pub frames: u64,
+    /// Packets flagged with `AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY`.
+    pub data_discontinuities: u64,
+    /// Packets flagged with `AUDCLNT_BUFFERFLAGS_TIMESTAMP_ERROR`.
+    pub timestamp_errors: u64,
+    /// Render writes skipped or flushed because an output buffer was too full.
+    pub overflows: u64,
+    /// Samples not delivered to the PCM callback because it fell behind.
+    pub tap_dropped_samples: u64,
+}
+
+impl RouterStatsSnapshot {
+    /// Total number of glitch flags seen (discontinuities + timestamp errors).
+    pub fn glitches(&self) -> u64 {
+        self.data_discontinuities + self.timestamp_errors
+    }
+}
+
+impl RouterStats {
+    pub(crate) fn record_packet(&self, frames: u32) {
+        self.packets.fetch_add(1, Ordering::Relaxed);
+        self.frames.fetch_add(frames as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_discontinuity(&self) {
+        self.data_discontinuities.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_timestamp_error(&self) {
+        self.timestamp_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_overflow(&self) {
+        self.overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_tap_drop(&self, samples: usize) {
+        self.tap_dropped_samples
+            .fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_processing(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos() as u64;
+        self.processing_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_packet_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_correlation(&self, correlation: Option<f32>) {
+        let value = correlation.unwrap_or(f32::NAN);
+        self.correlation_bits
+            .store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Starts metering the captured stream of `channels` channels.
+    pub(crate) fn attach_source_levels(&self, channels: usize) -> Arc<LevelSlots> {
+        let slots = Arc::new(LevelSlots::new(channels));
+        *self.source_levels.lock() = Some(Arc::clone(&slots));
+        slots
+    }
+
+    /// Drops all level and latency readings until streams are initialized
+    /// again (e.g. an output that fails to come back after a device restart
+    /// has none).
+    pub(crate) fn clear_readings(&self) {
+        *self.source_levels.lock() = None;
+        for output in self.outputs.lock().iter() {
+            *output.levels.lock() = None;
+            *output.latency.lock() = None;
+        }
+    }
+
+    /// Freezes the session wall-clock so the duty cycle stays meaningful after stop.
+    pub(crate) fn finish(&self) {
+        // 会话结束后不再有信号可测
+        self.record_correlation(None);
+        self.clear_readings();
+        let nanos = (self.started.elapsed().as_nanos() as u64).max(1);
+        let _ =
+            self.finished_nanos
+                .compare_exchange(0, nanos, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    /// Returns the counters for `device_id`, creating them on first use.
+    ///
+    /// Re-registering after a device restart keeps the existing counters.
+    pub(crate) fn register_output(&self, device_id: &str) -> Arc<OutputStats> {
+        let mut outputs = self.outputs.lock();
+        if let Some(existing) = outputs.iter().find(|o| o.device_id == device_id) {
+            return Arc::clone(existing);
+        }
+        let output = Arc::new(OutputStats {
+            device_id: device_id.to_string(),
+            underruns: AtomicU64::new(0),
+            overflows: AtomicU64::new(0),
+            clipped_samples: AtomicU64::new(0),
+            levels: Mutex::new(None),
+            latency: Mutex::new(None),
+        });
+        outputs.push(Arc::clone(&output));
+        output
+    }
+
+    /// Processing-time metrics and per-output counters.
+    pub fn performance(&self) -> RouterPerformance {
+        let packets = self.packets.load(Ordering::Relaxed);
+        let processing_nanos = self.processing_nanos.load(Ordering::Relaxed);
+        let wall_nanos = match self.finished_nanos.load(Ordering::Relaxed) {
+            0 => self.started.elapsed().as_nanos() as u64,
+            finished => finished,
+        };
+        RouterPerformance {
+            session_secs: wall_nanos as f64 / 1e9,
+            avg_packet_us: if packets == 0 {
+                0.0
+            } else {
+                processing_nanos as f64 / packets as f64 / 1e3
+            },
+            max_packet_us: self.max_packet_nanos.load(Ordering::Relaxed) as f64 / 1e3,
+            duty_cycle: if wall_nanos == 0 {
+                0.0
+            } else {
+                processing_nanos as f64 / wall_nanos as f64
+            },
+            outputs: self
+                .outputs
+                .lock()
+                .iter()
+                .map(|o| OutputStatus {
+                    device_id: o.device_id.clone(),
+                    underruns: o.underruns.load(Ordering::Relaxed),
+                    overflows: o.overflows.load(Ordering::Relaxed),
+                    clipped_samples: o.clipped_samples.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+
+    /// Current readings of the capture meters.
+    pub fn meters(&self) -> RouterMeters {
+        let correlation = f32::from_bits(self.correlation_bits.load(Ordering::Relaxed));
+        RouterMeters {
+            correlation: (!correlation.is_nan()).then_some(correlation),
+        }
+    }
+
+    /// Peak and RMS levels of the captured signal and of every running
+    /// output, over the last completed meter window.
+    pub fn levels(&self) -> RouterLevels {
+        RouterLevels {
+            source: self.source_levels.lock().as_ref().map(|s| s.levels()),
+            outputs: self
+                .outputs
+                .lock()
+                .iter()
+                .filter_map(|o| {
+                    o.levels.lock().as_ref().map(|slots| OutputLevels {
+                        device_id: o.device_id.clone(),
+                        levels: slots.levels(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Approximate latency added on the way to every running output.
+    pub fn latency(&self) -> Vec<OutputLatency> {
+        self.outputs
+            .lock()
+            .iter()
+            .filter_map(|o| {
+                o.latency
+                    .lock()
+                    .as_ref()
+                    .map(|slots| slots.latency(&o.device_id))
+            })
+            .collect()
+    }
+
+    /// Takes a consistent-enough copy of all counters.
+    pub fn snapshot(&self) -> RouterStatsSnapshot {
+        RouterStatsSnapshot {
+            packets: self.packets.load(Ordering::Relaxed),
+            frames: self.frames.load(Ordering::Relaxed),
+            data_discontinuities: self.data_discontinuities.load(Ordering::Relaxed),
+            timestamp_errors: self.timestamp_errors.load(Ordering::Relaxed),
+            overflows: self.overflows.load(Ordering::Relaxed),
+            tap_dropped_samples: self.tap_dropped_samples.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Processing cost of a router session, derived from [`RouterStats`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterPerformance {
+    /// Wall-clock length of the session so far.
+    pub session_secs: f64,
+    /// Mean time spent processing one capture packet, in microseconds.
+    pub avg_packet_us: f64,
+    /// Worst single-packet processing time, in microseconds.
+    pub max_packet_us: f64,
+    /// Fraction of wall-clock time the worker spent processing (0.0–1.0).
+    pub duty_cycle: f64,
+    pub outputs: Vec<OutputStatus>,
+}
+
+/// Counters of one output device.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputStatus {
+    pub device_id: String,
+    /// Times the render buffer was found empty before a write.
+    pub underruns: u64,
+    /// Packets skipped or flushed by the output's overflow policy.
+    pub overflows: u64,
+    /// Samples sent at or beyond full scale (audible distortion).
+    pub clipped_samples: u64,
+}
+
+/// Live measurements of the captured signal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterMeters {
+    /// Correlation of the front pair over the last ~300 ms, from −1
+    /// (cancels when summed to mono) to +1 (mono). `None` while the
+    /// source is silent, mono or the router is stopped.
+    pub correlation: Option<f32>,
+}
+
+/// Peak and RMS of each channel of a stream, in dBFS, floored at
+/// [`LEVEL_FLOOR_DB`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelLevels {
+    pub peak_db: Vec<f32>,
+    pub rms_db: Vec<f32>,
+}
+
+/// Levels of what one output device is sent, after all of its processing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputLevels {
+    pub device_id: String,
+    pub levels: ChannelLevels,
+}
+
+/// Result of [`crate::router::Router::levels`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterLevels {
+    /// The captured signal; `None` while the router is stopped.
+    pub source: Option<ChannelLevels>,
+    /// Outputs that are currently running.
+    pub outputs: Vec<OutputLevels>,
+}
+
+/// Approximate delay between the source playing a sample and one output
+/// playing it, from [`crate::router::Router::latency`], in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputLatency {
+    pub device_id: String,
+    /// Loopback capture stream latency reported by the audio engine.
+    pub capture_ms: f64,
+    /// Samples held back by the output's own delay and limiter look-ahead.
+    pub processing_ms: f64,
+    /// Audio written to the render buffer but not yet played, averaged
+    /// over the last few packets.
+    pub buffer_ms: f64,
+    /// Render stream latency reported by the audio engine.
+    pub engine_ms: f64,
+    /// Sum of the parts above.
+    pub total_ms: f64,
+}
+
+/// Result of [`crate::router::Router::status`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouterStatus {
+    pub running: bool,
+    pub stats: RouterStatsSnapshot,
+    pub performance: RouterPerformance,
+    pub meters: RouterMeters,
+}
+
+/// 检测窗口内的 glitch 计数，超过阈值时通知一次。
+///
+/// 每个窗口最多上报一次，避免持续故障时事件刷屏。
+#[derive(Debug)]
+pub(crate) struct GlitchMonitor {
+    threshold: u64,
+    window_start_glitches: u64,
+    reported: bool,
+}
+
+impl GlitchMonitor {
+    pub(crate) fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            window_start_glitches: 0,
+            reported: false,
+        }
+    }
+
+    /// 开始新的统计窗口。
+    pub(crate) fn reset_window(&mut self, snapshot: &RouterStatsSnapshot) {
+        self.window_start_glitches = snapshot.glitches();
+        self.reported = false;
+    }
+
+    /// 返回本窗口内新增的 glitch 数（仅在首次超过阈值时返回 Some）。
+    pub(crate) fn check(&mut self, snapshot: &RouterStatsSnapshot) -> Option<u64> {
+        if self.reported {
+            return None;
+        }
+        let delta = snapshot
+            .glitches()
+            .saturating_sub(self.window_start_glitches);
+        if delta >= self.threshold {
+            self.reported = true;
+            Some(delta)
+        } else {
+            None
+        }
+    }
+}
+
+/// 按输出设备统计窗口内的削波采样数，超过阈值时每个窗口通知一次。
+#[derive(Debug)]
+pub(crate) struct ClipMonitor {
+    threshold: u64,
+    /// 各设备窗口起点的累计削波数。
+    window_start: HashMap<String, u64>,
+    reported: Vec<String>,
+}
+
+impl ClipMonitor {
+    pub(crate) fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            window_start: HashMap::new(),
+            reported: Vec::new(),
+        }
+    }
+
+    /// 开始新的统计窗口。
+    pub(crate) fn reset_window(&mut self, outputs: &[OutputStatus]) {
+        self.window_start = outputs
+            .iter()
+            .map(|o| (o.device_id.clone(), o.clipped_samples))
+            .collect();
+        self.reported.clear();
+    }
+
+    /// 返回本窗口内首次超过阈值的设备及其新增削波数。
+    pub(crate) fn check(&mut self, outputs: &[OutputStatus]) -> Vec<(String, u64)> {
+        let mut exceeded = Vec::new();
+        for output in outputs {
+            if self.reported.contains(&output.device_id) {
+                continue;
+            }
+            // 窗口中途注册的设备从 0 开始计
+            let start = self
+                .window_start
+                .get(&output.device_id)
+                .copied()
+                .unwrap_or(0);
+            let delta = output.clipped_samples.saturating_sub(start);
+            if delta >= self.threshold {
+                self.reported.push(output.device_id.clone());
+                exceeded.push((output.device_id.clone(), delta));
+            }
+        }
+        exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glitch_monitor_reports_once_per_window() {
+        let stats = RouterStats::default();
+        let mut monitor = GlitchMonitor::new(3);
+        monitor.reset_window(&stats.snapshot());
+
+        stats.record_discontinuity();
+        stats.record_timestamp_error();
+        assert_eq!(monitor.check(&stats.snapshot()), None);
+
+        stats.record_discontinuity();
+        assert_eq!(monitor.check(&stats.snapshot()), Some(3));
+
+        stats.record_discontinuity();
+        assert_eq!(monitor.check(&stats.snapshot()), None);
+
+        monitor.reset_window(&stats.snapshot());
+        assert_eq!(monitor.check(&stats.snapshot()), None);
+    }
+
+    #[test]
+    fn clip_monitor_reports_each_output_once_per_window() {
+        let stats = RouterStats::default();
+        let out1 = stats.register_output("out1");
+        let mut monitor = ClipMonitor::new(10);
+        monitor.reset_window(&stats.performance().outputs);
+
+        out1.record_clipping(4);
+        let out2 = stats.register_output("out2");
+        out2.record_clipping(12);
+        assert_eq!(
+            monitor.check(&stats.performance().outputs),
+            vec![("out2".to_string(), 12)]
+        );
+
+        out1.record_clipping(6);
+        out2.record_clipping(50);
+        assert_eq!(
+            monitor.check(&stats.performance().outputs),
+            vec![("out1".to_string(), 10)]
+        );
+
+        monitor.reset_window(&stats.performance().outputs);
+        out2.record_clipping(9);
+        assert!(monitor.check(&stats.performance().outputs).is_empty());
+    }
+
+    #[test]
+    fn meters_report_correlation_until_finished() {
+        let stats = RouterStats::default();
+        assert_eq!(stats.meters().correlation, None);
+        stats.record_correlation(Some(-0.5));
+        assert_eq!(stats.meters().correlation, Some(-0.5));
+        stats.finish();
+        assert_eq!(stats.meters().correlation, None);
+    }
+
+    #[test]
+    fn performance_reports_per_packet_cost_and_output_counters() {
+        let stats = RouterStats::default();
+        stats.record_packet(480);
+        stats.record_processing(Duration::from_micros(100));
+        stats.record_packet(480);
+        stats.record_processing(Duration::from_micros(300));
+
+        let output = stats.register_output("out1");
+        output.record_underrun();
+        // 重启后重新注册同一设备，计数保持
+        stats.register_output("out1").record_overflow();
+        stats.finish();
+
+        let perf = stats.performance();
+        assert_eq!(perf.avg_packet_us, 200.0);
+        assert_eq!(perf.max_packet_us, 300.0);
+        assert!(perf.duty_cycle > 0.0);
+        assert_eq!(
+            perf.outputs,
+            vec![OutputStatus {
+                device_id: "out1".into(),
+                underruns: 1,
+                overflows: 1,
+                clipped_samples: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn levels_cover_metered_streams_until_finished() {
+        let stats = RouterStats::default();
+        assert_eq!(stats.levels(), RouterLevels::default());
+
+        stats
+            .attach_source_levels(2)
+            .store(&[1.0, 0.0], &[0.5, 0.0]);
+        let output = stats.register_output("out1");
+        stats.register_output("out2");
+        output.attach_levels(1).store(&[0.1], &[0.01]);
+
+        let levels = stats.levels();
+        let source = levels.source.unwrap();
+        assert_eq!(source.peak_db, [0.0, LEVEL_FLOOR_DB]);
+        assert!((source.rms_db[0] + 6.02).abs() < 0.01);
+        assert_eq!(levels.outputs.len(), 1);
+        assert_eq!(levels.outputs[0].device_id, "out1");
+        assert!((levels.outputs[0].levels.rms_db[0] + 40.0).abs() < 0.01);
+
+        stats.finish();
+        assert_eq!(stats.levels(), RouterLevels::default());
+    }
+
+    #[test]
+    fn latency_sums_fixed_parts_and_smoothed_buffer() {
+        let stats = RouterStats::default();
+        stats.register_output("out1");
+        let fixed = StreamLatency {
+            capture: Duration::from_millis(10),
+            processing: Duration::from_millis(5),
+            engine: Duration::from_millis(20),
+        };
+        let slots = stats.register_output("out2").attach_latency(fixed, 48_000);
+        assert_eq!(stats.latency()[0].buffer_ms, 0.0);
+
+        slots.record_buffered(480);
+        let latency = &stats.latency()[0];
+        assert_eq!(latency.device_id, "out2");
+        assert!((latency.buffer_ms - 10.0).abs() < 1e-6);
+        assert!((latency.total_ms - 45.0).abs() < 1e-6);
+
+        // 单次尖峰只按权重计入
+        slots.record_buffered(4800);
+        let buffer_ms = stats.latency()[0].buffer_ms;
+        assert!(buffer_ms > 10.0 && buffer_ms < 20.0);
+
+        stats.finish();
+        assert!(stats.latency().is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! PCM tap: delivers captured audio to the user callback off the real-time loop.
+//!
+//! The worker pushes converted f32 samples into a pre-sized lock-free SPSC ring
+//! and never waits; a separate tap thread drains the ring and invokes the
+//! callback. A slow consumer therefore only loses tap data (counted as drops),
+//! it can never stall capture or render.
+
+use std::cell::{Cell, UnsafeCell};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Ring capacity in samples (~0.68s of 48 kHz stereo).
+pub(crate) const TAP_CAPACITY_SAMPLES: usize = 1 << 16;
+
+/// How often the tap thread checks the ring when it is empty.
+const TAP_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+struct Ring {
+    buf: Box<[UnsafeCell<f32>]>,
+    mask: usize,
+    /// Next write position (only advanced by the producer).
+    head: AtomicUsize,
+    /// Next read position (only advanced by the consumer).
+    tail: AtomicUsize,
+    /// `sample_rate << 16 | channels` of every sample in the ring. Only
+    /// changes while the ring is empty.
+    format: AtomicU64,
+    closed: AtomicBool,
+}
+
+// SAFETY: 每个槽位在任一时刻只被一端访问：生产者只写 [head, tail+cap) 的空闲槽，
+// 消费者只读 [tail, head)，head/tail 的 Release/Acquire 保证可见性。
+unsafe impl Sync for Ring {}
+
+/// Rings the worker feeds with the converted capture signal.
+#[derive(Default)]
+pub(crate) struct Taps {
+    /// PCM for the user callback.
+    pub(crate) pcm: Option<TapProducer>,
+    /// Input of the spectrum analysis thread.
+    pub(crate) spectrum: Option<TapProducer>,
+}
+
+/// Writing half, owned by the router worker.
+pub(crate) struct TapProducer {
+    ring: Arc<Ring>,
+    /// 尚未生效的新格式，等消费者读完旧格式的样本后才生效。
+    pending_format: Cell<Option<u64>>,
+}
+
+/// Reading half, owned by the tap thread.
+pub(crate) struct TapConsumer {
+    ring: Arc<Ring>,
+}
+
+/// Creates a ring holding `capacity` samples (rounded up to a power of two).
+pub(crate) fn tap_channel(capacity: usize) -> (TapProducer, TapConsumer) {
+    let capacity = capacity.next_power_of_two();
+    let buf = (0..capacity).map(|_| UnsafeCell::new(0.0)).collect();
+    let ring = Arc::new(Ring {
+        buf,
+        mask: capacity - 1,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        format: AtomicU64::new(0),
+        closed: AtomicBool::new(false),
+    });
+    (
+        TapProducer {
+            ring: Arc::clone(&ring),
+            pending_format: Cell::new(None),
+        },
+        TapConsumer { ring },
+    )
+}
+
+impl TapProducer {
+    /// Announces the format of subsequently pushed samples.
+    ///
+    /// A new format takes effect once the consumer has drained the samples
+    /// queued in the old one; packets pushed until then are dropped.
+    pub(crate) fn set_format(&self, sample_rate: u32, channels: u16) {
+        let packed = (sample_rate as u64) << 16 | channels as u64;
+        let current = self.ring.format.load(Ordering::Relaxed);
+        self.pending_format
+            .set((packed != current).then_some(packed));
+        self.apply_pending_format();
+    }
+
+    /// Switches to the pending format if the ring is empty. Returns false
+    /// while a format change is still waiting.
+    fn apply_pending_format(&self) -> bool {
+        let Some(format) = self.pending_format.get() else {
+            return true;
+        };
+        let ring = &*self.ring;
+        if ring.tail.load(Ordering::Acquire) != ring.head.load(Ordering::Relaxed) {
+            return false;
+        }
+        ring.format.store(format, Ordering::Release);
+        self.pending_format.set(None);
+        true
+    }
+
+    /// Pushes a whole packet or nothing (keeps frames aligned). Never blocks.
+    /// Returns false when the packet was dropped because the ring is full or
+    /// still holds samples of a previous format.
+    pub(crate) fn push(&self, samples: &[f32]) -> bool {
+        if !self.apply_pending_format() {
+            return false;
+        }
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        let free = ring.buf.len() - head.wrapping_sub(tail);
+        if samples.len() > free {
+            return false;
+        }
+        for (i, &sample) in samples.iter().enumerate() {
+            let slot = &ring.buf[head.wrapping_add(i) & ring.mask];
+            unsafe { *slot.get() = sample };
+        }
+        ring.head
+            .store(head.wrapping_add(samples.len()), Ordering::Release);
+        true
+    }
+}
+
+impl Drop for TapProducer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+impl TapConsumer {
+    /// Moves up to `max` available samples into `out` (cleared first).
+    /// Returns `(sample_rate, channels)` of the data.
+    pub(crate) fn pop_into(&self, out: &mut Vec<f32>, max: usize) -> (u32, u16) {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        // 在 head 之后读取：格式只在队列为空时改变，读到的样本都属于这个格式
+        let format = ring.format.load(Ordering::Acquire);
+        let channels = (format & 0xFFFF) as usize;
+        let mut count = head.wrapping_sub(tail).min(max);
+        if channels > 0 {
+            count -= count % channels;
+        }
+
+        out.clear();
+        out.extend(
+            (0..count).map(|i| unsafe { *ring.buf[tail.wrapping_add(i) & ring.mask].get() }),
+        );
+        ring.tail.store(tail.wrapping_add(count), Ordering::Release);
+        ((format >> 16) as u32, channels as u16)
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.ring.closed.load(Ordering::Acquire)
+    }
+}
+
+/// Spawns the tap thread. It exits after the producer is dropped and the
+/// remaining samples have been delivered.
+pub(crate) fn spawn_tap_thread<F>(consumer: TapConsumer, cb: Arc<F>) -> thread::JoinHandle<()>
+where
+    F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
+{
+    thread::spawn(move || {
+        let mut chunk = Vec::with_capacity(TAP_CAPACITY_SAMPLES);
+        loop {
+            // 先读 closed 再取数据，保证关闭前写入的样本都能送达
+            let closed = consumer.is_closed();
+            let (sample_rate, channels) = consumer.pop_into(&mut chunk, TAP_CAPACITY_SAMPLES);
+            if !chunk.is_empty() {
+                cb(&chunk, sample_rate, channels);
+            } else if closed {
+                break;
+            } else {
+                thread::sleep(TAP_POLL_INTERVAL);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_drops_whole_packets_when_full_and_keeps_order() {
+        let (producer, consumer) = tap_channel(8);
+        producer.set_format(48_000, 2);
+
+        assert!(producer.push(&[1.0, 2.0, 3.0, 4.0]));
+        assert!(producer.push(&[5.0, 6.0]));
+        // 只剩 2 个空位，4 个样本的 packet 整体丢弃
+        assert!(!producer.push(&[7.0, 8.0, 9.0, 10.0]));
+
+        let mut out = Vec::new();
+        assert_eq!(consumer.pop_into(&mut out, 16), (48_000, 2));
+        assert_eq!(out, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        // 读走后空间释放，写指针跨越缓冲区末尾时仍保持顺序
+        assert!(producer.push(&[7.0, 8.0, 9.0, 10.0]));
+        consumer.pop_into(&mut out, 3);
+        // 按整帧读取（2 声道下 3 → 2）
+        assert_eq!(out, vec![7.0, 8.0]);
+        consumer.pop_into(&mut out, 16);
+        assert_eq!(out, vec![9.0, 10.0]);
+    }
+
+    #[test]
+    fn format_change_waits_until_queued_samples_are_read() {
+        let (producer, consumer) = tap_channel(16);
+        producer.set_format(48_000, 2);
+        assert!(producer.push(&[1.0, 2.0]));
+
+        // 切换源后，旧格式的样本读完之前新 packet 被丢弃
+        producer.set_format(44_100, 1);
+        assert!(!producer.push(&[3.0]));
+
+        let mut out = Vec::new();
+        assert_eq!(consumer.pop_into(&mut out, 16), (48_000, 2));
+        assert_eq!(out, vec![1.0, 2.0]);
+
+        assert!(producer.push(&[4.0]));
+        assert_eq!(consumer.pop_into(&mut out, 16), (44_100, 1));
+        assert_eq!(out, vec![4.0]);
+    }
+
+    #[test]
+    fn tap_thread_delivers_remaining_samples_after_close() {
+        let (producer, consumer) = tap_channel(64);
+        let received = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        let handle = spawn_tap_thread(
+            consumer,
+            Arc::new(move |samples: &[f32], rate: u32, channels: u16| {
+                assert_eq!((rate, channels), (44_100, 1));
+                sink.lock().extend_from_slice(samples);
+            }),
+        );
+
+        producer.set_format(44_100, 1);
+        producer.push(&[0.1, 0.2, 0.3]);
+        drop(producer);
+        handle.join().unwrap();
+
+        assert_eq!(*received.lock(), vec![0.1, 0.2, 0.3]);
+    }
+}
@@ -3,15 +3,40 @@
 use anyhow::Result;
 use std::sync::Arc;
 use std::sync::mpsc;
-use std::time::Duration;
-use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx, CoUninitialize};
+use std::time::{Duration, Instant};
 
+use crate::com_service::apartment::{Apartment, ComApartment};
 use crate::com_service::router::{
-    MixFormat, RouterInitialized, finalize_router, get_mix_format, initialize_router,
-    process_next_packet, setup_router_clients,
+    MAX_PACKETS_PER_BATCH, RouterInitialized, RouterSetupResult, finalize_router,
+    get_device_period, get_mix_format, initialize_router, process_available_packets,
+    setup_router_clients,
 };
 
+use super::affinity::apply_to_current_thread;
 use super::config::RouterConfig;
+use super::control::RouterControls;
+use super::priority;
+use super::spectrum::SpectrumFrame;
+use super::stats::{ClipMonitor, GlitchMonitor, RouterStats};
+use super::tap::Taps;
+
+/// Glitch 统计窗口长度。
+const GLITCH_WINDOW: Duration = Duration::from_secs(5);
+/// 一个窗口内 glitch（数据不连续/时间戳错误）达到该数量时上报事件。
+const GLITCH_EVENT_THRESHOLD: u64 = 3;
+/// 一个窗口内单个输出的削波采样数达到该数量时上报事件（与 glitch 共用窗口）。
+const CLIP_EVENT_THRESHOLD: u64 = 100;
+/// 检查削波计数的间隔；读取各输出计数需要加锁，不必每批都做。
+const CLIP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// 淡出时长之后再等待的时间：输出端缓冲区里还有淡出的尾部未播放。
+const FADE_OUT_MARGIN: Duration = Duration::from_millis(50);
+
+/// 无数据时等待时间的下限，避免忙等。
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// 无数据时等待时间的上限，不超过原先的固定间隔，轮询模式不会比以前多积压数据。
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(3);
+/// 无法获取设备周期时使用的等待时间。
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_millis(3);
 
 /// Worker 发送给主线程的事件。
 #[derive(Debug, Clone)]
@@ -24,39 +49,84 @@ pub enum WorkerEvent {
     Restarted,
     /// 发生不可恢复错误，路由已停止
     Failed(String),
+    /// worker 线程未报告错误就退出（如 panic），路由已停止；附带退出原因。
+    /// 由 [`crate::router::Router::poll_events`] 在事件通道断开时生成
+    Exited(String),
+    /// 捕获端在统计窗口内出现过多 glitch（DATA_DISCONTINUITY / TIMESTAMP_ERROR）
+    GlitchDetected {
+        /// 窗口内的 glitch 数量
+        count: u64,
+        /// 窗口长度（秒）
+        window_secs: u64,
+    },
+    /// 某个输出在统计窗口内有过多采样达到或超过满幅（经过全部 DSP 之后）
+    Clipping {
+        device_id: String,
+        /// 窗口内削波的采样数
+        count: u64,
+        /// 窗口长度（秒）
+        window_secs: u64,
+    },
+    /// 捕获信号的频谱（仅在 `RouterConfig::spectrum` 开启时发送）
+    Spectrum(SpectrumFrame),
+    /// 已按 [`crate::router::Router::switch_source`] 的请求改从该设备捕获
+    SourceSwitched(String),
+    /// 切换源失败，已回到原来的源继续路由；附带失败原因
+    SourceSwitchFailed(String),
+    /// 已按 [`crate::router::Router::fade_out`] 的请求淡出并播放完，
+    /// 输出保持静音直到停止
+    FadedOut,
+}
+
+/// 事件循环结束的原因。
+enum LoopExit {
+    /// 收到 stop 信号
+    Stopped,
+    /// 请求切换到该源设备，需要重新打开会话
+    SwitchSource(String),
 }
 
-pub fn run_worker<F>(
+pub fn run_worker(
     cfg: RouterConfig,
-    cb: Arc<F>,
+    taps: Taps,
+    stats: Arc<RouterStats>,
+    controls: Arc<RouterControls>,
     stop_rx: mpsc::Receiver<()>,
     ready_tx: mpsc::Sender<Result<()>>,
     event_tx: mpsc::Sender<WorkerEvent>,
-) -> Result<()>
-where
-    F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
-{
-    let result = setup_and_run_routing(cfg, cb, stop_rx, ready_tx, event_tx);
+) -> Result<()> {
+    let result = setup_and_run_routing(
+        cfg,
+        taps,
+        Arc::clone(&stats),
+        &controls,
+        stop_rx,
+        ready_tx,
+        event_tx,
+    );
+    stats.finish();
     if let Err(e) = &result {
         log::error!("Router worker exited with error: {e:?}");
     }
     result
 }
 
-fn setup_and_run_routing<F>(
-    cfg: RouterConfig,
-    cb: Arc<F>,
+fn setup_and_run_routing(
+    mut cfg: RouterConfig,
+    taps: Taps,
+    stats: Arc<RouterStats>,
+    controls: &RouterControls,
     stop_rx: mpsc::Receiver<()>,
     ready_tx: mpsc::Sender<Result<()>>,
     event_tx: mpsc::Sender<WorkerEvent>,
-) -> Result<()>
-where
-    F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
-{
-    let _com = ComApartment::mta()?;
+) -> Result<()> {
+    // 实时线程不处理消息，只能在 MTA 中运行
+    let _com = ComApartment::enter(Apartment::Mta)?;
+    apply_to_current_thread(&cfg.affinity);
+    let _priority = priority::apply_to_current_thread(cfg.engine.thread_priority);
 
     // 首次初始化
-    let (setup_res, mix_format, init_res) = match setup_and_initialize(&cfg) {
+    let mut session = match RoutingSession::open(&cfg, &stats, controls) {
         Ok(v) => v,
         Err(e) => {
             let _ = ready_tx.send(Err(anyhow::anyhow!("{e:?}")));
@@ -70,21 +140,41 @@ where
     let _ = event_tx.send(WorkerEvent::Started);
 
     // 主循环：事件循环 + 自动重启
-    let mut current_setup = setup_res;
-    let mut current_mix = mix_format;
-    let mut current_init = init_res;
-
     loop {
-        let loop_result = event_loop(&current_init, &current_mix, &cb, &stop_rx);
+        let loop_result = event_loop(&session, &taps, &stats, controls, &stop_rx, &event_tx);
 
         // 无论 event_loop 返回 Ok 还是 Err，都要 finalize 当前资源
-        let _ = finalize_router(&current_setup);
+        let _ = finalize_router(&session.setup);
 
         match loop_result {
-            Ok(()) => {
+            Ok(LoopExit::Stopped) => {
                 // 正常停止（收到 stop 信号）
                 return Ok(());
             }
+            Ok(LoopExit::SwitchSource(device_id)) => {
+                // 输出的实时控制按设备 id 保留，重新打开会话后增益、模式不变
+                let previous = cfg.source_device_id.replace(device_id.clone());
+                match RoutingSession::open(&cfg, &stats, controls) {
+                    Ok(new_session) => {
+                        session = new_session;
+                        log::info!("Switched source to {device_id}");
+                        let _ = event_tx.send(WorkerEvent::SourceSwitched(device_id));
+                    }
+                    Err(e) => {
+                        log::warn!("Switching source to {device_id} failed: {e:?}");
+                        cfg.source_device_id = previous;
+                        session = match RoutingSession::open(&cfg, &stats, controls) {
+                            Ok(session) => session,
+                            Err(reopen_err) => {
+                                let msg = format!("{reopen_err:?}");
+                                let _ = event_tx.send(WorkerEvent::Failed(msg));
+                                return Err(reopen_err);
+                            }
+                        };
+                        let _ = event_tx.send(WorkerEvent::SourceSwitchFailed(format!("{e:?}")));
+                    }
+                }
+            }
             Err(e) => {
                 let err_str = format!("{e:?}");
 
@@ -122,11 +212,9 @@ where
                     }
 
                     log::info!("Restart attempt {attempt}/10...");
-                    match setup_and_initialize(&cfg) {
-                        Ok((new_setup, new_mix, new_init)) => {
-                            current_setup = new_setup;
-                            current_mix = new_mix;
-                            current_init = new_init;
+                    match RoutingSession::open(&cfg, &stats, controls) {
+                        Ok(new_session) => {
+                            session = new_session;
                             restarted = true;
                             log::info!("Routing restarted successfully on attempt {attempt}");
                             let _ = event_tx.send(WorkerEvent::Restarted);
@@ -150,63 +238,166 @@ where
     }
 }
 
-/// 完成 WASAPI 客户端的 setup 和 initialize。
-/// 成功返回 (setup_res, mix_format, init_res)，失败返回 Err。
-fn setup_and_initialize(
-    cfg: &RouterConfig,
-) -> Result<(
-    crate::com_service::router::RouterSetupResult,
-    MixFormat,
-    RouterInitialized,
-)> {
-    let setup_res = setup_router_clients(cfg)?;
-    let mix_format = get_mix_format(&setup_res.source_client)?;
-    let init_res = initialize_router(
-        &setup_res.source_client,
-        &setup_res.output_clients,
-        &mix_format,
-    )?;
-    Ok((setup_res, mix_format, init_res))
+/// 一次路由会话（首次启动或每次重启）中持久存在的 worker 状态。
+///
+/// 已初始化的客户端、解析后的格式和轮询间隔都在 `open` 时确定一次，
+/// 事件循环只借用它们，不再逐 packet 重新构造。
+struct RoutingSession {
+    setup: RouterSetupResult,
+    init: RouterInitialized,
+    poll_interval: Duration,
 }
 
-struct ComApartment;
+impl RoutingSession {
+    /// 完成 WASAPI 客户端的 setup 和 initialize。
+    fn open(cfg: &RouterConfig, stats: &RouterStats, controls: &RouterControls) -> Result<Self> {
+        let setup = setup_router_clients(cfg)?;
+        let mix_format = get_mix_format(&setup.source_client)?;
+        let init = initialize_router(
+            &setup.source_client,
+            &setup.output_clients,
+            &mix_format,
+            stats,
+            controls,
+            cfg,
+        )?;
+        let poll_interval = session_poll_interval(&setup);
+        Ok(Self {
+            setup,
+            init,
+            poll_interval,
+        })
+    }
+
+    /// 等待 stop 信号，最长 `timeout`。事件驱动模式下改为等待捕获事件，
+    /// 有数据时提前返回；loopback 捕获在部分系统上不会置位事件，
+    /// 此时等同于按 `timeout` 轮询。
+    fn wait(
+        &self,
+        stop_rx: &mpsc::Receiver<()>,
+        timeout: Duration,
+    ) -> Result<(), mpsc::RecvTimeoutError> {
+        let event = match &self.init.capture_event {
+            Some(event) if !timeout.is_zero() => event,
+            _ => return stop_rx.recv_timeout(timeout),
+        };
+        event.wait(timeout);
+        match stop_rx.try_recv() {
+            Ok(()) => Ok(()),
+            Err(mpsc::TryRecvError::Empty) => Err(mpsc::RecvTimeoutError::Timeout),
+            Err(mpsc::TryRecvError::Disconnected) => Err(mpsc::RecvTimeoutError::Disconnected),
+        }
+    }
+}
 
-impl ComApartment {
-    fn mta() -> Result<Self> {
-        unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }
-            .map_err(|e| anyhow::anyhow!("CoInitializeEx failed: {e:?}"))?;
-        Ok(Self)
+/// 根据捕获端的设备周期计算无数据时的等待时间。
+fn session_poll_interval(setup: &RouterSetupResult) -> Duration {
+    match get_device_period(&setup.source_client) {
+        Ok(period) => {
+            let interval = poll_interval(Some(period));
+            log::info!("Capture device period {period:?}, polling every {interval:?}");
+            interval
+        }
+        Err(e) => {
+            log::warn!("{e}; polling every {FALLBACK_POLL_INTERVAL:?}");
+            poll_interval(None)
+        }
     }
 }
 
-impl Drop for ComApartment {
-    fn drop(&mut self) {
-        unsafe {
-            CoUninitialize();
+/// 引擎每个设备周期产生一个 packet。等待半个周期（不超过
+/// [`MAX_POLL_INTERVAL`]）可以让短周期设备的 packet 就绪后尽快取走。
+fn poll_interval(device_period: Option<Duration>) -> Duration {
+    match device_period {
+        Some(period) if !period.is_zero() => {
+            (period / 2).clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL)
         }
+        _ => FALLBACK_POLL_INTERVAL,
     }
 }
 
-fn event_loop<F>(
-    init_res: &RouterInitialized,
-    mix_format: &MixFormat,
-    cb: &Arc<F>,
+fn event_loop(
+    session: &RoutingSession,
+    taps: &Taps,
+    stats: &RouterStats,
+    controls: &RouterControls,
     stop_rx: &mpsc::Receiver<()>,
-) -> Result<()>
-where
-    F: Fn(&[f32], u32, u16) + Send + Sync + 'static,
-{
+    event_tx: &mpsc::Sender<WorkerEvent>,
+) -> Result<LoopExit> {
+    let mut glitch_monitor = GlitchMonitor::new(GLITCH_EVENT_THRESHOLD);
+    glitch_monitor.reset_window(&stats.snapshot());
+    let mut clip_monitor = ClipMonitor::new(CLIP_EVENT_THRESHOLD);
+    clip_monitor.reset_window(&stats.performance().outputs);
+    let mut last_clip_check = Instant::now();
+    let mut window_start = Instant::now();
+    let mut wait = session.poll_interval;
+    // 捕获端按实时速度推进淡出，按时长判断结束；源没有声音（没有 packet）时同样成立
+    let mut fade_out_end = None;
+
     loop {
-        match stop_rx.recv_timeout(Duration::from_millis(3)) {
+        if let Some(device_id) = controls.take_source_switch() {
+            return Ok(LoopExit::SwitchSource(device_id));
+        }
+        if let Some(duration_ms) = controls.take_fade_out() {
+            session.init.start_fade_out(duration_ms);
+            fade_out_end = Some(
+                Instant::now()
+                    + Duration::from_secs_f32(duration_ms.max(0.0) / 1000.0)
+                    + FADE_OUT_MARGIN,
+            );
+        }
+        if fade_out_end.is_some_and(|end| Instant::now() >= end) {
+            fade_out_end = None;
+            let _ = event_tx.send(WorkerEvent::FadedOut);
+        }
+        match session.wait(stop_rx, wait) {
             Ok(()) => break,
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                // 持续处理所有可用的音频包，直到没有数据为止。
+                // 一次处理所有可用的音频包，直到没有数据为止。
                 // 这样可以及时处理音频，避免缓冲积累和抖动。
-                loop {
-                    let processed = process_next_packet(init_res, mix_format, cb.clone())?;
-                    if !processed {
-                        break;
+                let drained = process_available_packets(&session.init, taps, stats, controls)?;
+                // 达到单批上限说明仍有积压，检查完 stop 信号后立即继续追赶
+                wait = if drained >= MAX_PACKETS_PER_BATCH {
+                    Duration::ZERO
+                } else {
+                    session.poll_interval
+                };
+
+                let snapshot = stats.snapshot();
+                if let Some(count) = glitch_monitor.check(&snapshot) {
+                    log::warn!(
+                        "Capture glitches detected: {count} in {}s (discontinuities={}, timestamp_errors={})",
+                        GLITCH_WINDOW.as_secs(),
+                        snapshot.data_discontinuities,
+                        snapshot.timestamp_errors
+                    );
+                    let _ = event_tx.send(WorkerEvent::GlitchDetected {
+                        count,
+                        window_secs: GLITCH_WINDOW.as_secs(),
+                    });
+                }
+                let window_ended = window_start.elapsed() >= GLITCH_WINDOW;
+                if window_ended || last_clip_check.elapsed() >= CLIP_CHECK_INTERVAL {
+                    let outputs = stats.performance().outputs;
+                    for (device_id, count) in clip_monitor.check(&outputs) {
+                        log::warn!(
+                            "Output {device_id} clipped {count} samples in {}s",
+                            GLITCH_WINDOW.as_secs()
+                        );
+                        let _ = event_tx.send(WorkerEvent::Clipping {
+                            device_id,
+                            count,
+                            window_secs: GLITCH_WINDOW.as_secs(),
+                        });
+                    }
+                    if window_ended {
+                        clip_monitor.reset_window(&outputs);
                     }
+                    last_clip_check = Instant::now();
+                }
+                if window_ended {
+                    glitch_monitor.reset_window(&snapshot);
+                    window_start = Instant::now();
                 }
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
@@ -214,5 +405,35 @@ where
             }
         }
     }
-    Ok(())
+    Ok(LoopExit::Stopped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_interval_follows_device_period() {
+        // 低延迟周期 4ms → 等待 2ms
+        assert_eq!(
+            poll_interval(Some(Duration::from_millis(4))),
+            Duration::from_millis(2)
+        );
+        // 低延迟设备不会忙等
+        assert_eq!(
+            poll_interval(Some(Duration::from_micros(500))),
+            MIN_POLL_INTERVAL
+        );
+        // 典型共享模式周期 10ms 及更长的周期不会等待过久
+        assert_eq!(
+            poll_interval(Some(Duration::from_millis(10))),
+            MAX_POLL_INTERVAL
+        );
+        assert_eq!(
+            poll_interval(Some(Duration::from_millis(100))),
+            MAX_POLL_INTERVAL
+        );
+        assert_eq!(poll_interval(Some(Duration::ZERO)), FALLBACK_POLL_INTERVAL);
+        assert_eq!(poll_interval(None), FALLBACK_POLL_INTERVAL);
+    }
 }
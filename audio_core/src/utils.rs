@@ -1,5 +1,6 @@
 use crate::com_service::device::DeviceState;
 use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+use windows::Win32::System::Com::VT_UI4;
 use windows::Win32::UI::Shell::PropertiesSystem::{IPropertyStore, PROPERTYKEY};
 use windows::core::GUID;
 
@@ -87,11 +88,38 @@ pub mod win_helpers {
         None
     }
 
+    /// Reads a `VT_UI4` device property (e.g. the endpoint form factor).
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`read_property_string`].
+    pub unsafe fn read_property_u32(store: &IPropertyStore, key: &PROPERTYKEY) -> Option<u32> {
+        let mut pv = unsafe { store.GetValue(key) }.ok()?;
+        let value = unsafe {
+            let inner = &pv.Anonymous.Anonymous;
+            (inner.vt == VT_UI4).then_some(inner.Anonymous.ulVal)
+        };
+        unsafe { PropVariantClear(&mut pv) };
+        value
+    }
+
     /// Property key for device-friendly name.
     pub const PKEY_DEVICE_FRIENDLY: PROPERTYKEY = PROPERTYKEY {
         fmtid: GUID::from_u128(0xa45c254e_df1c_4efd_8020_67d146a850e0),
         pid: 14,
     };
+
+    /// Property key for the device icon (`PKEY_DeviceClass_IconPath`).
+    pub const PKEY_DEVICE_ICON_PATH: PROPERTYKEY = PROPERTYKEY {
+        fmtid: GUID::from_u128(0x259abffc_50a7_47ce_af08_68c9a7d73366),
+        pid: 12,
+    };
+
+    /// Property key for the bus enumerator (`PKEY_Device_EnumeratorName`).
+    pub const PKEY_DEVICE_ENUMERATOR_NAME: PROPERTYKEY = PROPERTYKEY {
+        fmtid: GUID::from_u128(0xa45c254e_df1c_4efd_8020_67d146a850e0),
+        pid: 24,
+    };
 }
 
 /// Decodes a WAVEFORMATEXTENSIBLE channel mask into readable speaker positions.
@@ -167,9 +195,18 @@ pub fn decode_channel_mask(mask: u32) -> Vec<&'static str> {
     positions
 }
 
+/// Fields of a device mix format reported in [`DeviceInfo`](crate::com_service::device::DeviceInfo).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MixFormatInfo {
+    pub channels: Option<u16>,
+    pub channel_mask: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub bits_per_sample: Option<u16>,
+}
+
 /// Parses a WAVEFORMATEX pointer returned by `IAudioClient::GetMixFormat`.
 ///
-/// Returns a tuple of `(channels, channel_mask)`. The pointer is freed via CoTaskMemFree.
+/// Returns all fields as `None` for a null pointer. The pointer is freed via CoTaskMemFree.
 ///
 /// # Safety
 ///
@@ -177,15 +214,17 @@ pub fn decode_channel_mask(mask: u32) -> Vec<&'static str> {
 /// by `IAudioClient::GetMixFormat`. This function always frees non-null input.
 pub unsafe fn parse_mix_format(
     pwf: *const windows::Win32::Media::Audio::WAVEFORMATEX,
-) -> (Option<u16>, Option<u32>) {
+) -> MixFormatInfo {
     use windows::Win32::Media::Audio::WAVEFORMATEX;
 
     if pwf.is_null() {
-        return (None, None);
+        return MixFormatInfo::default();
     }
 
     unsafe {
         let channels = (*pwf).nChannels;
+        let sample_rate = (*pwf).nSamplesPerSec;
+        let bits_per_sample = (*pwf).wBitsPerSample;
         let mut channel_mask = None;
 
         const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
@@ -204,6 +243,11 @@ pub unsafe fn parse_mix_format(
 
         // Free the memory allocated by GetMixFormat
         win_helpers::CoTaskMemFree(pwf as *mut _);
-        (Some(channels), channel_mask)
+        MixFormatInfo {
+            channels: Some(channels),
+            channel_mask,
+            sample_rate: Some(sample_rate),
+            bits_per_sample: Some(bits_per_sample),
+        }
     }
 }
@@ -1,27 +1,91 @@
+use crate::document::preserve_formatting;
+use crate::hotkeys::Hotkeys;
+use crate::location::ConfigDir;
+use crate::migrate::{CURRENT_CONFIG_VERSION, migrate};
+use crate::overrides::ConfigOverrides;
+use crate::preset::RoutingPreset;
+use crate::profile::Profile;
 use anyhow::{Context, Result};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct Config {
     pub config_version: i32,
     pub general: General,
-    pub source_device_id: String,
+    /// What happens when the app starts; saved with each profile
+    #[serde(default)]
+    pub startup: StartupSettings,
+    /// Device captured as the source
+    #[serde(default)]
+    pub source: SourceSelection,
     #[serde(default)]
     pub outputs: Vec<Output>,
+    #[serde(default)]
+    pub com: ComSettings,
+    #[serde(default)]
+    pub affinity: ThreadAffinity,
+    #[serde(default)]
+    pub engine: EngineSettings,
+    #[serde(default)]
+    pub bass_management: BassManagement,
+    #[serde(default)]
+    pub mix_levels: MixLevels,
+    #[serde(default)]
+    pub noise_gate: NoiseGateSettings,
+    /// Fade-in when routing starts, in milliseconds (0 disables it)
+    #[serde(default = "default_fade_in_ms")]
+    pub fade_in_ms: f32,
+    /// Window of the peak/RMS meters, in milliseconds
+    #[serde(default = "default_meter_window_ms")]
+    pub meter_window_ms: f32,
+    /// Whether to publish the FFT spectrum of the source while routing
+    #[serde(default)]
+    pub spectrum: bool,
+    /// Follow the source device's Windows volume slider and mute with the
+    /// gain of every routed output
+    #[serde(default)]
+    pub mirror_source_volume: bool,
+    /// Mute the source device while routing so its own speakers do not play
+    /// along with the outputs; it is unmuted again on stop
+    #[serde(default)]
+    pub mute_source_while_routing: bool,
+    /// Output device made the Windows default while routing (e.g. the virtual
+    /// cable being captured); the previous defaults are restored on stop
+    #[serde(default)]
+    pub default_device_while_routing: Option<String>,
+    /// Actions taken when a matching output device becomes active
+    #[serde(default)]
+    pub auto_route_rules: Vec<AutoRouteRule>,
+    /// What happens when output devices are plugged in or out
+    #[serde(default)]
+    pub hotplug: HotplugSettings,
+    /// Global hotkeys, see [`crate::hotkeys`]
+    #[serde(default)]
+    pub hotkeys: Hotkeys,
+    /// Profile the current settings were loaded from; `None` if they are not
+    /// saved under any name
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    /// Saved setups, see [`crate::profile`]
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Saved source and output sets, see [`crate::preset`]
+    #[serde(default)]
+    pub presets: Vec<RoutingPreset>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct General {
     pub language: String,
-    pub minimized: bool,          // Whether to start minimized to tray
     pub start_with_windows: bool, // Whether to launch app at system startup
-    pub auto_route: bool,         // Whether to auto-start routing on app launch
     #[serde(default = "default_true")]
     pub nav_pane_expanded: bool,  // Whether the navigation pane is expanded
     #[serde(default)]
@@ -30,6 +94,24 @@ pub struct General {
     pub close_to_tray: bool,      // Whether closing the window minimizes to tray
     #[serde(default = "default_true")]
     pub auto_update_check: bool,  // Whether to automatically check for updates on startup
+    #[serde(default = "default_backup_count")]
+    pub backup_count: u32, // How many previous settings files to keep as settings.toml.bak.N
+    #[serde(default)]
+    pub resume_last_state: bool, // Whether to restore the routing state of the last session on launch
+    #[serde(default = "default_true")]
+    pub notifications: bool, // Whether to show a desktop notification when routing is interrupted
+    #[serde(default)]
+    pub update_channel: UpdateChannel, // Which releases the update check offers
+}
+
+impl General {
+    /// Language codes the UI has translations for.
+    pub const LANGUAGES: [&str; 2] = ["en", "zh"];
+    pub const BACKUP_COUNT_RANGE: std::ops::RangeInclusive<u32> = 0..=50;
+}
+
+fn default_backup_count() -> u32 {
+    5
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
@@ -40,6 +122,231 @@ pub enum Backdrop {
     Acrylic,
 }
 
+/// Releases offered by the update check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum UpdateChannel {
+    /// Only full releases
+    #[default]
+    Stable,
+    /// Pre-releases as well
+    Beta,
+}
+
+/// COM apartment model used to run a class of COM work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum Apartment {
+    /// Single-threaded apartment (COINIT_APARTMENTTHREADED)
+    Sta,
+    /// Multi-threaded apartment (COINIT_MULTITHREADED)
+    #[default]
+    Mta,
+}
+
+/// Default device role the routing source can follow instead of a fixed device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum SourceRole {
+    /// Default device for games, system sounds and most applications
+    Console,
+    /// Default communications device, usually the headset used for calls
+    Communications,
+}
+
+/// Where the routing source comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum SourceSelection {
+    /// Whatever is the system default output device for the role; routing
+    /// restarts when the default changes
+    Default(SourceRole),
+    /// A fixed device; an empty id means none has been chosen yet
+    Device(String),
+}
+
+impl Default for SourceSelection {
+    fn default() -> Self {
+        SourceSelection::Default(SourceRole::Console)
+    }
+}
+
+impl SourceSelection {
+    /// The fixed source device, if one is chosen.
+    pub fn device_id(&self) -> Option<&str> {
+        match self {
+            SourceSelection::Device(id) if !id.is_empty() => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The default device role the source follows, if any.
+    pub fn role(&self) -> Option<SourceRole> {
+        match self {
+            SourceSelection::Default(role) => Some(*role),
+            SourceSelection::Device(_) => None,
+        }
+    }
+}
+
+/// Apartment choice measured on this machine. `None` means not calibrated yet.
+///
+/// The router worker always runs in the MTA; files that still have a
+/// `streaming_apartment` key load fine and ignore it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+pub struct ComSettings {
+    /// Apartment for device enumeration work
+    #[serde(default)]
+    pub enumeration_apartment: Option<Apartment>,
+}
+
+/// CPU placement of the audio worker thread.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ThreadAffinity {
+    /// Logical processor indices the thread may run on; empty means any
+    #[serde(default)]
+    pub cores: Vec<u32>,
+    /// On hybrid CPUs, keep the thread off efficiency cores
+    #[serde(default)]
+    pub exclude_efficiency_cores: bool,
+}
+
+impl ThreadAffinity {
+    /// Whether the thread is left to the OS scheduler.
+    pub fn is_unrestricted(&self) -> bool {
+        self.cores.is_empty() && !self.exclude_efficiency_cores
+    }
+}
+
+/// "When device X connects, route to it": applied whenever an output device
+/// whose name matches becomes active.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct AutoRouteRule {
+    /// Case-insensitive part of the device's friendly name, e.g. `WH-1000XM5`
+    pub device_name: String,
+    /// Enable the device as an output
+    #[serde(default = "default_true")]
+    pub enable_output: bool,
+    /// Channel mode given to the output (as in [`Output::channel_mode`]);
+    /// `None` keeps the current one
+    #[serde(default)]
+    pub channel_mode: Option<String>,
+    /// Start routing if it is not running yet
+    #[serde(default)]
+    pub start_routing: bool,
+}
+
+/// Behavior on app launch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct StartupSettings {
+    /// Start minimized to the tray
+    pub minimized: bool,
+    /// Start routing right away
+    pub auto_route: bool,
+}
+
+/// What happens to an output whose device is unplugged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum UnplugAction {
+    /// Keep the output enabled; it plays again as soon as the device returns
+    #[default]
+    KeepAndWait,
+    /// Disable the output; it stays off until enabled again
+    Disable,
+}
+
+/// How the app reacts to output devices appearing and disappearing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct HotplugSettings {
+    /// Enable devices that were never configured as outputs when they appear
+    pub auto_enable_new_devices: bool,
+    pub on_unplug: UnplugAction,
+    /// Emit an event when an output device is plugged in or out
+    pub notify: bool,
+}
+
+impl Default for HotplugSettings {
+    fn default() -> Self {
+        Self {
+            auto_enable_new_devices: false,
+            on_unplug: UnplugAction::default(),
+            notify: true,
+        }
+    }
+}
+
+/// Audio engine settings, read whenever a routing session opens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct EngineSettings {
+    /// WASAPI buffer requested for the capture and render streams, in
+    /// milliseconds
+    pub buffer_ms: u32,
+    pub mode: EngineMode,
+    pub thread_priority: ThreadPriority,
+    /// Resampler of outputs that do not choose their own
+    pub resampler: ResamplerQuality,
+    /// Overflow policy of outputs that do not choose their own
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            buffer_ms: 50,
+            mode: EngineMode::default(),
+            thread_priority: ThreadPriority::default(),
+            resampler: ResamplerQuality::default(),
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl EngineSettings {
+    pub const BUFFER_RANGE_MS: std::ops::RangeInclusive<u32> = 10..=1000;
+
+    pub fn buffer_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.buffer_ms.into())
+    }
+}
+
+/// How the router worker finds out that captured audio is ready.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum EngineMode {
+    /// Check for packets every half device period
+    #[default]
+    Polling,
+    /// Wake up when WASAPI signals the capture event; falls back to polling
+    /// where loopback capture does not signal it
+    EventDriven,
+}
+
+/// Scheduling priority of the router worker thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ThreadPriority {
+    #[default]
+    Normal,
+    /// THREAD_PRIORITY_HIGHEST
+    High,
+    /// THREAD_PRIORITY_TIME_CRITICAL
+    TimeCritical,
+    /// Registered with MMCSS as a "Pro Audio" task
+    ProAudio,
+}
+
+impl AutoRouteRule {
+    pub fn matches(&self, friendly_name: &str) -> bool {
+        !self.device_name.is_empty()
+            && friendly_name
+                .to_lowercase()
+                .contains(&self.device_name.to_lowercase())
+    }
+}
+
+impl ComSettings {
+    pub fn is_calibrated(&self) -> bool {
+        self.enumeration_apartment.is_some()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct Output {
     pub device_id: String,
@@ -48,6 +355,475 @@ pub struct Output {
     /// Mix mode: "Stereo", "Left", "Right", "Center", etc.
     #[serde(default)]
     pub channel_mode: Option<String>,
+    /// What to do when the render buffer is too full for the next packet;
+    /// `None` uses [`EngineSettings::overflow_policy`]
+    #[serde(default)]
+    pub overflow_policy: Option<OverflowPolicy>,
+    /// Gain matrix used when `channel_mode` is "Matrix"
+    #[serde(default)]
+    pub channel_matrix: Option<ChannelMatrix>,
+    /// Whether "Downmix" mode folds the LFE channel into L/R
+    #[serde(default)]
+    pub downmix_lfe: bool,
+    /// 10-band graphic EQ preset
+    #[serde(default)]
+    pub eq_preset: EqPreset,
+    /// Output gain in dB (0 = unchanged)
+    #[serde(default)]
+    pub gain_db: f32,
+    /// Delay added to this output, e.g. to line it up with slower speakers
+    #[serde(default)]
+    pub delay_ms: f32,
+    /// Output plays silence while routing keeps running
+    #[serde(default)]
+    pub muted: bool,
+    /// Bitmask of render channels whose polarity is inverted (bit 0 = first channel)
+    #[serde(default)]
+    pub phase_invert: u32,
+    /// Level trim of each render channel after mixing
+    #[serde(default)]
+    pub channel_trim: ChannelTrim,
+    /// Part this output plays in bass management
+    #[serde(default)]
+    pub bass_role: BassRole,
+    /// Brickwall limiter applied last
+    #[serde(default)]
+    pub limiter: LimiterSettings,
+    /// Loudness normalization towards a target level
+    #[serde(default)]
+    pub loudness: LoudnessSettings,
+    /// Headphone crossfeed of the front pair
+    #[serde(default)]
+    pub crossfeed: CrossfeedPreset,
+    /// Mid/side gains of the front pair (stereo width)
+    #[serde(default)]
+    pub mid_side: MidSideSettings,
+    /// Dither used when processed audio is written to a 16-bit stream
+    #[serde(default)]
+    pub dither: DitherMode,
+    /// Sample-rate conversion when the device runs at a different rate;
+    /// `None` uses [`EngineSettings::resampler`]
+    #[serde(default)]
+    pub resampler: Option<ResamplerQuality>,
+    /// Third-party effect plugins, processed in order after the EQ
+    #[serde(default)]
+    pub plugins: Vec<PluginSlot>,
+    /// Order of the reorderable DSP stages
+    #[serde(default)]
+    pub dsp_chain: DspChain,
+    /// Unix time (seconds) the device was first found missing; `None` while
+    /// it is present. The settings are kept and apply again when it returns.
+    #[serde(default)]
+    pub missing_since: Option<u64>,
+}
+
+impl Output {
+    pub const GAIN_RANGE_DB: std::ops::RangeInclusive<f32> = -60.0..=12.0;
+    pub const DELAY_RANGE_MS: std::ops::RangeInclusive<f32> = 0.0..=2000.0;
+
+    /// A disabled output for `device_id` with default settings.
+    pub fn new(device_id: String) -> Self {
+        Self {
+            device_id,
+            enabled: false,
+            channel_mode: None,
+            overflow_policy: None,
+            channel_matrix: None,
+            downmix_lfe: false,
+            eq_preset: EqPreset::default(),
+            gain_db: 0.0,
+            delay_ms: 0.0,
+            muted: false,
+            phase_invert: 0,
+            channel_trim: ChannelTrim::default(),
+            bass_role: BassRole::default(),
+            limiter: LimiterSettings::default(),
+            loudness: LoudnessSettings::default(),
+            crossfeed: CrossfeedPreset::default(),
+            mid_side: MidSideSettings::default(),
+            dither: DitherMode::default(),
+            resampler: None,
+            plugins: Vec::new(),
+            dsp_chain: DspChain::default(),
+            missing_since: None,
+        }
+    }
+}
+
+/// A CLAP effect plugin in an output's DSP chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct PluginSlot {
+    /// Path of the `.clap` file
+    pub path: String,
+    /// Plugin id inside the file; empty picks the first plugin
+    pub plugin_id: String,
+    pub enabled: bool,
+    /// Saved plugin state as hex; empty keeps the plugin defaults
+    pub state: String,
+}
+
+impl Default for PluginSlot {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            plugin_id: String::new(),
+            enabled: true,
+            state: String::new(),
+        }
+    }
+}
+
+impl PluginSlot {
+    pub fn validate(&self) -> Result<()> {
+        let extension = Path::new(&self.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("clap") => {}
+            // VST3 需要 C++ 接口的宿主实现，目前只支持 CLAP
+            Some("vst3") => anyhow::bail!("plugin {}: VST3 is not supported", self.path),
+            _ => anyhow::bail!("plugin {:?} is not a .clap file", self.path),
+        }
+        self.state_bytes()
+            .with_context(|| format!("plugin {}", self.path))?;
+        Ok(())
+    }
+
+    /// Decodes the saved state.
+    pub fn state_bytes(&self) -> Result<Vec<u8>> {
+        let hex = self.state.as_bytes();
+        if !hex.len().is_multiple_of(2) {
+            anyhow::bail!("state has an odd number of hex digits");
+        }
+        hex.chunks_exact(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .context("state is not valid hex")
+            })
+            .collect()
+    }
+
+    /// Stores `bytes` as the saved state.
+    pub fn set_state_bytes(&mut self, bytes: &[u8]) {
+        self.state = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    }
+}
+
+/// Per-output brickwall limiter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct LimiterSettings {
+    pub enabled: bool,
+    /// Highest output level in dBFS
+    pub threshold_db: f32,
+    /// Time for the gain to recover after a peak, in milliseconds
+    pub release_ms: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -1.0,
+            release_ms: 50.0,
+        }
+    }
+}
+
+/// Gate on the captured signal, applied before it is routed to any output.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct NoiseGateSettings {
+    pub enabled: bool,
+    /// Level below which the gate closes, in dBFS
+    pub threshold_db: f32,
+    /// Time the gate stays open after the signal drops, in milliseconds
+    pub hold_ms: f32,
+    /// Fade-out time once the hold has expired, in milliseconds
+    pub release_ms: f32,
+}
+
+impl Default for NoiseGateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -60.0,
+            hold_ms: 100.0,
+            release_ms: 200.0,
+        }
+    }
+}
+
+impl NoiseGateSettings {
+    pub const THRESHOLD_RANGE_DB: std::ops::RangeInclusive<f32> = -90.0..=0.0;
+    pub const HOLD_RANGE_MS: std::ops::RangeInclusive<f32> = 0.0..=2000.0;
+    pub const RELEASE_RANGE_MS: std::ops::RangeInclusive<f32> = 1.0..=2000.0;
+
+    pub fn validate(&self) -> Result<()> {
+        if !Self::THRESHOLD_RANGE_DB.contains(&self.threshold_db) {
+            anyhow::bail!(
+                "noise gate threshold {} dB is out of range",
+                self.threshold_db
+            );
+        }
+        if !Self::HOLD_RANGE_MS.contains(&self.hold_ms) {
+            anyhow::bail!("noise gate hold {} ms is out of range", self.hold_ms);
+        }
+        if !Self::RELEASE_RANGE_MS.contains(&self.release_ms) {
+            anyhow::bail!("noise gate release {} ms is out of range", self.release_ms);
+        }
+        Ok(())
+    }
+}
+
+/// Per-output loudness normalization (EBU R128).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct LoudnessSettings {
+    pub enabled: bool,
+    /// Loudness the output is steered to, in LUFS
+    pub target_lufs: f32,
+    /// Largest boost or cut applied, in dB
+    pub max_gain_db: f32,
+}
+
+impl Default for LoudnessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lufs: -18.0,
+            max_gain_db: 12.0,
+        }
+    }
+}
+
+impl LoudnessSettings {
+    pub const TARGET_RANGE_LUFS: std::ops::RangeInclusive<f32> = -40.0..=-5.0;
+    pub const MAX_GAIN_RANGE_DB: std::ops::RangeInclusive<f32> = 0.0..=24.0;
+
+    pub fn validate(&self) -> Result<()> {
+        if !Self::TARGET_RANGE_LUFS.contains(&self.target_lufs) {
+            anyhow::bail!("loudness target {} LUFS is out of range", self.target_lufs);
+        }
+        if !Self::MAX_GAIN_RANGE_DB.contains(&self.max_gain_db) {
+            anyhow::bail!("loudness max gain {} dB is out of range", self.max_gain_db);
+        }
+        Ok(())
+    }
+}
+
+/// Per-output mid/side processing of the front pair: L/R is split into
+/// mid (L+R) and side (L−R), each gets its own gain, and the result is
+/// converted back. A side gain below 0 dB narrows the image, above widens it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct MidSideSettings {
+    pub enabled: bool,
+    /// Gain of the mid signal in dB; the bottom of the range mutes it
+    pub mid_db: f32,
+    /// Gain of the side signal in dB; the bottom of the range mutes it
+    pub side_db: f32,
+}
+
+impl Default for MidSideSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mid_db: 0.0,
+            side_db: 0.0,
+        }
+    }
+}
+
+impl MidSideSettings {
+    pub const GAIN_RANGE_DB: std::ops::RangeInclusive<f32> = -60.0..=12.0;
+
+    pub fn validate(&self) -> Result<()> {
+        if !Self::GAIN_RANGE_DB.contains(&self.mid_db) {
+            anyhow::bail!("mid gain {} dB is out of range", self.mid_db);
+        }
+        if !Self::GAIN_RANGE_DB.contains(&self.side_db) {
+            anyhow::bail!("side gain {} dB is out of range", self.side_db);
+        }
+        Ok(())
+    }
+}
+
+impl LimiterSettings {
+    pub const THRESHOLD_RANGE_DB: std::ops::RangeInclusive<f32> = -30.0..=0.0;
+    pub const RELEASE_RANGE_MS: std::ops::RangeInclusive<f32> = 1.0..=1000.0;
+
+    pub fn validate(&self) -> Result<()> {
+        if !Self::THRESHOLD_RANGE_DB.contains(&self.threshold_db) {
+            anyhow::bail!("limiter threshold {} dB is out of range", self.threshold_db);
+        }
+        if !Self::RELEASE_RANGE_MS.contains(&self.release_ms) {
+            anyhow::bail!("limiter release {} ms is out of range", self.release_ms);
+        }
+        Ok(())
+    }
+}
+
+/// Role of an output in bass management.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum BassRole {
+    /// Receives the full frequency range
+    #[default]
+    FullRange,
+    /// Receives only what is above the crossover
+    Satellite,
+    /// Receives the lows of all channels summed to mono
+    Subwoofer,
+}
+
+/// Crossover shared by the satellite and subwoofer outputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct BassManagement {
+    /// Crossover frequency in Hz
+    #[serde(default = "default_crossover_hz")]
+    pub crossover_hz: f32,
+}
+
+impl Default for BassManagement {
+    fn default() -> Self {
+        Self {
+            crossover_hz: default_crossover_hz(),
+        }
+    }
+}
+
+impl BassManagement {
+    /// Crossover frequencies accepted by [`Config::validate`].
+    pub const CROSSOVER_RANGE_HZ: std::ops::RangeInclusive<f32> = 40.0..=250.0;
+}
+
+fn default_crossover_hz() -> f32 {
+    80.0
+}
+
+/// Linear gains of the channels that "Downmix" folds into L/R and "Upmix"
+/// derives from L/R.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct MixLevels {
+    /// Center (and back center) into L/R; ITU-R BS.775 uses -3 dB
+    pub downmix_center: f32,
+    /// Surrounds into their side's front channel
+    pub downmix_surround: f32,
+    /// LFE into L/R, for outputs that include it
+    pub downmix_lfe: f32,
+    /// Center from L+R
+    pub upmix_center: f32,
+    /// Surrounds from the L/R difference (ambience)
+    pub upmix_surround: f32,
+    /// LFE from L+R
+    pub upmix_lfe: f32,
+}
+
+impl Default for MixLevels {
+    fn default() -> Self {
+        let minus_3db = std::f32::consts::FRAC_1_SQRT_2;
+        Self {
+            downmix_center: minus_3db,
+            downmix_surround: minus_3db,
+            downmix_lfe: minus_3db,
+            upmix_center: 0.5,
+            upmix_surround: 0.5,
+            upmix_lfe: 0.5,
+        }
+    }
+}
+
+impl MixLevels {
+    /// Highest accepted level (+6 dB).
+    pub const MAX: f32 = 2.0;
+
+    /// Checks that every level is a gain between 0 and [`MixLevels::MAX`].
+    pub fn validate(&self) -> Result<()> {
+        for (name, level) in [
+            ("downmix_center", self.downmix_center),
+            ("downmix_surround", self.downmix_surround),
+            ("downmix_lfe", self.downmix_lfe),
+            ("upmix_center", self.upmix_center),
+            ("upmix_surround", self.upmix_surround),
+            ("upmix_lfe", self.upmix_lfe),
+        ] {
+            if !(0.0..=Self::MAX).contains(&level) {
+                anyhow::bail!("mix level {name} = {level} is outside 0..={}", Self::MAX);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Named 10-band graphic EQ curves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum EqPreset {
+    /// No equalization
+    #[default]
+    Flat,
+    /// Lift the lowest octaves
+    BassBoost,
+    /// Cut rumble, emphasize voice intelligibility
+    Speech,
+}
+
+/// Bauer crossfeed strengths for headphone outputs (bs2b presets).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum CrossfeedPreset {
+    #[default]
+    Off,
+    /// 700 Hz, 4.5 dB: closest to a virtual speaker pair
+    Default,
+    /// 700 Hz, 6 dB
+    ChuMoy,
+    /// 650 Hz, 9.5 dB: strongest, for hard-panned recordings
+    JanMeier,
+}
+
+/// Requantization of processed audio to 16-bit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum DitherMode {
+    /// Plain truncation
+    Off,
+    /// Triangular (TPDF) dither
+    #[default]
+    Tpdf,
+    /// TPDF dither with first-order noise shaping
+    NoiseShaped,
+}
+
+/// Who converts the source rate to the output device's rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ResamplerQuality {
+    /// Let the Windows audio engine convert (AUTOCONVERTPCM)
+    #[default]
+    System,
+    /// Short sinc kernel, lowest CPU use
+    Fast,
+    Balanced,
+    /// Long sinc kernel, steepest anti-aliasing filter
+    High,
+}
+
+/// How an output handles a render buffer that is above its latency target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum OverflowPolicy {
+    /// Skip the incoming packet (lowest latency, short gaps)
+    #[default]
+    DropNewest,
+    /// Trim the start of incoming packets until back at the target; flush
+    /// the queued audio only when a packet does not fit
+    DropOldest,
+    /// Ignore the target and write while there is room (latency grows up to
+    /// the buffer size); skip packets that do not fit
+    Block,
+    /// Skip packets until the buffer drains well below the target, then resume
+    Resync,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
@@ -60,6 +836,12 @@ pub enum ChannelMode {
     Swap,
     LeftOnly,
     RightOnly,
+    /// Fold a surround source down to stereo (ITU-R BS.775 coefficients)
+    Downmix,
+    /// Spread a stereo source over all speakers of a surround output
+    Upmix,
+    /// Custom gains from [`Output::channel_matrix`]
+    Matrix,
 }
 
 impl ChannelMode {
@@ -71,6 +853,9 @@ impl ChannelMode {
             Some("Swap") => Self::Swap,
             Some("LeftOnly") => Self::LeftOnly,
             Some("RightOnly") => Self::RightOnly,
+            Some("Downmix") => Self::Downmix,
+            Some("Upmix") => Self::Upmix,
+            Some("Matrix") => Self::Matrix,
             _ => Self::Stereo,
         }
     }
@@ -84,86 +869,881 @@ impl ChannelMode {
             Self::Swap => "Swap",
             Self::LeftOnly => "LeftOnly",
             Self::RightOnly => "RightOnly",
+            Self::Downmix => "Downmix",
+            Self::Upmix => "Upmix",
+            Self::Matrix => "Matrix",
+        }
+    }
+}
+
+/// Custom channel routing gains indexed `[input][output]`: the linear gain from
+/// capture channel `input` to render channel `output`.
+///
+/// For example `[[1.0, 0.0, 0.5], [0.0, 1.0, 0.5]]` sends L→0, R→1 and
+/// (L+R)*0.5→2. Serialized as a plain nested array.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+pub struct ChannelMatrix(pub Vec<Vec<f32>>);
+
+impl ChannelMatrix {
+    /// Pass-through matrix for `channels` channels.
+    pub fn identity(channels: usize) -> Self {
+        Self(
+            (0..channels)
+                .map(|i| {
+                    (0..channels)
+                        .map(|o| if i == o { 1.0 } else { 0.0 })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Number of input (capture) channels.
+    pub fn inputs(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Number of output (render) channels.
+    pub fn outputs(&self) -> usize {
+        self.0.first().map_or(0, Vec::len)
+    }
+
+    /// Gain from `input` to `output`; 0.0 outside the matrix.
+    pub fn gain(&self, input: usize, output: usize) -> f32 {
+        self.0
+            .get(input)
+            .and_then(|row| row.get(output))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let outputs = self.outputs();
+        if outputs == 0 {
+            anyhow::bail!("channel matrix is empty");
+        }
+        if let Some(i) = self.0.iter().position(|row| row.len() != outputs) {
+            anyhow::bail!(
+                "channel matrix row {i} has {} gains, expected {outputs}",
+                self.0[i].len()
+            );
+        }
+        if self.0.iter().flatten().any(|g| !g.is_finite()) {
+            anyhow::bail!("channel matrix contains a non-finite gain");
+        }
+        Ok(())
+    }
+}
+
+/// Per-channel level trim in dB, indexed by render channel, e.g.
+/// `[0.0, 0.0, -1.5, 2.0]`. Channels without an entry are left at 0 dB.
+/// Serialized as a plain array.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+pub struct ChannelTrim(pub Vec<f32>);
+
+impl ChannelTrim {
+    pub const RANGE_DB: std::ops::RangeInclusive<f32> = -24.0..=12.0;
+    /// One entry per speaker position a channel mask can describe.
+    pub const MAX_CHANNELS: usize = 32;
+
+    /// Trim of render channel `channel` in dB.
+    pub fn db(&self, channel: usize) -> f32 {
+        self.0.get(channel).copied().unwrap_or(0.0)
+    }
+
+    /// No channel is trimmed.
+    pub fn is_flat(&self) -> bool {
+        self.0.iter().all(|&db| db == 0.0)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.0.len() > Self::MAX_CHANNELS {
+            anyhow::bail!("channel trim has {} entries", self.0.len());
+        }
+        if let Some(i) = self.0.iter().position(|db| !Self::RANGE_DB.contains(db)) {
+            anyhow::bail!("channel {i} trim {} dB is out of range", self.0[i]);
+        }
+        Ok(())
+    }
+}
+
+/// A reorderable stage of an output's DSP chain. Channel mapping, bass
+/// management, polarity and channel trim always run before the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum DspStage {
+    MidSide,
+    Crossfeed,
+    Eq,
+    Plugins,
+    Loudness,
+    Gain,
+    Limiter,
+}
+
+impl DspStage {
+    pub const DEFAULT_ORDER: [DspStage; 7] = [
+        DspStage::MidSide,
+        DspStage::Crossfeed,
+        DspStage::Eq,
+        DspStage::Plugins,
+        DspStage::Loudness,
+        DspStage::Gain,
+        DspStage::Limiter,
+    ];
+}
+
+/// Processing order of an output's DSP stages, e.g. `["Gain", "Eq"]`.
+///
+/// Stages that are not listed run after the listed ones, in their default
+/// order. Serialized as a plain array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DspChain(pub Vec<DspStage>);
+
+impl Default for DspChain {
+    fn default() -> Self {
+        Self(DspStage::DEFAULT_ORDER.to_vec())
+    }
+}
+
+impl DspChain {
+    /// Every stage once, in processing order.
+    pub fn stages(&self) -> Vec<DspStage> {
+        let mut stages = self.0.clone();
+        for stage in DspStage::DEFAULT_ORDER {
+            if !stages.contains(&stage) {
+                stages.push(stage);
+            }
+        }
+        stages
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (i, stage) in self.0.iter().enumerate() {
+            if self.0[..i].contains(stage) {
+                anyhow::bail!("DSP stage {stage:?} is listed more than once");
+            }
+        }
+        // 限幅器之后的处理可能再次越过阈值
+        if self.stages().last() != Some(&DspStage::Limiter) {
+            anyhow::bail!("the limiter must be the last DSP stage");
+        }
+        Ok(())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub(crate) fn default_fade_in_ms() -> f32 {
+    50.0
+}
+
+fn default_meter_window_ms() -> f32 {
+    300.0
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            config_version: CURRENT_CONFIG_VERSION,
+            general: General {
+                language: "en".to_string(),
+                start_with_windows: false,
+                nav_pane_expanded: true,
+                backdrop: Backdrop::default(),
+                close_to_tray: true,
+                auto_update_check: true,
+                backup_count: default_backup_count(),
+                resume_last_state: false,
+                notifications: true,
+                update_channel: UpdateChannel::default(),
+            },
+            startup: StartupSettings::default(),
+            source: SourceSelection::default(),
+            outputs: Vec::new(),
+            com: ComSettings::default(),
+            affinity: ThreadAffinity::default(),
+            engine: EngineSettings::default(),
+            bass_management: BassManagement::default(),
+            mix_levels: MixLevels::default(),
+            noise_gate: NoiseGateSettings::default(),
+            fade_in_ms: default_fade_in_ms(),
+            meter_window_ms: default_meter_window_ms(),
+            spectrum: false,
+            mirror_source_volume: false,
+            mute_source_while_routing: false,
+            default_device_while_routing: None,
+            auto_route_rules: Vec::new(),
+            hotplug: HotplugSettings::default(),
+            hotkeys: Hotkeys::default(),
+            active_profile: None,
+            profiles: Vec::new(),
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub const FADE_IN_RANGE_MS: std::ops::RangeInclusive<f32> = 0.0..=5000.0;
+    pub const METER_WINDOW_RANGE_MS: std::ops::RangeInclusive<f32> = 10.0..=3000.0;
+
+    /// Flags the outputs whose device is not in `present` as missing since
+    /// `now`, and clears the flag of those that are. Returns whether any
+    /// output changed.
+    pub fn track_missing_outputs(&mut self, present: &[&str], now: u64) -> bool {
+        let mut changed = false;
+        for output in &mut self.outputs {
+            let missing_since = if present.contains(&output.device_id.as_str()) {
+                None
+            } else {
+                Some(output.missing_since.unwrap_or(now))
+            };
+            changed |= output.missing_since != missing_since;
+            output.missing_since = missing_since;
+        }
+        changed
+    }
+
+    /// Puts the settings in `scope` back to their defaults. Saved profiles
+    /// and presets are only removed by [`ResetScope::All`]; the COM
+    /// calibration of this machine is always kept.
+    pub fn reset(&mut self, scope: ResetScope) {
+        let defaults = Config::default();
+        match scope {
+            ResetScope::Routing => {
+                self.source = defaults.source;
+                self.outputs = defaults.outputs;
+                self.affinity = defaults.affinity;
+                self.engine = defaults.engine;
+                self.bass_management = defaults.bass_management;
+                self.mix_levels = defaults.mix_levels;
+                self.noise_gate = defaults.noise_gate;
+                self.fade_in_ms = defaults.fade_in_ms;
+                self.mirror_source_volume = defaults.mirror_source_volume;
+                self.mute_source_while_routing = defaults.mute_source_while_routing;
+                self.default_device_while_routing = defaults.default_device_while_routing;
+                self.auto_route_rules = defaults.auto_route_rules;
+                self.hotplug = defaults.hotplug;
+            }
+            ResetScope::Preferences => {
+                self.general = defaults.general;
+                self.startup = defaults.startup;
+                self.meter_window_ms = defaults.meter_window_ms;
+                self.spectrum = defaults.spectrum;
+                self.hotkeys = defaults.hotkeys;
+            }
+            ResetScope::All => {
+                let com = std::mem::take(&mut self.com);
+                *self = Config { com, ..defaults };
+            }
+        }
+    }
+
+    /// Checks the whole config, including the saved profiles.
+    ///
+    /// # Errors
+    /// Returns a [`ValidationErrors`] listing every invalid field; downcast
+    /// the error to show the problems next to the fields they belong to.
+    pub fn validate(&self) -> Result<()> {
+        let errors = self.field_errors();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors).into())
+        }
+    }
+
+    /// Every invalid field of the config; empty if it is valid.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        let mut errors = FieldErrors::default();
+        let crossover = self.bass_management.crossover_hz;
+        if !BassManagement::CROSSOVER_RANGE_HZ.contains(&crossover) {
+            errors.push(
+                "bass_management.crossover_hz",
+                format!("crossover {crossover} Hz is out of range"),
+            );
+        }
+        errors.check("mix_levels", self.mix_levels.validate());
+        errors.check("noise_gate", self.noise_gate.validate());
+        if !EngineSettings::BUFFER_RANGE_MS.contains(&self.engine.buffer_ms) {
+            errors.push(
+                "engine.buffer_ms",
+                format!("buffer of {} ms is out of range", self.engine.buffer_ms),
+            );
+        }
+        if !Self::FADE_IN_RANGE_MS.contains(&self.fade_in_ms) {
+            errors.push(
+                "fade_in_ms",
+                format!("fade-in {} ms is out of range", self.fade_in_ms),
+            );
+        }
+        if !Self::METER_WINDOW_RANGE_MS.contains(&self.meter_window_ms) {
+            errors.push(
+                "meter_window_ms",
+                format!("meter window {} ms is out of range", self.meter_window_ms),
+            );
+        }
+        if !General::LANGUAGES.contains(&self.general.language.as_str()) {
+            errors.push(
+                "general.language",
+                format!("unknown language {:?}", self.general.language),
+            );
+        }
+        if !General::BACKUP_COUNT_RANGE.contains(&self.general.backup_count) {
+            errors.push(
+                "general.backup_count",
+                format!("{} backups is out of range", self.general.backup_count),
+            );
+        }
+        for (i, rule) in self.auto_route_rules.iter().enumerate() {
+            if rule.device_name.trim().is_empty() {
+                errors.push(
+                    format!("auto_route_rules[{i}].device_name"),
+                    "device name is empty",
+                );
+            }
+        }
+        // 跟随默认设备时源是哪个设备要到路由时才知道，届时跳过与源相同的输出
+        if let Some(source) = self.source.device_id()
+            && self
+                .outputs
+                .iter()
+                .any(|o| o.enabled && o.device_id == source)
+        {
+            errors.push(
+                "source",
+                format!("source {source} is also an enabled output"),
+            );
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            let field = |name: &str| format!("outputs[{i}].{name}");
+            if self.outputs[..i]
+                .iter()
+                .any(|o| o.device_id == output.device_id)
+            {
+                errors.push(
+                    field("device_id"),
+                    format!("output {} is listed more than once", output.device_id),
+                );
+            }
+            if let Some(matrix) = &output.channel_matrix {
+                errors.check(field("channel_matrix"), matrix.validate());
+            }
+            if !Output::GAIN_RANGE_DB.contains(&output.gain_db) {
+                errors.push(
+                    field("gain_db"),
+                    format!("gain {} dB is out of range", output.gain_db),
+                );
+            }
+            if !Output::DELAY_RANGE_MS.contains(&output.delay_ms) {
+                errors.push(
+                    field("delay_ms"),
+                    format!("delay {} ms is out of range", output.delay_ms),
+                );
+            }
+            errors.check(field("limiter"), output.limiter.validate());
+            errors.check(field("loudness"), output.loudness.validate());
+            errors.check(field("mid_side"), output.mid_side.validate());
+            errors.check(field("channel_trim"), output.channel_trim.validate());
+            errors.check(field("dsp_chain"), output.dsp_chain.validate());
+            for (j, plugin) in output.plugins.iter().enumerate() {
+                errors.check(field(&format!("plugins[{j}]")), plugin.validate());
+            }
+        }
+        errors.0.extend(self.hotkey_field_errors());
+        errors.0.extend(self.profile_field_errors());
+        errors.0.extend(self.preset_field_errors());
+        errors.0
+    }
+}
+
+/// A problem with one config field, as reported by [`Config::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct FieldError {
+    /// Path of the field, e.g. `outputs[1].gain_db` or `general.language`
+    pub field: String,
+    pub message: String,
+}
+
+/// Error returned by [`Config::validate`]: every invalid field, in config order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// A problem found by [`ConfigManager::validate_file`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ValidationIssue {
+    /// Path of the invalid field; `None` if the file as a whole cannot be
+    /// read, parsed or migrated
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl From<FieldError> for ValidationIssue {
+    fn from(error: FieldError) -> Self {
+        Self {
+            field: Some(error.field),
+            message: error.message,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Records the error of a nested `validate()` call under `field`.
+    fn check(&mut self, field: impl Into<String>, result: Result<()>) {
+        if let Err(e) = result {
+            self.push(field, format!("{e:#}"));
+        }
+    }
+}
+
+/// Manager providing thread-safe access and persistence.
+pub struct ConfigManager {
+    path: PathBuf,
+    inner: Arc<RwLock<Config>>,
+    /// 最近一次读写后文件的修改时间和大小，用于发现外部修改。
+    file_stamp: Mutex<Option<FileStamp>>,
+    /// 最近一次读写的文件内容的哈希；保存前与磁盘上的内容比较，
+    /// 避免覆盖其他进程在此期间写入的修改
+    file_hash: Mutex<u64>,
+    /// 每次从文件读取后叠加，保存时不写入文件
+    overrides: ConfigOverrides,
+    /// 启动时设置文件损坏、已改用备份或默认配置时的说明
+    recovery: Option<LoadRecovery>,
+    /// 延迟写入的等待时间；`None` 时每次 update 立即保存
+    write_delay: Option<Duration>,
+    /// 最近一次尚未写入文件的修改时间
+    unsaved_since: Mutex<Option<Instant>>,
+}
+
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn content_hash(s: &str) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Takes the advisory lock on the settings file at `path`, held by every
+/// process (other instances, scripts) while it reads or writes the file.
+/// Released when the returned handle is dropped.
+fn lock_settings(path: &Path) -> Result<fs::File> {
+    let lock_path = path.with_extension("toml.lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("opening config lock: {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("locking config: {}", lock_path.display()))?;
+    Ok(file)
+}
+
+/// Error returned by [`ConfigManager::save`] when the settings file was
+/// changed by another program since the manager last read or wrote it.
+///
+/// The unsaved config stays in memory: [`ConfigManager::reload_if_changed`]
+/// replaces it with the file, [`ConfigManager::save_overwriting`] writes it
+/// over the other program's changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigConflict {
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for ConfigConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was changed by another program; reload it or overwrite it",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for ConfigConflict {}
+
+/// How [`ConfigManager::load`] recovered from a `settings.toml` it could
+/// not read, for the UI to tell the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct LoadRecovery {
+    /// Where the broken file was moved to
+    pub quarantined: PathBuf,
+    /// Backup the settings were restored from (see
+    /// [`ConfigManager::backups`]); `None` if the defaults are used
+    pub restored_backup: Option<u32>,
+    /// Why the file could not be loaded
+    pub reason: String,
+}
+
+/// File format for [`ConfigManager::export`] and [`ConfigManager::import`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format implied by the file extension (`.toml` or `.json`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
         }
     }
 }
 
-fn default_true() -> bool {
-    true
+/// Group of settings for [`ConfigManager::reset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ResetScope {
+    /// Source, outputs with their DSP, session-wide processing, engine, auto
+    /// route rules and hotplug rules
+    Routing,
+    /// Language, autostart, window and startup behavior, meters and hotkeys
+    Preferences,
+    /// Everything, including saved profiles and presets
+    All,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            config_version: 1,
-            general: General {
-                language: "en".to_string(),
-                auto_route: false,
-                minimized: false,
-                start_with_windows: false,
-                nav_pane_expanded: true,
-                backdrop: Backdrop::default(),
-                close_to_tray: true,
-                auto_update_check: true,
-            },
-            source_device_id: String::new(),
-            outputs: Vec::new(),
+/// Removes `null` object members (exported `None` fields), which TOML cannot
+/// represent; a missing field deserializes to `None` again.
+fn strip_json_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_json_nulls);
         }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_json_nulls),
+        _ => {}
     }
 }
 
-impl Config {
-    pub fn validate(&self) -> Result<()> {
-        // Currently no validation needed
-        Ok(())
+/// Reads a settings file or export. The format is taken from the extension;
+/// other files are read as JSON if they start with `{` and as TOML otherwise.
+fn read_config_table(path: &Path) -> Result<toml::Table> {
+    let s = fs::read_to_string(path)
+        .with_context(|| format!("reading config file: {}", path.display()))?;
+    let format = ConfigFormat::from_path(path).unwrap_or_else(|| {
+        if s.trim_start().starts_with('{') {
+            ConfigFormat::Json
+        } else {
+            ConfigFormat::Toml
+        }
+    });
+    match format {
+        ConfigFormat::Toml => toml::from_str(&s).context("parsing TOML config"),
+        ConfigFormat::Json => {
+            let mut value: serde_json::Value =
+                serde_json::from_str(&s).context("parsing JSON config")?;
+            strip_json_nulls(&mut value);
+            serde_json::from_value(value).context("parsing JSON config")
+        }
     }
 }
 
-/// Manager providing thread-safe access and persistence.
-pub struct ConfigManager {
-    path: PathBuf,
-    inner: Arc<RwLock<Config>>,
+/// Migrates a parsed settings file to the current schema, applies the
+/// overrides and deserializes it. Returns the config and the version the file
+/// was written with.
+fn config_from_table(mut table: toml::Table, overrides: &ConfigOverrides) -> Result<(Config, i32)> {
+    let version = migrate(&mut table).context("migrating config")?;
+    overrides.apply(&mut table)?;
+    let cfg: Config = toml::Value::Table(table)
+        .try_into()
+        .context("parsing config")?;
+    if !overrides.is_empty() {
+        overrides.check_known(&toml::Table::try_from(&cfg).context("serializing config")?)?;
+    }
+    cfg.validate()?;
+    Ok((cfg, version))
+}
+
+/// [`config_from_table`] for the text of a settings file.
+fn config_from_str(s: &str, overrides: &ConfigOverrides) -> Result<(Config, i32)> {
+    let table: toml::Table = toml::from_str(s).context("parsing TOML config")?;
+    config_from_table(table, overrides)
+}
+
+fn backup_file(path: &Path, n: u32) -> PathBuf {
+    path.with_extension(format!("toml.bak.{n}"))
+}
+
+/// Whether `s` comes from a newer version of the app. Such a file is not
+/// broken, so it is reported instead of being moved aside.
+fn written_by_newer_version(s: &str) -> bool {
+    toml::from_str::<toml::Table>(s)
+        .ok()
+        .and_then(|table| table.get("config_version")?.as_integer())
+        .is_some_and(|version| version > i64::from(CURRENT_CONFIG_VERSION))
+}
+
+/// Moves the unreadable settings file at `path` aside and puts the most
+/// recent valid backup, or the defaults if there is none, in its place.
+/// Returns the new file content and the config read from it.
+fn recover_settings(
+    path: &Path,
+    overrides: &ConfigOverrides,
+    error: &anyhow::Error,
+) -> Result<(String, (Config, i32), LoadRecovery)> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // 同一秒内多次损坏时不覆盖之前隔离的文件
+    let quarantined = (0..)
+        .map(|i| match i {
+            0 => path.with_extension(format!("toml.corrupt.{now}")),
+            i => path.with_extension(format!("toml.corrupt.{now}-{i}")),
+        })
+        .find(|candidate| !candidate.exists())
+        .context("no free name for the broken config")?;
+    fs::rename(path, &quarantined)
+        .with_context(|| format!("moving broken config to {}", quarantined.display()))?;
+
+    let restored = (1..)
+        .map(|n| (n, backup_file(path, n)))
+        .take_while(|(_, backup)| backup.exists())
+        .find_map(|(n, backup)| {
+            let s = fs::read_to_string(backup).ok()?;
+            let loaded = config_from_str(&s, overrides).ok()?;
+            Some((Some(n), s, loaded))
+        });
+    let (restored_backup, s, loaded) = match restored {
+        Some(restored) => restored,
+        None => {
+            let s =
+                toml::to_string_pretty(&Config::default()).context("serializing default config")?;
+            let loaded = config_from_str(&s, overrides)?;
+            (None, s, loaded)
+        }
+    };
+    fs::write(path, &s).with_context(|| format!("writing config file: {}", path.display()))?;
+    let recovery = LoadRecovery {
+        quarantined,
+        restored_backup,
+        reason: format!("{error:#}"),
+    };
+    Ok((s, loaded, recovery))
 }
 
 impl ConfigManager {
-    /// Load config from given base path (parent directory), or from default directory if None.
+    /// Load config from given base path (parent directory), or from the
+    /// directory resolved by [`ConfigDir::resolve`] for this process if None.
     /// If file does not exist, a default config is created and written.
+    ///
+    /// A file written with an older `config_version` is migrated (see
+    /// [`crate::migrate`]) and saved back; the original is kept next to it as
+    /// `settings.v<N>.toml.bak`.
+    ///
+    /// A file that cannot be read as a valid config is moved aside as
+    /// `settings.toml.corrupt.<unix time>` and replaced with the most recent
+    /// valid backup, or the defaults; [`Self::recovery`] then says so. Files
+    /// from a newer version of the app are an error instead.
     pub fn load(basepath: Option<PathBuf>) -> Result<Self> {
-        let config_dir = basepath.unwrap_or_else(default_config_dir);
+        Self::load_with_overrides(basepath, ConfigOverrides::default())
+    }
+
+    /// Like [`Self::load`], with `overrides` layered over the file now and
+    /// on every later reload. They are not saved to the file.
+    ///
+    /// # Errors
+    /// Besides the errors of [`Self::load`], fails if an override names an
+    /// unknown setting or makes the config invalid.
+    pub fn load_with_overrides(
+        basepath: Option<PathBuf>,
+        overrides: ConfigOverrides,
+    ) -> Result<Self> {
+        let config_dir =
+            basepath.unwrap_or_else(|| ConfigDir::resolve(std::env::args_os().skip(1)).path);
         let config_path = config_dir.join("settings.toml");
+        // create parent dir if needed
+        fs::create_dir_all(&config_dir)
+            .with_context(|| format!("creating config dir: {}", config_dir.display()))?;
+        let lock = lock_settings(&config_path)?;
 
         if config_path.exists() {
-            let s = fs::read_to_string(&config_path)
+            let bytes = fs::read(&config_path)
                 .with_context(|| format!("reading config file: {}", config_path.display()))?;
-            let cfg: Config = toml::from_str(&s).context("parsing TOML config")?;
-            cfg.validate()?;
-            Ok(Self {
-                path: config_path,
-                inner: Arc::new(RwLock::new(cfg)),
-            })
+            let loaded = String::from_utf8(bytes.clone())
+                .context("config file is not valid UTF-8")
+                .and_then(|s| Ok((config_from_str(&s, &overrides)?, s)));
+            let ((cfg, version), s, recovery) = match loaded {
+                Ok((loaded, s)) => (loaded, s, None),
+                Err(e) => {
+                    let s = String::from_utf8_lossy(&bytes);
+                    // 由覆盖项引起的错误不代表文件损坏
+                    if written_by_newer_version(&s)
+                        || (!overrides.is_empty()
+                            && config_from_str(&s, &ConfigOverrides::default()).is_ok())
+                    {
+                        return Err(e);
+                    }
+                    let (s, loaded, recovery) = recover_settings(&config_path, &overrides, &e)?;
+                    (loaded, s, Some(recovery))
+                }
+            };
+            let mut mgr = Self::new(config_path, cfg, overrides, &s);
+            mgr.recovery = recovery;
+            if version < CURRENT_CONFIG_VERSION {
+                let backup = mgr.path.with_extension(format!("v{version}.toml.bak"));
+                fs::copy(&mgr.path, &backup)
+                    .with_context(|| format!("backing up config to {}", backup.display()))?;
+                drop(lock);
+                mgr.save()?;
+            }
+            Ok(mgr)
         } else {
-            // create parent dir if needed
-            fs::create_dir_all(&config_dir)
-                .with_context(|| format!("creating config dir: {}", config_dir.display()))?;
-
             let cfg = Config::default();
             let toml_str = toml::to_string_pretty(&cfg).context("serializing default config")?;
             let mut f = fs::File::create(&config_path)
                 .with_context(|| format!("creating config file: {}", config_path.display()))?;
             f.write_all(toml_str.as_bytes())?;
-            Ok(Self {
-                path: config_path,
-                inner: Arc::new(RwLock::new(cfg)),
-            })
+            let cfg = if overrides.is_empty() {
+                cfg
+            } else {
+                let table = toml::from_str(&toml_str).context("parsing TOML config")?;
+                config_from_table(table, &overrides)?.0
+            };
+            Ok(Self::new(config_path, cfg, overrides, &toml_str))
+        }
+    }
+
+    /// `content` is the text of the file `cfg` was read from or written to.
+    fn new(path: PathBuf, cfg: Config, overrides: ConfigOverrides, content: &str) -> Self {
+        let stamp = file_stamp(&path);
+        Self {
+            path,
+            inner: Arc::new(RwLock::new(cfg)),
+            file_stamp: Mutex::new(stamp),
+            file_hash: Mutex::new(content_hash(content)),
+            overrides,
+            recovery: None,
+            write_delay: None,
+            unsaved_since: Mutex::new(None),
+        }
+    }
+
+    /// Set if `settings.toml` was broken on load and had to be replaced,
+    /// see [`Self::load`].
+    pub fn recovery(&self) -> Option<&LoadRecovery> {
+        self.recovery.as_ref()
+    }
+
+    /// Overrides layered over the file, see [`crate::overrides`].
+    pub fn overrides(&self) -> &ConfigOverrides {
+        &self.overrides
+    }
+
+    /// Reloads the config if `settings.toml` was changed by something other
+    /// than this manager, e.g. edited by hand. Meant to be polled.
+    ///
+    /// Returns `true` if a new config was loaded. A changed file that does
+    /// not parse or validate is reported as an error once and otherwise
+    /// ignored; the current config stays in place until the file is fixed.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let stamp = file_stamp(&self.path);
+        {
+            let mut last = self.file_stamp.lock();
+            if stamp.is_none() || stamp == *last {
+                return Ok(false);
+            }
+            *last = stamp;
+        }
+        let s = {
+            let _lock = lock_settings(&self.path)?;
+            fs::read_to_string(&self.path)
+                .with_context(|| format!("reading config file: {}", self.path.display()))?
+        };
+        let table: toml::Table = toml::from_str(&s).context("parsing TOML config")?;
+        let (cfg, version) = config_from_table(table, &self.overrides)?;
+        *self.inner.write() = cfg;
+        *self.file_hash.lock() = content_hash(&s);
+        // 与手动保存冲突时相同：以文件为准，丢弃尚未写入的修改
+        *self.unsaved_since.lock() = None;
+        if version < CURRENT_CONFIG_VERSION {
+            self.save()?;
         }
+        Ok(true)
     }
 
-    /// Save current config to disk atomically.
+    /// Save current config to disk atomically. Comments and the order of
+    /// keys in the existing file are kept; only changed values are rewritten.
+    /// Overridden settings keep the value the file has.
+    ///
+    /// # Errors
+    /// Returns a [`ConfigConflict`] instead of writing if another program
+    /// changed the file since this manager last read or wrote it.
     pub fn save(&self) -> Result<()> {
+        self.write(true)
+    }
+
+    /// Like [`Self::save`], but replaces changes other programs made to the
+    /// file instead of failing with a [`ConfigConflict`].
+    pub fn save_overwriting(&self) -> Result<()> {
+        self.write(false)
+    }
+
+    fn write(&self, check_conflict: bool) -> Result<()> {
         let cfg = self.inner.read().clone();
         cfg.validate()?;
         let tmp = self.path.with_extension("toml.tmp");
-        let s = toml::to_string_pretty(&cfg).context("serializing config")?;
+        let _lock = lock_settings(&self.path)?;
+        let old = fs::read_to_string(&self.path).ok();
+        if check_conflict
+            && let Some(old) = &old
+            && content_hash(old) != *self.file_hash.lock()
+        {
+            return Err(ConfigConflict {
+                path: self.path.clone(),
+            }
+            .into());
+        }
+        let mut s = if self.overrides.is_empty() {
+            toml::to_string_pretty(&cfg).context("serializing config")?
+        } else {
+            let mut table = toml::Table::try_from(&cfg).context("serializing config")?;
+            let file = old.as_deref().and_then(|s| toml::from_str(s).ok());
+            self.overrides
+                .restore(&mut table, &file.unwrap_or_default());
+            toml::to_string_pretty(&table).context("serializing config")?
+        };
+        if let Some(old) = old {
+            s = preserve_formatting(&old, &s);
+            // 内容未变时不轮换，避免备份被相同的副本挤掉
+            if old != s {
+                self.rotate_backups(cfg.general.backup_count)?;
+            }
+        }
         let mut f = fs::File::create(&tmp)
             .with_context(|| format!("creating tmp config file: {}", tmp.display()))?;
         f.write_all(s.as_bytes())?;
@@ -175,38 +1755,190 @@ impl ConfigManager {
                 self.path.display()
             )
         })?;
+        *self.file_stamp.lock() = file_stamp(&self.path);
+        *self.file_hash.lock() = content_hash(&s);
+        *self.unsaved_since.lock() = None;
+        Ok(())
+    }
+
+    /// Makes [`Self::update`] leave saving to [`Self::flush_if_due`] until
+    /// no change has been made for `delay`, so e.g. dragging a gain slider
+    /// writes the file once. `None` (the default) saves on every update.
+    pub fn set_write_delay(&mut self, delay: Option<Duration>) {
+        self.write_delay = delay;
+    }
+
+    /// Whether updates are waiting to be written to the file.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved_since.lock().is_some()
+    }
+
+    /// Saves pending updates now. Does nothing if there are none.
+    pub fn flush(&self) -> Result<()> {
+        if self.has_unsaved_changes() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Saves pending updates once the write delay has passed since the last
+    /// one. Meant to be polled; returns whether the file was written.
+    pub fn flush_if_due(&self) -> Result<bool> {
+        let due = match (*self.unsaved_since.lock(), self.write_delay) {
+            (Some(since), Some(delay)) => since.elapsed() >= delay,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if due {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    /// Path of backup `n`; 1 is the most recent.
+    pub fn backup_path(&self, n: u32) -> PathBuf {
+        backup_file(&self.path, n)
+    }
+
+    /// Numbers of the backups on disk, most recent first.
+    pub fn backups(&self) -> Vec<u32> {
+        (1..)
+            .take_while(|&n| self.backup_path(n).exists())
+            .collect()
+    }
+
+    /// Copies the settings file to backup 1, moving older backups one number
+    /// up and deleting those beyond `keep`.
+    fn rotate_backups(&self, keep: u32) -> Result<()> {
+        for n in (keep.max(1)..).take_while(|&n| self.backup_path(n).exists()) {
+            fs::remove_file(self.backup_path(n))
+                .with_context(|| format!("removing old config backup {n}"))?;
+        }
+        if keep == 0 {
+            return Ok(());
+        }
+        for n in (1..keep).rev() {
+            let from = self.backup_path(n);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(n + 1))
+                    .with_context(|| format!("rotating config backup {n}"))?;
+            }
+        }
+        fs::copy(&self.path, self.backup_path(1))
+            .with_context(|| format!("backing up config to {}", self.backup_path(1).display()))?;
         Ok(())
     }
 
+    /// Replaces the config with backup `n` (see [`Self::backups`]). The
+    /// config being replaced becomes backup 1, so a restore can be undone.
+    ///
+    /// # Errors
+    /// Returns an error if the backup does not exist or is not a valid
+    /// config; the current config is kept then.
+    pub fn restore_backup(&self, n: u32) -> Result<()> {
+        let path = self.backup_path(n);
+        let s = fs::read_to_string(&path)
+            .with_context(|| format!("reading config backup: {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&s).context("parsing TOML config")?;
+        let (cfg, _) = config_from_table(table, &self.overrides)?;
+        self.update(|current| *current = cfg)
+    }
+
+    /// Writes the current config to `path` in `format`, e.g. to move it to
+    /// another machine or attach it to a bug report.
+    pub fn export(&self, path: &Path, format: ConfigFormat) -> Result<()> {
+        let cfg = self.inner.read().clone();
+        let s = match format {
+            ConfigFormat::Toml => toml::to_string_pretty(&cfg).context("serializing config")?,
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(&cfg).context("serializing config")?
+            }
+        };
+        fs::write(path, s).with_context(|| format!("writing config export: {}", path.display()))
+    }
+
+    /// Replaces the config with the one exported to `path` and saves it.
+    ///
+    /// The format is taken from the extension; other files are read as JSON
+    /// if they start with `{` and as TOML otherwise. Exports from older
+    /// versions are migrated. The COM calibration of this machine is kept.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, parsed, migrated or
+    /// validated; the current config is left untouched.
+    pub fn import(&self, path: &Path) -> Result<()> {
+        let table = read_config_table(path)?;
+        let (mut cfg, _) = config_from_table(table, &self.overrides)?;
+        // 校准结果只对测量它的机器有效
+        cfg.com = self.inner.read().com.clone();
+        self.update(|current| *current = cfg)
+    }
+
+    /// Checks a settings file or export the way [`Self::import`] would read
+    /// it, without loading it. Returns every invalid field, or the single
+    /// reason the file cannot be read at all; empty if it can be imported.
+    pub fn validate_file(path: &Path) -> Vec<ValidationIssue> {
+        let whole_file = |e: anyhow::Error| {
+            vec![ValidationIssue {
+                field: None,
+                message: format!("{e:#}"),
+            }]
+        };
+        let mut table = match read_config_table(path) {
+            Ok(table) => table,
+            Err(e) => return whole_file(e),
+        };
+        if let Err(e) = migrate(&mut table).context("migrating config") {
+            return whole_file(e);
+        }
+        match toml::Value::Table(table).try_into::<Config>() {
+            Ok(cfg) => cfg.field_errors().into_iter().map(Into::into).collect(),
+            Err(e) => whole_file(anyhow::Error::new(e).context("parsing config")),
+        }
+    }
+
     /// Returns a cloneable handle to the inner Arc<RwLock<Config>> to allow reads/writes.
     pub fn handle(&self) -> Arc<RwLock<Config>> {
         self.inner.clone()
     }
 
-    /// Atomically update config using closure and persist to disk.
+    /// Atomically update config using closure and persist to disk, or with
+    /// a write delay (see [`Self::set_write_delay`]) mark it for saving.
+    /// The update is dropped if the result does not pass [`Config::validate`].
     pub fn update<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce(&mut Config),
     {
         {
             let mut cfg = self.inner.write();
-            f(&mut cfg);
+            let mut updated = cfg.clone();
+            f(&mut updated);
+            updated.validate()?;
+            *cfg = updated;
+        }
+        if self.write_delay.is_some() {
+            *self.unsaved_since.lock() = Some(Instant::now());
+            return Ok(());
         }
         self.save()
     }
 
+    /// Resets the settings in `scope` (see [`Config::reset`]) and saves.
+    pub fn reset(&self, scope: ResetScope) -> Result<()> {
+        self.update(|cfg| cfg.reset(scope))
+    }
+
     /// Access path used for persistence (useful for tests)
     pub fn path(&self) -> &Path {
         &self.path
     }
 }
 
-fn default_config_dir() -> PathBuf {
-    // Use the directory where the executable is located
-    std::env::current_exe()
-        .ok()
-        .and_then(|exe_path| exe_path.parent().map(|p| p.to_path_buf()))
-        .unwrap_or_else(|| Path::new(".").to_path_buf())
+impl Drop for ConfigManager {
+    fn drop(&mut self) {
+        // 退出时写入延迟中的修改；此时已无法报告错误
+        let _ = self.flush();
+    }
 }
 
 #[cfg(test)]
@@ -220,26 +1952,363 @@ mod tests {
             config_version: 1,
             general: General {
                 language: "en".to_string(),
-                auto_route: false,
-                minimized: false,
                 start_with_windows: false,
                 nav_pane_expanded: true,
                 backdrop: Backdrop::default(),
                 close_to_tray: true,
                 auto_update_check: true,
+                backup_count: default_backup_count(),
+                resume_last_state: false,
+                notifications: true,
+                update_channel: UpdateChannel::default(),
+            },
+            startup: StartupSettings {
+                minimized: true,
+                auto_route: true,
             },
-            source_device_id: "src1".to_string(),
+            source: SourceSelection::Default(SourceRole::Communications),
             outputs: vec![Output {
                 device_id: "out1".to_string(),
                 enabled: true,
                 channel_mode: None,
+                overflow_policy: Some(OverflowPolicy::Resync),
+                channel_matrix: Some(ChannelMatrix(vec![
+                    vec![1.0, 0.0, 0.5],
+                    vec![0.0, 1.0, 0.5],
+                ])),
+                downmix_lfe: true,
+                eq_preset: EqPreset::Speech,
+                gain_db: -6.5,
+                delay_ms: 12.5,
+                muted: true,
+                phase_invert: 0b1000,
+                channel_trim: ChannelTrim(vec![0.0, 0.0, -1.5]),
+                bass_role: BassRole::Subwoofer,
+                limiter: LimiterSettings {
+                    enabled: true,
+                    threshold_db: -3.0,
+                    release_ms: 100.0,
+                },
+                loudness: LoudnessSettings {
+                    enabled: true,
+                    target_lufs: -16.0,
+                    max_gain_db: 6.0,
+                },
+                crossfeed: CrossfeedPreset::ChuMoy,
+                mid_side: MidSideSettings {
+                    enabled: true,
+                    mid_db: 0.0,
+                    side_db: 3.0,
+                },
+                dither: DitherMode::NoiseShaped,
+                resampler: Some(ResamplerQuality::High),
+                dsp_chain: DspChain(vec![DspStage::Gain, DspStage::Eq]),
+                missing_since: Some(1_700_000_000),
+                plugins: vec![PluginSlot {
+                    path: "C:\\Plugins\\Comp.clap".to_string(),
+                    state: "00ff10".to_string(),
+                    ..PluginSlot::default()
+                }],
+            }],
+            com: ComSettings {
+                enumeration_apartment: Some(Apartment::Sta),
+            },
+            affinity: ThreadAffinity {
+                cores: vec![2, 3],
+                exclude_efficiency_cores: true,
+            },
+            engine: EngineSettings {
+                buffer_ms: 20,
+                mode: EngineMode::EventDriven,
+                thread_priority: ThreadPriority::ProAudio,
+                resampler: ResamplerQuality::Balanced,
+                overflow_policy: OverflowPolicy::Block,
+            },
+            bass_management: BassManagement {
+                crossover_hz: 100.0,
+            },
+            mix_levels: MixLevels {
+                upmix_surround: 0.25,
+                ..MixLevels::default()
+            },
+            noise_gate: NoiseGateSettings {
+                enabled: true,
+                ..NoiseGateSettings::default()
+            },
+            fade_in_ms: 250.0,
+            meter_window_ms: 100.0,
+            spectrum: true,
+            mirror_source_volume: true,
+            mute_source_while_routing: true,
+            default_device_while_routing: Some("cable".into()),
+            auto_route_rules: vec![AutoRouteRule {
+                device_name: "WH-1000XM5".into(),
+                enable_output: true,
+                channel_mode: Some("Mono".into()),
+                start_routing: true,
+            }],
+            hotplug: HotplugSettings {
+                auto_enable_new_devices: true,
+                on_unplug: UnplugAction::Disable,
+                notify: false,
+            },
+            hotkeys: Hotkeys {
+                toggle_routing: Some("Ctrl+Alt+R".parse().unwrap()),
+                profiles: [("Desk".to_string(), "Win+F9".parse().unwrap())].into(),
+                ..Hotkeys::default()
+            },
+            active_profile: Some("Desk".into()),
+            profiles: vec![crate::profile::Profile {
+                name: "Desk".into(),
+                settings: Config {
+                    source: SourceSelection::Device("desk".into()),
+                    fade_in_ms: 20.0,
+                    ..Config::default()
+                }
+                .profile_settings(),
+            }],
+            presets: vec![crate::preset::RoutingPreset {
+                name: "Speakers".into(),
+                source: SourceSelection::default(),
+                outputs: vec![crate::preset::PresetOutput {
+                    device_id: "speakers".into(),
+                    channel_mode: Some("Mono".into()),
+                }],
             }],
         };
         let s = toml::to_string_pretty(&cfg).expect("serialize");
         let decoded: Config = toml::from_str(&s).expect("deserialize");
         assert_eq!(decoded.config_version, 1);
+        assert_eq!(decoded.startup, cfg.startup);
+        assert_eq!(decoded.source, cfg.source);
         assert_eq!(decoded.outputs.len(), 1);
         assert_eq!(decoded.outputs[0].device_id, "out1");
+        assert_eq!(
+            decoded.outputs[0].overflow_policy,
+            Some(OverflowPolicy::Resync)
+        );
+        assert_eq!(
+            decoded.outputs[0].channel_matrix,
+            cfg.outputs[0].channel_matrix
+        );
+        assert!(decoded.outputs[0].downmix_lfe);
+        assert_eq!(decoded.outputs[0].eq_preset, EqPreset::Speech);
+        assert_eq!(decoded.outputs[0].gain_db, -6.5);
+        assert_eq!(decoded.outputs[0].delay_ms, 12.5);
+        assert!(decoded.outputs[0].muted);
+        assert_eq!(decoded.outputs[0].phase_invert, 0b1000);
+        assert_eq!(decoded.outputs[0].channel_trim, cfg.outputs[0].channel_trim);
+        assert_eq!(decoded.outputs[0].bass_role, BassRole::Subwoofer);
+        assert_eq!(decoded.outputs[0].limiter, cfg.outputs[0].limiter);
+        assert_eq!(decoded.outputs[0].loudness, cfg.outputs[0].loudness);
+        assert_eq!(decoded.outputs[0].crossfeed, CrossfeedPreset::ChuMoy);
+        assert_eq!(decoded.outputs[0].mid_side, cfg.outputs[0].mid_side);
+        assert_eq!(decoded.outputs[0].dither, DitherMode::NoiseShaped);
+        assert_eq!(decoded.outputs[0].resampler, Some(ResamplerQuality::High));
+        assert_eq!(decoded.outputs[0].plugins, cfg.outputs[0].plugins);
+        assert_eq!(decoded.outputs[0].dsp_chain, cfg.outputs[0].dsp_chain);
+        assert_eq!(decoded.outputs[0].missing_since, Some(1_700_000_000));
+        assert_eq!(decoded.bass_management.crossover_hz, 100.0);
+        assert_eq!(decoded.engine, cfg.engine);
+        assert_eq!(decoded.mix_levels, cfg.mix_levels);
+        assert_eq!(decoded.noise_gate, cfg.noise_gate);
+        assert_eq!(decoded.fade_in_ms, 250.0);
+        assert_eq!(decoded.meter_window_ms, 100.0);
+        assert!(decoded.spectrum);
+        assert!(decoded.mirror_source_volume);
+        assert!(decoded.mute_source_while_routing);
+        assert_eq!(
+            decoded.default_device_while_routing.as_deref(),
+            Some("cable")
+        );
+        assert_eq!(decoded.auto_route_rules, cfg.auto_route_rules);
+        assert_eq!(decoded.hotplug, cfg.hotplug);
+        assert_eq!(decoded.hotkeys, cfg.hotkeys);
+        assert_eq!(decoded.active_profile.as_deref(), Some("Desk"));
+        assert_eq!(
+            decoded.profiles[0].settings.source.device_id(),
+            Some("desk")
+        );
+        assert_eq!(decoded.profiles[0].settings.fade_in_ms, 20.0);
+        assert_eq!(decoded.presets, cfg.presets);
+        assert_eq!(decoded.com.enumeration_apartment, Some(Apartment::Sta));
+        assert_eq!(decoded.affinity.cores, vec![2, 3]);
+        assert!(decoded.affinity.exclude_efficiency_cores);
+    }
+
+    #[test]
+    fn missing_outputs_keep_the_time_they_disappeared() {
+        let mut cfg = Config {
+            outputs: vec![Output::new("a".into()), Output::new("b".into())],
+            ..Config::default()
+        };
+        assert!(cfg.track_missing_outputs(&["a"], 100));
+        assert!(!cfg.track_missing_outputs(&["a"], 200));
+        assert_eq!(cfg.outputs[0].missing_since, None);
+        assert_eq!(cfg.outputs[1].missing_since, Some(100));
+
+        assert!(cfg.track_missing_outputs(&["a", "b"], 300));
+        assert_eq!(cfg.outputs[1].missing_since, None);
+    }
+
+    #[test]
+    fn auto_route_rule_matches_name_case_insensitively() {
+        let rule: AutoRouteRule = toml::from_str(r#"device_name = "wh-1000xm5""#).unwrap();
+        assert!(rule.enable_output);
+        assert!(!rule.start_routing);
+        assert!(rule.matches("Headphones (WH-1000XM5)"));
+        assert!(!rule.matches("Speakers (Realtek Audio)"));
+
+        let empty = AutoRouteRule {
+            device_name: String::new(),
+            ..rule
+        };
+        assert!(!empty.matches("Speakers"));
+    }
+
+    #[test]
+    fn missing_com_section_defaults_to_uncalibrated() {
+        let s = toml::to_string_pretty(&Config::default()).expect("serialize");
+        let without_com: String = s
+            .lines()
+            .take_while(|l| !l.starts_with("[com]"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let decoded: Config = toml::from_str(&without_com).expect("deserialize");
+        assert!(!decoded.com.is_calibrated());
+
+        // 旧版本还记录了路由线程的 apartment，现在固定为 MTA
+        let legacy = format!(
+            "{without_com}\n[com]\nenumeration_apartment = \"Sta\"\nstreaming_apartment = \"Sta\"\n"
+        );
+        let decoded: Config = toml::from_str(&legacy).expect("deserialize legacy");
+        assert_eq!(decoded.com.enumeration_apartment, Some(Apartment::Sta));
+    }
+
+    #[test]
+    fn validate_reports_every_bad_field() {
+        let mut cfg = Config {
+            source: SourceSelection::Device("a".into()),
+            outputs: vec![
+                Output {
+                    enabled: true,
+                    gain_db: 30.0,
+                    ..Output::new("a".into())
+                },
+                Output {
+                    delay_ms: -1.0,
+                    ..Output::new("a".into())
+                },
+            ],
+            ..Config::default()
+        };
+        cfg.general.language = "xx".into();
+        cfg.engine.buffer_ms = 5;
+        let err = cfg.validate().unwrap_err();
+        let fields: Vec<_> = err
+            .downcast_ref::<ValidationErrors>()
+            .unwrap()
+            .0
+            .iter()
+            .map(|e| e.field.as_str())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "engine.buffer_ms",
+                "general.language",
+                "source",
+                "outputs[0].gain_db",
+                "outputs[1].device_id",
+                "outputs[1].delay_ms",
+            ]
+        );
+
+        // 跟随默认设备时源设备 id 不参与检查
+        cfg.source = SourceSelection::Default(SourceRole::Console);
+        assert!(!cfg.field_errors().iter().any(|e| e.field == "source"));
+    }
+
+    #[test]
+    fn update_keeps_config_when_result_is_invalid() {
+        let td = tempdir().expect("tempdir");
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert!(mgr.update(|c| c.fade_in_ms = -1.0).is_err());
+        assert_eq!(mgr.handle().read().fade_in_ms, Config::default().fade_in_ms);
+        let saved = fs::read_to_string(mgr.path()).unwrap();
+        assert!(toml::from_str::<Config>(&saved).unwrap().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_ragged_channel_matrix() {
+        let mut cfg = Config::default();
+        cfg.outputs.push(Output {
+            channel_matrix: Some(ChannelMatrix(vec![vec![1.0, 0.0], vec![1.0]])),
+            ..Output::new("out1".to_string())
+        });
+        let err = cfg.validate().unwrap_err();
+        assert!(format!("{err:#}").contains("row 1"));
+
+        cfg.outputs[0].channel_matrix = Some(ChannelMatrix::identity(2));
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn partial_mix_levels_keep_defaults() {
+        let levels: MixLevels = toml::from_str("upmix_lfe = 1.0").unwrap();
+        assert_eq!(levels.upmix_lfe, 1.0);
+        assert_eq!(levels.downmix_center, MixLevels::default().downmix_center);
+
+        let mut cfg = Config::default();
+        cfg.mix_levels.downmix_lfe = -1.0;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn dsp_chain_appends_missing_stages_and_keeps_limiter_last() {
+        let chain = DspChain(vec![DspStage::Gain, DspStage::Eq]);
+        assert!(chain.validate().is_ok());
+        assert_eq!(
+            chain.stages(),
+            [
+                DspStage::Gain,
+                DspStage::Eq,
+                DspStage::MidSide,
+                DspStage::Crossfeed,
+                DspStage::Plugins,
+                DspStage::Loudness,
+                DspStage::Limiter,
+            ]
+        );
+
+        assert!(
+            DspChain(vec![DspStage::Eq, DspStage::Eq])
+                .validate()
+                .is_err()
+        );
+        assert!(
+            DspChain(vec![DspStage::Limiter, DspStage::Gain])
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn plugin_slots_accept_clap_with_hex_state_only() {
+        let mut slot = PluginSlot {
+            path: "C:\\Plugins\\Reverb.CLAP".to_string(),
+            ..PluginSlot::default()
+        };
+        slot.set_state_bytes(&[0x00, 0xab, 0x7f]);
+        assert_eq!(slot.state, "00ab7f");
+        assert_eq!(slot.state_bytes().unwrap(), vec![0x00, 0xab, 0x7f]);
+        assert!(slot.validate().is_ok());
+
+        slot.state = "0g".to_string();
+        assert!(slot.validate().is_err());
+        slot.state.clear();
+        slot.path = "C:\\Plugins\\Reverb.vst3".to_string();
+        assert!(format!("{:#}", slot.validate().unwrap_err()).contains("VST3"));
     }
 
     #[test]
@@ -252,7 +2321,329 @@ mod tests {
         assert!(expected_config_path.exists());
         let cfg = mgr.handle();
         let c = cfg.read();
-        assert_eq!(c.config_version, 1);
+        assert_eq!(c.config_version, CURRENT_CONFIG_VERSION);
+    }
+
+    #[test]
+    fn load_migrates_and_backs_up_old_file() {
+        let td = tempdir().unwrap();
+        let config_path = td.path().join("settings.toml");
+        let old = r#"
+config_version = 1
+source_device_id = "src"
+
+[general]
+language = "en"
+minimized = false
+start_with_windows = false
+auto_route = false
+
+[[outputs]]
+device_id = "out"
+channel_mode = "Left"
+"#;
+        fs::write(&config_path, old).unwrap();
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        {
+            let cfg = mgr.handle();
+            let c = cfg.read();
+            assert_eq!(c.config_version, CURRENT_CONFIG_VERSION);
+            assert_eq!(c.outputs[0].channel_mode.as_deref(), Some("LeftMono"));
+            assert_eq!(c.source.device_id(), Some("src"));
+        }
+        let saved = fs::read_to_string(&config_path).unwrap();
+        assert!(saved.contains(&format!("config_version = {CURRENT_CONFIG_VERSION}")));
+        let backup = td.path().join("settings.v1.toml.bak");
+        assert_eq!(fs::read_to_string(backup).unwrap(), old);
+    }
+
+    #[test]
+    fn export_and_import_roundtrip_in_both_formats() {
+        let td = tempdir().unwrap();
+        let mgr = ConfigManager::load(Some(td.path().join("a"))).expect("load");
+        mgr.update(|c| {
+            c.source = SourceSelection::Device("src".into());
+            c.fade_in_ms = 120.0;
+        })
+        .expect("update");
+
+        for (file, format) in [
+            ("export.toml", ConfigFormat::Toml),
+            ("export.json", ConfigFormat::Json),
+            ("export.txt", ConfigFormat::Json),
+        ] {
+            let path = td.path().join(file);
+            mgr.export(&path, format).expect("export");
+
+            let other_dir = td.path().join(format!("{file}.d"));
+            let other = ConfigManager::load(Some(other_dir)).expect("load");
+            other
+                .update(|c| c.com.enumeration_apartment = Some(Apartment::Sta))
+                .expect("update");
+            other.import(&path).expect("import");
+            let cfg = other.handle();
+            let c = cfg.read();
+            assert_eq!(c.source.device_id(), Some("src"), "{file}");
+            assert_eq!(c.fade_in_ms, 120.0, "{file}");
+            assert_eq!(c.com.enumeration_apartment, Some(Apartment::Sta), "{file}");
+        }
+    }
+
+    #[test]
+    fn import_migrates_old_exports_and_rejects_invalid_ones() {
+        let td = tempdir().unwrap();
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let old = td.path().join("old.json");
+        fs::write(
+            &old,
+            r#"{"config_version": 1, "source_device_id": "src",
+                "general": {"language": "zh", "minimized": false,
+                            "start_with_windows": false, "auto_route": false},
+                "outputs": [{"device_id": "out", "channel_mode": "Right"}]}"#,
+        )
+        .unwrap();
+        mgr.import(&old).expect("import");
+        assert_eq!(
+            mgr.handle().read().outputs[0].channel_mode.as_deref(),
+            Some("RightMono")
+        );
+
+        let invalid = td.path().join("invalid.toml");
+        let cfg = Config {
+            fade_in_ms: -1.0,
+            ..Config::default()
+        };
+        fs::write(&invalid, toml::to_string_pretty(&cfg).unwrap()).unwrap();
+        assert!(mgr.import(&invalid).is_err());
+        assert_eq!(mgr.handle().read().general.language, "zh");
+    }
+
+    #[test]
+    fn validate_file_lists_issues_without_loading() {
+        let td = tempdir().unwrap();
+        let path = td.path().join("check.toml");
+        let cfg = Config {
+            fade_in_ms: -1.0,
+            meter_window_ms: 0.0,
+            ..Config::default()
+        };
+        fs::write(&path, toml::to_string_pretty(&cfg).unwrap()).unwrap();
+        let fields: Vec<_> = ConfigManager::validate_file(&path)
+            .into_iter()
+            .filter_map(|issue| issue.field)
+            .collect();
+        assert_eq!(fields, ["fade_in_ms", "meter_window_ms"]);
+
+        fs::write(&path, toml::to_string_pretty(&Config::default()).unwrap()).unwrap();
+        assert!(ConfigManager::validate_file(&path).is_empty());
+
+        fs::write(&path, "config_version = \"two\"").unwrap();
+        let issues = ConfigManager::validate_file(&path);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, None);
+        assert_eq!(
+            ConfigManager::validate_file(&td.path().join("missing.toml"))[0].field,
+            None
+        );
+    }
+
+    #[test]
+    fn load_replaces_a_broken_file_with_a_backup_or_the_defaults() {
+        let td = tempdir().unwrap();
+        let path = td.path().join("settings.toml");
+        let valid = Config {
+            fade_in_ms: 120.0,
+            ..Config::default()
+        };
+        fs::write(&path, "config_version = [").unwrap();
+        fs::write(backup_file(&path, 1), "not toml").unwrap();
+        fs::write(
+            backup_file(&path, 2),
+            toml::to_string_pretty(&valid).unwrap(),
+        )
+        .unwrap();
+
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let recovery = mgr.recovery().expect("recovered").clone();
+        assert_eq!(recovery.restored_backup, Some(2));
+        assert!(recovery.reason.contains("TOML"), "{}", recovery.reason);
+        assert_eq!(
+            fs::read_to_string(&recovery.quarantined).unwrap(),
+            "config_version = ["
+        );
+        assert_eq!(mgr.handle().read().fade_in_ms, 120.0);
+        drop(mgr);
+
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert!(mgr.recovery().is_none());
+        drop(mgr);
+
+        fs::remove_file(backup_file(&path, 2)).unwrap();
+        fs::write(&path, "config_version = 1\nfade_in_ms = -5.0\n").unwrap();
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert_eq!(mgr.recovery().unwrap().restored_backup, None);
+        assert_ne!(mgr.recovery().unwrap().quarantined, recovery.quarantined);
+        assert!(recovery.quarantined.exists());
+        assert_eq!(mgr.handle().read().fade_in_ms, default_fade_in_ms());
+        drop(mgr);
+
+        let newer = format!("config_version = {}\n", CURRENT_CONFIG_VERSION + 1);
+        fs::write(&path, &newer).unwrap();
+        assert!(ConfigManager::load(Some(td.path().to_path_buf())).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn write_delay_batches_updates_until_flushed() {
+        let td = tempdir().unwrap();
+        let mut mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let on_disk = |mgr: &ConfigManager| -> Config {
+            toml::from_str(&fs::read_to_string(mgr.path()).unwrap()).unwrap()
+        };
+        mgr.set_write_delay(Some(Duration::from_secs(3600)));
+        for ms in 1..=3 {
+            mgr.update(|c| c.fade_in_ms = ms as f32).expect("update");
+        }
+        assert!(mgr.has_unsaved_changes());
+        assert!(!mgr.flush_if_due().unwrap());
+        assert_eq!(on_disk(&mgr).fade_in_ms, default_fade_in_ms());
+        assert!(mgr.update(|c| c.fade_in_ms = -1.0).is_err());
+
+        mgr.flush().expect("flush");
+        assert!(!mgr.has_unsaved_changes());
+        assert_eq!(on_disk(&mgr).fade_in_ms, 3.0);
+
+        mgr.set_write_delay(Some(Duration::ZERO));
+        mgr.update(|c| c.fade_in_ms = 4.0).expect("update");
+        assert!(mgr.flush_if_due().unwrap());
+        assert_eq!(on_disk(&mgr).fade_in_ms, 4.0);
+
+        mgr.set_write_delay(Some(Duration::from_secs(3600)));
+        mgr.update(|c| c.fade_in_ms = 5.0).expect("update");
+        let path = mgr.path().to_path_buf();
+        drop(mgr);
+        let saved: Config = toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(saved.fade_in_ms, 5.0);
+    }
+
+    #[test]
+    fn save_rotates_backups_and_restores_them() {
+        let td = tempdir().expect("tempdir");
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        mgr.update(|c| c.general.backup_count = 2).unwrap();
+        assert_eq!(mgr.backups(), [1]);
+        for fade in [100.0, 200.0, 300.0] {
+            mgr.update(|c| c.fade_in_ms = fade).unwrap();
+        }
+        // 内容不变的保存不产生备份
+        mgr.save().unwrap();
+        assert_eq!(mgr.backups(), [1, 2]);
+
+        mgr.restore_backup(2).unwrap();
+        assert_eq!(mgr.handle().read().fade_in_ms, 100.0);
+        let undo = fs::read_to_string(mgr.backup_path(1)).unwrap();
+        assert!(undo.contains("fade_in_ms = 300.0"));
+
+        fs::write(mgr.backup_path(2), "not toml").unwrap();
+        assert!(mgr.restore_backup(2).is_err());
+        assert!(mgr.restore_backup(3).is_err());
+        assert_eq!(mgr.handle().read().fade_in_ms, 100.0);
+
+        mgr.update(|c| c.general.backup_count = 0).unwrap();
+        assert!(mgr.backups().is_empty());
+    }
+
+    #[test]
+    fn reload_picks_up_external_edits_only() {
+        let td = tempdir().unwrap();
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert!(!mgr.reload_if_changed().unwrap());
+        mgr.update(|c| c.fade_in_ms = 10.0).expect("update");
+        assert!(!mgr.reload_if_changed().unwrap());
+
+        let edited = Config {
+            source: SourceSelection::Device("edited-by-hand".into()),
+            ..Config::default()
+        };
+        fs::write(mgr.path(), toml::to_string_pretty(&edited).unwrap()).unwrap();
+        assert!(mgr.reload_if_changed().unwrap());
+        assert_eq!(
+            mgr.handle().read().source.device_id(),
+            Some("edited-by-hand")
+        );
+
+        fs::write(mgr.path(), "config_version = \"two\"").unwrap();
+        assert!(mgr.reload_if_changed().is_err());
+        assert!(!mgr.reload_if_changed().unwrap());
+        assert_eq!(
+            mgr.handle().read().source.device_id(),
+            Some("edited-by-hand")
+        );
+    }
+
+    #[test]
+    fn reset_keeps_the_other_sections() {
+        let td = tempdir().unwrap();
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let customize = |c: &mut Config| {
+            c.general.language = "zh".into();
+            c.startup.auto_route = true;
+            c.source = SourceSelection::Device("src".into());
+            c.outputs.push(Output::new("out".into()));
+            c.fade_in_ms = 500.0;
+            c.com.enumeration_apartment = Some(Apartment::Sta);
+            c.save_preset("Desk").unwrap();
+        };
+        mgr.update(customize).expect("update");
+
+        mgr.reset(ResetScope::Routing).expect("reset");
+        {
+            let cfg = mgr.handle();
+            let c = cfg.read();
+            assert!(c.outputs.is_empty());
+            assert_eq!(c.source, SourceSelection::default());
+            assert_eq!(c.fade_in_ms, default_fade_in_ms());
+            assert_eq!(c.general.language, "zh");
+            assert!(c.startup.auto_route);
+            assert_eq!(c.presets.len(), 1);
+        }
+
+        mgr.update(customize).expect("update");
+        mgr.reset(ResetScope::Preferences).expect("reset");
+        {
+            let cfg = mgr.handle();
+            let c = cfg.read();
+            assert_eq!(c.general.language, "en");
+            assert!(!c.startup.auto_route);
+            assert_eq!(c.outputs.len(), 1);
+            assert_eq!(c.fade_in_ms, 500.0);
+        }
+
+        mgr.reset(ResetScope::All).expect("reset");
+        let cfg = mgr.handle();
+        let c = cfg.read();
+        assert!(c.outputs.is_empty() && c.presets.is_empty());
+        assert_eq!(c.com.enumeration_apartment, Some(Apartment::Sta));
+    }
+
+    #[test]
+    fn save_refuses_to_overwrite_another_writer() {
+        let td = tempdir().unwrap();
+        let first = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let second = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        second.update(|c| c.fade_in_ms = 1234.5).expect("update");
+
+        let err = first.update(|c| c.meter_window_ms = 50.0).unwrap_err();
+        assert!(err.downcast_ref::<ConfigConflict>().is_some());
+        assert!(first.reload_if_changed().unwrap());
+        assert_eq!(first.handle().read().fade_in_ms, 1234.5);
+        first.update(|c| c.meter_window_ms = 50.0).expect("update");
+
+        second.update(|c| c.fade_in_ms = 20.0).unwrap_err();
+        second.save_overwriting().expect("overwrite");
+        let saved = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert_eq!(saved.handle().read().fade_in_ms, 20.0);
+        assert_ne!(saved.handle().read().meter_window_ms, 50.0);
     }
 
     #[test]
@@ -268,4 +2659,40 @@ mod tests {
         let s = fs::read_to_string(&expected_config_path).expect("read file");
         assert!(s.contains("language = \"zh\""));
     }
+
+    #[test]
+    fn overrides_apply_on_load_and_reload_but_are_not_saved() {
+        let td = tempdir().unwrap();
+        ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        let mut overrides = ConfigOverrides::default();
+        overrides.push(
+            "engine.buffer_ms".into(),
+            toml::Value::Integer(20),
+            "test".into(),
+        );
+        let mgr = ConfigManager::load_with_overrides(Some(td.path().to_path_buf()), overrides)
+            .expect("load with overrides");
+        assert_eq!(mgr.handle().read().engine.buffer_ms, 20);
+
+        mgr.update(|c| c.general.language = "zh".into()).unwrap();
+        let s = fs::read_to_string(mgr.path()).unwrap();
+        assert!(s.contains("language = \"zh\""));
+        assert!(s.contains("buffer_ms = 50"));
+
+        let edited = s.replace("language = \"zh\"", "language = \"en\" # edited");
+        fs::write(mgr.path(), edited).unwrap();
+        assert!(mgr.reload_if_changed().unwrap());
+        assert_eq!(mgr.handle().read().general.language, "en");
+        assert_eq!(mgr.handle().read().engine.buffer_ms, 20);
+
+        let mut unknown = ConfigOverrides::default();
+        unknown.push(
+            "general.langauge".into(),
+            toml::Value::String("en".into()),
+            "test".into(),
+        );
+        assert!(
+            ConfigManager::load_with_overrides(Some(td.path().to_path_buf()), unknown).is_err()
+        );
+    }
 }
@@ -0,0 +1,176 @@
+//! Keeps the user's formatting of `settings.toml` when the app saves it.
+//!
+//! The config is serialized as usual and then merged into the document read
+//! from disk: keys whose value did not change are left as they are, changed
+//! values keep the comments around them, and only added or removed keys
+//! touch the rest of the file. New keys go to the end of their table.
+
+use toml_edit::{ArrayOfTables, Document, Item, Table, TableLike, Value};
+
+/// `updated` written over `existing` with as little change to `existing` as
+/// possible. Falls back to `updated` if either does not parse.
+pub(crate) fn preserve_formatting(existing: &str, updated: &str) -> String {
+    let (Ok(mut doc), Ok(new)) = (existing.parse::<Document>(), updated.parse::<Document>()) else {
+        return updated.to_string();
+    };
+    merge_table(doc.as_table_mut(), new.as_table());
+    // 表头按位置排序输出；新插入的表沿用生成文档中的位置会错位，
+    // 对数组表还会改变子表归属，因此按遍历顺序重新编号
+    renumber_tables(doc.as_table_mut(), &mut 0);
+    doc.to_string()
+}
+
+fn merge_table(old: &mut Table, new: &Table) {
+    old.retain(|key, _| new.contains_key(key));
+    for (key, item) in new.iter() {
+        match old.get_mut(key) {
+            Some(old_item) => merge_item(old_item, item),
+            None => {
+                old.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut Item, new: &Item) {
+    if same_item(old, new) {
+        return;
+    }
+    match (old, new) {
+        (Item::Table(old), Item::Table(new)) => merge_table(old, new),
+        (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => merge_array_of_tables(old, new),
+        (Item::Value(old), Item::Value(new)) => {
+            let decor = old.decor().clone();
+            *old = new.clone();
+            *old.decor_mut() = decor;
+        }
+        (old, new) => *old = new.clone(),
+    }
+}
+
+/// Merges the tables by index, so e.g. comments on an `[[outputs]]` entry
+/// stay with the same entry as long as none is inserted before it.
+fn merge_array_of_tables(old: &mut ArrayOfTables, new: &ArrayOfTables) {
+    while old.len() > new.len() {
+        old.remove(old.len() - 1);
+    }
+    for (i, table) in new.iter().enumerate() {
+        match old.get_mut(i) {
+            Some(old) => merge_table(old, table),
+            None => old.push(table.clone()),
+        }
+    }
+}
+
+fn same_item(a: &Item, b: &Item) -> bool {
+    if let (Some(a), Some(b)) = (a.as_table_like(), b.as_table_like()) {
+        return same_table(a, b);
+    }
+    match (a, b) {
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_table(a, b))
+        }
+        (Item::Value(a), Item::Value(b)) => same_value(a, b),
+        _ => false,
+    }
+}
+
+fn same_table(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, a)| b.get(key).is_some_and(|b| same_item(a, b)))
+}
+
+/// Compares values, ignoring how they are written.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => same_table(a, b),
+        _ => false,
+    }
+}
+
+fn renumber_tables(table: &mut Table, next: &mut usize) {
+    table.set_position(*next);
+    *next += 1;
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => renumber_tables(table, next),
+            Item::ArrayOfTables(tables) => tables
+                .iter_mut()
+                .for_each(|table| renumber_tables(table, next)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_keys_keep_their_comments_and_order() {
+        let existing = r#"# 手动编辑过的配置
+config_version = 4
+
+[general]
+minimized = true # start in the tray
+language = "en"
+nav_pane_expanded = true
+
+[[outputs]]
+# desk speakers
+device_id = "a"
+gain_db = -3.00
+"#;
+        let updated = r#"config_version = 4
+
+[general]
+language = "zh"
+minimized = true
+backdrop = "Mica"
+
+[[outputs]]
+device_id = "a"
+gain_db = -3.0
+
+[outputs.limiter]
+enabled = true
+
+[[outputs]]
+device_id = "b"
+"#;
+        let merged = preserve_formatting(existing, updated);
+        assert_eq!(
+            merged,
+            r#"# 手动编辑过的配置
+config_version = 4
+
+[general]
+minimized = true # start in the tray
+language = "zh"
+backdrop = "Mica"
+
+[[outputs]]
+# desk speakers
+device_id = "a"
+gain_db = -3.00
+
+[outputs.limiter]
+enabled = true
+
+[[outputs]]
+device_id = "b"
+"#
+        );
+        assert_eq!(preserve_formatting(&merged, updated), merged);
+        assert_eq!(preserve_formatting("not = [toml", updated), updated);
+    }
+}
@@ -0,0 +1,410 @@
+//! Global hotkeys: key chords such as `Ctrl+Alt+R` bound to app actions.
+//!
+//! Chords are written as text in `[hotkeys]` and parsed on load, so the UI
+//! gets the modifiers and the Windows virtual-key code ready for
+//! `RegisterHotKey`.
+
+use crate::config::{Config, FieldError};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The `[hotkeys]` section. Unbound actions are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct Hotkeys {
+    pub toggle_routing: Option<KeyChord>,
+    /// Mutes every output, or unmutes them if all are muted
+    pub mute_outputs: Option<KeyChord>,
+    /// Activates the profile after the active one
+    pub next_profile: Option<KeyChord>,
+    pub volume_up: Option<KeyChord>,
+    pub volume_down: Option<KeyChord>,
+    /// Chords that activate a profile, by profile name
+    pub profiles: BTreeMap<String, KeyChord>,
+}
+
+/// What a hotkey does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum HotkeyAction {
+    ToggleRouting,
+    MuteOutputs,
+    NextProfile,
+    VolumeUp,
+    VolumeDown,
+    ActivateProfile(String),
+}
+
+/// A chord bound to an action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct HotkeyBinding {
+    pub action: HotkeyAction,
+    pub chord: KeyChord,
+}
+
+impl Hotkeys {
+    /// Every bound chord, in the order of the section.
+    pub fn bindings(&self) -> Vec<HotkeyBinding> {
+        let actions = [
+            (HotkeyAction::ToggleRouting, &self.toggle_routing),
+            (HotkeyAction::MuteOutputs, &self.mute_outputs),
+            (HotkeyAction::NextProfile, &self.next_profile),
+            (HotkeyAction::VolumeUp, &self.volume_up),
+            (HotkeyAction::VolumeDown, &self.volume_down),
+        ];
+        let profiles = self.profiles.iter().map(|(name, chord)| HotkeyBinding {
+            action: HotkeyAction::ActivateProfile(name.clone()),
+            chord: *chord,
+        });
+        actions
+            .into_iter()
+            .filter_map(|(action, chord)| chord.map(|chord| HotkeyBinding { action, chord }))
+            .chain(profiles)
+            .collect()
+    }
+}
+
+impl HotkeyAction {
+    /// Path of the action's key under `hotkeys`.
+    fn field(&self) -> String {
+        match self {
+            Self::ToggleRouting => "toggle_routing".into(),
+            Self::MuteOutputs => "mute_outputs".into(),
+            Self::NextProfile => "next_profile".into(),
+            Self::VolumeUp => "volume_up".into(),
+            Self::VolumeDown => "volume_down".into(),
+            Self::ActivateProfile(name) => format!("profiles.{name}"),
+        }
+    }
+}
+
+/// Modifier keys held for a chord.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub win: bool,
+}
+
+impl Modifiers {
+    /// `MOD_*` flags for `RegisterHotKey`.
+    pub fn flags(self) -> u32 {
+        u32::from(self.alt)
+            | u32::from(self.ctrl) << 1
+            | u32::from(self.shift) << 2
+            | u32::from(self.win) << 3
+    }
+}
+
+/// The non-modifier key of a chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub enum Key {
+    /// `A`-`Z` or `0`-`9`
+    Char(char),
+    /// `F1`-`F24`
+    Function(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Tab,
+    Enter,
+    Escape,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    PlayPause,
+    NextTrack,
+    PrevTrack,
+}
+
+/// Names and virtual-key codes of the keys without a value; the first name
+/// of a key is the one written back.
+const NAMED_KEYS: &[(&str, Key, u32)] = &[
+    ("Up", Key::Up, 0x26),
+    ("Down", Key::Down, 0x28),
+    ("Left", Key::Left, 0x25),
+    ("Right", Key::Right, 0x27),
+    ("Space", Key::Space, 0x20),
+    ("Tab", Key::Tab, 0x09),
+    ("Enter", Key::Enter, 0x0D),
+    ("Return", Key::Enter, 0x0D),
+    ("Esc", Key::Escape, 0x1B),
+    ("Escape", Key::Escape, 0x1B),
+    ("Backspace", Key::Backspace, 0x08),
+    ("Insert", Key::Insert, 0x2D),
+    ("Ins", Key::Insert, 0x2D),
+    ("Delete", Key::Delete, 0x2E),
+    ("Del", Key::Delete, 0x2E),
+    ("Home", Key::Home, 0x24),
+    ("End", Key::End, 0x23),
+    ("PageUp", Key::PageUp, 0x21),
+    ("PgUp", Key::PageUp, 0x21),
+    ("PageDown", Key::PageDown, 0x22),
+    ("PgDn", Key::PageDown, 0x22),
+    ("VolumeUp", Key::VolumeUp, 0xAF),
+    ("VolumeDown", Key::VolumeDown, 0xAE),
+    ("VolumeMute", Key::VolumeMute, 0xAD),
+    ("PlayPause", Key::PlayPause, 0xB3),
+    ("NextTrack", Key::NextTrack, 0xB0),
+    ("PrevTrack", Key::PrevTrack, 0xB1),
+];
+
+impl Key {
+    /// Windows virtual-key code.
+    pub fn virtual_key(self) -> u32 {
+        match self {
+            // VK_0..VK_9 and VK_A..VK_Z are the ASCII codes
+            Key::Char(c) => c as u32,
+            Key::Function(n) => 0x6F + u32::from(n),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(_, key, _)| *key == self)
+                .map_or(0, |(_, _, vk)| *vk),
+        }
+    }
+
+    /// Whether the key types text or edits it, so binding it without Ctrl,
+    /// Alt or Win would take it away from every other application.
+    fn needs_modifier(self) -> bool {
+        !matches!(
+            self,
+            Key::Function(_)
+                | Key::VolumeUp
+                | Key::VolumeDown
+                | Key::VolumeMute
+                | Key::PlayPause
+                | Key::NextTrack
+                | Key::PrevTrack
+        )
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next())
+            && c.is_ascii_alphanumeric()
+        {
+            return Ok(Key::Char(c.to_ascii_uppercase()));
+        }
+        if let Some(n) = s
+            .strip_prefix(['F', 'f'])
+            .and_then(|n| n.parse::<u8>().ok())
+        {
+            if !(1..=24).contains(&n) {
+                bail!("there is no key F{n}");
+            }
+            return Ok(Key::Function(n));
+        }
+        NAMED_KEYS
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, key, _)| *key)
+            .ok_or_else(|| anyhow!("unknown key {s:?}"))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{c}"),
+            Key::Function(n) => write!(f, "F{n}"),
+            _ => {
+                let name = NAMED_KEYS.iter().find(|(_, key, _)| key == self);
+                f.write_str(name.map_or("?", |(name, _, _)| name))
+            }
+        }
+    }
+}
+
+/// Modifiers plus one key, written like `Ctrl+Alt+R` (case-insensitive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            if key.is_some() {
+                bail!("{s:?}: the key must come last");
+            }
+            let flag = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "alt" => &mut modifiers.alt,
+                "shift" => &mut modifiers.shift,
+                "win" | "super" | "meta" => &mut modifiers.win,
+                "" => bail!("{s:?}: empty key name"),
+                _ => {
+                    key = Some(part.parse::<Key>().map_err(|e| anyhow!("{s:?}: {e}"))?);
+                    continue;
+                }
+            };
+            if std::mem::replace(flag, true) {
+                bail!("{s:?}: {part} is given twice");
+            }
+        }
+        let key = key.ok_or_else(|| anyhow!("{s:?}: no key besides the modifiers"))?;
+        if key.needs_modifier() && !(modifiers.ctrl || modifiers.alt || modifiers.win) {
+            bail!("{s:?}: {key} needs Ctrl, Alt or Win");
+        }
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.modifiers;
+        for (held, name) in [
+            (m.ctrl, "Ctrl"),
+            (m.alt, "Alt"),
+            (m.shift, "Shift"),
+            (m.win, "Win"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+// 序列化为 "Ctrl+Alt+R" 形式的字符串
+impl Type for KeyChord {
+    fn inline(
+        type_map: &mut specta::TypeCollection,
+        generics: specta::Generics,
+    ) -> specta::DataType {
+        String::inline(type_map, generics)
+    }
+}
+
+impl Config {
+    /// Chords bound to more than one action and chords for missing profiles.
+    pub(crate) fn hotkey_field_errors(&self) -> Vec<FieldError> {
+        let bindings = self.hotkeys.bindings();
+        let mut errors = Vec::new();
+        for (i, binding) in bindings.iter().enumerate() {
+            let field = format!("hotkeys.{}", binding.action.field());
+            if let Some(first) = bindings[..i].iter().find(|b| b.chord == binding.chord) {
+                errors.push(FieldError {
+                    field: field.clone(),
+                    message: format!(
+                        "{} is already bound to {}",
+                        binding.chord,
+                        first.action.field()
+                    ),
+                });
+            }
+            if let HotkeyAction::ActivateProfile(name) = &binding.action
+                && self.profile(name).is_none()
+            {
+                errors.push(FieldError {
+                    field,
+                    message: format!("profile {name:?} does not exist"),
+                });
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_parse_case_insensitively_and_print_canonically() {
+        let chord: KeyChord = "alt + ctrl+r".parse().unwrap();
+        assert_eq!(chord.to_string(), "Ctrl+Alt+R");
+        assert_eq!(chord.modifiers.flags(), 0x3);
+        assert_eq!(chord.key.virtual_key(), 0x52);
+
+        let chord: KeyChord = "Shift+Win+PgDn".parse().unwrap();
+        assert_eq!(chord.to_string(), "Shift+Win+PageDown");
+        assert_eq!("F13".parse::<KeyChord>().unwrap().key.virtual_key(), 0x7C);
+        assert_eq!(
+            "VolumeMute".parse::<KeyChord>().unwrap().key,
+            Key::VolumeMute
+        );
+
+        for bad in [
+            "",
+            "Ctrl",
+            "Ctrl+",
+            "Ctrl+Ctrl+A",
+            "Ctrl+A+Alt",
+            "Shift+A",
+            "Ctrl+F25",
+            "Ctrl+Foo",
+        ] {
+            assert!(bad.parse::<KeyChord>().is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn validate_reports_clashing_and_dangling_bindings() {
+        let mut cfg: Config = toml::from_str(&format!(
+            r#"
+            config_version = {}
+            [general]
+            language = "en"
+            start_with_windows = false
+            [hotkeys]
+            toggle_routing = "Ctrl+Alt+R"
+            volume_up = "ctrl+alt+r"
+            [hotkeys.profiles]
+            Desk = "Ctrl+Alt+1"
+            "#,
+            crate::migrate::CURRENT_CONFIG_VERSION
+        ))
+        .unwrap();
+        let fields: Vec<_> = cfg
+            .hotkey_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, ["hotkeys.volume_up", "hotkeys.profiles.Desk"]);
+
+        cfg.hotkeys.volume_up = None;
+        cfg.create_profile("Desk").unwrap();
+        cfg.validate().unwrap();
+        cfg.delete_profile("Desk").unwrap();
+        assert!(cfg.hotkeys.profiles.is_empty());
+    }
+}
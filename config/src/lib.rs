@@ -1,3 +1,17 @@
 pub mod config;
+mod document;
+pub mod hotkeys;
+pub mod location;
+pub mod migrate;
+pub mod overrides;
+pub mod preset;
+pub mod profile;
+pub mod schema;
+pub mod state;
 
-pub use config::{Config, ConfigManager};
+pub use config::{
+    Config, ConfigConflict, ConfigFormat, ConfigManager, FieldError, LoadRecovery, ResetScope,
+    ValidationErrors, ValidationIssue,
+};
+pub use location::ConfigDir;
+pub use overrides::ConfigOverrides;
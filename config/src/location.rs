@@ -0,0 +1,142 @@
+//! Where `settings.toml` lives: next to the executable (portable mode) or in
+//! the per-user app data directory.
+//!
+//! The directory is resolved in this order; the first match wins:
+//!
+//! 1. Command line: `--config-dir <dir>`, or `--portable` for the
+//!    executable's directory
+//! 2. The `AUDIOROUTER_CONFIG_DIR` environment variable
+//! 3. A file named `portable` next to the executable
+//! 4. `%LOCALAPPDATA%\AudioRouter` (`%APPDATA%` if unset)
+//!
+//! A portable copy only works where the executable's directory is writable,
+//! which is not the case for an installation under Program Files.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the config directory.
+pub const CONFIG_DIR_ENV: &str = "AUDIOROUTER_CONFIG_DIR";
+/// Marker file next to the executable that turns on portable mode.
+pub const PORTABLE_MARKER: &str = "portable";
+/// Name of the app's folder under the app data directory.
+const APP_DIR_NAME: &str = "AudioRouter";
+
+/// What decided the config directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigDirSource {
+    CommandLine,
+    Environment,
+    PortableMarker,
+    AppData,
+}
+
+/// The resolved config directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDir {
+    pub path: PathBuf,
+    pub source: ConfigDirSource,
+    /// Whether `path` is the executable's directory.
+    pub portable: bool,
+}
+
+impl ConfigDir {
+    /// Resolves the directory for this process from `args` (without the
+    /// program name), the environment and the executable's location.
+    pub fn resolve(args: impl IntoIterator<Item = OsString>) -> Self {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let app_data = std::env::var_os("LOCALAPPDATA")
+            .or_else(|| std::env::var_os("APPDATA"))
+            .map(PathBuf::from);
+        resolve_with(
+            args,
+            std::env::var_os(CONFIG_DIR_ENV),
+            exe_dir.as_deref(),
+            app_data.as_deref(),
+        )
+    }
+}
+
+fn resolve_with(
+    args: impl IntoIterator<Item = OsString>,
+    env_dir: Option<OsString>,
+    exe_dir: Option<&Path>,
+    app_data: Option<&Path>,
+) -> ConfigDir {
+    let exe_dir = exe_dir.unwrap_or(Path::new("."));
+    let dir = |path: PathBuf, source| ConfigDir {
+        portable: path == exe_dir,
+        path,
+        source,
+    };
+
+    let mut args = args.into_iter();
+    let mut portable = false;
+    while let Some(arg) = args.next() {
+        if arg == "--portable" {
+            portable = true;
+        } else if arg == "--config-dir" {
+            if let Some(path) = args.next() {
+                return dir(path.into(), ConfigDirSource::CommandLine);
+            }
+        } else if let Some(path) = arg.to_str().and_then(|a| a.strip_prefix("--config-dir=")) {
+            return dir(path.into(), ConfigDirSource::CommandLine);
+        }
+    }
+    if portable {
+        return dir(exe_dir.to_path_buf(), ConfigDirSource::CommandLine);
+    }
+    if let Some(path) = env_dir.filter(|p| !p.is_empty()) {
+        return dir(path.into(), ConfigDirSource::Environment);
+    }
+    if exe_dir.join(PORTABLE_MARKER).is_file() {
+        return dir(exe_dir.to_path_buf(), ConfigDirSource::PortableMarker);
+    }
+    let app_data = app_data.unwrap_or(exe_dir);
+    dir(app_data.join(APP_DIR_NAME), ConfigDirSource::AppData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn first_matching_source_wins() {
+        let exe = tempdir().expect("tempdir");
+        let exe = exe.path();
+        let app_data = Path::new("appdata");
+        let env = || Some(OsString::from("from-env"));
+
+        let resolved = resolve_with(
+            args(&["--minimized", "--config-dir", "cli"]),
+            env(),
+            Some(exe),
+            Some(app_data),
+        );
+        assert_eq!(resolved.path, Path::new("cli"));
+        assert_eq!(resolved.source, ConfigDirSource::CommandLine);
+
+        let resolved = resolve_with(args(&["--portable"]), env(), Some(exe), Some(app_data));
+        assert_eq!(resolved.path, exe);
+        assert!(resolved.portable);
+
+        let resolved = resolve_with(args(&[]), env(), Some(exe), Some(app_data));
+        assert_eq!(resolved.source, ConfigDirSource::Environment);
+
+        let resolved = resolve_with(args(&[]), None, Some(exe), Some(app_data));
+        assert_eq!(resolved.path, app_data.join("AudioRouter"));
+        assert!(!resolved.portable);
+
+        std::fs::write(exe.join(PORTABLE_MARKER), "").unwrap();
+        let resolved = resolve_with(args(&[]), None, Some(exe), Some(app_data));
+        assert_eq!(resolved.source, ConfigDirSource::PortableMarker);
+        assert!(resolved.portable);
+    }
+}
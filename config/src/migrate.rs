@@ -0,0 +1,302 @@
+//! Upgrades settings files written by older versions of the app.
+//!
+//! Migrations run on the raw TOML table before it is deserialized into
+//! [`crate::Config`], so a renamed or restructured field is carried over
+//! instead of failing to parse or silently falling back to its default.
+//! Each step upgrades exactly one version; a file several versions behind
+//! goes through every step in order.
+
+use anyhow::{Context, Result, bail};
+use toml::{Table, Value};
+
+/// Schema version written by this build.
+pub const CURRENT_CONFIG_VERSION: i32 = 5;
+
+/// `MIGRATIONS[i]` upgrades version `i + 1` to `i + 2`.
+const MIGRATIONS: [fn(&mut Table) -> Result<()>; (CURRENT_CONFIG_VERSION - 1) as usize] =
+    [v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5];
+
+/// Upgrades `table` in place to [`CURRENT_CONFIG_VERSION`] and returns the
+/// version it was written with.
+///
+/// # Errors
+/// Returns an error if `config_version` is not a known version (including
+/// files written by a newer app) or a migration step fails.
+pub fn migrate(table: &mut Table) -> Result<i32> {
+    let version = match table.get("config_version") {
+        Some(Value::Integer(version)) => i32::try_from(*version).unwrap_or(i32::MAX),
+        Some(other) => bail!("config_version must be an integer, found {other}"),
+        None => bail!("config_version is missing"),
+    };
+    if version < 1 {
+        bail!("unknown config_version {version}");
+    }
+    if version > CURRENT_CONFIG_VERSION {
+        bail!(
+            "config_version {version} was written by a newer version of the app \
+             (this one supports up to {CURRENT_CONFIG_VERSION})"
+        );
+    }
+
+    for (from, step) in (version..).zip(&MIGRATIONS[(version - 1) as usize..]) {
+        step(table).with_context(|| format!("migrating config from version {from}"))?;
+        table.insert("config_version".into(), Value::Integer(i64::from(from) + 1));
+    }
+    Ok(version)
+}
+
+/// v2 stores channel modes by their full name; v1 still accepted the early
+/// "Left" and "Right" spellings.
+fn v1_to_v2(table: &mut Table) -> Result<()> {
+    for list in ["outputs", "auto_route_rules"] {
+        let Some(items) = table.get_mut(list) else {
+            continue;
+        };
+        let Value::Array(items) = items else {
+            bail!("{list} must be an array");
+        };
+        for item in items {
+            if let Some(Value::String(mode)) = item.get_mut("channel_mode") {
+                match mode.as_str() {
+                    "Left" => *mode = "LeftMono".into(),
+                    "Right" => *mode = "RightMono".into(),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// v3 replaces `source_device_id` and the optional `source_role` with one
+/// `source` selection, in the config itself and in every saved profile.
+fn v2_to_v3(table: &mut Table) -> Result<()> {
+    merge_source(table)?;
+    for_each_profile(table, merge_source)
+}
+
+/// v4 lets outputs leave `resampler` and `overflow_policy` to the `[engine]`
+/// section. Older versions always wrote both, so the old defaults are
+/// dropped and those outputs follow the engine settings.
+fn v3_to_v4(table: &mut Table) -> Result<()> {
+    inherit_engine_defaults(table)?;
+    for_each_profile(table, inherit_engine_defaults)
+}
+
+/// v5 moves `minimized` and `auto_route` from `[general]` to `[startup]`
+/// and saves them, like the hotplug rules, with each profile. Existing
+/// profiles get the values the file had, so switching keeps the behavior.
+fn v4_to_v5(table: &mut Table) -> Result<()> {
+    let mut startup = Table::new();
+    if let Some(Value::Table(general)) = table.get_mut("general") {
+        for key in ["minimized", "auto_route"] {
+            if let Some(value) = general.remove(key) {
+                startup.insert(key.into(), value);
+            }
+        }
+    }
+    let hotplug = table.get("hotplug").cloned();
+    if let Some(Value::Array(profiles)) = table.get_mut("profiles") {
+        for profile in profiles {
+            if let Some(Value::Table(settings)) = profile.get_mut("settings") {
+                settings.insert("startup".into(), Value::Table(startup.clone()));
+                if let Some(hotplug) = &hotplug {
+                    settings.insert("hotplug".into(), hotplug.clone());
+                }
+            }
+        }
+    }
+    table.insert("startup".into(), Value::Table(startup));
+    Ok(())
+}
+
+/// Runs `step` on the settings of every saved profile.
+fn for_each_profile(table: &mut Table, step: fn(&mut Table) -> Result<()>) -> Result<()> {
+    let Some(profiles) = table.get_mut("profiles") else {
+        return Ok(());
+    };
+    let Value::Array(profiles) = profiles else {
+        bail!("profiles must be an array");
+    };
+    for profile in profiles {
+        if let Some(Value::Table(settings)) = profile.get_mut("settings") {
+            step(settings)?;
+        }
+    }
+    Ok(())
+}
+
+fn merge_source(table: &mut Table) -> Result<()> {
+    let device_id = table.remove("source_device_id");
+    let (kind, value) = match (table.remove("source_role"), device_id) {
+        (Some(Value::String(role)), _) => ("Default", role),
+        (Some(other), _) => bail!("source_role must be a string, found {other}"),
+        (None, Some(Value::String(id))) if !id.is_empty() => ("Device", id),
+        // 未选择源设备时改为跟随默认设备
+        (None, _) => ("Default", "Console".to_string()),
+    };
+    let mut source = Table::new();
+    source.insert(kind.into(), Value::String(value));
+    table.insert("source".into(), Value::Table(source));
+    Ok(())
+}
+
+fn inherit_engine_defaults(table: &mut Table) -> Result<()> {
+    let Some(outputs) = table.get_mut("outputs") else {
+        return Ok(());
+    };
+    let Value::Array(outputs) = outputs else {
+        bail!("outputs must be an array");
+    };
+    for output in outputs.iter_mut().filter_map(Value::as_table_mut) {
+        for (key, old_default) in [("resampler", "System"), ("overflow_policy", "DropNewest")] {
+            if output.get(key).and_then(Value::as_str) == Some(old_default) {
+                output.remove(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Table {
+        toml::from_str(s).expect("parse")
+    }
+
+    #[test]
+    fn v1_channel_mode_aliases_are_spelled_out() {
+        let mut table = parse(
+            r#"
+            config_version = 1
+            [[outputs]]
+            device_id = "a"
+            channel_mode = "Left"
+            [[outputs]]
+            device_id = "b"
+            channel_mode = "Swap"
+            [[auto_route_rules]]
+            device_name = "USB"
+            channel_mode = "Right"
+            "#,
+        );
+        assert_eq!(migrate(&mut table).unwrap(), 1);
+        assert_eq!(
+            table["config_version"].as_integer(),
+            Some(CURRENT_CONFIG_VERSION.into())
+        );
+        let modes: Vec<_> = ["outputs", "auto_route_rules"]
+            .iter()
+            .flat_map(|list| table[*list].as_array().unwrap())
+            .map(|item| item["channel_mode"].as_str().unwrap())
+            .collect();
+        assert_eq!(modes, ["LeftMono", "Swap", "RightMono"]);
+    }
+
+    #[test]
+    fn v2_source_fields_become_one_selection() {
+        let mut table = parse(
+            r#"
+            config_version = 2
+            source_device_id = "a"
+            [[profiles]]
+            name = "Calls"
+            settings = { source_device_id = "b", source_role = "Communications" }
+            [[profiles]]
+            name = "Empty"
+            settings = { source_device_id = "" }
+            "#,
+        );
+        migrate(&mut table).unwrap();
+        let sources: Vec<_> = std::iter::once(&table)
+            .chain(
+                table["profiles"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p["settings"].as_table().unwrap()),
+            )
+            .map(|t| {
+                assert!(!t.contains_key("source_device_id") && !t.contains_key("source_role"));
+                t["source"].to_string()
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                r#"{ Device = "a" }"#,
+                r#"{ Default = "Communications" }"#,
+                r#"{ Default = "Console" }"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn v3_default_output_policies_follow_the_engine() {
+        let mut table = parse(
+            r#"
+            config_version = 3
+            [[outputs]]
+            device_id = "a"
+            resampler = "System"
+            overflow_policy = "Resync"
+            [[profiles]]
+            name = "Desk"
+            settings = { outputs = [{ device_id = "b", overflow_policy = "DropNewest" }] }
+            "#,
+        );
+        migrate(&mut table).unwrap();
+        let output = table["outputs"][0].as_table().unwrap();
+        assert!(!output.contains_key("resampler"));
+        assert_eq!(output["overflow_policy"].as_str(), Some("Resync"));
+        let output = table["profiles"][0]["settings"]["outputs"][0]
+            .as_table()
+            .unwrap();
+        assert!(!output.contains_key("overflow_policy"));
+    }
+
+    #[test]
+    fn v4_startup_settings_move_into_profiles() {
+        let mut table = parse(
+            r#"
+            config_version = 4
+            [general]
+            language = "en"
+            minimized = true
+            auto_route = true
+            [hotplug]
+            on_unplug = "Disable"
+            [[profiles]]
+            name = "Desk"
+            settings = { fade_in_ms = 20.0 }
+            "#,
+        );
+        migrate(&mut table).unwrap();
+        let general = table["general"].as_table().unwrap();
+        assert!(!general.contains_key("minimized"));
+        assert!(!general.contains_key("auto_route"));
+        assert_eq!(table["startup"]["auto_route"].as_bool(), Some(true));
+        let settings = &table["profiles"][0]["settings"];
+        assert_eq!(settings["startup"], table["startup"]);
+        assert_eq!(settings["hotplug"]["on_unplug"].as_str(), Some("Disable"));
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let mut table = parse("config_version = 5\nsource = { Device = \"x\" }");
+        let before = table.clone();
+        assert_eq!(migrate(&mut table).unwrap(), CURRENT_CONFIG_VERSION);
+        assert_eq!(table, before);
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        for version in ["0", "6", "\"1\""] {
+            let mut table = parse(&format!("config_version = {version}"));
+            assert!(migrate(&mut table).is_err(), "{version}");
+        }
+        assert!(migrate(&mut Table::new()).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+//! Settings forced for a single run from the environment or the command
+//! line, e.g. by the shortcut of a kiosk deployment. They are layered over
+//! `settings.toml` whenever it is loaded and never written back to it.
+//!
+//! - `--set <path>=<value>` or `AUDIOROUTER_<PATH>=<value>` sets any key. On
+//!   the command line the path is dotted (`engine.buffer_ms`, `outputs.0.volume`);
+//!   in a variable name the levels are separated by `__`
+//!   (`AUDIOROUTER_ENGINE__BUFFER_MS`) and the path is lowercased.
+//! - `--source-device <id>` or `AUDIOROUTER_SOURCE_DEVICE=<id>` pins the
+//!   source to a fixed device.
+//!
+//! Values are read as TOML (`true`, `20`, `"Mica"`, `{ Device = "..." }`);
+//! anything that does not parse is taken as a string. The command line wins
+//! over the environment.
+//!
+//! An overridden setting can still be changed in the app, but the change
+//! only lasts until exit: the file keeps the value it had.
+
+use crate::location::CONFIG_DIR_ENV;
+use anyhow::{Context, Result, anyhow, bail};
+use std::ffi::OsString;
+use toml::{Table, Value};
+
+const ENV_PREFIX: &str = "AUDIOROUTER_";
+const SOURCE_DEVICE_ENV: &str = "AUDIOROUTER_SOURCE_DEVICE";
+
+/// One forced setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    /// Dotted key path, e.g. `general.language`
+    pub path: String,
+    pub value: Value,
+    /// Flag or variable it came from, for error messages
+    pub origin: String,
+}
+
+/// Ordered list of overrides; a later entry for the same path wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigOverrides {
+    entries: Vec<Override>,
+}
+
+impl ConfigOverrides {
+    /// Overrides given to this process through its environment and command
+    /// line. Arguments other than the override flags are ignored.
+    pub fn from_process() -> Result<Self> {
+        Self::parse(std::env::vars_os(), std::env::args_os().skip(1))
+    }
+
+    fn parse(
+        env: impl IntoIterator<Item = (OsString, OsString)>,
+        args: impl IntoIterator<Item = OsString>,
+    ) -> Result<Self> {
+        let mut overrides = Self::default();
+
+        let mut vars: Vec<_> = env
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name != CONFIG_DIR_ENV)
+            .collect();
+        // 环境变量无固定顺序，排序后同一路径的覆盖结果可复现
+        vars.sort();
+        for (name, value) in vars {
+            let value = value
+                .into_string()
+                .map_err(|_| anyhow!("{name} is not valid Unicode"))?;
+            if name == SOURCE_DEVICE_ENV {
+                overrides.push_source_device(value, name);
+            } else {
+                let path = name[ENV_PREFIX.len()..].to_lowercase().replace("__", ".");
+                overrides.push(path, parse_value(&value), name);
+            }
+        }
+
+        let mut args = args
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned());
+        while let Some(arg) = args.next() {
+            if arg == "--set" || arg == "--source-device" {
+                let value = args
+                    .next()
+                    .with_context(|| format!("{arg} needs a value"))?;
+                overrides.push_arg(&arg, value)?;
+            } else if let Some(value) = arg.strip_prefix("--set=") {
+                overrides.push_arg("--set", value.to_string())?;
+            } else if let Some(value) = arg.strip_prefix("--source-device=") {
+                overrides.push_arg("--source-device", value.to_string())?;
+            }
+        }
+        Ok(overrides)
+    }
+
+    fn push_arg(&mut self, flag: &str, value: String) -> Result<()> {
+        if flag == "--source-device" {
+            self.push_source_device(value, flag.to_string());
+            return Ok(());
+        }
+        let Some((path, value)) = value.split_once('=') else {
+            bail!("{flag} expects <path>=<value>, got `{value}`");
+        };
+        self.push(
+            path.trim().to_string(),
+            parse_value(value.trim()),
+            format!("{flag} {path}"),
+        );
+        Ok(())
+    }
+
+    fn push_source_device(&mut self, id: String, origin: String) {
+        let mut source = Table::new();
+        source.insert("Device".into(), Value::String(id));
+        self.push("source".into(), Value::Table(source), origin);
+    }
+
+    /// Adds an override after the existing ones.
+    pub fn push(&mut self, path: String, value: Value, origin: String) {
+        self.entries.push(Override {
+            path,
+            value,
+            origin,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Override> {
+        self.entries.iter()
+    }
+
+    /// Writes the overrides into a migrated settings table.
+    pub(crate) fn apply(&self, table: &mut Table) -> Result<()> {
+        for entry in &self.entries {
+            set(table, &entry.path, entry.value.clone())
+                .with_context(|| format!("applying override from {}", entry.origin))?;
+        }
+        Ok(())
+    }
+
+    /// Fails for overrides of keys the config does not have, which would
+    /// otherwise be dropped silently when deserializing. `applied` is the
+    /// config deserialized from the table passed to [`Self::apply`].
+    pub(crate) fn check_known(&self, applied: &Table) -> Result<()> {
+        match self
+            .entries
+            .iter()
+            .find(|e| get(applied, &e.path).is_none())
+        {
+            Some(entry) => bail!("unknown setting `{}` in {}", entry.path, entry.origin),
+            None => Ok(()),
+        }
+    }
+
+    /// Puts back the values `file` has for the overridden keys, so saving
+    /// `table` does not persist the overrides.
+    pub(crate) fn restore(&self, table: &mut Table, file: &Table) {
+        for entry in &self.entries {
+            match get(file, &entry.path) {
+                Some(value) => {
+                    let _ = set(table, &entry.path, value.clone());
+                }
+                None => remove(table, &entry.path),
+            }
+        }
+    }
+}
+
+/// Reads `s` as a TOML value, or as a plain string if it is not one.
+fn parse_value(s: &str) -> Value {
+    toml::from_str::<Table>(&format!("v = {s}"))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| Value::String(s.to_string()))
+}
+
+fn get<'a>(table: &'a Table, path: &str) -> Option<&'a Value> {
+    let (first, rest) = split_first(path);
+    let mut value = table.get(first)?;
+    for key in rest {
+        value = match value {
+            Value::Table(t) => t.get(key)?,
+            Value::Array(a) => a.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn set(table: &mut Table, path: &str, new: Value) -> Result<()> {
+    let (first, rest) = split_first(path);
+    if first.is_empty() {
+        bail!("empty setting path");
+    }
+    let Some((last, parents)) = rest.split_last() else {
+        table.insert(first.to_string(), new);
+        return Ok(());
+    };
+    let mut value = table
+        .entry(first)
+        .or_insert_with(|| Value::Table(Table::new()));
+    for key in parents {
+        value = child(value, key).with_context(|| format!("`{path}` is not a setting"))?;
+    }
+    match value {
+        Value::Table(t) => {
+            t.insert(last.to_string(), new);
+        }
+        value => *child(value, last).with_context(|| format!("`{path}` is not a setting"))? = new,
+    }
+    Ok(())
+}
+
+/// The entry `key` of a table (created if missing) or of an array.
+fn child<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Table(t) => Some(t.entry(key).or_insert_with(|| Value::Table(Table::new()))),
+        Value::Array(a) => a.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+fn remove(table: &mut Table, path: &str) {
+    match path.rsplit_once('.') {
+        None => {
+            table.remove(path);
+        }
+        Some((parent, key)) => {
+            let (first, rest) = split_first(parent);
+            let mut value = table.get_mut(first);
+            for k in rest {
+                value = match value {
+                    Some(Value::Table(t)) => t.get_mut(k),
+                    Some(Value::Array(a)) => k.parse::<usize>().ok().and_then(|i| a.get_mut(i)),
+                    _ => None,
+                };
+            }
+            if let Some(Value::Table(t)) = value {
+                t.remove(key);
+            }
+        }
+    }
+}
+
+fn split_first(path: &str) -> (&str, Vec<&str>) {
+    let mut keys = path.split('.');
+    let first = keys.next().unwrap_or_default();
+    (first, keys.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn flags_and_variables_become_overrides() {
+        let env = [
+            ("AUDIOROUTER_CONFIG_DIR", "D:\\kiosk"),
+            ("AUDIOROUTER_ENGINE__BUFFER_MS", "20"),
+            ("AUDIOROUTER_SOURCE_DEVICE", "{0.0.0.00000000}.{abc}"),
+            ("PATH", "C:\\Windows"),
+        ]
+        .map(|(k, v)| (OsString::from(k), OsString::from(v)));
+        let args = os(&[
+            "--minimized",
+            "--set",
+            "general.language=en",
+            "--set=start_routing_on_launch=true",
+        ]);
+        let overrides = ConfigOverrides::parse(env, args).unwrap();
+        let entries: Vec<_> = overrides
+            .iter()
+            .map(|e| (e.path.as_str(), &e.value))
+            .collect();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0], ("engine.buffer_ms", &Value::Integer(20)));
+        assert_eq!(entries[1].0, "source");
+        assert_eq!(
+            get(entries[1].1.as_table().unwrap(), "Device"),
+            Some(&Value::String("{0.0.0.00000000}.{abc}".into()))
+        );
+        assert_eq!(
+            entries[2],
+            ("general.language", &Value::String("en".into()))
+        );
+        assert_eq!(
+            entries[3],
+            ("start_routing_on_launch", &Value::Boolean(true))
+        );
+
+        assert!(ConfigOverrides::parse([], os(&["--set", "general.language"])).is_err());
+        assert!(ConfigOverrides::parse([], os(&["--source-device"])).is_err());
+    }
+
+    #[test]
+    fn restore_keeps_the_file_values() {
+        let file: Table = toml::from_str("[general]\nlanguage = \"zh\"\n").unwrap();
+        let mut overrides = ConfigOverrides::default();
+        overrides.push(
+            "general.language".into(),
+            Value::String("en".into()),
+            "test".into(),
+        );
+        overrides.push("engine.buffer_ms".into(), Value::Integer(20), "test".into());
+
+        let mut table = file.clone();
+        overrides.apply(&mut table).unwrap();
+        assert_eq!(get(&table, "engine.buffer_ms"), Some(&Value::Integer(20)));
+        overrides.restore(&mut table, &file);
+        assert_eq!(
+            table,
+            toml::from_str("[general]\nlanguage = \"zh\"\n[engine]\n").unwrap()
+        );
+
+        overrides.push(
+            "general.language.x".into(),
+            Value::Integer(1),
+            "test".into(),
+        );
+        assert!(overrides.apply(&mut table).is_err());
+    }
+}
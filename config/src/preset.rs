@@ -0,0 +1,182 @@
+//! Routing presets, e.g. "Everything to headphones" or "Speakers + kitchen".
+//!
+//! Lighter than a [profile](crate::profile): a preset only records the
+//! source and which outputs play in which channel mode. Applying one enables
+//! exactly those outputs and leaves their DSP and every other setting as is.
+
+use crate::config::{Config, FieldError, Output, SourceSelection};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// An output enabled by a preset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct PresetOutput {
+    pub device_id: String,
+    /// Channel mode given to the output (as in [`Output::channel_mode`])
+    #[serde(default)]
+    pub channel_mode: Option<String>,
+}
+
+/// Source and output set saved under a name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct RoutingPreset {
+    pub name: String,
+    #[serde(default)]
+    pub source: SourceSelection,
+    #[serde(default)]
+    pub outputs: Vec<PresetOutput>,
+}
+
+impl Config {
+    pub fn preset(&self, name: &str) -> Option<&RoutingPreset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    /// Saves the current source and enabled outputs as preset `name`,
+    /// replacing a preset of that name.
+    ///
+    /// # Errors
+    /// Returns an error if the name is empty.
+    pub fn save_preset(&mut self, name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("preset name is empty");
+        }
+        let preset = RoutingPreset {
+            name: name.to_string(),
+            source: self.source.clone(),
+            outputs: self
+                .outputs
+                .iter()
+                .filter(|o| o.enabled)
+                .map(|o| PresetOutput {
+                    device_id: o.device_id.clone(),
+                    channel_mode: o.channel_mode.clone(),
+                })
+                .collect(),
+        };
+        match self.presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        Ok(())
+    }
+
+    /// # Errors
+    /// Returns an error if the preset does not exist.
+    pub fn delete_preset(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .presets
+            .iter()
+            .position(|p| p.name == name)
+            .with_context(|| format!("preset {name:?} does not exist"))?;
+        self.presets.remove(index);
+        Ok(())
+    }
+
+    /// Switches to the source of preset `name` and enables exactly its
+    /// outputs, in their saved channel modes. Outputs not configured yet are
+    /// added with default settings.
+    ///
+    /// # Errors
+    /// Returns an error if the preset does not exist; nothing changes then.
+    pub fn apply_preset(&mut self, name: &str) -> Result<()> {
+        let preset = self
+            .preset(name)
+            .with_context(|| format!("preset {name:?} does not exist"))?
+            .clone();
+        self.source = preset.source;
+        for output in &mut self.outputs {
+            output.enabled = false;
+        }
+        for wanted in preset.outputs {
+            let index = match self
+                .outputs
+                .iter()
+                .position(|o| o.device_id == wanted.device_id)
+            {
+                Some(index) => index,
+                None => {
+                    self.outputs.push(Output::new(wanted.device_id));
+                    self.outputs.len() - 1
+                }
+            };
+            let output = &mut self.outputs[index];
+            output.enabled = true;
+            if wanted.channel_mode.is_some() {
+                output.channel_mode = wanted.channel_mode;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn preset_field_errors(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        for (index, preset) in self.presets.iter().enumerate() {
+            let field = format!("presets[{index}].name");
+            if preset.name.trim().is_empty() {
+                errors.push(FieldError {
+                    field: field.clone(),
+                    message: "preset name is empty".into(),
+                });
+            }
+            if self.presets[..index].iter().any(|p| p.name == preset.name) {
+                errors.push(FieldError {
+                    field,
+                    message: format!("preset {:?} exists twice", preset.name),
+                });
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applying_a_preset_enables_exactly_its_outputs() {
+        let mut cfg = Config {
+            source: SourceSelection::Device("src".into()),
+            outputs: vec![
+                Output {
+                    enabled: true,
+                    gain_db: -3.0,
+                    ..Output::new("headphones".into())
+                },
+                Output::new("speakers".into()),
+            ],
+            ..Config::default()
+        };
+        cfg.save_preset("Headphones").unwrap();
+        assert!(cfg.save_preset("").is_err());
+
+        cfg.outputs[0].enabled = false;
+        cfg.outputs[1].enabled = true;
+        cfg.outputs[1].channel_mode = Some("Mono".into());
+        cfg.save_preset("Speakers").unwrap();
+        cfg.presets[1].outputs.push(PresetOutput {
+            device_id: "kitchen".into(),
+            channel_mode: Some("LeftMono".into()),
+        });
+
+        cfg.apply_preset("Headphones").unwrap();
+        let enabled: Vec<_> = cfg.outputs.iter().filter(|o| o.enabled).collect();
+        assert_eq!(enabled.len(), 1);
+        assert_eq!(enabled[0].device_id, "headphones");
+        assert_eq!(enabled[0].gain_db, -3.0);
+
+        cfg.apply_preset("Speakers").unwrap();
+        let enabled: Vec<_> = cfg.outputs.iter().filter(|o| o.enabled).collect();
+        assert_eq!(enabled.len(), 2);
+        assert_eq!(enabled[1].device_id, "kitchen");
+        assert_eq!(enabled[1].channel_mode.as_deref(), Some("LeftMono"));
+        cfg.validate().unwrap();
+
+        cfg.delete_preset("Speakers").unwrap();
+        assert!(cfg.apply_preset("Speakers").is_err());
+        cfg.presets.push(cfg.presets[0].clone());
+        assert!(cfg.validate().is_err());
+    }
+}
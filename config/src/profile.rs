@@ -0,0 +1,269 @@
+//! Named routing profiles, e.g. "Desk", "Living room" or "Streaming".
+//!
+//! The active setup always lives in the top-level [`Config`] fields that the
+//! rest of the app reads. A profile is a saved copy of those fields; switching
+//! profiles first stores the current fields into the active profile, then
+//! loads the new one.
+
+use crate::config::{
+    BassManagement, Config, FieldError, HotplugSettings, MixLevels, NoiseGateSettings, Output,
+    SourceSelection, StartupSettings, default_fade_in_ms,
+};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// The part of [`Config`] that belongs to a profile: the source, the outputs
+/// with their DSP, the session-wide processing, and what happens on launch
+/// and on hotplug.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct ProfileSettings {
+    #[serde(default)]
+    pub startup: StartupSettings,
+    #[serde(default)]
+    pub source: SourceSelection,
+    #[serde(default)]
+    pub outputs: Vec<Output>,
+    #[serde(default)]
+    pub bass_management: BassManagement,
+    #[serde(default)]
+    pub mix_levels: MixLevels,
+    #[serde(default)]
+    pub noise_gate: NoiseGateSettings,
+    #[serde(default = "default_fade_in_ms")]
+    pub fade_in_ms: f32,
+    #[serde(default)]
+    pub mirror_source_volume: bool,
+    #[serde(default)]
+    pub mute_source_while_routing: bool,
+    #[serde(default)]
+    pub default_device_while_routing: Option<String>,
+    #[serde(default)]
+    pub hotplug: HotplugSettings,
+}
+
+/// Routing setup saved under a name.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Profile {
+    pub name: String,
+    pub settings: ProfileSettings,
+}
+
+impl Config {
+    /// The current profile settings.
+    pub fn profile_settings(&self) -> ProfileSettings {
+        ProfileSettings {
+            startup: self.startup,
+            source: self.source.clone(),
+            outputs: self.outputs.clone(),
+            bass_management: self.bass_management.clone(),
+            mix_levels: self.mix_levels,
+            noise_gate: self.noise_gate,
+            fade_in_ms: self.fade_in_ms,
+            mirror_source_volume: self.mirror_source_volume,
+            mute_source_while_routing: self.mute_source_while_routing,
+            default_device_while_routing: self.default_device_while_routing.clone(),
+            hotplug: self.hotplug.clone(),
+        }
+    }
+
+    /// Replaces the current profile settings with `settings`.
+    pub fn apply_profile_settings(&mut self, settings: ProfileSettings) {
+        self.startup = settings.startup;
+        self.source = settings.source;
+        self.outputs = settings.outputs;
+        self.bass_management = settings.bass_management;
+        self.mix_levels = settings.mix_levels;
+        self.noise_gate = settings.noise_gate;
+        self.fade_in_ms = settings.fade_in_ms;
+        self.mirror_source_volume = settings.mirror_source_volume;
+        self.mute_source_while_routing = settings.mute_source_while_routing;
+        self.default_device_while_routing = settings.default_device_while_routing;
+        self.hotplug = settings.hotplug;
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Saves the current settings as a new profile `name` without switching to it.
+    ///
+    /// # Errors
+    /// Returns an error if the name is empty or already taken.
+    pub fn create_profile(&mut self, name: &str) -> Result<()> {
+        self.check_new_profile_name(name)?;
+        let settings = self.profile_settings();
+        self.profiles.push(Profile {
+            name: name.to_string(),
+            settings,
+        });
+        Ok(())
+    }
+
+    /// Copies profile `from` to a new profile `name`. Copying the active
+    /// profile takes its current, possibly unsaved, settings.
+    ///
+    /// # Errors
+    /// Returns an error if `from` does not exist or `name` is empty or taken.
+    pub fn clone_profile(&mut self, from: &str, name: &str) -> Result<()> {
+        self.check_new_profile_name(name)?;
+        let settings = if self.active_profile.as_deref() == Some(from) {
+            self.profile_settings()
+        } else {
+            self.profile(from)
+                .with_context(|| format!("profile {from:?} does not exist"))?
+                .settings
+                .clone()
+        };
+        self.profiles.push(Profile {
+            name: name.to_string(),
+            settings,
+        });
+        Ok(())
+    }
+
+    /// Deletes profile `name`. Deleting the active profile keeps its settings
+    /// as the current ones, no longer saved under any name. A hotkey bound to
+    /// the profile is removed with it.
+    ///
+    /// # Errors
+    /// Returns an error if the profile does not exist.
+    pub fn delete_profile(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .profiles
+            .iter()
+            .position(|p| p.name == name)
+            .with_context(|| format!("profile {name:?} does not exist"))?;
+        self.profiles.remove(index);
+        self.hotkeys.profiles.remove(name);
+        if self.active_profile.as_deref() == Some(name) {
+            self.active_profile = None;
+        }
+        Ok(())
+    }
+
+    /// Stores the current settings into the active profile, then loads
+    /// profile `name` and makes it active.
+    ///
+    /// # Errors
+    /// Returns an error if the profile does not exist; nothing changes then.
+    pub fn activate_profile(&mut self, name: &str) -> Result<()> {
+        let settings = self
+            .profile(name)
+            .with_context(|| format!("profile {name:?} does not exist"))?
+            .settings
+            .clone();
+        let current = self.profile_settings();
+        if let Some(active) = self.active_profile.take()
+            && let Some(profile) = self.profiles.iter_mut().find(|p| p.name == active)
+        {
+            profile.settings = current;
+        }
+        self.apply_profile_settings(settings);
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    fn check_new_profile_name(&self, name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("profile name is empty");
+        }
+        if self.profile(name).is_some() {
+            bail!("profile {name:?} already exists");
+        }
+        Ok(())
+    }
+
+    /// Checks the profile names and the settings saved in every profile.
+    pub(crate) fn profile_field_errors(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        for (index, profile) in self.profiles.iter().enumerate() {
+            let error = |field: String, message: String| FieldError {
+                field: format!("profiles[{index}].{field}"),
+                message,
+            };
+            if profile.name.trim().is_empty() {
+                errors.push(error("name".into(), "profile name is empty".into()));
+            }
+            if self.profiles[..index]
+                .iter()
+                .any(|p| p.name == profile.name)
+            {
+                errors.push(error(
+                    "name".into(),
+                    format!("profile {:?} exists twice", profile.name),
+                ));
+            }
+            // 其余字段取默认值，只报告属于配置方案的字段
+            let mut cfg = Config::default();
+            cfg.apply_profile_settings(profile.settings.clone());
+            errors.extend(cfg.field_errors().into_iter().map(|e| {
+                error(
+                    format!("settings.{}", e.field),
+                    format!("profile {:?}: {}", profile.name, e.message),
+                )
+            }));
+        }
+        if let Some(active) = &self.active_profile
+            && self.profile(active).is_none()
+        {
+            errors.push(FieldError {
+                field: "active_profile".into(),
+                message: format!("active profile {active:?} does not exist"),
+            });
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_profiles_saves_and_restores_settings() {
+        let mut cfg = Config {
+            source: SourceSelection::Device("desk-src".into()),
+            ..Config::default()
+        };
+        cfg.create_profile("Desk").unwrap();
+        cfg.activate_profile("Desk").unwrap();
+        assert!(cfg.create_profile("Desk").is_err());
+        assert!(cfg.create_profile(" ").is_err());
+
+        cfg.clone_profile("Desk", "Streaming").unwrap();
+        cfg.activate_profile("Streaming").unwrap();
+        cfg.source = SourceSelection::Device("stream-src".into());
+        cfg.fade_in_ms = 500.0;
+        cfg.startup.auto_route = true;
+
+        cfg.activate_profile("Desk").unwrap();
+        assert_eq!(cfg.source.device_id(), Some("desk-src"));
+        assert_eq!(cfg.fade_in_ms, Config::default().fade_in_ms);
+        assert!(!cfg.startup.auto_route);
+
+        cfg.activate_profile("Streaming").unwrap();
+        assert_eq!(cfg.source.device_id(), Some("stream-src"));
+        assert_eq!(cfg.fade_in_ms, 500.0);
+        assert!(cfg.startup.auto_route);
+        cfg.validate().unwrap();
+
+        cfg.delete_profile("Streaming").unwrap();
+        assert_eq!(cfg.active_profile, None);
+        assert_eq!(cfg.source.device_id(), Some("stream-src"));
+        assert!(cfg.activate_profile("Streaming").is_err());
+        assert!(cfg.delete_profile("Streaming").is_err());
+    }
+
+    #[test]
+    fn validate_checks_saved_profiles() {
+        let mut cfg = Config::default();
+        cfg.create_profile("Desk").unwrap();
+        cfg.profiles[0].settings.fade_in_ms = -1.0;
+        assert!(format!("{:#}", cfg.validate().unwrap_err()).contains("Desk"));
+
+        cfg.profiles[0].settings.fade_in_ms = 0.0;
+        cfg.active_profile = Some("Missing".into());
+        assert!(cfg.validate().is_err());
+    }
+}
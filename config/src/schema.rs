@@ -0,0 +1,291 @@
+//! JSON Schema of the settings format, generated from the `specta::Type`
+//! definitions so it always matches the structs in [`crate::config`].
+//!
+//! Every named type becomes an entry in `$defs`. Fields with a default are
+//! not `required`, and unknown keys are allowed because the app ignores
+//! them. `null` is accepted wherever an `Option` is, as in JSON exports; in
+//! `settings.toml` such keys are left out instead.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+use specta::datatype::{
+    DataType, EnumRepr, EnumType, EnumVariants, Field, LiteralType, PrimitiveType, StructFields,
+    StructType,
+};
+use specta::{NamedType, TypeCollection};
+use std::path::Path;
+
+/// Structs with `#[serde(default)]` on the whole struct, which specta does
+/// not record: none of their fields is required.
+const DEFAULTED_STRUCTS: &[&str] = &[
+    "EngineSettings",
+    "Hotkeys",
+    "HotplugSettings",
+    "LimiterSettings",
+    "LoudnessSettings",
+    "MidSideSettings",
+    "MixLevels",
+    "NoiseGateSettings",
+    "PluginSlot",
+    "StartupSettings",
+];
+
+/// The schema of [`Config`], in JSON Schema draft 2020-12.
+pub fn json_schema() -> Value {
+    let mut types = TypeCollection::default();
+    types.register::<Config>();
+    let root = types
+        .get(Config::sid())
+        .map_or("Config".into(), |ty| ty.name().clone());
+    let defs: Map<String, Value> = (&types)
+        .into_iter()
+        .map(|(_, ty)| {
+            let mut schema = data_type(&ty.inner);
+            describe(&mut schema, ty.docs());
+            (ty.name().to_string(), schema)
+        })
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "AudioRouter settings",
+        "$ref": format!("#/$defs/{root}"),
+        "$defs": defs,
+    })
+}
+
+/// Writes [`json_schema`] to `path`, e.g. for an editor to validate
+/// hand-edited exports against.
+pub fn write_json_schema(path: &Path) -> Result<()> {
+    let s = serde_json::to_string_pretty(&json_schema()).context("serializing config schema")?;
+    std::fs::write(path, s).with_context(|| format!("writing config schema: {}", path.display()))
+}
+
+fn data_type(ty: &DataType) -> Value {
+    match ty {
+        DataType::Any | DataType::Unknown | DataType::Generic(_) => json!({}),
+        DataType::Primitive(p) => primitive(p),
+        DataType::Literal(l) => literal(l),
+        DataType::List(list) => {
+            let mut schema = json!({ "type": "array", "items": data_type(list.ty()) });
+            if let Some(len) = list.length() {
+                schema["minItems"] = len.into();
+                schema["maxItems"] = len.into();
+            }
+            if list.unique() {
+                schema["uniqueItems"] = true.into();
+            }
+            schema
+        }
+        DataType::Map(map) => {
+            json!({ "type": "object", "additionalProperties": data_type(map.value_ty()) })
+        }
+        DataType::Nullable(inner) => json!({ "anyOf": [data_type(inner), { "type": "null" }] }),
+        DataType::Struct(s) => structure(s),
+        DataType::Enum(e) => enumeration(e),
+        DataType::Tuple(t) => tuple(t.elements()),
+        DataType::Reference(r) => json!({ "$ref": format!("#/$defs/{}", r.name()) }),
+    }
+}
+
+fn primitive(p: &PrimitiveType) -> Value {
+    use PrimitiveType::*;
+    match p {
+        i8 | i16 | i32 | i64 | i128 | isize => json!({ "type": "integer" }),
+        u8 | u16 | u32 | u64 | u128 | usize => json!({ "type": "integer", "minimum": 0 }),
+        f32 | f64 => json!({ "type": "number" }),
+        bool => json!({ "type": "boolean" }),
+        char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        String => json!({ "type": "string" }),
+    }
+}
+
+fn literal(l: &LiteralType) -> Value {
+    let value = match l {
+        LiteralType::i8(v) => json!(v),
+        LiteralType::i16(v) => json!(v),
+        LiteralType::i32(v) => json!(v),
+        LiteralType::u8(v) => json!(v),
+        LiteralType::u16(v) => json!(v),
+        LiteralType::u32(v) => json!(v),
+        LiteralType::f32(v) => json!(v),
+        LiteralType::f64(v) => json!(v),
+        LiteralType::bool(v) => json!(v),
+        LiteralType::String(v) => json!(v),
+        LiteralType::char(v) => json!(v),
+        _ => Value::Null,
+    };
+    json!({ "const": value })
+}
+
+fn tuple(elements: &[DataType]) -> Value {
+    match elements {
+        [] => json!({ "type": "null" }),
+        [single] => data_type(single),
+        _ => json!({
+            "type": "array",
+            "prefixItems": elements.iter().map(data_type).collect::<Vec<_>>(),
+            "items": false,
+        }),
+    }
+}
+
+fn structure(s: &StructType) -> Value {
+    let all_optional = DEFAULTED_STRUCTS.contains(&s.name().as_ref());
+    match s.fields() {
+        StructFields::Unit => json!({ "type": "null" }),
+        StructFields::Unnamed(fields) => {
+            let types: Vec<_> = fields
+                .fields()
+                .iter()
+                .filter_map(Field::ty)
+                .cloned()
+                .collect();
+            tuple(&types)
+        }
+        StructFields::Named(fields) => object(fields.fields(), all_optional),
+    }
+}
+
+fn object(fields: &[(std::borrow::Cow<'static, str>, Field)], all_optional: bool) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, field) in fields {
+        let Some(ty) = field.ty() else { continue };
+        let mut schema = data_type(ty);
+        describe(&mut schema, field.docs());
+        if !all_optional && !field.optional() && !matches!(ty, DataType::Nullable(_)) {
+            required.push(Value::from(name.as_ref()));
+        }
+        properties.insert(name.to_string(), schema);
+    }
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    schema
+}
+
+fn enumeration(e: &EnumType) -> Value {
+    let variants = e.variants().iter().filter(|(_, v)| !v.skip());
+    let mut names = Vec::new();
+    let mut options = Vec::new();
+    for (name, variant) in variants {
+        let inner = match variant.inner() {
+            EnumVariants::Unit => None,
+            EnumVariants::Named(fields) => Some(object(fields.fields(), false)),
+            EnumVariants::Unnamed(fields) => {
+                let types: Vec<_> = fields
+                    .fields()
+                    .iter()
+                    .filter_map(Field::ty)
+                    .cloned()
+                    .collect();
+                Some(tuple(&types))
+            }
+        };
+        match (e.repr(), inner) {
+            (EnumRepr::External, None) => names.push(Value::from(name.as_ref())),
+            (EnumRepr::External, Some(mut inner)) => {
+                describe(&mut inner, variant.docs());
+                options.push(json!({
+                    "type": "object",
+                    "properties": { name.as_ref(): inner },
+                    "required": [name.as_ref()],
+                    "additionalProperties": false,
+                }));
+            }
+            (EnumRepr::Untagged, inner) => {
+                options.push(inner.unwrap_or_else(|| json!({ "type": "null" })))
+            }
+            // 配置中没有内部或相邻标记的枚举，遇到时不做约束
+            _ => options.push(json!({})),
+        }
+    }
+    if !names.is_empty() {
+        options.insert(0, json!({ "enum": names }));
+    }
+    match options.len() {
+        1 => options.remove(0),
+        _ => json!({ "oneOf": options }),
+    }
+}
+
+fn describe(schema: &mut Value, docs: &str) {
+    // 文档注释每行带有前导空格
+    let docs: Vec<_> = docs.lines().map(str::trim).collect();
+    let docs = docs.join("\n");
+    if !docs.trim().is_empty() {
+        schema["description"] = docs.trim().into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The smallest value the schema accepts: only required keys, the
+    /// first option of every choice and one element in every list.
+    /// `required` collects the JSON pointers of the required keys.
+    fn minimal(schema: &Value, defs: &Value, at: &str, required: &mut Vec<String>) -> Value {
+        if let Some(name) = schema["$ref"].as_str() {
+            let name = name.trim_start_matches("#/$defs/");
+            return minimal(&defs[name], defs, at, required);
+        }
+        if let Some(first) = schema["oneOf"].get(0).or(schema["anyOf"].get(0)) {
+            return minimal(first, defs, at, required);
+        }
+        if let Some(first) = schema["enum"].get(0) {
+            return first.clone();
+        }
+        match schema["type"].as_str() {
+            Some("integer" | "number") => json!(0),
+            Some("boolean") => json!(false),
+            Some("string") => json!(""),
+            Some("array") => json!([minimal(
+                &schema["items"],
+                defs,
+                &format!("{at}/0"),
+                required
+            )]),
+            Some("object") => {
+                let mut object = Map::new();
+                for key in schema["required"].as_array().into_iter().flatten() {
+                    let key = key.as_str().unwrap();
+                    let at = format!("{at}/{key}");
+                    let value = minimal(&schema["properties"][key], defs, &at, required);
+                    required.push(at);
+                    object.insert(key.into(), value);
+                }
+                Value::Object(object)
+            }
+            _ => Value::Null,
+        }
+    }
+
+    #[test]
+    fn required_keys_are_exactly_those_without_a_default() {
+        let schema = json_schema();
+        for name in DEFAULTED_STRUCTS {
+            assert!(schema["$defs"][name].is_object(), "{name} not in schema");
+        }
+
+        let mut required = Vec::new();
+        let config = minimal(&schema, &schema["$defs"], "", &mut required);
+        assert!(!required.is_empty());
+        serde_json::from_value::<Config>(config.clone()).expect("minimal config deserializes");
+        for pointer in required {
+            let (parent, key) = pointer.rsplit_once('/').unwrap();
+            let mut without = config.clone();
+            without
+                .pointer_mut(parent)
+                .and_then(Value::as_object_mut)
+                .unwrap()
+                .remove(key);
+            assert!(
+                serde_json::from_value::<Config>(without).is_err(),
+                "{pointer} is marked required but has a default"
+            );
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Routing state of the last session, kept in `state.toml` next to the
+//! settings so the app can resume where it left off.
+//!
+//! Unlike the settings this is written by the app alone, whenever the user
+//! starts or stops routing. Quitting the app leaves it untouched, so after a
+//! crash or reboot it still says routing was active.
+//!
+//! It also records changes the app makes to the system while routing (a
+//! muted source, replaced default devices), so the next start can undo them
+//! if the app did not get to.
+
+use crate::config::ConfigManager;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::PathBuf;
+
+/// What the app was doing when it last changed routing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct RuntimeState {
+    pub routing_active: bool,
+    /// Profile active at the time
+    pub profile: Option<String>,
+    /// Source device the app muted and has not unmuted yet
+    pub muted_source: Option<String>,
+    /// System defaults the app replaced and has not restored yet
+    pub replaced_defaults: Vec<ReplacedDefault>,
+}
+
+/// Role a system default output device is chosen for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum DefaultRole {
+    Console,
+    Multimedia,
+    Communications,
+}
+
+/// A system default output device replaced while routing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct ReplacedDefault {
+    pub role: DefaultRole,
+    /// The device that was the default before
+    pub device_id: String,
+}
+
+impl ConfigManager {
+    fn state_path(&self) -> PathBuf {
+        self.path().with_file_name("state.toml")
+    }
+
+    /// The recorded state, or `None` if nothing was recorded yet or the file
+    /// cannot be read.
+    pub fn runtime_state(&self) -> Option<RuntimeState> {
+        let s = fs::read_to_string(self.state_path()).ok()?;
+        toml::from_str(&s).ok()
+    }
+
+    pub fn save_runtime_state(&self, state: &RuntimeState) -> Result<()> {
+        let path = self.state_path();
+        let tmp = path.with_extension("toml.tmp");
+        let s = toml::to_string_pretty(state).context("serializing runtime state")?;
+        fs::write(&tmp, s).with_context(|| format!("writing runtime state: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("renaming runtime state to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn state_roundtrips_beside_the_settings() {
+        let td = tempdir().expect("tempdir");
+        let mgr = ConfigManager::load(Some(td.path().to_path_buf())).expect("load");
+        assert_eq!(mgr.runtime_state(), None);
+
+        let state = RuntimeState {
+            routing_active: true,
+            profile: Some("Desk".into()),
+            muted_source: Some("speakers".into()),
+            replaced_defaults: vec![ReplacedDefault {
+                role: DefaultRole::Communications,
+                device_id: "headset".into(),
+            }],
+        };
+        mgr.save_runtime_state(&state).unwrap();
+        assert_eq!(mgr.runtime_state(), Some(state));
+        assert!(!mgr.reload_if_changed().unwrap());
+
+        fs::write(td.path().join("state.toml"), "routing_active = 1").unwrap();
+        assert_eq!(mgr.runtime_state(), None);
+    }
+}
@@ -4,10 +4,11 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use app_core::controller::AppController;
+use audio_core::dsp::EqPreset;
 use audio_core::router::ChannelMode;
 use windows_reactor::*;
 
-use crate::tray::TrayCommand;
+use crate::tray::{TrayCommand, TrayDevices};
 use crate::window_utils;
 
 /// 更新状态机，用于设置页面的 UI 展示
@@ -158,11 +159,11 @@ impl Component for RootComponent {
         });
 
         // 启动时后台静默检查更新（受配置控制）
-        let auto_update_enabled = {
+        let (auto_update_enabled, update_channel) = {
             let c = self.controller.lock().unwrap();
             let cfg = c.config_manager.handle();
             let enabled = cfg.read().general.auto_update_check;
-            enabled
+            (enabled, c.update_channel())
         };
         let update_state_clone = Arc::clone(&self.update_state);
         cx.use_effect(auto_update_enabled, move || {
@@ -173,7 +174,7 @@ impl Component for RootComponent {
             std::thread::spawn(move || {
                 // 延迟 2 秒再检查，避免影响启动速度
                 std::thread::sleep(std::time::Duration::from_secs(2));
-                let result = crate::update::check_for_updates();
+                let result = crate::update::check_for_updates(update_channel);
                 let new_state = match result {
                     crate::update::UpdateCheckResult::UpToDate => {
                         log::info!("Update check: already up to date (v{})", crate::update::current_version());
@@ -212,6 +213,24 @@ impl Component for RootComponent {
                     let mut c = controller.lock().unwrap();
                     c.refresh_devices();
                     c.poll_router_events();
+
+                    // 热键在配置中修改后重新注册，按下的热键在这里执行
+                    crate::hotkeys::update_hotkeys(c.hotkey_bindings());
+                    while let Some(action) = crate::hotkeys::try_recv_hotkey() {
+                        if let Err(e) = c.run_hotkey(&action) {
+                            log::warn!("Hotkey {action:?} failed: {e:#}");
+                        }
+                    }
+                    while let Some(command) = crate::remote::try_recv_command() {
+                        if let Err(e) = c.run_cli_command(&command) {
+                            log::warn!("Command {command:?} failed: {e:#}");
+                        }
+                    }
+                    crate::tray::update_tray_routing(&c.i18n, c.is_running, c.can_start_routing());
+                    let devices = TrayDevices::from_controller(&c);
+                    crate::tray::update_tray_tooltip(&c.i18n, &devices, c.is_running);
+                    crate::tray::update_tray_devices(&c.i18n, devices);
+                    crate::toast::show_notifications(c.take_notifications());
                 }
 
                 // 托盘图标左键点击与托盘菜单项点击复用同一个命令处理逻辑。
@@ -219,7 +238,27 @@ impl Component for RootComponent {
                 let handle_command = |cmd: TrayCommand| match cmd {
                     TrayCommand::ToggleWindow => window_utils::toggle_window(),
                     TrayCommand::ShowWindow => window_utils::show_and_focus_window(),
-                    TrayCommand::Quit => std::process::exit(0),
+                    TrayCommand::ToggleRouting => {
+                        let mut c = controller.lock().unwrap();
+                        if c.is_running {
+                            c.stop_routing();
+                        } else {
+                            c.start_routing();
+                        }
+                    }
+                    TrayCommand::ToggleOutput(device_id) => {
+                        let mut c = controller.lock().unwrap();
+                        let enabled = {
+                            let handle = c.config_manager.handle();
+                            let cfg = handle.read();
+                            cfg.outputs.iter().any(|o| o.device_id == device_id && o.enabled)
+                        };
+                        c.set_output_enabled(&device_id, !enabled);
+                    }
+                    TrayCommand::Quit => {
+                        controller.lock().unwrap().shutdown();
+                        std::process::exit(0)
+                    }
                 };
                 while let Some(cmd) = crate::tray::try_recv_tray_event() {
                     handle_command(cmd);
@@ -385,6 +424,9 @@ fn home_page(
         i18n.t("channelModes.Swap").to_string(),
         i18n.t("channelModes.LeftOnly").to_string(),
         i18n.t("channelModes.RightOnly").to_string(),
+        i18n.t("channelModes.Downmix").to_string(),
+        i18n.t("channelModes.Upmix").to_string(),
+        i18n.t("channelModes.Matrix").to_string(),
     ];
 
     // 每个声道模式的处理逻辑说明,作为 ComboBox 的悬浮提示。
@@ -397,6 +439,16 @@ fn home_page(
         i18n.t("channelModeDesc.Swap").to_string(),
         i18n.t("channelModeDesc.LeftOnly").to_string(),
         i18n.t("channelModeDesc.RightOnly").to_string(),
+        i18n.t("channelModeDesc.Downmix").to_string(),
+        i18n.t("channelModeDesc.Upmix").to_string(),
+        i18n.t("channelModeDesc.Matrix").to_string(),
+    ];
+
+    // 图形均衡预设,顺序与 EqPreset 的声明顺序一致。
+    let eq_preset_items: Vec<String> = vec![
+        i18n.t("eqPresets.Flat").to_string(),
+        i18n.t("eqPresets.BassBoost").to_string(),
+        i18n.t("eqPresets.Speech").to_string(),
     ];
 
     // 源设备下拉列表
@@ -428,7 +480,7 @@ fn home_page(
         .map(|device| {
             let device_id = device.id.clone();
 
-            let (enabled, selected_mode_index) = {
+            let (enabled, selected_mode_index, selected_eq_index) = {
                 let c = controller.lock().unwrap();
                 let handle = c.config_manager.handle();
                 let cfg = handle.read();
@@ -439,7 +491,8 @@ fn home_page(
                     .map(|s| ChannelMode::from_config(Some(s)))
                     .unwrap_or(ChannelMode::Stereo);
                 let index = mode as i32;
-                (enabled, index)
+                let eq_index = output.map(|o| o.eq_preset as i32).unwrap_or(0);
+                (enabled, index, eq_index)
             };
 
             // 当前选中模式对应的处理逻辑说明,用作 ComboBox 悬浮提示。
@@ -480,7 +533,10 @@ fn home_page(
                                     3 => ChannelMode::Mono,
                                     4 => ChannelMode::Swap,
                                     5 => ChannelMode::LeftOnly,
-                                    _ => ChannelMode::RightOnly,
+                                    6 => ChannelMode::RightOnly,
+                                    7 => ChannelMode::Downmix,
+                                    8 => ChannelMode::Upmix,
+                                    _ => ChannelMode::Matrix,
                                 };
                                 let mut c = controller_clone.lock().unwrap();
                                 c.set_output_channel_mode(&device_id, mode);
@@ -489,8 +545,32 @@ fn home_page(
                     })
                     .tooltip(selected_desc)
                     .grid_column(2),
+                    Element::from({
+                        let controller_clone = Arc::clone(&controller);
+                        let refresh = make_setter.clone();
+                        let device_id = device_id.clone();
+                        ComboBox::new(eq_preset_items.clone())
+                            .selected_index(selected_eq_index)
+                            .on_selection_changed(move |index| {
+                                let preset = match index {
+                                    1 => EqPreset::BassBoost,
+                                    2 => EqPreset::Speech,
+                                    _ => EqPreset::Flat,
+                                };
+                                let mut c = controller_clone.lock().unwrap();
+                                c.set_output_eq_preset(&device_id, preset);
+                                refresh();
+                            })
+                    })
+                    .tooltip(i18n.t("EqPreset").to_string())
+                    .grid_column(3),
                 ))
-                .columns([GridLength::Auto, GridLength::STAR, GridLength::Auto])
+                .columns([
+                    GridLength::Auto,
+                    GridLength::STAR,
+                    GridLength::Auto,
+                    GridLength::Auto,
+                ])
                 .column_spacing(12.0),
             )
         })
@@ -559,7 +639,7 @@ fn settings_page(
     set_theme_choice: SetState<ThemeChoice>,
     update_state: Arc<Mutex<UpdateState>>,
 ) -> Element {
-    let (start_with_windows, start_minimized, auto_route, close_to_tray, auto_update_check, lang_index, theme_index, backdrop_index) = {
+    let (start_with_windows, start_minimized, auto_route, resume_last_state, close_to_tray, notifications, auto_update_check, lang_index, theme_index, backdrop_index, channel_index) = {
         let c = controller.lock().unwrap();
         let draft = &c.draft_general;
         let lang_idx = match draft.language.as_str() {
@@ -576,15 +656,22 @@ fn settings_page(
             config::config::Backdrop::MicaAlt => 1,
             config::config::Backdrop::Acrylic => 2,
         };
+        let channel_idx = match draft.update_channel {
+            config::config::UpdateChannel::Stable => 0,
+            config::config::UpdateChannel::Beta => 1,
+        };
         (
             draft.start_with_windows,
-            draft.minimized,
-            draft.auto_route,
+            c.draft_startup.minimized,
+            c.draft_startup.auto_route,
+            draft.resume_last_state,
             draft.close_to_tray,
+            draft.notifications,
             draft.auto_update_check,
             lang_idx,
             theme_idx,
             backdrop_idx,
+            channel_idx,
         )
     };
 
@@ -599,6 +686,19 @@ fn settings_page(
         i18n.t("BackdropMicaAlt").to_string(),
         i18n.t("BackdropAcrylic").to_string(),
     ];
+    let channel_items = vec![
+        i18n.t("UpdateChannelStable").to_string(),
+        i18n.t("UpdateChannelBeta").to_string(),
+    ];
+
+    // 设置文件和 logs 文件夹在同一目录下
+    let folder_ctrl = controller.clone();
+    let open_folder_btn = button(i18n.t("OpenConfigFolder")).on_click(move || {
+        let c = folder_ctrl.lock().unwrap();
+        if let Err(e) = c.open_config_folder() {
+            log::warn!("Open config folder failed: {e}");
+        }
+    });
 
     let back_ctrl = controller.clone();
     let back_nav = set_nav_selected.clone();
@@ -647,7 +747,7 @@ fn settings_page(
                                     let controller_clone = Arc::clone(&controller);
                                     move |checked| {
                                         let mut c = controller_clone.lock().unwrap();
-                                        c.draft_general.minimized = checked;
+                                        c.draft_startup.minimized = checked;
                                     }
                                 }),
                         ),
@@ -658,7 +758,18 @@ fn settings_page(
                                     let controller_clone = Arc::clone(&controller);
                                     move |checked| {
                                         let mut c = controller_clone.lock().unwrap();
-                                        c.draft_general.auto_route = checked;
+                                        c.draft_startup.auto_route = checked;
+                                    }
+                                }),
+                        ),
+                        Element::from(
+                            check_box(resume_last_state)
+                                .content(i18n.t("ResumeLastState"))
+                                .on_checked({
+                                    let controller_clone = Arc::clone(&controller);
+                                    move |checked| {
+                                        let mut c = controller_clone.lock().unwrap();
+                                        c.draft_general.resume_last_state = checked;
                                     }
                                 }),
                         ),
@@ -673,6 +784,17 @@ fn settings_page(
                                     }
                                 }),
                         ),
+                        Element::from(
+                            check_box(notifications)
+                                .content(i18n.t("ShowNotifications"))
+                                .on_checked({
+                                    let controller_clone = Arc::clone(&controller);
+                                    move |checked| {
+                                        let mut c = controller_clone.lock().unwrap();
+                                        c.draft_general.notifications = checked;
+                                    }
+                                }),
+                        ),
                         Element::from(
                             check_box(auto_update_check)
                                 .content(i18n.t("AutoUpdateCheck"))
@@ -753,6 +875,26 @@ fn settings_page(
                             ))
                             .spacing(8.0),
                         ),
+                        Element::from(
+                            hstack((
+                                Element::from(text_block(i18n.t("UpdateChannel"))),
+                                Element::from(
+                                    ComboBox::new(channel_items)
+                                        .selected_index(channel_index)
+                                        .on_selection_changed({
+                                            let controller_clone = Arc::clone(&controller);
+                                            move |index| {
+                                                let mut c = controller_clone.lock().unwrap();
+                                                c.draft_general.update_channel = match index {
+                                                    1 => config::config::UpdateChannel::Beta,
+                                                    _ => config::config::UpdateChannel::Stable,
+                                                };
+                                            }
+                                        }),
+                                ),
+                            ))
+                            .spacing(8.0),
+                        ),
                     ))
                     .spacing(14.0),
                 )
@@ -762,8 +904,10 @@ fn settings_page(
             ),
             Element::from(build_update_section(
                 Arc::clone(&update_state),
+                Arc::clone(&controller),
                 i18n.clone(),
             )),
+            Element::from(open_folder_btn),
             Element::from(
                 hstack((Element::from(cancel_btn), Element::from(save_btn)))
                     .spacing(8.0)
@@ -781,14 +925,22 @@ fn settings_page(
 /// 触发重渲染。这样避免了把 Rc<SetState> 传到后台线程的问题。
 fn build_update_section(
     update_state: Arc<Mutex<UpdateState>>,
+    controller: Arc<Mutex<AppController>>,
     i18n: app_core::i18n::I18n,
 ) -> Element {
     let state = update_state.lock().unwrap().clone();
     let current_ver = crate::update::current_version();
+    let (channel, info) = {
+        let c = controller.lock().unwrap();
+        (c.update_channel(), c.app_info(current_ver))
+    };
 
     let header = hstack((
         Element::from(text_block(i18n.t("CheckForUpdates")).bold()),
-        Element::from(text_block(format!("v{current_ver}")).font_size(12.0)),
+        Element::from(
+            text_block(format!("v{current_ver} ({}, {})", info.git_hash, info.build_date))
+                .font_size(12.0),
+        ),
     ))
     .spacing(8.0);
 
@@ -799,7 +951,7 @@ fn build_update_section(
                 *state_clone.lock().unwrap() = UpdateState::Checking;
                 let sc = Arc::clone(&state_clone);
                 std::thread::spawn(move || {
-                    let result = crate::update::check_for_updates();
+                    let result = crate::update::check_for_updates(channel);
                     let new_state = match result {
                         crate::update::UpdateCheckResult::UpToDate => UpdateState::UpToDate,
                         crate::update::UpdateCheckResult::NewVersion {
@@ -833,7 +985,7 @@ fn build_update_section(
                 *state_clone.lock().unwrap() = UpdateState::Checking;
                 let sc = Arc::clone(&state_clone);
                 std::thread::spawn(move || {
-                    let result = crate::update::check_for_updates();
+                    let result = crate::update::check_for_updates(channel);
                     let new_state = match result {
                         crate::update::UpdateCheckResult::UpToDate => UpdateState::UpToDate,
                         crate::update::UpdateCheckResult::NewVersion {
@@ -944,6 +1096,7 @@ fn build_update_section(
             let install_btn = button(i18n.t("InstallAndRestart"))
                 .accent()
                 .on_click(move || {
+                    controller.lock().unwrap().shutdown();
                     crate::update::launch_installer_and_quit(&path);
                 });
             Element::from(vstack((
@@ -959,7 +1112,7 @@ fn build_update_section(
                 *state_clone.lock().unwrap() = UpdateState::Checking;
                 let sc = Arc::clone(&state_clone);
                 std::thread::spawn(move || {
-                    let result = crate::update::check_for_updates();
+                    let result = crate::update::check_for_updates(channel);
                     let new_state = match result {
                         crate::update::UpdateCheckResult::UpToDate => UpdateState::UpToDate,
                         crate::update::UpdateCheckResult::NewVersion {
@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use config::hotkeys::{HotkeyAction, HotkeyBinding};
+use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, UnregisterHotKey, MOD_NOREPEAT,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    GetMessageW, PeekMessageW, PostThreadMessageW, MSG, PM_NOREMOVE, WM_APP, WM_HOTKEY,
+};
+
+/// 通知热键线程按最新绑定重新注册。
+const WM_REREGISTER: u32 = WM_APP + 1;
+
+/// 热键运行时状态，保存在 UI 线程的 thread_local 中。
+///
+/// RegisterHotKey 不传窗口句柄时，WM_HOTKEY 投递到注册线程的消息队列；
+/// WinUI 的消息循环不归我们管，因此在专用线程上注册并转发动作。
+struct HotkeyState {
+    thread_id: u32,
+    bindings: Arc<Mutex<Vec<HotkeyBinding>>>,
+    actions: Receiver<HotkeyAction>,
+}
+
+thread_local! {
+    static HOTKEY_STATE: RefCell<Option<HotkeyState>> = const { RefCell::new(None) };
+}
+
+/// 启动热键线程并注册 `bindings`。
+///
+/// 组合键被其他程序占用时只记录警告，其余热键照常生效。
+pub fn init_hotkeys(bindings: Vec<HotkeyBinding>) -> anyhow::Result<()> {
+    let bindings = Arc::new(Mutex::new(bindings));
+    let (action_tx, actions) = mpsc::channel();
+    let (id_tx, id_rx) = mpsc::channel();
+    {
+        let bindings = Arc::clone(&bindings);
+        std::thread::Builder::new()
+            .name("hotkeys".into())
+            .spawn(move || run(bindings, action_tx, id_tx))?;
+    }
+    let thread_id = id_rx.recv()?;
+    HOTKEY_STATE.with(|s| {
+        *s.borrow_mut() = Some(HotkeyState {
+            thread_id,
+            bindings,
+            actions,
+        });
+    });
+    Ok(())
+}
+
+/// 配置中的热键变化后重新注册；与当前绑定相同时不做任何事。
+pub fn update_hotkeys(bindings: Vec<HotkeyBinding>) {
+    HOTKEY_STATE.with(|s| {
+        let state = s.borrow();
+        let Some(state) = state.as_ref() else {
+            return;
+        };
+        {
+            let mut current = state.bindings.lock().unwrap();
+            if *current == bindings {
+                return;
+            }
+            *current = bindings;
+        }
+        if unsafe { PostThreadMessageW(state.thread_id, WM_REREGISTER, 0, 0) } == 0 {
+            log::warn!("Failed to notify the hotkey thread of changed bindings");
+        }
+    });
+}
+
+/// 尝试接收一个被按下的热键对应的动作。
+pub fn try_recv_hotkey() -> Option<HotkeyAction> {
+    HOTKEY_STATE.with(|s| s.borrow().as_ref()?.actions.try_recv().ok())
+}
+
+fn run(
+    bindings: Arc<Mutex<Vec<HotkeyBinding>>>,
+    actions: Sender<HotkeyAction>,
+    id_tx: Sender<u32>,
+) {
+    let mut msg: MSG = unsafe { std::mem::zeroed() };
+    // 线程首次取消息时才创建消息队列，之前 PostThreadMessageW 会失败
+    unsafe { PeekMessageW(&mut msg, std::ptr::null_mut(), WM_APP, WM_APP, PM_NOREMOVE) };
+    let _ = id_tx.send(unsafe { GetCurrentThreadId() });
+
+    let mut registered = register(&bindings.lock().unwrap());
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        match msg.message {
+            WM_HOTKEY => {
+                if let Some(action) = registered.get(msg.wParam).cloned().flatten() {
+                    if actions.send(action).is_err() {
+                        break;
+                    }
+                }
+            }
+            WM_REREGISTER => {
+                unregister(registered.len());
+                registered = register(&bindings.lock().unwrap());
+            }
+            _ => {}
+        }
+    }
+    unregister(registered.len());
+}
+
+/// 按顺序注册热键，热键 id 即下标；返回每个 id 对应的动作，注册失败的为 None。
+fn register(bindings: &[HotkeyBinding]) -> Vec<Option<HotkeyAction>> {
+    bindings
+        .iter()
+        .enumerate()
+        .map(|(id, binding)| {
+            let chord = binding.chord;
+            let ok = unsafe {
+                RegisterHotKey(
+                    std::ptr::null_mut(),
+                    id as i32,
+                    chord.modifiers.flags() | MOD_NOREPEAT,
+                    chord.key.virtual_key(),
+                )
+            } != 0;
+            if !ok {
+                log::warn!("Failed to register hotkey {chord} for {:?}; it may be taken by another program", binding.action);
+            }
+            ok.then(|| binding.action.clone())
+        })
+        .collect()
+}
+
+fn unregister(count: usize) {
+    for id in 0..count {
+        unsafe { UnregisterHotKey(std::ptr::null_mut(), id as i32) };
+    }
+}
@@ -1,27 +1,28 @@
 // 在 release 模式下隐藏 Windows 控制台窗口；debug 模式保留便于查看日志。
 // 隐藏控制台后，env_logger 默认输出到 stderr 不会显示，因此日志改为写文件
-// （位于 LOCALAPPDATA\AudioRouter\logs\winui3_gui.log）。
+// （位于配置目录下的 logs\winui3_gui.log，默认为 LOCALAPPDATA\AudioRouter）。
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::sync::{Arc, Mutex};
 
+use app_core::cli::CliCommand;
 use app_core::controller::AppController;
 use audio_core::router::Router;
-use config::ConfigManager;
+use config::{ConfigDir, ConfigManager, ConfigOverrides};
 use windows_reactor::*;
 
 mod app;
+mod hotkeys;
 mod pane_bg_override;
+mod remote;
+mod toast;
 mod tray;
 mod update;
 mod window_utils;
 
-fn app_config_dir() -> std::path::PathBuf {
-    std::env::var_os("LOCALAPPDATA")
-        .map(std::path::PathBuf::from)
-        .or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from(".")))
-        .join("AudioRouter")
+/// 配置目录：命令行参数 > 环境变量 > 便携模式标记文件 > LOCALAPPDATA，见 [`ConfigDir`]。
+fn app_config_dir() -> ConfigDir {
+    ConfigDir::resolve(std::env::args_os().skip(1))
 }
 
 /// 解析项目资源文件路径，兼容多种运行场景：
@@ -76,7 +77,8 @@ pub fn resolve_asset_path(rel_path: &str) -> std::path::PathBuf {
 // === 单例检测 ===
 //
 // 使用 Win32 命名互斥量实现单例：首个实例创建互斥量并保活到进程退出，
-// 第二个实例启动时发现同名互斥量已存在，则激活已有窗口后退出。
+// 第二个实例启动时发现同名互斥量已存在，则把命令行命令转发给已有实例，
+// 没有命令时激活已有窗口，然后退出。
 // 互斥量名使用 "Local\" 前缀，限制在当前用户会话内（多用户/多会话场景各自独立）。
 
 /// 互斥量名称。前缀 `Local\` 限制在当前会话内。
@@ -88,7 +90,7 @@ const SINGLE_INSTANCE_WINDOW_TITLE: &str = "AudioRouter";
 ///
 /// 返回 `Some(handle)` 表示这是首个实例，`handle` 是互斥量句柄，
 /// 必须保活到进程退出（进程退出时 OS 自动释放，从而允许下一个实例启动）。
-/// 返回 `None` 表示已有实例在运行，调用方应退出。
+/// 返回 `None` 表示已有实例在运行，调用方应转发命令或激活已有窗口后退出。
 fn acquire_single_instance() -> Option<*mut core::ffi::c_void> {
     use std::os::windows::ffi::OsStrExt;
     let wide: Vec<u16> = std::ffi::OsStr::new(SINGLE_INSTANCE_MUTEX_NAME)
@@ -108,9 +110,8 @@ fn acquire_single_instance() -> Option<*mut core::ffi::c_void> {
     const ERROR_ALREADY_EXISTS: u32 = 183;
     let last_error = unsafe { windows_sys::Win32::Foundation::GetLastError() };
     if last_error == ERROR_ALREADY_EXISTS {
-        // 已有实例在运行，关闭本进程的句柄并尝试激活已有窗口
+        // 已有实例在运行，关闭本进程的句柄
         unsafe { windows_sys::Win32::Foundation::CloseHandle(handle) };
-        activate_existing_window();
         return None;
     }
 
@@ -154,6 +155,24 @@ fn activate_existing_window() {
     }
 }
 
+/// `--list-devices`：把输出设备列表打印到启动本进程的控制台。
+///
+/// release 模式没有自己的控制台，需先附加到父进程（命令提示符、PowerShell）的控制台。
+fn print_device_list() {
+    unsafe {
+        windows_sys::Win32::System::Console::AttachConsole(
+            windows_sys::Win32::System::Console::ATTACH_PARENT_PROCESS,
+        );
+    }
+    match audio_core::com_service::device::get_all_output_devices() {
+        Ok(devices) => print!("{}", app_core::cli::format_device_list(&devices)),
+        Err(e) => {
+            eprintln!("Failed to list devices: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
 /// 初始化日志：release 模式下写入文件，debug 模式下输出到 stderr。
 ///
 /// 日志文件位于 `LOCALAPPDATA\AudioRouter\logs\winui3_gui.log`，
@@ -170,7 +189,7 @@ fn init_logger() {
     // debug 模式下保留 stderr，便于开发时直接查看。
     #[cfg(not(debug_assertions))]
     {
-        let log_dir = app_config_dir().join("logs");
+        let log_dir = app_config_dir().path.join("logs");
         let _ = std::fs::create_dir_all(&log_dir);
         let log_path = log_dir.join("winui3_gui.log");
         match std::fs::OpenOptions::new()
@@ -198,24 +217,80 @@ fn init_logger() {
 fn main() -> windows_reactor::Result<()> {
     init_logger();
 
-    // 单例检测：如果已有实例在运行，激活已有窗口后退出。
+    // 命令行控制命令（--profile、--start-routing、--stop、--restart-routing、
+    // --list-devices），供脚本和任务计划程序使用；参数有误时直接退出，让脚本能发现错误。
+    let mut commands = match CliCommand::parse(std::env::args_os().skip(1)) {
+        Ok(commands) => commands,
+        Err(e) => {
+            log::error!("Invalid command line: {e:#}");
+            std::process::exit(2);
+        }
+    };
+    // 列出设备不需要运行中的实例，在本进程中完成；没有其他命令时不启动界面
+    if commands.contains(&CliCommand::ListDevices) {
+        print_device_list();
+        commands.retain(|c| *c != CliCommand::ListDevices);
+        if commands.is_empty() {
+            return Ok(());
+        }
+    }
+
+    // 单例检测：如果已有实例在运行，把命令转发给它，没有命令时激活已有窗口，然后退出。
     // _mutex_handle 必须保活到 main 结束，进程退出时 OS 自动释放互斥量。
     let _mutex_handle = match acquire_single_instance() {
         Some(handle) => handle,
-        None => {
+        None if commands.is_empty() => {
             log::info!("Another instance is already running. Activating it and exiting.");
+            activate_existing_window();
+            return Ok(());
+        }
+        None => {
+            if remote::forward_commands(&commands) {
+                log::info!("Forwarded {commands:?} to the running instance.");
+            } else {
+                log::error!("The running instance did not accept {commands:?}.");
+                std::process::exit(1);
+            }
             return Ok(());
         }
     };
 
-    let app_local_data_dir = app_config_dir();
-    let config_manager = ConfigManager::load(Some(app_local_data_dir)).expect("load config");
+    let config_dir = app_config_dir();
+    log::info!(
+        "Config directory: {} ({:?}{})",
+        config_dir.path.display(),
+        config_dir.source,
+        if config_dir.portable { ", portable" } else { "" }
+    );
+    // 环境变量和命令行中的覆盖项只在本次运行生效；无效时忽略，按文件启动
+    let overrides = ConfigOverrides::from_process().unwrap_or_else(|e| {
+        log::error!("Ignoring config overrides: {e:#}");
+        ConfigOverrides::default()
+    });
+    for o in overrides.iter() {
+        log::info!("Config override from {}: {} = {}", o.origin, o.path, o.value);
+    }
+    let mut config_manager =
+        match ConfigManager::load_with_overrides(Some(config_dir.path.clone()), overrides) {
+            Ok(manager) => manager,
+            Err(e) => {
+                log::error!("Config overrides rejected, starting without them: {e:#}");
+                ConfigManager::load(Some(config_dir.path)).expect("load config")
+            }
+        };
+    // 拖动增益、EQ 等滑块时合并写入，由界面定时器在停止修改后保存
+    config_manager.set_write_delay(Some(std::time::Duration::from_millis(500)));
     let router = Router::new();
     let controller = Arc::new(Mutex::new(AppController::new(config_manager, router)));
 
     {
         let mut c = controller.lock().unwrap();
         c.init();
+        for command in &commands {
+            if let Err(e) = c.run_cli_command(command) {
+                log::error!("Command {command:?} failed: {e:#}");
+            }
+        }
     }
 
     // 初始化系统代理监听：读取当前代理并启动后台线程监听注册表变化，
@@ -235,6 +310,21 @@ fn main() -> windows_reactor::Result<()> {
         }
     }
 
+    {
+        let bindings = controller.lock().unwrap().hotkey_bindings();
+        if let Err(e) = hotkeys::init_hotkeys(bindings) {
+            log::warn!("Failed to initialize global hotkeys: {e}");
+        }
+    }
+
+    if let Err(e) = remote::init_command_listener() {
+        log::warn!("Failed to listen for commands from other instances: {e}");
+    }
+
+    if let Err(e) = toast::init_notifications() {
+        log::warn!("Failed to start the notification thread: {e}");
+    }
+
     // 从配置读取初始 backdrop，在窗口创建时直接应用。
     // 必须通过 App::backdrop 在窗口创建阶段设置，而非在组件 use_effect 中
     // 事后调用 set_backdrop——后者依赖的 ROOT_WINDOW 在 UI 首次挂载后才设置，
@@ -268,5 +358,9 @@ fn main() -> windows_reactor::Result<()> {
         log::warn!("Window icon not found: {}", icon_path.display());
     }
 
-    app.run(move || app::RootComponent::new(Arc::clone(&controller)))
+    // 窗口关闭后同样停止路由并撤销对系统的改动
+    let exit_controller = Arc::clone(&controller);
+    let result = app.run(move || app::RootComponent::new(Arc::clone(&controller)));
+    exit_controller.lock().unwrap().shutdown();
+    result
 }
@@ -0,0 +1,166 @@
+//! 把命令行控制命令（`--profile`、`--start-routing` 等）转发给已运行的实例。
+//!
+//! 首个实例在专用线程上创建一个只接收消息的窗口（HWND_MESSAGE），后启动的
+//! 实例找到该窗口后用 WM_COPYDATA 发送命令参数；收到的命令经通道交给界面
+//! 定时器执行，与托盘和热键命令相同。
+
+use std::cell::RefCell;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+
+use app_core::cli::CliCommand;
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, FindWindowExW, GetMessageW, RegisterClassW,
+    SendMessageTimeoutW, HWND_MESSAGE, MSG, SMTO_ABORTIFHUNG, WM_COPYDATA, WNDCLASSW,
+};
+
+/// 接收命令的窗口类名。
+const WINDOW_CLASS: &str = "AudioRouter-Commands";
+/// WM_COPYDATA 的 dwData，用于识别本应用发送的数据。
+const COPYDATA_COMMANDS: usize = 0x4152_434D;
+/// 已有实例无响应时放弃转发的等待时间（毫秒）。
+const SEND_TIMEOUT_MS: u32 = 5000;
+
+/// 窗口过程在命令线程上运行，通过全局发送端把命令交给 UI 线程。
+static COMMAND_SENDER: OnceLock<Mutex<Sender<CliCommand>>> = OnceLock::new();
+
+thread_local! {
+    static COMMAND_RECEIVER: RefCell<Option<Receiver<CliCommand>>> = const { RefCell::new(None) };
+}
+
+/// 启动接收转发命令的线程。
+pub fn init_command_listener() -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    if COMMAND_SENDER.set(Mutex::new(tx)).is_err() {
+        anyhow::bail!("command listener is already running");
+    }
+    std::thread::Builder::new()
+        .name("cli-commands".into())
+        .spawn(run_listener)?;
+    COMMAND_RECEIVER.with(|r| *r.borrow_mut() = Some(rx));
+    Ok(())
+}
+
+/// 尝试接收一个转发来的命令。
+pub fn try_recv_command() -> Option<CliCommand> {
+    COMMAND_RECEIVER.with(|r| r.borrow().as_ref()?.try_recv().ok())
+}
+
+/// 把 `commands` 发给已运行的实例。找不到其接收窗口或对方无响应时返回 false。
+pub fn forward_commands(commands: &[CliCommand]) -> bool {
+    let class = wide(WINDOW_CLASS);
+    let hwnd = unsafe {
+        FindWindowExW(
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            class.as_ptr(),
+            std::ptr::null(),
+        )
+    };
+    if hwnd.is_null() {
+        return false;
+    }
+    // 参数以 NUL 分隔，接收端按命令行参数重新解析
+    let data = commands
+        .iter()
+        .flat_map(CliCommand::to_args)
+        .collect::<Vec<_>>()
+        .join("\0");
+    let copy = COPYDATASTRUCT {
+        dwData: COPYDATA_COMMANDS,
+        cbData: data.len() as u32,
+        lpData: data.as_ptr() as *mut _,
+    };
+    let mut result = 0;
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            hwnd,
+            WM_COPYDATA,
+            0,
+            &copy as *const COPYDATASTRUCT as LPARAM,
+            SMTO_ABORTIFHUNG,
+            SEND_TIMEOUT_MS,
+            &mut result,
+        )
+    };
+    sent != 0 && result != 0
+}
+
+fn run_listener() {
+    let class = wide(WINDOW_CLASS);
+    let hwnd = unsafe {
+        let instance = GetModuleHandleW(std::ptr::null());
+        let mut wc: WNDCLASSW = std::mem::zeroed();
+        wc.lpfnWndProc = Some(window_proc);
+        wc.hInstance = instance;
+        wc.lpszClassName = class.as_ptr();
+        if RegisterClassW(&wc) == 0 {
+            log::warn!("Failed to register command window class");
+            return;
+        }
+        CreateWindowExW(
+            0,
+            class.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null(),
+        )
+    };
+    if hwnd.is_null() {
+        log::warn!("Failed to create command window, commands from later instances are ignored");
+        return;
+    }
+
+    let mut msg: MSG = unsafe { std::mem::zeroed() };
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        unsafe { DispatchMessageW(&msg) };
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_COPYDATA {
+        return unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) };
+    }
+    let copy = unsafe { &*(lparam as *const COPYDATASTRUCT) };
+    if copy.dwData != COPYDATA_COMMANDS || copy.lpData.is_null() {
+        return 0;
+    }
+    let bytes =
+        unsafe { std::slice::from_raw_parts(copy.lpData as *const u8, copy.cbData as usize) };
+    let args = String::from_utf8_lossy(bytes);
+    let commands = match CliCommand::parse(args.split('\0').map(Into::into)) {
+        Ok(commands) => commands,
+        Err(e) => {
+            log::warn!("Ignoring forwarded commands: {e:#}");
+            return 0;
+        }
+    };
+    let Some(sender) = COMMAND_SENDER.get() else {
+        return 0;
+    };
+    let sender = sender.lock().unwrap();
+    for command in commands {
+        log::info!("Command from another instance: {command:?}");
+        let _ = sender.send(command);
+    }
+    1
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
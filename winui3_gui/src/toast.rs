@@ -0,0 +1,193 @@
+//! 用通知区域气泡显示桌面通知；Windows 10 起系统把气泡显示为 toast，
+//! 并收进通知中心。
+//!
+//! 气泡必须挂在一个通知区域图标上，而 tray-icon 不提供气泡接口，因此在专用
+//! 线程上为每条通知临时添加一个图标，气泡关闭后再移除。多条通知依次显示。
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use app_core::notifications::Notification;
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD, NIM_DELETE,
+    NIN_BALLOONHIDE, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK, NOTIFYICONDATAW,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, LoadIconW, PostMessageW,
+    RegisterClassW, IDI_APPLICATION, MSG, WM_APP, WNDCLASSW,
+};
+
+/// 接收通知图标回调的窗口类名。
+const WINDOW_CLASS: &str = "AudioRouter-Notifications";
+/// 有新通知入队，请求显示下一条。
+const WM_SHOW_NEXT: u32 = WM_APP + 1;
+/// 临时通知图标的回调消息，lParam 为气泡事件。
+const WM_ICON_CALLBACK: u32 = WM_APP + 2;
+const ICON_ID: u32 = 1;
+
+/// 待显示的通知，由 UI 线程入队、通知线程取出。
+static QUEUE: Mutex<VecDeque<Notification>> = Mutex::new(VecDeque::new());
+/// 通知线程的窗口，创建前为空。
+static WINDOW: AtomicPtr<core::ffi::c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+thread_local! {
+    /// 通知线程上是否有气泡正在显示。
+    static SHOWING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 启动显示通知的线程。
+pub fn init_notifications() -> anyhow::Result<()> {
+    std::thread::Builder::new()
+        .name("notifications".into())
+        .spawn(run)?;
+    Ok(())
+}
+
+/// 依次显示 `notifications`，不等待显示完成。
+pub fn show_notifications(notifications: Vec<Notification>) {
+    if notifications.is_empty() {
+        return;
+    }
+    QUEUE.lock().unwrap().extend(notifications);
+    // 窗口尚未创建时，创建后会显示已入队的通知
+    let hwnd = WINDOW.load(Ordering::Acquire);
+    if !hwnd.is_null() {
+        unsafe { PostMessageW(hwnd, WM_SHOW_NEXT, 0, 0) };
+    }
+}
+
+fn run() {
+    let class = wide(WINDOW_CLASS);
+    let hwnd = unsafe {
+        let instance = GetModuleHandleW(std::ptr::null());
+        let mut wc: WNDCLASSW = std::mem::zeroed();
+        wc.lpfnWndProc = Some(window_proc);
+        wc.hInstance = instance;
+        wc.lpszClassName = class.as_ptr();
+        if RegisterClassW(&wc) == 0 {
+            log::warn!("Failed to register notification window class");
+            return;
+        }
+        // 隐藏的顶层窗口：只用于接收通知图标的回调
+        CreateWindowExW(
+            0,
+            class.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null(),
+        )
+    };
+    if hwnd.is_null() {
+        log::warn!("Failed to create notification window, desktop notifications are disabled");
+        return;
+    }
+    WINDOW.store(hwnd, Ordering::Release);
+    show_next(hwnd);
+
+    let mut msg: MSG = unsafe { std::mem::zeroed() };
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        unsafe { DispatchMessageW(&msg) };
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_SHOW_NEXT => {
+            if !SHOWING.get() {
+                show_next(hwnd);
+            }
+            0
+        }
+        WM_ICON_CALLBACK => {
+            let event = (lparam & 0xFFFF) as u32;
+            let closed = matches!(
+                event,
+                NIN_BALLOONTIMEOUT | NIN_BALLOONUSERCLICK | NIN_BALLOONHIDE
+            );
+            if closed && SHOWING.get() {
+                remove_icon(hwnd);
+                show_next(hwnd);
+            }
+            0
+        }
+        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+}
+
+/// 取出下一条通知，添加带气泡的临时图标；显示失败时跳到再下一条。
+fn show_next(hwnd: HWND) {
+    loop {
+        let Some(notification) = QUEUE.lock().unwrap().pop_front() else {
+            return;
+        };
+        if show(hwnd, &notification) {
+            SHOWING.set(true);
+            return;
+        }
+        log::warn!("Failed to show notification: {}", notification.title);
+    }
+}
+
+fn show(hwnd: HWND, notification: &Notification) -> bool {
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP | NIF_INFO;
+    data.uCallbackMessage = WM_ICON_CALLBACK;
+    data.hIcon = unsafe {
+        let icon = LoadIconW(
+            GetModuleHandleW(std::ptr::null()),
+            wide("MAINICON").as_ptr(),
+        );
+        if icon.is_null() {
+            LoadIconW(std::ptr::null_mut(), IDI_APPLICATION)
+        } else {
+            icon
+        }
+    };
+    copy_wide(&mut data.szTip, "AudioRouter");
+    copy_wide(&mut data.szInfoTitle, &notification.title);
+    copy_wide(&mut data.szInfo, &notification.body);
+    data.dwInfoFlags = NIIF_INFO;
+    unsafe { Shell_NotifyIconW(NIM_ADD, &data) != 0 }
+}
+
+fn remove_icon(hwnd: HWND) {
+    let data = icon_data(hwnd);
+    unsafe { Shell_NotifyIconW(NIM_DELETE, &data) };
+    SHOWING.set(false);
+}
+
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let mut data: NOTIFYICONDATAW = unsafe { std::mem::zeroed() };
+    data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
+    data.hWnd = hwnd;
+    data.uID = ICON_ID;
+    data
+}
+
+/// 复制到定长的 UTF-16 缓冲区，过长时截断并保留结尾的 NUL。
+fn copy_wide(buf: &mut [u16], s: &str) {
+    let len = buf.len() - 1;
+    for (dst, src) in buf.iter_mut().zip(s.encode_utf16().take(len)) {
+        *dst = src;
+    }
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
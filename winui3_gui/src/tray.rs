@@ -1,15 +1,28 @@
 use std::cell::RefCell;
 
+use app_core::controller::AppController;
 use app_core::i18n::I18n;
 use tray_icon::{
-    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    menu::{
+        CheckMenuItem, IsMenuItem, Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem, Submenu,
+    },
     TrayIcon, TrayIconBuilder, TrayIconEvent, Icon,
 };
 
 /// 托盘运行时状态，保存在 thread_local 中以便运行时更新菜单文本。
 struct TrayState {
     show_item: MenuItem,
+    routing_item: MenuItem,
+    /// 菜单项当前对应的路由状态，状态不变时不重复设置文本
+    is_running: bool,
+    devices_menu: Submenu,
+    /// 子菜单当前显示的内容；None 表示需要重建（如切换语言后）
+    devices: Option<TrayDevices>,
+    device_items: Vec<Box<dyn IsMenuItem>>,
+    /// 输出设备勾选项的菜单 id 与设备 id
+    output_ids: Vec<(MenuId, String)>,
     quit_item: MenuItem,
+    tooltip: String,
     tray_icon: TrayIcon,
 }
 
@@ -21,9 +34,49 @@ thread_local! {
 pub enum TrayCommand {
     ToggleWindow,
     ShowWindow,
+    ToggleRouting,
+    /// 切换该设备是否作为输出
+    ToggleOutput(String),
     Quit,
 }
 
+/// 托盘“输出设备”子菜单的内容：顶部是源设备，下面是可勾选的输出设备。
+#[derive(Clone, PartialEq)]
+pub struct TrayDevices {
+    pub source: Option<String>,
+    pub outputs: Vec<TrayOutput>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TrayOutput {
+    pub device_id: String,
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl TrayDevices {
+    /// 从控制器当前的设备列表和配置生成。
+    pub fn from_controller(c: &AppController) -> Self {
+        let source = c
+            .selected_source
+            .as_ref()
+            .and_then(|id| c.devices.iter().find(|d| &d.id == id))
+            .map(|d| d.friendly_name.clone());
+        let handle = c.config_manager.handle();
+        let cfg = handle.read();
+        let outputs = c
+            .filtered_target_devices()
+            .into_iter()
+            .map(|d| TrayOutput {
+                device_id: d.id.clone(),
+                name: d.friendly_name.clone(),
+                enabled: cfg.outputs.iter().any(|o| o.device_id == d.id && o.enabled),
+            })
+            .collect();
+        Self { source, outputs }
+    }
+}
+
 /// 初始化系统托盘图标，并在 thread_local 中保持存活。
 ///
 /// `i18n` 用于翻译菜单项文本；左键点击不会弹出菜单（只在右键点击时弹出），
@@ -32,15 +85,20 @@ pub fn init_tray(i18n: I18n) -> anyhow::Result<()> {
     let icon = load_icon()?;
 
     let show_text = i18n.t("TrayShowHide").to_string();
+    let routing_text = i18n.t("TrayStartRouting").to_string();
     let quit_text = i18n.t("TrayQuit").to_string();
     let tooltip_text = i18n.t("AppTitle").to_string();
 
     let tray_menu = Menu::new();
     let show_item = MenuItem::new(&show_text, true, None);
+    let routing_item = MenuItem::new(&routing_text, false, None);
+    let devices_menu = Submenu::new(i18n.t("OutputDevices"), true);
     let quit_item = MenuItem::new(&quit_text, true, None);
     let separator = PredefinedMenuItem::separator();
 
     tray_menu.append(&show_item)?;
+    tray_menu.append(&routing_item)?;
+    tray_menu.append(&devices_menu)?;
     tray_menu.append(&separator)?;
     tray_menu.append(&quit_item)?;
 
@@ -54,7 +112,14 @@ pub fn init_tray(i18n: I18n) -> anyhow::Result<()> {
     TRAY_STATE.with(|s| {
         *s.borrow_mut() = Some(TrayState {
             show_item,
+            routing_item,
+            is_running: false,
+            devices_menu,
+            devices: None,
+            device_items: Vec::new(),
+            output_ids: Vec::new(),
             quit_item,
+            tooltip: tooltip_text,
             tray_icon,
         });
     });
@@ -65,14 +130,131 @@ pub fn init_tray(i18n: I18n) -> anyhow::Result<()> {
 /// 运行时更新托盘菜单文本和 tooltip，用于语言切换后同步。
 pub fn update_tray_language(i18n: &I18n) {
     TRAY_STATE.with(|s| {
-        if let Some(state) = s.borrow().as_ref() {
+        if let Some(state) = s.borrow_mut().as_mut() {
             state.show_item.set_text(i18n.t("TrayShowHide"));
+            state.routing_item.set_text(routing_text(i18n, state.is_running));
+            state.devices_menu.set_text(i18n.t("OutputDevices"));
+            state.devices = None;
             state.quit_item.set_text(i18n.t("TrayQuit"));
-            let _ = state.tray_icon.set_tooltip(Some(i18n.t("AppTitle")));
+            state.tooltip = i18n.t("AppTitle").to_string();
+            let _ = state.tray_icon.set_tooltip(Some(&state.tooltip));
+        }
+    });
+}
+
+/// 让“开始/停止路由”菜单项跟随路由状态：运行中显示“停止路由”，
+/// 未运行时显示“开始路由”，且只有 `can_start` 时可点击。
+pub fn update_tray_routing(i18n: &I18n, is_running: bool, can_start: bool) {
+    TRAY_STATE.with(|s| {
+        if let Some(state) = s.borrow_mut().as_mut() {
+            if state.is_running != is_running {
+                state.is_running = is_running;
+                state.routing_item.set_text(routing_text(i18n, is_running));
+            }
+            let enabled = is_running || can_start;
+            if state.routing_item.is_enabled() != enabled {
+                state.routing_item.set_enabled(enabled);
+            }
         }
     });
 }
 
+/// tooltip 的最大长度（UTF-16 单元），超出部分 Windows 会直接截断。
+const MAX_TOOLTIP_LEN: usize = 127;
+
+/// 在 tooltip 中显示当前路由，如“扬声器 → 厨房, 耳机（运行中）”。
+/// 没有源设备或没有启用的输出时只显示应用名称。
+pub fn update_tray_tooltip(i18n: &I18n, devices: &TrayDevices, is_running: bool) {
+    let outputs: Vec<&str> = devices
+        .outputs
+        .iter()
+        .filter(|o| o.enabled)
+        .map(|o| o.name.as_str())
+        .collect();
+    let mut tooltip = i18n.t("AppTitle").to_string();
+    if let (Some(source), false) = (&devices.source, outputs.is_empty()) {
+        let state = i18n.t(if is_running { "Running" } else { "Stopped" });
+        let summary = i18n
+            .t("TrayRouteSummary")
+            .replace("{source}", source)
+            .replace("{outputs}", &outputs.join(", "))
+            .replace("{state}", state);
+        tooltip = format!("{tooltip}\n{summary}");
+    }
+    if tooltip.encode_utf16().count() > MAX_TOOLTIP_LEN {
+        let mut len = 1; // 省略号
+        tooltip = tooltip
+            .chars()
+            .take_while(|c| {
+                len += c.len_utf16();
+                len <= MAX_TOOLTIP_LEN
+            })
+            .collect::<String>()
+            + "…";
+    }
+
+    TRAY_STATE.with(|s| {
+        if let Some(state) = s.borrow_mut().as_mut() {
+            if state.tooltip != tooltip {
+                let _ = state.tray_icon.set_tooltip(Some(&tooltip));
+                state.tooltip = tooltip;
+            }
+        }
+    });
+}
+
+/// 设备列表或输出配置变化后重建“输出设备”子菜单；内容不变时不做任何事。
+pub fn update_tray_devices(i18n: &I18n, devices: TrayDevices) {
+    TRAY_STATE.with(|s| {
+        if let Some(state) = s.borrow_mut().as_mut() {
+            if state.devices.as_ref() == Some(&devices) {
+                return;
+            }
+            if let Err(e) = rebuild_devices_menu(state, i18n, &devices) {
+                log::warn!("Failed to rebuild tray device menu: {e}");
+            }
+            state.devices = Some(devices);
+        }
+    });
+}
+
+fn rebuild_devices_menu(
+    state: &mut TrayState,
+    i18n: &I18n,
+    devices: &TrayDevices,
+) -> anyhow::Result<()> {
+    for item in state.device_items.drain(..) {
+        state.devices_menu.remove(&*item)?;
+    }
+    state.output_ids.clear();
+
+    let mut items: Vec<Box<dyn IsMenuItem>> = Vec::new();
+    if let Some(source) = &devices.source {
+        let text = i18n.t("TraySource").replace("{name}", source);
+        items.push(Box::new(MenuItem::new(text, false, None)));
+        items.push(Box::new(PredefinedMenuItem::separator()));
+    }
+    if devices.outputs.is_empty() {
+        items.push(Box::new(MenuItem::new(i18n.t("NoDevices"), false, None)));
+    }
+    for output in &devices.outputs {
+        let item = CheckMenuItem::new(&output.name, true, output.enabled, None);
+        state
+            .output_ids
+            .push((item.id().clone(), output.device_id.clone()));
+        items.push(Box::new(item));
+    }
+    for item in &items {
+        state.devices_menu.append(&**item)?;
+    }
+    state.device_items = items;
+    Ok(())
+}
+
+fn routing_text(i18n: &I18n, is_running: bool) -> &str {
+    i18n.t(if is_running { "TrayStopRouting" } else { "TrayStartRouting" })
+}
+
 /// 尝试接收托盘图标点击事件。
 pub fn try_recv_tray_event() -> Option<TrayCommand> {
     while let Ok(event) = TrayIconEvent::receiver().try_recv() {
@@ -97,6 +279,12 @@ pub fn try_recv_menu_event() -> Option<TrayCommand> {
             let state = borrow.as_ref()?;
             if event.id == *state.show_item.id() {
                 Some(TrayCommand::ToggleWindow)
+            } else if event.id == *state.routing_item.id() {
+                Some(TrayCommand::ToggleRouting)
+            } else if let Some((_, device_id)) =
+                state.output_ids.iter().find(|(id, _)| event.id == *id)
+            {
+                Some(TrayCommand::ToggleOutput(device_id.clone()))
             } else if event.id == *state.quit_item.id() {
                 Some(TrayCommand::Quit)
             } else {
@@ -1,6 +1,7 @@
 //! 通过 GitHub API 检测新版本并执行自动更新。
 //!
-//! - 检测：GET https://api.github.com/repos/{owner}/{repo}/releases/latest
+//! - 检测：GET https://api.github.com/repos/{owner}/{repo}/releases/latest；
+//!   Beta 通道改为取 releases 列表中最新的非草稿版本（可以是预发布版本）
 //! - 对比：semver 比较当前版本与最新版 tag（去掉前缀 v）
 //! - 下载：从 release assets 中匹配 AudioRouter-Setup-*-x64.exe
 //! - 安装：下载到临时目录后启动安装包并退出当前进程
@@ -8,6 +9,7 @@
 use std::path::PathBuf;
 use std::sync::{Arc, OnceLock, RwLock};
 
+use config::config::UpdateChannel;
 use semver::Version;
 
 const GITHUB_OWNER: &str = "fangfuzha";
@@ -265,27 +267,31 @@ pub fn current_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
 
-/// 向 GitHub API 请求最新 release 信息并比较版本。
+/// 向 GitHub API 请求 `channel` 上最新的 release 信息并比较版本。
 ///
 /// 阻塞调用，应在后台线程中执行。
-pub fn check_for_updates() -> UpdateCheckResult {
-    let url = format!(
-        "https://api.github.com/repos/{}/{}/releases/latest",
-        GITHUB_OWNER, GITHUB_REPO
-    );
+pub fn check_for_updates(channel: UpdateChannel) -> UpdateCheckResult {
+    let url = match channel {
+        UpdateChannel::Stable => format!(
+            "https://api.github.com/repos/{}/{}/releases/latest",
+            GITHUB_OWNER, GITHUB_REPO
+        ),
+        // 列表按发布时间倒序
+        UpdateChannel::Beta => format!(
+            "https://api.github.com/repos/{}/{}/releases?per_page=20",
+            GITHUB_OWNER, GITHUB_REPO
+        ),
+    };
 
     let agent = build_agent();
 
-    let release: GithubRelease = match agent
+    let resp = match agent
         .get(&url)
         .set("User-Agent", USER_AGENT)
         .set("Accept", "application/vnd.github.v3+json")
         .call()
     {
-        Ok(resp) => match resp.into_json() {
-            Ok(r) => r,
-            Err(e) => return UpdateCheckResult::Failed(format!("parse response: {e}")),
-        },
+        Ok(resp) => resp,
         Err(ureq::Error::Status(code, resp)) => {
             return UpdateCheckResult::Failed(format!(
                 "HTTP {code}: {}",
@@ -294,8 +300,19 @@ pub fn check_for_updates() -> UpdateCheckResult {
         }
         Err(e) => return UpdateCheckResult::Failed(format!("network error: {e}")),
     };
+    let release = match channel {
+        UpdateChannel::Stable => resp.into_json::<GithubRelease>().map(Some),
+        UpdateChannel::Beta => resp
+            .into_json::<Vec<GithubRelease>>()
+            .map(|releases| releases.into_iter().find(|r| !r.draft)),
+    };
+    let release = match release {
+        Ok(Some(r)) => r,
+        Ok(None) => return UpdateCheckResult::UpToDate,
+        Err(e) => return UpdateCheckResult::Failed(format!("parse response: {e}")),
+    };
 
-    if release.draft || release.prerelease {
+    if release.draft || (release.prerelease && channel == UpdateChannel::Stable) {
         return UpdateCheckResult::UpToDate;
     }
 